@@ -0,0 +1,228 @@
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        academic_record::UpdateResultWeighting, auth_user::AuthUserDto, common_details::UserRole,
+        student::Student,
+    },
+    guards::role_guard::{check_admin_or_staff, require_parent_child_access},
+    helpers::event_helpers::get_school_id_from_request,
+    models::api_request_model::RequestQuery,
+    services::{
+        academic_record_service::{AcademicRecordService, ResultVisibility},
+        parent_service::ParentService,
+    },
+    utils::{db_utils::get_database, object_id::parse_object_id_value},
+};
+
+fn request_school_id(req: &HttpRequest) -> Option<ObjectId> {
+    get_school_id_from_request(req).and_then(|id| ObjectId::parse_str(&id).ok())
+}
+
+/// Load the student and check the caller may see their record. Students and
+/// parents only ever see finalized, published results.
+pub(crate) async fn authorize_student(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    student_id: &str,
+) -> Result<(Student, ResultVisibility), HttpResponse> {
+    let student_oid =
+        parse_object_id_value(student_id).map_err(|err| HttpResponse::BadRequest().json(err))?;
+
+    let db = get_database(req, state);
+    let student = db
+        .collection::<Student>("students")
+        .find_one(doc! { "_id": student_oid })
+        .await
+        .map_err(|e| {
+            HttpResponse::BadRequest().json(serde_json::json!({ "message": e.to_string() }))
+        })?
+        .ok_or_else(|| {
+            HttpResponse::NotFound().json(serde_json::json!({ "message": "Student not found" }))
+        })?;
+
+    let visibility = match user.role {
+        Some(UserRole::ADMIN) | Some(UserRole::SCHOOLSTAFF) | Some(UserRole::TEACHER) => {
+            ResultVisibility::All
+        }
+        Some(UserRole::STUDENT) => {
+            let own = student.user_id.map(|id| id.to_hex()) == Some(user.id.clone())
+                || student_oid.to_hex() == user.id;
+            if !own {
+                return Err(HttpResponse::Forbidden().json(serde_json::json!({
+                    "message": "You can only view your own academic record"
+                })));
+            }
+            ResultVisibility::Published
+        }
+        _ => {
+            require_parent_child_access(user, student_id, &ParentService::new(&db))
+                .await
+                .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))?;
+            ResultVisibility::Published
+        }
+    };
+
+    Ok((student, visibility))
+}
+
+#[get("/weighting")]
+async fn get_weighting(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let education_year_id = match query.education_year_id.as_deref() {
+        Some(id) => match parse_object_id_value(id) {
+            Ok(id) => id,
+            Err(err) => return HttpResponse::BadRequest().json(err),
+        },
+        None => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "education_year_id is required"
+            }))
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = AcademicRecordService::new(&db);
+
+    match service
+        .get_weighting(request_school_id(&req), &education_year_id)
+        .await
+    {
+        Ok(weighting) => HttpResponse::Ok().json(weighting),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/weighting")]
+async fn update_weighting(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<UpdateResultWeighting>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let db = get_database(&req, &state);
+    let service = AcademicRecordService::new(&db);
+
+    match service
+        .update_weighting(request_school_id(&req), &data)
+        .await
+    {
+        Ok(weighting) => HttpResponse::Ok().json(weighting),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Term results and the annual result for one education year
+#[get("/students/{student_id}/years/{education_year_id}")]
+async fn get_annual_record(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (student_id, education_year_id) = path.into_inner();
+
+    let (student, visibility) = match authorize_student(&req, &state, &user, &student_id).await
+    {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let education_year_id = match parse_object_id_value(&education_year_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let Some(student_id) = student.id else {
+        return HttpResponse::NotFound().json(serde_json::json!({ "message": "Student not found" }));
+    };
+
+    let db = get_database(&req, &state);
+    let service = AcademicRecordService::new(&db);
+
+    match service
+        .annual_record(&student_id, &education_year_id, visibility)
+        .await
+    {
+        Ok(Some(record)) => HttpResponse::Ok().json(record),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "message": "No results recorded for this education year"
+        })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/students/{student_id}/cumulative")]
+async fn get_cumulative_record(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (student, visibility) =
+        match authorize_student(&req, &state, &user, &path.into_inner()).await {
+            Ok(v) => v,
+            Err(res) => return res,
+        };
+    let Some(student_id) = student.id else {
+        return HttpResponse::NotFound().json(serde_json::json!({ "message": "Student not found" }));
+    };
+
+    let db = get_database(&req, &state);
+    let service = AcademicRecordService::new(&db);
+
+    match service.cumulative_record(&student_id, visibility).await {
+        Ok(record) => HttpResponse::Ok().json(record),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Finalized results only, across every school the student attended;
+/// students and families see the published ones
+#[get("/students/{student_id}/transcript")]
+async fn get_transcript(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (student, visibility) =
+        match authorize_student(&req, &state, &user, &path.into_inner()).await {
+            Ok(v) => v,
+            Err(res) => return res,
+        };
+
+    let db = get_database(&req, &state);
+    let service = AcademicRecordService::new(&db);
+
+    match service
+        .transcript(&state, &student, request_school_id(&req), visibility)
+        .await
+    {
+        Ok(transcript) => HttpResponse::Ok().json(transcript),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_weighting).service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(update_weighting)
+            .service(get_annual_record)
+            .service(get_cumulative_record)
+            .service(get_transcript),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "academic-records", blueprint);
+}
@@ -0,0 +1,142 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::doc;
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        academic_year_rollover::StartRollover, audit_log::AuditSeverity, auth_user::AuthUserDto,
+    },
+    guards::role_guard::check_admin,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{academic_year_service::AcademicYearService, audit_log_service::AuditLogService},
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+#[get("/rollovers")]
+async fn get_rollovers(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let service = AcademicYearService::new(&db);
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service.get_all(query.limit, query.skip, extra_match).await {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Progress report of a rollover job
+#[get("/rollovers/{id}")]
+async fn get_rollover(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = AcademicYearService::new(&db);
+
+    match service.find_one(&id).await {
+        Ok(rollover) => HttpResponse::Ok().json(rollover),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/rollovers")]
+async fn start_rollover(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<StartRollover>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": err.to_string()
+        }));
+    }
+
+    let user_id = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let school_id = get_school_id_from_request(&req)
+        .and_then(|id| parse_object_id_value(&id).ok());
+
+    let db = get_database(&req, &state);
+    let service = AcademicYearService::new(&db);
+
+    match service
+        .start_rollover(school_id, data.into_inner(), user_id)
+        .await
+    {
+        Ok(rollover) => {
+            if let (Some(school_id), Some(id)) = (school_id, rollover.id) {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "academic_year.rollover",
+                        "academic_year_rollover",
+                        id,
+                        Some(doc! {
+                            "from_education_year_id": rollover.from_education_year_id,
+                            "label": rollover.label.clone(),
+                        }),
+                        None,
+                        Some(AuditSeverity::WARNING),
+                    )
+                    .await
+                    .ok();
+            }
+
+            HttpResponse::Accepted().json(rollover)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/rollovers/{id}/resume")]
+async fn resume_rollover(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": err.to_string()
+        }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = AcademicYearService::new(&db);
+
+    match service.resume_rollover(&id).await {
+        Ok(rollover) => HttpResponse::Accepted().json(rollover),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_rollovers)
+            .service(get_rollover)
+            .service(start_rollover)
+            .service(resume_rollover),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "academic-years", blueprint);
+}
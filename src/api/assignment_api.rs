@@ -1,17 +1,24 @@
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
-use mongodb::bson::doc;
+use mongodb::bson::{doc, oid::ObjectId};
 
 use crate::{
     config::state::AppState,
     domain::{
-        assignment::{Assignment, AssignmentPartial, Submission, SubmissionPartial},
+        ai_feedback::{AiFeedbackSettings, GenerateAiFeedback},
+        assignment::{
+            Assignment, AssignmentPartial, GradeSubmission, Submission, SubmissionPartial,
+            SubmitContribution,
+        },
         auth_user::AuthUserDto,
         common_details::UserRole,
     },
+    errors::AppError,
     guards::role_guard::{require_permission, require_feature_enabled, require_parent_child_access},
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
     services::{
+        ai_feedback_service::{AiFeedbackService, AI_FEEDBACK_FEATURE},
+        assignment_group_service::AssignmentGroupService,
         assignment_service::AssignmentService, 
         event_service::EventService,
         role_service::RoleService,
@@ -433,6 +440,29 @@ async fn submit_assignment(
     }
 }
 
+/// Effective submission rules: deadline, late window, penalty and whether
+/// resubmitting is allowed
+#[get("/{id}/policy")]
+async fn get_submission_policy(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = AssignmentService::new(&db);
+
+    let assignment = match service.find_one_assignment(Some(&id), None).await {
+        Ok(assignment) => assignment,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+
+    match service.submission_policy(&assignment).await {
+        Ok(policy) => HttpResponse::Ok().json(policy),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 #[get("/{id}/submissions")]
 async fn get_assignment_submissions(
     req: HttpRequest,
@@ -496,7 +526,7 @@ async fn grade_submission(
     req: HttpRequest,
     user: web::ReqData<AuthUserDto>,
     path: web::Path<(String, String)>,
-    data: web::Json<serde_json::Value>,
+    data: web::Json<GradeSubmission>,
     state: web::Data<AppState>,
 ) -> impl Responder {
     // Only teachers can grade
@@ -540,25 +570,12 @@ async fn grade_submission(
         }
     }
 
-    // Extract grading data
-    let score = match data.get("score").and_then(|v| v.as_f64()) {
-        Some(s) => s,
-        None => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "message": "Score is required"
-            }));
-        }
-    };
-
-    let feedback = data
-        .get("feedback")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    let feedback_file = data
-        .get("feedback_file")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+    let grade = data.into_inner();
+    if grade.score.is_none() && grade.criterion_scores.is_none() && !grade.accept_ai_feedback {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "message": "Score is required"
+        }));
+    }
 
     // Get teacher ObjectId
     let graded_by = match parse_object_id_value(&user.id) {
@@ -587,7 +604,64 @@ async fn grade_submission(
     };
 
     match service
-        .grade_submission(&submission_id, score, feedback, feedback_file, graded_by)
+        .grade_submission(&submission_id, grade, graded_by)
+        .await
+    {
+        Ok(submission) => {
+            let submission_clone = submission.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = submission_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "submission",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &submission_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(submission)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Re-run auto-grading for a quiz submission, e.g. after fixing an answer key
+#[post("/submissions/{id}/auto-grade")]
+async fn auto_grade_submission(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let school_id = match get_school_id_from_request(&req) {
+        Some(id) => id,
+        None => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "School ID required"
+            }));
+        }
+    };
+
+    let role_service = RoleService::new(&db);
+    if let Err(e) = require_permission(&user, &school_id, "assignment.update", &role_service).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": e
+        }));
+    }
+
+    let actor = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let submission_id = IdType::from_string(path.into_inner());
+
+    match AssignmentService::new(&db)
+        .trigger_auto_grading(&submission_id, actor)
         .await
     {
         Ok(submission) => {
@@ -612,6 +686,102 @@ async fn grade_submission(
     }
 }
 
+/// Draft AI feedback for a submission; it stays hidden from the student
+/// until accepted through grading
+#[post("/submissions/{id}/ai-feedback")]
+async fn generate_ai_feedback(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: Option<web::Json<GenerateAiFeedback>>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let school_id = match get_school_id_from_request(&req) {
+        Some(id) => id,
+        None => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "School ID required"
+            }));
+        }
+    };
+
+    let role_service = RoleService::new(&db);
+    if let Err(e) = require_permission(&user, &school_id, "submission.grade", &role_service).await
+    {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": e
+        }));
+    }
+
+    match FeatureService::new(&db)
+        .is_feature_enabled_or(
+            &IdType::from_string(school_id.clone()),
+            AI_FEEDBACK_FEATURE,
+            false,
+        )
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "message": format!("Feature '{}' is disabled for this school", AI_FEEDBACK_FEATURE)
+            }));
+        }
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    }
+
+    let submission_id = IdType::from_string(path.into_inner());
+    let rubric = data.and_then(|d| d.into_inner().rubric);
+    let school_oid = parse_object_id_value(&school_id).ok();
+
+    match AiFeedbackService::new(&db)
+        .generate(&submission_id, school_oid, rubric)
+        .await
+    {
+        Ok(submission) => HttpResponse::Ok().json(submission),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/ai-feedback/settings")]
+async fn update_ai_feedback_settings(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<AiFeedbackSettings>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let school_id = match get_school_id_from_request(&req) {
+        Some(id) => id,
+        None => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "School ID required"
+            }));
+        }
+    };
+
+    let role_service = RoleService::new(&db);
+    if let Err(e) = require_permission(&user, &school_id, "feature.toggle", &role_service).await {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": e
+        }));
+    }
+
+    let settings = data.into_inner();
+    match FeatureService::new(&db)
+        .toggle_feature(
+            &IdType::from_string(school_id),
+            AI_FEEDBACK_FEATURE,
+            settings.enabled,
+        )
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().json(settings),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 #[get("/submissions/{id}")]
 async fn get_submission_by_id(
     req: HttpRequest,
@@ -629,40 +799,58 @@ async fn get_submission_by_id(
         Err(err) => return HttpResponse::NotFound().json(err),
     };
 
+    // Students and parents see a group submission through one member
+    let member_ids = match submission_member_ids(&db, &submission).await {
+        Ok(ids) => ids,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let mut viewing_member = None;
+
     // Students can only view their own submissions
     if matches!(user.role, Some(UserRole::STUDENT)) {
-        if let (Some(student_id), Ok(user_oid)) =
-            (submission.student_id, parse_object_id_value(&user.id))
-        {
+        if let Ok(user_oid) = parse_object_id_value(&user.id) {
             let student_collection = db.collection::<mongodb::bson::Document>("students");
-            match student_collection.find_one(doc! { "_id": student_id, "user_id": user_oid })
+            match student_collection
+                .find_one(doc! { "_id": { "$in": &member_ids }, "user_id": user_oid })
                 .await
             {
+                Ok(Some(student_doc)) => {
+                    viewing_member = student_doc.get_object_id("_id").ok();
+                }
                 Ok(None) | Err(_) => {
                     return HttpResponse::Forbidden().json(serde_json::json!({
                         "message": "You can only view your own submissions"
                     }));
                 }
-                _ => {}
             }
         }
     }
     
     // Parents can view their children's submissions
     if matches!(user.role, Some(UserRole::PARENT)) {
-        if let Some(student_id) = submission.student_id {
-            let parent_service = ParentService::new(&db);
-            let student_id_str = student_id.to_hex();
-            
-            if let Err(e) = require_parent_child_access(&user, &student_id_str, &parent_service).await {
+        if member_ids.is_empty() {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "message": "Cannot verify parent access: student ID not found"
+            }));
+        }
+
+        let parent_service = ParentService::new(&db);
+        let mut access = Err(String::new());
+        for student_id in &member_ids {
+            access = require_parent_child_access(&user, &student_id.to_hex(), &parent_service)
+                .await
+                .map(|_| *student_id);
+            if access.is_ok() {
+                break;
+            }
+        }
+        match access {
+            Ok(student_id) => viewing_member = Some(student_id),
+            Err(e) => {
                 return HttpResponse::Forbidden().json(serde_json::json!({
                     "message": e
                 }));
             }
-        } else {
-            return HttpResponse::Forbidden().json(serde_json::json!({
-                "message": "Cannot verify parent access: student ID not found"
-            }));
         }
     }
 
@@ -674,7 +862,13 @@ async fn get_submission_by_id(
         .await
     {
         Ok(mut data) => {
-            if let Some(submission) = data.data.pop() {
+            if let Some(mut submission) = data.data.pop() {
+                if matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT)) {
+                    submission.submission.hide_ai_draft();
+                    if let Some(student_id) = &viewing_member {
+                        submission.submission.keep_member_grade(student_id);
+                    }
+                }
                 HttpResponse::Ok().json(submission)
             } else {
                 HttpResponse::NotFound().json(serde_json::json!({
@@ -710,12 +904,16 @@ async fn update_submission(
                     }));
                 }
 
-                // Verify ownership
-                if let (Some(student_id), Ok(user_oid)) =
-                    (submission.student_id, parse_object_id_value(&user.id))
+                // Verify ownership; any member may update a group submission
+                let member_ids = match submission_member_ids(&db, &submission).await {
+                    Ok(ids) => ids,
+                    Err(err) => return HttpResponse::BadRequest().json(err),
+                };
+                if let (false, Ok(user_oid)) = (member_ids.is_empty(), parse_object_id_value(&user.id))
                 {
                     let student_collection = db.collection::<mongodb::bson::Document>("students");
-                    match student_collection.find_one(doc! { "_id": student_id, "user_id": user_oid })
+                    match student_collection
+                        .find_one(doc! { "_id": { "$in": &member_ids }, "user_id": user_oid })
                         .await
                     {
                         Ok(None) | Err(_) => {
@@ -731,8 +929,23 @@ async fn update_submission(
         }
     }
 
-    match service.update_submission(&id, &data.into_inner()).await {
-        Ok(submission) => {
+    let is_student = matches!(user.role, Some(UserRole::STUDENT));
+    let mut update = data.into_inner();
+    if is_student {
+        update.ai_feedback = None;
+        update.ai_suggested_score = None;
+        update.ai_feedback_status = None;
+        update.ai_feedback_generated_at = None;
+        update.group_id = None;
+        update.contributions = None;
+        update.member_grades = None;
+    }
+
+    match service.update_submission(&id, &update).await {
+        Ok(mut submission) => {
+            if is_student {
+                submission.hide_ai_draft();
+            }
             let submission_clone = submission.clone();
             let state_clone = state.clone();
             actix_rt::spawn(async move {
@@ -754,6 +967,73 @@ async fn update_submission(
     }
 }
 
+/// Students on the submission: the group's members, or the one student
+async fn submission_member_ids(
+    db: &mongodb::Database,
+    submission: &Submission,
+) -> Result<Vec<ObjectId>, AppError> {
+    match submission.group_id {
+        Some(group_id) => Ok(AssignmentGroupService::new(db)
+            .find_one(&IdType::ObjectId(group_id))
+            .await?
+            .member_ids),
+        None => Ok(submission.student_id.into_iter().collect()),
+    }
+}
+
+/// A group member's statement of their part in the shared submission
+#[put("/submissions/{id}/contribution")]
+async fn submit_contribution(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SubmitContribution>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if !matches!(user.role, Some(UserRole::STUDENT)) {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "Only students can write contribution statements"
+        }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+
+    let student_id = match parse_object_id_value(&user.id) {
+        Ok(user_oid) => {
+            let student_collection = db.collection::<mongodb::bson::Document>("students");
+            match student_collection.find_one(doc! { "user_id": user_oid }).await {
+                Ok(Some(student_doc)) => match student_doc.get_object_id("_id") {
+                    Ok(student_oid) => student_oid,
+                    Err(_) => {
+                        return HttpResponse::BadRequest().json(serde_json::json!({
+                            "message": "Invalid student ID"
+                        }));
+                    }
+                },
+                _ => {
+                    return HttpResponse::BadRequest().json(serde_json::json!({
+                        "message": "Student record not found for this user"
+                    }));
+                }
+            }
+        }
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match AssignmentService::new(&db)
+        .set_contribution(&id, student_id, data.into_inner().statement)
+        .await
+    {
+        Ok(mut submission) => {
+            submission.hide_ai_draft();
+            submission.keep_member_grade(&student_id);
+            HttpResponse::Ok().json(submission)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 fn blueprint(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all_assignments)
         .service(get_assignment_by_id)
@@ -765,9 +1045,14 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
                 .service(update_assignment)
                 .service(delete_assignment)
                 .service(submit_assignment)
+                .service(get_submission_policy)
                 .service(get_assignment_submissions)
                 .service(grade_submission)
+                .service(auto_grade_submission)
+                .service(generate_ai_feedback)
+                .service(update_ai_feedback_settings)
                 .service(get_submission_by_id)
+                .service(submit_contribution)
                 .service(update_submission),
         );
 }
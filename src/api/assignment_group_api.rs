@@ -0,0 +1,304 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Database,
+};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        assignment_group::{CreateAssignmentGroup, UpdateAssignmentGroup},
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+    },
+    guards::role_guard::check_admin_staff_or_teacher,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{assignment_group_service::AssignmentGroupService, event_service::EventService},
+    utils::{db_utils::get_database, object_id::parse_object_id_value},
+};
+
+/// Student record of the signed-in user
+async fn current_student_id(db: &Database, user: &AuthUserDto) -> Result<ObjectId, HttpResponse> {
+    let user_oid =
+        parse_object_id_value(&user.id).map_err(|e| HttpResponse::BadRequest().json(e))?;
+    db.collection::<Document>("students")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .and_then(|s| s.get_object_id("_id").ok())
+        .ok_or_else(|| {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "Student record not found for this user"
+            }))
+        })
+}
+
+fn only_students(user: &AuthUserDto) -> Result<(), HttpResponse> {
+    if matches!(user.role, Some(UserRole::STUDENT)) {
+        Ok(())
+    } else {
+        Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "Only students can join or leave groups"
+        })))
+    }
+}
+
+fn path_object_id(value: String) -> Result<ObjectId, HttpResponse> {
+    IdType::from_string(value)
+        .to_object_id()
+        .map_err(|e| HttpResponse::BadRequest().json(e))
+}
+
+#[get("/assignments/{id}")]
+async fn get_assignment_groups(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    let db = get_database(&req, &state);
+    match AssignmentGroupService::new(&db)
+        .get_all_with_members(assignment_id, query.limit, query.skip)
+        .await
+    {
+        Ok(groups) => HttpResponse::Ok().json(groups),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/assignments/{id}/mine")]
+async fn get_my_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match AssignmentGroupService::new(&db)
+        .find_for_student(assignment_id, student_id)
+        .await
+    {
+        Ok(Some(group)) => HttpResponse::Ok().json(group),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "message": "You are not in a group for this assignment"
+        })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Teachers form groups with any members; on self-enrolment assignments a
+/// student can start a group with themselves as its first member
+#[post("/assignments/{id}")]
+async fn create_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<CreateAssignmentGroup>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    let db = get_database(&req, &state);
+    let student_id = if matches!(user.role, Some(UserRole::STUDENT)) {
+        match current_student_id(&db, &user).await {
+            Ok(id) => Some(id),
+            Err(resp) => return resp,
+        }
+    } else {
+        if let Err(e) = check_admin_staff_or_teacher(&user) {
+            return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+        }
+        None
+    };
+
+    let created_by = parse_object_id_value(&user.id).ok();
+    match AssignmentGroupService::new(&db)
+        .create(assignment_id, data.into_inner(), student_id, created_by)
+        .await
+    {
+        Ok(group) => {
+            let group_clone = group.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = group_clone.id {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "assignment_group",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &group_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Created().json(group)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpdateAssignmentGroup>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    match AssignmentGroupService::new(&db)
+        .update(&id, data.into_inner())
+        .await
+    {
+        Ok(group) => {
+            let group_clone = group.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = group_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "assignment_group",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &group_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(group)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    match AssignmentGroupService::new(&db).delete(&id).await {
+        Ok(group) => {
+            let group_clone = group.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = group_clone.id {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "assignment_group",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &group_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(group)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/join")]
+async fn join_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = only_students(&user) {
+        return resp;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match AssignmentGroupService::new(&db).join(&id, student_id).await {
+        Ok(group) => HttpResponse::Ok().json(group),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/leave")]
+async fn leave_group(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = only_students(&user) {
+        return resp;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match AssignmentGroupService::new(&db)
+        .leave(&id, student_id)
+        .await
+    {
+        Ok(group) => HttpResponse::Ok().json(group),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_my_group)
+            .service(get_assignment_groups)
+            .service(create_group)
+            .service(join_group)
+            .service(leave_group)
+            .service(update_group)
+            .service(delete_group),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "assignment-groups", blueprint);
+}
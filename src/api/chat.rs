@@ -0,0 +1,523 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::Deserialize;
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        chat::ChatChannel,
+        common_details::UserRole,
+    },
+    guards::role_guard::{check_admin_staff_or_teacher, require_feature_enabled},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    schema::common_schema::ActorRef,
+    services::{
+        chat_service::ChatService, event_service::EventService, feature_service::FeatureService,
+    },
+    utils::db_utils::get_database,
+};
+
+#[derive(Debug, Deserialize)]
+struct ToggleRequest {
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostChatMessageRequest {
+    content: String,
+}
+
+/// Resolve the school member (student / teacher document id) behind the token
+fn current_member(user: &AuthUserDto) -> Result<(ObjectId, UserRole), HttpResponse> {
+    let role = user.role.clone().ok_or_else(|| {
+        HttpResponse::Forbidden().json(serde_json::json!({ "message": "User role is required" }))
+    })?;
+
+    let id = user.current_school_user_id.as_ref().unwrap_or(&user.id);
+    let id = ObjectId::parse_str(id).map_err(|_| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "Invalid user ID" }))
+    })?;
+
+    Ok((id, role))
+}
+
+fn parse_id(value: &str, label: &str) -> Result<ObjectId, HttpResponse> {
+    ObjectId::parse_str(value).map_err(|_| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": format!("Invalid {}", label) }))
+    })
+}
+
+async fn ensure_chat_enabled(req: &HttpRequest, state: &web::Data<AppState>) -> Result<(), HttpResponse> {
+    let Some(school_id) = get_school_id_from_request(req) else {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "message": "School ID required"
+        })));
+    };
+
+    let feature_service = FeatureService::new(&get_database(req, state));
+    require_feature_enabled(&school_id, "chat.enabled", &feature_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+/// Load the channel and check the caller can see it. Membership is kept
+/// current by the enrolment and teaching assignment writes.
+async fn load_channel(
+    service: &ChatService,
+    channel_id: &ObjectId,
+    member_id: &ObjectId,
+    role: &UserRole,
+) -> Result<ChatChannel, HttpResponse> {
+    let channel = service
+        .find_channel(&IdType::ObjectId(*channel_id))
+        .await
+        .map_err(|e| HttpResponse::NotFound().json(e))?;
+
+    let is_staff = matches!(role, UserRole::ADMIN | UserRole::SCHOOLSTAFF);
+    if !is_staff && !channel.is_member(member_id) {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You are not a member of this channel"
+        })));
+    }
+
+    Ok(channel)
+}
+
+/// Teachers of the channel and school staff moderate it
+fn ensure_moderator(channel: &ChatChannel, member_id: &ObjectId, role: &UserRole) -> Result<(), HttpResponse> {
+    match role {
+        UserRole::ADMIN | UserRole::SCHOOLSTAFF => Ok(()),
+        UserRole::TEACHER if channel.is_member(member_id) => Ok(()),
+        _ => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "Only the channel's teachers can moderate it"
+        }))),
+    }
+}
+
+#[get("/channels")]
+async fn get_my_channels(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    let result = match role {
+        UserRole::ADMIN | UserRole::SCHOOLSTAFF => service.find_channels(doc! {}).await,
+        _ => service.member_channels(&member_id, &role).await,
+    };
+
+    match result {
+        Ok(channels) => HttpResponse::Ok().json(channels),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/classes/{class_id}/channels")]
+async fn get_class_channels(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let class_id = match parse_id(&path.into_inner(), "class ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    match service.class_channels(&class_id).await {
+        Ok(channels) => {
+            let is_staff = matches!(role, UserRole::ADMIN | UserRole::SCHOOLSTAFF);
+            let visible: Vec<ChatChannel> = channels
+                .into_iter()
+                .filter(|c| is_staff || c.is_member(&member_id))
+                .collect();
+            HttpResponse::Ok().json(visible)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/classes/{class_id}/sync")]
+async fn sync_class_channels(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let class_id = match parse_id(&path.into_inner(), "class ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    match service.sync_class_channels(&class_id).await {
+        Ok(channels) => HttpResponse::Ok().json(channels),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/classes/{class_id}/student-chat")]
+async fn set_student_chat(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    body: web::Json<ToggleRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let class_id = match parse_id(&path.into_inner(), "class ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    // Muting is allowed for anyone who moderates the class channel
+    let class_channel = match service.class_channels(&class_id).await {
+        Ok(channels) => channels.into_iter().find(|c| c.class_subject_id.is_none()),
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let moderator_check = match &class_channel {
+        Some(channel) => ensure_moderator(channel, &member_id, &role),
+        None => check_admin_staff_or_teacher(&user)
+            .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e }))),
+    };
+    if let Err(res) = moderator_check {
+        return res;
+    }
+
+    match service.set_student_chat(&class_id, body.enabled).await {
+        Ok(class) => {
+            let cloned = class.clone();
+            let state_clone = state.clone();
+
+            actix_rt::spawn(async move {
+                if let Some(id) = cloned.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "class",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &cloned,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(class)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/channels/{id}")]
+async fn get_channel(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let channel_id = match parse_id(&path.into_inner(), "channel ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    match load_channel(&service, &channel_id, &member_id, &role).await {
+        Ok(channel) => HttpResponse::Ok().json(channel),
+        Err(res) => res,
+    }
+}
+
+#[put("/channels/{id}/announcement-only")]
+async fn set_announcement_only(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    body: web::Json<ToggleRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let channel_id = match parse_id(&path.into_inner(), "channel ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    let channel = match load_channel(&service, &channel_id, &member_id, &role).await {
+        Ok(channel) => channel,
+        Err(res) => return res,
+    };
+    if let Err(res) = ensure_moderator(&channel, &member_id, &role) {
+        return res;
+    }
+
+    match service
+        .set_announcement_only(&IdType::ObjectId(channel_id), body.enabled)
+        .await
+    {
+        Ok(channel) => {
+            let cloned = channel.clone();
+            let state_clone = state.clone();
+
+            actix_rt::spawn(async move {
+                EventService::broadcast_updated(
+                    &state_clone,
+                    "chat_channel",
+                    &channel_id.to_hex(),
+                    get_school_id_from_request(&req),
+                    &cloned,
+                )
+                .await;
+            });
+
+            HttpResponse::Ok().json(channel)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/channels/{id}/messages")]
+async fn get_channel_messages(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let channel_id = match parse_id(&path.into_inner(), "channel ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    if let Err(res) = load_channel(&service, &channel_id, &member_id, &role).await {
+        return res;
+    }
+
+    match service
+        .get_messages(&channel_id, query.limit, query.skip)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/channels/{id}/messages")]
+async fn post_channel_message(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    body: web::Json<PostChatMessageRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let channel_id = match parse_id(&path.into_inner(), "channel ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    let channel = match load_channel(&service, &channel_id, &member_id, &role).await {
+        Ok(channel) => channel,
+        Err(res) => return res,
+    };
+
+    if let Err(err) = service.ensure_can_post(&channel, &member_id, &role).await {
+        return HttpResponse::Forbidden().json(err);
+    }
+
+    let sender = ActorRef { id: member_id, role };
+    match service
+        .post_message(&channel, sender, Some(user.name.clone()), &body.content)
+        .await
+    {
+        Ok(message) => {
+            let cloned = message.clone();
+            let state_clone = state.clone();
+
+            actix_rt::spawn(async move {
+                if let Some(id) = cloned.id {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "chat_message",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &cloned,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Created().json(message)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/channels/{id}/messages/{message_id}")]
+async fn delete_channel_message(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_chat_enabled(&req, &state).await {
+        return res;
+    }
+
+    let (member_id, role) = match current_member(&user) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let (channel_id, message_id) = path.into_inner();
+    let channel_id = match parse_id(&channel_id, "channel ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let message_id = match parse_id(&message_id, "message ID") {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = ChatService::new(&db);
+
+    let channel = match load_channel(&service, &channel_id, &member_id, &role).await {
+        Ok(channel) => channel,
+        Err(res) => return res,
+    };
+
+    let message = match service.find_message(&channel_id, &message_id).await {
+        Ok(message) => message,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+
+    // Senders delete their own messages, moderators delete anything
+    if message.sender.id != member_id {
+        if let Err(res) = ensure_moderator(&channel, &member_id, &role) {
+            return res;
+        }
+    }
+
+    match service.delete_message(&message_id).await {
+        Ok(_) => {
+            let cloned = message.clone();
+            let state_clone = state.clone();
+
+            actix_rt::spawn(async move {
+                EventService::broadcast_deleted(
+                    &state_clone,
+                    "chat_message",
+                    &message_id.to_hex(),
+                    get_school_id_from_request(&req),
+                    &cloned,
+                )
+                .await;
+            });
+
+            HttpResponse::Ok().json(message)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_my_channels)
+            .service(get_class_channels)
+            .service(sync_class_channels)
+            .service(set_student_chat)
+            .service(get_channel)
+            .service(set_announcement_only)
+            .service(get_channel_messages)
+            .service(post_channel_message)
+            .service(delete_channel_message),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "chat", blueprint);
+}
@@ -12,7 +12,9 @@ use crate::{
     errors::AppError,
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
-    services::{class_service::ClassService, event_service::EventService},
+    services::{
+        chat_service::ChatService, class_service::ClassService, event_service::EventService,
+    },
     utils::{
         api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
     },
@@ -193,6 +195,9 @@ async fn update_class(
 
     match service.update(&id, &data.into_inner()).await {
         Ok(class) => {
+            // The class teacher sits in every channel of the class
+            ChatService::refresh_classes(&db, class.id);
+
             let class_clone = class.clone();
             let state_clone = state.clone();
 
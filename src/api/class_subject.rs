@@ -5,7 +5,10 @@ use crate::{
     domain::class_subject::{ClassSubject, ClassSubjectPartial},
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
-    services::{class_subject_service::ClassSubjectService, event_service::EventService},
+    services::{
+        chat_service::ChatService, class_subject_service::ClassSubjectService,
+        event_service::EventService,
+    },
     utils::{api_utils::build_extra_match, db_utils::get_database},
 };
 
@@ -162,6 +165,8 @@ async fn create_class_subject(
 
     match service.create(data.into_inner()).await {
         Ok(subject) => {
+            ChatService::refresh_classes(&db, subject.class_id);
+
             // Broadcast event
             let clone = subject.clone();
             let state_clone = state.clone();
@@ -196,8 +201,17 @@ async fn update_class_subject(
     let service = ClassSubjectService::new(&db);
 
     let id = IdType::from_string(path.into_inner());
+    // Teacher and class changes move the subject channel's members
+    let previous_class_id = service
+        .find_one(Some(&id), None)
+        .await
+        .ok()
+        .and_then(|s| s.class_id);
+
     match service.update_subject(&id, &data.into_inner()).await {
         Ok(subject) => {
+            ChatService::refresh_classes(&db, previous_class_id.into_iter().chain(subject.class_id));
+
             // event
             let clone = subject.clone();
             let state_clone = state.clone();
@@ -233,6 +247,8 @@ async fn delete_class_subject(
 
     match service.delete_subject(&id).await {
         Ok(subject) => {
+            ChatService::refresh_classes(&db, subject.class_id);
+
             let state_clone = state.clone();
             let clone = subject.clone();
             actix_rt::spawn(async move {
@@ -0,0 +1,383 @@
+use actix_multipart::Multipart;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use futures::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    api::academic_record_api::authorize_student,
+    config::state::AppState,
+    domain::{
+        audit_log::AuditSeverity,
+        auth_user::AuthUserDto,
+        competency::{CompetencyAssessment, CreateCompetencyAssessment, UpdateCompetencyAssessment},
+    },
+    guards::role_guard::{check_admin_or_staff, check_admin_staff_or_teacher, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{
+        audit_log_service::AuditLogService, competency_service::CompetencyService,
+        event_service::EventService, role_service::RoleService,
+    },
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> CompetencyService {
+    CompetencyService::new(&get_database(req, state), &state.db.main_db())
+}
+
+fn broadcast_assessment(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    assessment: &CompetencyAssessment,
+    created: bool,
+) {
+    let assessment_clone = assessment.clone();
+    let state_clone = state.clone();
+    let school_id = get_school_id_from_request(req);
+    actix_rt::spawn(async move {
+        if let Some(id) = assessment_clone.id {
+            if created {
+                EventService::broadcast_created(
+                    &state_clone,
+                    "competency_assessment",
+                    &id.to_hex(),
+                    school_id,
+                    &assessment_clone,
+                )
+                .await;
+            } else {
+                EventService::broadcast_updated(
+                    &state_clone,
+                    "competency_assessment",
+                    &id.to_hex(),
+                    school_id,
+                    &assessment_clone,
+                )
+                .await;
+            }
+        }
+    });
+}
+
+#[get("")]
+async fn get_assessments(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_all(query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_assessment(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_one(&id).await {
+        Ok(assessment) => HttpResponse::Ok().json(assessment),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("")]
+async fn create_assessment(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<CreateCompetencyAssessment>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let assessor_id = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+
+    match service(&req, &state)
+        .create(school_id, data.into_inner(), assessor_id)
+        .await
+    {
+        Ok(assessment) => {
+            broadcast_assessment(&req, &state, &assessment, true);
+            HttpResponse::Created().json(assessment)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_assessment(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpdateCompetencyAssessment>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).update(&id, data.into_inner()).await {
+        Ok(assessment) => {
+            broadcast_assessment(&req, &state, &assessment, false);
+            HttpResponse::Ok().json(assessment)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_assessment(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).delete(&id).await {
+        Ok(assessment) => HttpResponse::Ok().json(assessment),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Multipart upload: a "file" field and an optional "description" text field
+#[post("/{id}/evidence")]
+async fn upload_evidence(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    mut payload: Multipart,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let uploader_id = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let mut file_bytes: Option<Vec<u8>> = None;
+    let mut file_name: Option<String> = None;
+    let mut description: Option<String> = None;
+
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(f) => f,
+            Err(e) => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "message": format!("Multipart error: {}", e) }))
+            }
+        };
+
+        let is_file = field.name() == Some("file");
+        if is_file {
+            file_name = field
+                .content_disposition()
+                .and_then(|cd| cd.get_filename())
+                .map(|name| name.to_string());
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            match chunk {
+                Ok(d) => bytes.extend_from_slice(&d),
+                Err(e) => {
+                    return HttpResponse::BadRequest()
+                        .json(serde_json::json!({ "message": format!("Read error: {}", e) }))
+                }
+            }
+        }
+
+        if is_file {
+            file_bytes = Some(bytes);
+        } else if field.name() == Some("description") {
+            description = String::from_utf8(bytes).ok().filter(|d| !d.trim().is_empty());
+        }
+    }
+
+    let Some(file_bytes) = file_bytes.filter(|b| !b.is_empty()) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "Evidence file is required" }));
+    };
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state)
+        .add_evidence(
+            &id,
+            file_bytes,
+            file_name.as_deref().unwrap_or("evidence"),
+            description,
+            uploader_id,
+        )
+        .await
+    {
+        Ok(assessment) => {
+            broadcast_assessment(&req, &state, &assessment, false);
+            HttpResponse::Ok().json(assessment)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Assessors sign off their own judgements; signing off someone else's
+/// needs the competency.sign_off permission
+#[post("/{id}/sign-off")]
+async fn sign_off_assessment(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let signer_id = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+
+    let assessment = match service.find_one(&id).await {
+        Ok(a) => a,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+    if assessment.assessed_by != signer_id {
+        let Some(school_id) = get_school_id_from_request(&req) else {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "message": "School ID required" }));
+        };
+        let role_service = RoleService::new(&get_database(&req, &state));
+        if let Err(e) =
+            require_permission(&user, &school_id, "competency.sign_off", &role_service).await
+        {
+            return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+        }
+    }
+
+    match service.sign_off(&id, signer_id).await {
+        Ok(assessment) => {
+            let school_id =
+                get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+            if let (Some(school_id), Some(assessment_id)) = (school_id, assessment.id) {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "competency.sign_off",
+                        "competency_assessment",
+                        assessment_id,
+                        Some(doc! {
+                            "student_id": assessment.student_id,
+                            "class_subject_id": assessment.class_subject_id,
+                            "attempt": assessment.attempt,
+                            "result": mongodb::bson::to_bson(&assessment.result).unwrap()
+                        }),
+                        None,
+                        Some(AuditSeverity::INFO),
+                    )
+                    .await
+                    .ok();
+            }
+
+            broadcast_assessment(&req, &state, &assessment, false);
+            HttpResponse::Ok().json(assessment)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Module and trade competency of one student, from signed-off assessments
+#[get("/students/{student_id}/report")]
+async fn get_student_report(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (student, _) = match authorize_student(&req, &state, &user, &path.into_inner()).await {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state).student_report_for(&student).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/classes/{class_id}/report")]
+async fn get_class_report(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let class_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match service(&req, &state).class_report(class_id).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_assessments)
+            .service(create_assessment)
+            .service(get_student_report)
+            .service(get_class_report)
+            .service(get_assessment)
+            .service(update_assessment)
+            .service(delete_assessment)
+            .service(upload_evidence)
+            .service(sign_off_assessment),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "competency-assessments", blueprint);
+}
@@ -1,21 +1,25 @@
 use std::str::FromStr;
 
-use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use actix_web::{get, post, put, web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     config::state::AppState,
     domain::{
+        audit_log::AuditSeverity,
         auth_user::AuthUserDto,
         common_details::UserRole,
-        conversation::{Conversation, ConversationKey},
+        conversation::{Conversation, ConversationKey, KeyRotationTrigger},
     },
     errors::AppError,
     middleware::school_token_middleware::OptionalSchoolTokenMiddleware,
     models::{id_model::IdType, school_token_model::SchoolToken},
     schema::common_schema::ActorRef,
-    services::conversation_service::ConversationService,
+    services::{
+        audit_log_service::AuditLogService, conversation_service::ConversationService,
+        event_service::EventService, safeguarding_service::SafeguardingService,
+    },
     utils::db_utils::get_database,
 };
 
@@ -25,6 +29,25 @@ struct CreateConversationRequest {
     is_group: bool,
     name: Option<String>,
     encrypted_keys: Vec<EncryptedKeyForUser>,
+    key_rotation_interval_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct RotateKeyRequest {
+    encrypted_keys: Vec<EncryptedKeyForUser>,
+    reason: Option<String>,
+    #[serde(default)]
+    trigger: KeyRotationTrigger,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyRotationPolicyRequest {
+    key_rotation_interval_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyQueryParams {
+    version: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -40,6 +63,58 @@ struct QueryParams {
     limit: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OversightQueryParams {
+    student_id: Option<String>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// Ensure exactly one well-formed encrypted key is provided for every participant
+fn validate_encrypted_keys(
+    participants: &[ActorRef],
+    encrypted_keys: &[EncryptedKeyForUser],
+) -> Result<(), HttpResponse> {
+    if encrypted_keys.len() != participants.len() {
+        return Err(HttpResponse::BadRequest().json(AppError {
+            message: format!(
+                "Must provide exactly one encrypted key per participant. Expected {}, got {}",
+                participants.len(),
+                encrypted_keys.len()
+            ),
+        }));
+    }
+
+    for key_data in encrypted_keys {
+        let matching_participant = participants
+            .iter()
+            .find(|p| p.id.to_hex() == key_data.user_id && p.role == key_data.user_role);
+
+        if matching_participant.is_none() {
+            return Err(HttpResponse::BadRequest().json(AppError {
+                message: format!(
+                    "Encrypted key for user {} with role {:?} does not match any participant",
+                    key_data.user_id, key_data.user_role
+                ),
+            }));
+        }
+
+        // Validate base64 format
+        if base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            &key_data.encrypted_key,
+        )
+        .is_err()
+        {
+            return Err(HttpResponse::UnprocessableEntity().json(AppError {
+                message: "Invalid encrypted key format. Must be valid base64.".to_string(),
+            }));
+        }
+    }
+
+    Ok(())
+}
+
 #[post("")]
 async fn create_conversation(
     req: HttpRequest,
@@ -143,47 +218,72 @@ async fn create_conversation(
         }
     }
 
-    // Validate encrypted keys match participants
-    if encrypted_keys.len() != participants.len() {
-        return HttpResponse::BadRequest().json(AppError {
-            message: format!(
-                "Must provide exactly one encrypted key per participant. Expected {}, got {}",
-                participants.len(),
-                encrypted_keys.len()
-            ),
-        });
+    if let Some(days) = body.key_rotation_interval_days {
+        if !(1..=365).contains(&days) {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Key rotation interval must be between 1 and 365 days".to_string(),
+            });
+        }
     }
 
-    // Validate each encrypted key matches a participant
-    for key_data in &encrypted_keys {
-        let matching_participant = participants
-            .iter()
-            .find(|p| p.id.to_hex() == key_data.user_id && p.role == key_data.user_role);
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
 
-        if matching_participant.is_none() {
-            return HttpResponse::BadRequest().json(AppError {
-                message: format!(
-                    "Encrypted key for user {} with role {:?} does not match any participant",
-                    key_data.user_id, key_data.user_role
-                ),
-            });
+    // Safeguarding rules are per school; every rule concerns students, so
+    // students can only be messaged inside their school
+    let mut observers = vec![];
+    let safeguarding = SafeguardingService::new(&db);
+    if school_id.is_none() {
+        match safeguarding.involves_student(&participants).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return HttpResponse::Forbidden().json(AppError {
+                    message: "Conversations with students must be started inside their school"
+                        .to_string(),
+                })
+            }
+            Err(err) => return HttpResponse::BadRequest().json(err),
         }
+    }
+    if let Some(school_id) = school_id {
+        let policy = match safeguarding.get_policy(&school_id).await {
+            Ok(policy) => policy,
+            Err(err) => return HttpResponse::BadRequest().json(err),
+        };
 
-        // Validate base64 format
-        if base64::Engine::decode(
-            &base64::engine::general_purpose::STANDARD,
-            &key_data.encrypted_key,
-        )
-        .is_err()
+        let creator = ActorRef {
+            id: auth_user_object_id,
+            role: auth_user_role.clone(),
+        };
+        if let Err(err) = safeguarding
+            .check_new_conversation(&policy, &creator, &participants)
+            .await
         {
-            return HttpResponse::UnprocessableEntity().json(AppError {
-                message: "Invalid encrypted key format. Must be valid base64.".to_string(),
-            });
+            return HttpResponse::Forbidden().json(err);
+        }
+
+        observers = match safeguarding.required_observers(&policy, &participants).await {
+            Ok(observers) => observers,
+            Err(err) => return HttpResponse::BadRequest().json(err),
+        };
+
+        let missing_observer_key = observers.iter().any(|o| {
+            !encrypted_keys
+                .iter()
+                .any(|k| k.user_id == o.id.to_hex() && k.user_role == o.role)
+        });
+        if missing_observer_key {
+            return HttpResponse::UnprocessableEntity().json(serde_json::json!({
+                "message": "Encrypted keys are required for the parents overseeing this conversation",
+                "required_observers": observers,
+            }));
         }
     }
 
-    let db = get_database(&req, &state);
-    let service = ConversationService::new(&db);
+    let key_holders: Vec<ActorRef> = participants.iter().chain(observers.iter()).cloned().collect();
+    if let Err(response) = validate_encrypted_keys(&key_holders, &encrypted_keys) {
+        return response;
+    }
 
     // Check for duplicate 1-on-1 conversations
     if !body.is_group {
@@ -207,9 +307,12 @@ async fn create_conversation(
         id: None,
         school_id,
         participants: participants.clone(),
+        observers,
         is_group: body.is_group,
         name: body.name.clone(),
         encryption_key_version: 1,
+        key_rotation_interval_days: body.key_rotation_interval_days,
+        last_key_rotated_at: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
@@ -238,6 +341,7 @@ async fn create_conversation(
             user_id,
             user_role: key_data.user_role.clone(),
             encrypted_key_for_user: key_data.encrypted_key.clone(),
+            key_version: 1,
             created_at: chrono::Utc::now(),
         };
 
@@ -328,9 +432,9 @@ async fn get_conversation(
         }
     };
 
-    // Fetch conversation with participant check in one query
+    // Fetch conversation with participant (or read-only observer) check in one query
     let extra_match = doc! {
-        "participants.id": auth_user_id
+        "$or": [{ "participants.id": auth_user_id }, { "observers.id": auth_user_id }]
     };
 
     let conversation = match service
@@ -357,6 +461,7 @@ async fn get_conversation_key(
     user: web::ReqData<AuthUserDto>,
     state: web::Data<AppState>,
     path: web::Path<String>,
+    query: web::Query<KeyQueryParams>,
 ) -> impl Responder {
     let auth_user = user.into_inner();
 
@@ -383,7 +488,7 @@ async fn get_conversation_key(
 
     // Get key directly - if it doesn't exist, user is not a participant
     let key = match service
-        .get_conversation_key(conversation_id, auth_user_id)
+        .get_conversation_key(conversation_id, auth_user_id, query.version)
         .await
     {
         Ok(k) => k,
@@ -400,6 +505,416 @@ async fn get_conversation_key(
     HttpResponse::Ok().json(key)
 }
 
+#[get("/{id}/keys")]
+async fn get_conversation_keys(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    let conversation_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid conversation ID".to_string(),
+            })
+        }
+    };
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    // All key versions held by the caller, so older messages stay decryptable
+    match service
+        .get_conversation_keys(conversation_id, auth_user_id)
+        .await
+    {
+        Ok(keys) if keys.is_empty() => HttpResponse::Forbidden().json(AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        }),
+        Ok(keys) => HttpResponse::Ok().json(serde_json::json!({ "keys": keys })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/rotate-key")]
+async fn rotate_conversation_key(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<RotateKeyRequest>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    let conversation_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid conversation ID".to_string(),
+            })
+        }
+    };
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    let conversation = match service
+        .find_one(
+            Some(&IdType::ObjectId(conversation_id)),
+            Some(doc! { "participants.id": auth_user_id }),
+        )
+        .await
+    {
+        Ok(conv) => conv,
+        Err(_) => {
+            return HttpResponse::Forbidden().json(AppError {
+                message: "You are not a participant in this conversation".to_string(),
+            })
+        }
+    };
+
+    if let Err(response) = validate_encrypted_keys(&conversation.key_holders(), &body.encrypted_keys) {
+        return response;
+    }
+
+    let mut new_keys = Vec::with_capacity(body.encrypted_keys.len());
+    for key_data in &body.encrypted_keys {
+        let user_id = match ObjectId::parse_str(&key_data.user_id) {
+            Ok(id) => id,
+            Err(_) => {
+                return HttpResponse::BadRequest().json(AppError {
+                    message: "Invalid user ID in encrypted_keys".to_string(),
+                })
+            }
+        };
+
+        new_keys.push(ConversationKey {
+            id: None,
+            conversation_id,
+            user_id,
+            user_role: key_data.user_role.clone(),
+            encrypted_key_for_user: key_data.encrypted_key.clone(),
+            key_version: conversation.encryption_key_version + 1,
+            created_at: chrono::Utc::now(),
+        });
+    }
+
+    let rotated_by = ActorRef {
+        id: auth_user_id,
+        role: auth_user.role.clone().unwrap_or(UserRole::STUDENT),
+    };
+
+    let (updated, rotation) = match service
+        .rotate_key(
+            &conversation,
+            new_keys,
+            rotated_by,
+            body.trigger,
+            body.reason.clone(),
+        )
+        .await
+    {
+        Ok(result) => result,
+        Err(err) => return HttpResponse::Conflict().json(err),
+    };
+
+    // Cross-school conversations have no school of their own; their rotations
+    // are audited under the school the caller is working in
+    let audit_school_id = updated
+        .school_id
+        .or_else(|| {
+            req.extensions()
+                .get::<SchoolToken>()
+                .and_then(|token| ObjectId::from_str(&token.id).ok())
+        })
+        .or_else(|| {
+            auth_user
+                .current_school_id
+                .as_deref()
+                .and_then(|id| ObjectId::from_str(id).ok())
+        });
+
+    if let Some(school_id) = audit_school_id {
+        let audit_service = AuditLogService::new(&state.db.main_db());
+        audit_service
+            .log_event(
+                school_id,
+                &auth_user,
+                "conversation.key_rotate",
+                "conversation",
+                conversation_id,
+                Some(doc! {
+                    "from_version": rotation.from_version,
+                    "to_version": rotation.to_version,
+                    "trigger": mongodb::bson::to_bson(&rotation.trigger).ok(),
+                    "reason": rotation.reason.clone(),
+                }),
+                None,
+                Some(AuditSeverity::INFO),
+            )
+            .await
+            .ok();
+    }
+
+    let updated_clone = updated.clone();
+    let state_clone = state.clone();
+    actix_rt::spawn(async move {
+        EventService::broadcast_updated(
+            &state_clone,
+            "conversation",
+            &conversation_id.to_hex(),
+            updated_clone.school_id.map(|id| id.to_hex()),
+            &updated_clone,
+        )
+        .await;
+    });
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "conversation": updated,
+        "rotation": rotation
+    }))
+}
+
+#[get("/{id}/key-rotations")]
+async fn get_key_rotations(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    let conversation_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid conversation ID".to_string(),
+            })
+        }
+    };
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    match service.is_participant(conversation_id, auth_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().json(AppError {
+                message: "You are not a participant in this conversation".to_string(),
+            })
+        }
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    }
+
+    match service.get_key_rotations(conversation_id).await {
+        Ok(rotations) => HttpResponse::Ok().json(serde_json::json!({ "rotations": rotations })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}/key-rotation-policy")]
+async fn update_key_rotation_policy(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<KeyRotationPolicyRequest>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    let conversation_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid conversation ID".to_string(),
+            })
+        }
+    };
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    match service.is_participant(conversation_id, auth_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().json(AppError {
+                message: "You are not a participant in this conversation".to_string(),
+            })
+        }
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    }
+
+    match service
+        .update_key_rotation_interval(conversation_id, body.key_rotation_interval_days)
+        .await
+    {
+        Ok(conversation) => HttpResponse::Ok().json(conversation),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Conversations whose scheduled key rotation is due. Clients poll this and
+/// perform the rotation, since only they can encrypt the new key per member.
+#[get("/rotation-due")]
+async fn get_rotation_due(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    let school_id = req
+        .extensions()
+        .get::<SchoolToken>()
+        .and_then(|token| ObjectId::from_str(&token.id).ok());
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    let extra_match = match school_id {
+        Some(school_id) => doc! { "school_id": school_id },
+        None => doc! { "school_id": { "$exists": false } },
+    };
+
+    match service.get_rotation_due(auth_user_id, Some(extra_match)).await {
+        Ok(conversations) => {
+            HttpResponse::Ok().json(serde_json::json!({ "conversations": conversations }))
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Conversations a parent can read under the school's oversight policy
+#[get("/oversight")]
+async fn get_oversight_conversations(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+    query: web::Query<OversightQueryParams>,
+) -> impl Responder {
+    let auth_user = user.into_inner();
+
+    if auth_user.role != Some(UserRole::PARENT) {
+        return HttpResponse::Forbidden().json(AppError {
+            message: "Only parents can view overseen conversations".to_string(),
+        });
+    }
+
+    let school_id = match req
+        .extensions()
+        .get::<SchoolToken>()
+        .and_then(|token| ObjectId::from_str(&token.id).ok())
+    {
+        Some(id) => id,
+        None => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "School context is required".to_string(),
+            })
+        }
+    };
+
+    let auth_user_id = match ObjectId::parse_str(&auth_user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(AppError {
+                message: "Invalid user ID".to_string(),
+            })
+        }
+    };
+
+    let page = query.page.unwrap_or(1);
+    let limit = query.limit.unwrap_or(20);
+    let skip = (page - 1) * limit;
+
+    let db = get_database(&req, &state);
+    let service = ConversationService::new(&db);
+
+    let mut extra_match = doc! {
+        "school_id": school_id,
+        "observers.id": auth_user_id,
+    };
+
+    if let Some(student_id) = &query.student_id {
+        let parent_service = crate::services::parent_service::ParentService::new(&db);
+        if let Err(e) = crate::guards::role_guard::require_parent_child_access(
+            &auth_user,
+            student_id,
+            &parent_service,
+        )
+        .await
+        {
+            return HttpResponse::Forbidden().json(AppError { message: e });
+        }
+
+        match ObjectId::parse_str(student_id) {
+            Ok(id) => {
+                extra_match.insert("participants.id", id);
+            }
+            Err(_) => {
+                return HttpResponse::BadRequest().json(AppError {
+                    message: "Invalid student ID".to_string(),
+                })
+            }
+        }
+    }
+
+    match service
+        .get_all_with_relations(Some(limit), Some(skip), Some(extra_match))
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 fn blueprint(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("")
@@ -407,8 +922,14 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
             .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
             .service(create_conversation)
             .service(get_conversations)
+            .service(get_rotation_due)
+            .service(get_oversight_conversations)
             .service(get_conversation)
-            .service(get_conversation_key),
+            .service(get_conversation_key)
+            .service(get_conversation_keys)
+            .service(rotate_conversation_key)
+            .service(get_key_rotations)
+            .service(update_key_rotation_policy),
     );
 }
 
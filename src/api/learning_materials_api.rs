@@ -1,16 +1,54 @@
 use actix_multipart::Multipart;
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use futures::StreamExt;
-use mongodb::bson;
+use mongodb::bson::{doc, oid::ObjectId, Document};
 
 use crate::{
     config::state::AppState, domain::{
         auth_user::AuthUserDto,
         common_details::UserRole,
-        learning_material::{LearningMaterial, LearningMaterialPartial},
-    }, guards::role_guard::check_admin_staff_or_teacher, helpers::event_helpers::get_school_id_from_request, models::{api_request_model::RequestQuery, id_model::IdType}, services::{event_service::EventService, learning_material_service::LearningMaterialService}, utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value}
+        learning_material::{LearningMaterial, LearningMaterialPartial, RecordMaterialEvent},
+    }, guards::role_guard::{check_admin_staff_or_teacher, require_parent_child_access}, helpers::event_helpers::get_school_id_from_request, models::{api_request_model::RequestQuery, id_model::IdType}, services::{event_service::EventService, learning_material_service::LearningMaterialService, material_progress_service::MaterialProgressService, parent_service::ParentService}, utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value}
 };
 
+/// Student record of the signed-in user
+async fn current_student_id(db: &mongodb::Database, user: &AuthUserDto) -> Option<ObjectId> {
+    let user_oid = parse_object_id_value(&user.id).ok()?;
+    db.collection::<Document>("students").find_one(doc! { "user_id": user_oid }).await.ok().flatten().and_then(|s| s.get_object_id("_id").ok())
+}
+
+/// Materials the student has completed, for prerequisite checks
+async fn student_completions(db: &mongodb::Database, user: &AuthUserDto) -> Result<Vec<ObjectId>, HttpResponse> {
+    match current_student_id(db, user).await {
+        Some(student_id) => LearningMaterialService::new(db).completed_ids(student_id).await.map_err(|err| HttpResponse::BadRequest().json(err)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Students see released materials whose prerequisite they completed;
+/// parents see released materials
+async fn visibility_filter(db: &mongodb::Database, user: &AuthUserDto) -> Result<Option<Document>, HttpResponse> {
+    match user.role {
+        Some(UserRole::STUDENT) => {
+            let completed = student_completions(db, user).await?;
+            Ok(Some(LearningMaterialService::visible_filter(Some(&completed))))
+        }
+        Some(UserRole::PARENT) => Ok(Some(LearningMaterialService::visible_filter(None))),
+        _ => Ok(None),
+    }
+}
+
+/// Whether a student or parent may open this material yet
+async fn check_available(db: &mongodb::Database, user: &AuthUserDto, material: &LearningMaterial) -> Result<(), HttpResponse> {
+    let completed = match user.role {
+        Some(UserRole::STUDENT) => student_completions(db, user).await?,
+        // Parents are not held back by their child's progress
+        Some(UserRole::PARENT) => material.prerequisite_id.into_iter().collect(),
+        _ => return Ok(()),
+    };
+    material.is_available(&completed).map_err(|message| HttpResponse::Forbidden().json(serde_json::json!({"message": message})))
+}
+
 #[get("")]
 async fn get_all_materials(
     req: HttpRequest,
@@ -26,8 +64,10 @@ async fn get_all_materials(
         Err(err) => return err,
     }.unwrap_or_default();
 
-    if matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT)) {
-        extra_match.insert("is_published", bson::to_bson(&true).unwrap());
+    match visibility_filter(&db, &user).await {
+        Ok(Some(visible)) => extra_match.extend(visible),
+        Ok(None) => {}
+        Err(err) => return err,
     }
 
     match service.get_all(query.filter.clone(), query.limit, query.skip, extra_match).await {
@@ -46,14 +86,15 @@ async fn get_all_materials_with_relations(
     let db = get_database(&req, &state);
     let service = LearningMaterialService::new(&db);
 
-    use mongodb::bson;
     let mut extra_match = match build_extra_match(&query) {
         Ok(doc) => doc,
         Err(err) => return err,
     }.unwrap_or_default();
 
-    if matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT)) {
-        extra_match.insert("is_published", bson::to_bson(&true).unwrap());
+    match visibility_filter(&db, &user).await {
+        Ok(Some(visible)) => extra_match.extend(visible),
+        Ok(None) => {}
+        Err(err) => return err,
     }
 
     match service.get_all_with_relations(query.filter.clone(), query.limit, query.skip, extra_match).await {
@@ -75,8 +116,8 @@ async fn get_material_by_id(
 
     match service.find_one(Some(&id), None).await {
         Ok(material) => {
-            if !material.is_published && matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT)) {
-                return HttpResponse::Forbidden().json(serde_json::json!({"message": "Access denied"}));
+            if let Err(err) = check_available(&db, &user, &material).await {
+                return err;
             }
             HttpResponse::Ok().json(material)
         }
@@ -97,8 +138,8 @@ async fn get_material_by_id_with_relations(
 
     match service.find_one_with_relations(Some(&id), None).await {
         Ok(data) => {
-            if !data.learning_material.is_published && matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT)) {
-                return HttpResponse::Forbidden().json(serde_json::json!({"message": "Access denied"}));
+            if let Err(err) = check_available(&db, &user, &data.learning_material).await {
+                return err;
             }
             HttpResponse::Ok().json(data)
         }
@@ -298,6 +339,127 @@ async fn delete_material(
     }
 }
 
+/// Students mark a material as done, unlocking what depends on it
+#[post("/{id}/complete")]
+async fn complete_material(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if !matches!(user.role, Some(UserRole::STUDENT)) {
+        return HttpResponse::Forbidden().json(serde_json::json!({"message": "Only students can complete materials"}));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({"message": "Student record not found for this user"})),
+    };
+
+    match LearningMaterialService::new(&db).mark_complete(&id, student_id).await {
+        Ok(completion) => HttpResponse::Ok().json(completion),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Students report opening, downloading or finishing a material, with
+/// the time spent on videos
+#[post("/{id}/events")]
+async fn record_material_event(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<RecordMaterialEvent>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if !matches!(user.role, Some(UserRole::STUDENT)) {
+        return HttpResponse::Forbidden().json(serde_json::json!({"message": "Only student activity is tracked"}));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({"message": "Student record not found for this user"})),
+    };
+
+    match MaterialProgressService::new(&db).record_event(&id, student_id, data.into_inner()).await {
+        Ok(engagement) => HttpResponse::Ok().json(engagement),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Who in the class has opened or completed the material, and who has not
+#[get("/{id}/engagement")]
+async fn get_material_engagement(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({"message": err}));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    match MaterialProgressService::new(&db).material_report(&id).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// The signed-in student's progress per subject
+#[get("/progress")]
+async fn get_my_progress(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Some(id) => id,
+        None => return HttpResponse::BadRequest().json(serde_json::json!({"message": "Student record not found for this user"})),
+    };
+
+    match MaterialProgressService::new(&db).student_progress(student_id).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Engagement summary of a student, for their teachers and parents
+#[get("/progress/students/{student_id}")]
+async fn get_student_progress(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let student_id = path.into_inner();
+    let db = get_database(&req, &state);
+
+    let allowed = if matches!(user.role, Some(UserRole::PARENT)) {
+        require_parent_child_access(&user, &student_id, &ParentService::new(&db)).await
+    } else {
+        check_admin_staff_or_teacher(&user)
+    };
+    if let Err(err) = allowed {
+        return HttpResponse::Forbidden().json(serde_json::json!({"message": err}));
+    }
+
+    let student_id = match IdType::from_string(student_id).to_object_id() {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    match MaterialProgressService::new(&db).summary(student_id).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 #[get("/count")]
 async fn count_materials(
     req: HttpRequest,
@@ -322,6 +484,8 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("")
             .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_my_progress)
+            .service(get_student_progress)
             .service(get_all_materials)
             .service(get_all_materials_with_relations)
             .service(get_material_by_id)
@@ -329,6 +493,9 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
             .service(create_material)
             .service(update_material)
             .service(delete_material)
+            .service(complete_material)
+            .service(record_material_event)
+            .service(get_material_engagement)
             .service(count_materials),
     );
 }
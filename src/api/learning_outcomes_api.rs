@@ -0,0 +1,401 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        class_subject::ClassSubject,
+        common_details::UserRole,
+        learning_outcome::{
+            LearningOutcome, LearningOutcomePartial, SubjectTopic, SubjectTopicPartial, TopicLinks,
+        },
+    },
+    guards::role_guard::check_admin_staff_or_teacher,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{event_service::EventService, learning_outcome_service::LearningOutcomeService},
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+/// Requests without a school token work on template subjects in the main
+/// database, school requests on class subjects
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> LearningOutcomeService {
+    LearningOutcomeService::new(&get_database(req, state))
+}
+
+fn broadcast<T: serde::Serialize + Send + 'static>(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    entity: &'static str,
+    id: Option<ObjectId>,
+    data: T,
+    created: bool,
+) {
+    let state_clone = state.clone();
+    let school_id = get_school_id_from_request(req);
+    actix_rt::spawn(async move {
+        if let Some(id) = id {
+            if created {
+                EventService::broadcast_created(&state_clone, entity, &id.to_hex(), school_id, &data)
+                    .await;
+            } else {
+                EventService::broadcast_updated(&state_clone, entity, &id.to_hex(), school_id, &data)
+                    .await;
+            }
+        }
+    });
+}
+
+#[get("")]
+async fn get_outcomes(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_outcomes(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/topics")]
+async fn get_topics(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_topics(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Outcome tree of a subject with the materials and assignments of every
+/// topic. Students and parents only see published resources.
+#[get("/subjects/{subject_id}/tree")]
+async fn get_outcome_tree(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let subject_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let published_only = matches!(user.role, Some(UserRole::STUDENT) | Some(UserRole::PARENT));
+
+    match service(&req, &state)
+        .get_outcome_tree(subject_id, published_only)
+        .await
+    {
+        Ok(tree) => HttpResponse::Ok().json(tree),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Copy the outcomes of the template a class subject was created from
+#[post("/subjects/{class_subject_id}/copy-template")]
+async fn copy_template_outcomes(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let class_subject_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let subject = match db
+        .collection::<ClassSubject>("class_subjects")
+        .find_one(doc! { "_id": class_subject_id })
+        .await
+    {
+        Ok(Some(subject)) => subject,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "message": "Class subject not found" }))
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "message": e.to_string() }))
+        }
+    };
+    let Some(template_subject_id) = subject.main_subject_id else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "Class subject was not created from a template" }));
+    };
+
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+    let created_by = parse_object_id_value(&user.id).ok();
+    let template_service = LearningOutcomeService::new(&state.db.main_db());
+
+    match LearningOutcomeService::new(&db)
+        .copy_from_template(
+            &template_service,
+            template_subject_id,
+            class_subject_id,
+            school_id.or(subject.school_id),
+            created_by,
+        )
+        .await
+    {
+        Ok(created) => HttpResponse::Created().json(created),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_outcome(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_outcome(&id).await {
+        Ok(outcome) => HttpResponse::Ok().json(outcome),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("")]
+async fn create_outcome(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<LearningOutcome>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let mut dto = data.into_inner();
+    dto.created_by = parse_object_id_value(&user.id).ok();
+    if dto.school_id.is_none() {
+        dto.school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+    }
+
+    match service(&req, &state).create_outcome(dto).await {
+        Ok(outcome) => {
+            broadcast(&req, &state, "learning_outcome", outcome.id, outcome.clone(), true);
+            HttpResponse::Created().json(outcome)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_outcome(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<LearningOutcomePartial>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).update_outcome(&id, &data).await {
+        Ok(outcome) => {
+            broadcast(&req, &state, "learning_outcome", outcome.id, outcome.clone(), false);
+            HttpResponse::Ok().json(outcome)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_outcome(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).delete_outcome(&id).await {
+        Ok(outcome) => {
+            let state_clone = state.clone();
+            let school_id = get_school_id_from_request(&req);
+            let outcome_clone = outcome.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = outcome_clone.id {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "learning_outcome",
+                        &id.to_hex(),
+                        school_id,
+                        &outcome_clone,
+                    )
+                    .await;
+                }
+            });
+            HttpResponse::Ok().json(outcome)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/topics/{id}")]
+async fn get_topic(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_topic(&id).await {
+        Ok(topic) => HttpResponse::Ok().json(topic),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/topics")]
+async fn create_topic(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<SubjectTopic>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    match service(&req, &state).create_topic(data.into_inner()).await {
+        Ok(topic) => {
+            broadcast(&req, &state, "subject_topic", topic.id, topic.clone(), true);
+            HttpResponse::Created().json(topic)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/topics/{id}")]
+async fn update_topic(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SubjectTopicPartial>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).update_topic(&id, &data).await {
+        Ok(topic) => {
+            broadcast(&req, &state, "subject_topic", topic.id, topic.clone(), false);
+            HttpResponse::Ok().json(topic)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/topics/{id}")]
+async fn delete_topic(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).delete_topic(&id).await {
+        Ok(topic) => HttpResponse::Ok().json(topic),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Attach materials and assignments of the topic's subject
+#[put("/topics/{id}/links")]
+async fn link_topic_resources(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<TopicLinks>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).link_resources(&id, &data).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/topics/{id}/links")]
+async fn unlink_topic_resources(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<TopicLinks>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).unlink_resources(&id, &data).await {
+        Ok(result) => HttpResponse::Ok().json(result),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_outcomes)
+        .service(get_topics)
+        .service(get_topic)
+        .service(get_outcome)
+        .service(
+            web::scope("")
+                .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+                .service(get_outcome_tree)
+                .service(copy_template_outcomes)
+                .service(create_outcome)
+                .service(update_outcome)
+                .service(delete_outcome)
+                .service(create_topic)
+                .service(update_topic)
+                .service(delete_topic)
+                .service(link_topic_resources)
+                .service(unlink_topic_resources),
+        );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "learning-outcomes", blueprint);
+}
@@ -0,0 +1,490 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        class_subject::ClassSubject,
+        common_details::UserRole,
+        lesson_plan::{
+            CopyLessonPlan, CreateLessonPlan, LessonPlan, LessonPlanTransitionRequest,
+            SchemeOfWorkQuery, UpdateLessonPlan,
+        },
+    },
+    guards::role_guard::{check_admin_staff_or_teacher, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    schema::common_schema::ActorRef,
+    services::{
+        event_service::EventService, lesson_plan_service::LessonPlanService,
+        role_service::RoleService,
+    },
+    utils::{
+        api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
+    },
+};
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> LessonPlanService {
+    LessonPlanService::new(&get_database(req, state), &state.db.main_db())
+}
+
+fn actor(user: &AuthUserDto) -> Result<ActorRef, HttpResponse> {
+    let id = parse_object_id_value(&user.id).map_err(|err| HttpResponse::BadRequest().json(err))?;
+    Ok(ActorRef {
+        id,
+        role: user.role.clone().unwrap_or(UserRole::TEACHER),
+    })
+}
+
+/// Approval is granted to heads of studies through roles, admins always pass
+async fn ensure_permission(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    permission: &str,
+) -> Result<(), HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+    })?;
+
+    let role_service = RoleService::new(&get_database(req, state));
+    require_permission(user, &school_id, permission, &role_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+fn broadcast_plan(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    plan: &LessonPlan,
+    action: &'static str,
+) {
+    let plan_clone = plan.clone();
+    let state_clone = state.clone();
+    let school_id = get_school_id_from_request(req);
+    actix_rt::spawn(async move {
+        if let Some(id) = plan_clone.id {
+            let id = id.to_hex();
+            match action {
+                "created" => {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "lesson_plan",
+                        &id,
+                        school_id,
+                        &plan_clone,
+                    )
+                    .await
+                }
+                "deleted" => {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "lesson_plan",
+                        &id,
+                        school_id,
+                        &plan_clone,
+                    )
+                    .await
+                }
+                _ => {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "lesson_plan",
+                        &id,
+                        school_id,
+                        &plan_clone,
+                    )
+                    .await
+                }
+            }
+        }
+    });
+}
+
+/// Teachers only plan lessons for subjects they teach
+async fn ensure_subject_teacher(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    subject: &ClassSubject,
+) -> Result<(), HttpResponse> {
+    if !matches!(user.role, Some(UserRole::TEACHER)) {
+        return Ok(());
+    }
+
+    let user_oid =
+        parse_object_id_value(&user.id).map_err(|e| HttpResponse::BadRequest().json(e))?;
+    let teacher = get_database(req, state)
+        .collection::<Document>("teachers")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .and_then(|t| t.get_object_id("_id").ok());
+
+    if teacher.is_none() || teacher != subject.teacher_id {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You can only plan lessons for subjects you teach"
+        })));
+    }
+    Ok(())
+}
+
+/// Teachers only change plans they wrote themselves
+fn ensure_own_plan(user: &AuthUserDto, plan: &LessonPlan) -> Result<(), HttpResponse> {
+    if matches!(user.role, Some(UserRole::TEACHER)) && plan.created_by.to_hex() != user.id {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You can only change lesson plans you wrote"
+        })));
+    }
+    Ok(())
+}
+
+/// Plans of the school; heads of studies use `?field=status&value=SUBMITTED`
+/// for the ones waiting on them
+#[get("")]
+async fn get_lesson_plans(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_all(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Scheme of work of a class subject for a term, from its topics and
+/// timetable periods
+#[get("/schemes/{class_subject_id}")]
+async fn get_scheme_of_work(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    query: web::Query<SchemeOfWorkQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let class_subject_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let year_id = query.education_year_id.clone().map(IdType::from_string);
+
+    match service(&req, &state)
+        .scheme_of_work(class_subject_id, year_id.as_ref(), query.term_order)
+        .await
+    {
+        Ok(scheme) => HttpResponse::Ok().json(scheme),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_one(&id).await {
+        Ok(plan) => HttpResponse::Ok().json(plan),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("")]
+async fn create_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<CreateLessonPlan>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let created_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let service = service(&req, &state);
+
+    let subject = match parse_object_id_value(&data.class_subject_id) {
+        Ok(id) => match service.find_subject(id).await {
+            Ok(subject) => subject,
+            Err(err) => return HttpResponse::NotFound().json(err),
+        },
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    if let Err(res) = ensure_subject_teacher(&req, &state, &user, &subject).await {
+        return res;
+    }
+
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+    match service
+        .create(school_id, data.into_inner(), created_by)
+        .await
+    {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "created");
+            HttpResponse::Created().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpdateLessonPlan>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+    match service.find_one(&id).await {
+        Ok(plan) => {
+            if let Err(res) = ensure_own_plan(&user, &plan) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service.update(&id, data.into_inner()).await {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "updated");
+            HttpResponse::Ok().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+    match service.find_one(&id).await {
+        Ok(plan) => {
+            if let Err(res) = ensure_own_plan(&user, &plan) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service.delete(&id).await {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "deleted");
+            HttpResponse::Ok().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Teacher submits a draft to the head of studies
+#[post("/{id}/submit")]
+async fn submit_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<LessonPlanTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let plan_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let service = service(&req, &state);
+    match service.find_one(&IdType::ObjectId(plan_id)).await {
+        Ok(plan) => {
+            if let Err(res) = ensure_own_plan(&user, &plan) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service
+        .submit(plan_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "updated");
+            HttpResponse::Ok().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/approve")]
+async fn approve_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<LessonPlanTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "lesson_plans.approve").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let plan_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match service(&req, &state)
+        .approve(plan_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "updated");
+            HttpResponse::Ok().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Send a plan back to the teacher, a note is required
+#[post("/{id}/return")]
+async fn return_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<LessonPlanTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "lesson_plans.approve").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let plan_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match service(&req, &state)
+        .return_to_draft(plan_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(plan) => {
+            broadcast_plan(&req, &state, &plan, "updated");
+            HttpResponse::Ok().json(plan)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Copy a plan to other classes of the same main class as new drafts
+#[post("/{id}/copy")]
+async fn copy_lesson_plan(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<CopyLessonPlan>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let created_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+    match service.find_one(&id).await {
+        Ok(plan) => {
+            if let Err(res) = ensure_own_plan(&user, &plan) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service.copy(&id, data.into_inner(), created_by).await {
+        Ok(plans) => {
+            for plan in &plans {
+                broadcast_plan(&req, &state, plan, "created");
+            }
+            HttpResponse::Created().json(plans)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_lesson_plans)
+            .service(get_scheme_of_work)
+            .service(create_lesson_plan)
+            .service(get_lesson_plan)
+            .service(update_lesson_plan)
+            .service(delete_lesson_plan)
+            .service(submit_lesson_plan)
+            .service(approve_lesson_plan)
+            .service(return_lesson_plan)
+            .service(copy_lesson_plan),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "lesson-plans", blueprint);
+}
@@ -0,0 +1,270 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, Document};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        learning_material::{MaterialFolder, MaterialFolderPartial, ReorderItems},
+    },
+    guards::role_guard::check_admin_staff_or_teacher,
+    helpers::event_helpers::get_school_id_from_request,
+    models::id_model::IdType,
+    services::{
+        event_service::EventService, learning_material_service::LearningMaterialService,
+        material_folder_service::MaterialFolderService,
+    },
+    utils::{db_utils::get_database, object_id::parse_object_id_value},
+};
+
+/// Folders and materials of a subject. Students only see what has been
+/// released to them; parents see published, released materials.
+#[get("/subjects/{id}")]
+async fn get_subject_tree(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let subject_id = match IdType::from_string(path.into_inner()).to_object_id() {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let db = get_database(&req, &state);
+
+    let visible = match user.role {
+        Some(UserRole::STUDENT) => {
+            let student = match parse_object_id_value(&user.id) {
+                Ok(user_oid) => db
+                    .collection::<Document>("students")
+                    .find_one(doc! { "user_id": user_oid })
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|s| s.get_object_id("_id").ok()),
+                Err(err) => return HttpResponse::BadRequest().json(err),
+            };
+            let completed = match student {
+                Some(student_id) => match LearningMaterialService::new(&db)
+                    .completed_ids(student_id)
+                    .await
+                {
+                    Ok(ids) => ids,
+                    Err(err) => return HttpResponse::BadRequest().json(err),
+                },
+                None => Vec::new(),
+            };
+            Some(LearningMaterialService::visible_filter(Some(&completed)))
+        }
+        Some(UserRole::PARENT) => Some(LearningMaterialService::visible_filter(None)),
+        _ => None,
+    };
+
+    match MaterialFolderService::new(&db)
+        .tree(subject_id, visible)
+        .await
+    {
+        Ok(tree) => HttpResponse::Ok().json(tree),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("")]
+async fn create_folder(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<MaterialFolder>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let db = get_database(&req, &state);
+    let mut folder = data.into_inner();
+    folder.created_by = parse_object_id_value(&user.id).ok();
+    if folder.school_id.is_none() {
+        folder.school_id = get_school_id_from_request(&req)
+            .and_then(|id| IdType::from_string(id).to_object_id().ok());
+    }
+
+    match MaterialFolderService::new(&db).create(folder).await {
+        Ok(created) => {
+            let created_clone = created.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = created_clone.id {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "material_folder",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &created_clone,
+                    )
+                    .await;
+                }
+            });
+            HttpResponse::Created().json(created)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/reorder")]
+async fn reorder_folders(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<ReorderItems>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let db = get_database(&req, &state);
+    match MaterialFolderService::new(&db)
+        .reorder_folders(&data.ids)
+        .await
+    {
+        Ok(folders) => HttpResponse::Ok().json(folders),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Order of the materials outside any folder
+#[put("/unfiled/materials/reorder")]
+async fn reorder_unfiled_materials(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<ReorderItems>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let db = get_database(&req, &state);
+    match MaterialFolderService::new(&db)
+        .reorder_materials(None, &data.ids)
+        .await
+    {
+        Ok(materials) => HttpResponse::Ok().json(materials),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}/materials/reorder")]
+async fn reorder_folder_materials(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<ReorderItems>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let folder_id = match IdType::from_string(path.into_inner()).to_object_id() {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let db = get_database(&req, &state);
+    match MaterialFolderService::new(&db)
+        .reorder_materials(Some(folder_id), &data.ids)
+        .await
+    {
+        Ok(materials) => HttpResponse::Ok().json(materials),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_folder(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<MaterialFolderPartial>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    match MaterialFolderService::new(&db).update(&id, &data).await {
+        Ok(updated) => {
+            let updated_clone = updated.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = updated_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "material_folder",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &updated_clone,
+                    )
+                    .await;
+                }
+            });
+            HttpResponse::Ok().json(updated)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_folder(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(err) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": err }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    match MaterialFolderService::new(&db).delete(&id).await {
+        Ok(folder) => {
+            let folder_clone = folder.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = folder_clone.id {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "material_folder",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &folder_clone,
+                    )
+                    .await;
+                }
+            });
+            HttpResponse::Ok().json(folder)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_subject_tree)
+            .service(create_folder)
+            .service(reorder_folders)
+            .service(reorder_unfiled_materials)
+            .service(reorder_folder_materials)
+            .service(update_folder)
+            .service(delete_folder),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "material-folders", blueprint);
+}
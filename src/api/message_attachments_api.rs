@@ -0,0 +1,270 @@
+use std::str::FromStr;
+
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        message_attachment::{MessageAttachment, UpdateAttachmentLimits, MAX_CHUNK_BYTES},
+    },
+    errors::AppError,
+    guards::role_guard::check_admin_or_staff,
+    middleware::school_token_middleware::OptionalSchoolTokenMiddleware,
+    models::{id_model::IdType, school_token_model::SchoolToken},
+    schema::common_schema::ActorRef,
+    services::{
+        conversation_service::ConversationService,
+        message_attachment_service::MessageAttachmentService,
+    },
+    utils::db_utils::get_database,
+};
+
+#[derive(Debug, Deserialize)]
+struct StartUploadRequest {
+    conversation_id: String,
+    file_name: String,
+    mime_type: String,
+    total_size: i64,
+    chunk_size: Option<i64>,
+    /// Clients opt out of encryption to get server-side thumbnails
+    #[serde(default = "default_encrypted")]
+    encrypted: bool,
+}
+
+fn default_encrypted() -> bool {
+    true
+}
+
+fn auth_user(req: &HttpRequest) -> Result<(AuthUserDto, ObjectId), AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    Ok((auth_user, auth_user_id))
+}
+
+fn school_id(req: &HttpRequest) -> Option<ObjectId> {
+    req.extensions()
+        .get::<SchoolToken>()
+        .and_then(|token| ObjectId::from_str(&token.id).ok())
+}
+
+/// Only the uploader can push chunks, complete or cancel an upload
+async fn find_own_attachment(
+    service: &MessageAttachmentService,
+    id: String,
+    auth_user_id: ObjectId,
+) -> Result<MessageAttachment, AppError> {
+    let attachment = service.find_one(&IdType::String(id)).await?;
+
+    if attachment.uploaded_by.id != auth_user_id {
+        return Err(AppError {
+            message: "You can only manage your own uploads".to_string(),
+        });
+    }
+
+    Ok(attachment)
+}
+
+#[get("/limits")]
+async fn get_limits(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+
+    let limits = service.get_limits(school_id(&req)).await?;
+
+    Ok(HttpResponse::Ok().json(limits))
+}
+
+#[put("/limits")]
+async fn update_limits(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<UpdateAttachmentLimits>,
+) -> Result<HttpResponse, AppError> {
+    let (auth_user, _) = auth_user(&req)?;
+    check_admin_or_staff(&auth_user).map_err(|e| AppError { message: e })?;
+
+    let school_id = school_id(&req).ok_or_else(|| AppError {
+        message: "School context is required".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+
+    let limits = service.update_limits(school_id, &body).await?;
+
+    Ok(HttpResponse::Ok().json(limits))
+}
+
+#[post("")]
+async fn start_upload(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<StartUploadRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (auth_user, auth_user_id) = auth_user(&req)?;
+
+    let conversation_id = ObjectId::parse_str(&body.conversation_id).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let service = MessageAttachmentService::new(&db);
+
+    let conversation = conv_service
+        .find_one(
+            Some(&IdType::ObjectId(conversation_id)),
+            Some(mongodb::bson::doc! { "participants.id": auth_user_id }),
+        )
+        .await
+        .map_err(|_| AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        })?;
+
+    let uploaded_by = ActorRef {
+        id: auth_user_id,
+        role: auth_user.role.clone().unwrap_or(UserRole::STUDENT),
+    };
+
+    let attachment = service
+        .start_upload(
+            conversation_id,
+            conversation.school_id,
+            uploaded_by,
+            &body.file_name,
+            &body.mime_type,
+            body.total_size,
+            body.chunk_size,
+            body.encrypted,
+        )
+        .await?;
+
+    Ok(HttpResponse::Created().json(attachment))
+}
+
+#[get("/{id}")]
+async fn get_attachment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let (_, auth_user_id) = auth_user(&req)?;
+
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+    let conv_service = ConversationService::new(&db);
+
+    let attachment = service.find_one(&IdType::String(path.into_inner())).await?;
+
+    if !conv_service
+        .is_participant(attachment.conversation_id, auth_user_id)
+        .await?
+    {
+        return Err(AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(attachment))
+}
+
+/// Chunks are sent as the raw request body (already encrypted by the client)
+#[put("/{id}/chunks/{index}")]
+async fn upload_chunk(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, i32)>,
+    body: web::Bytes,
+) -> Result<HttpResponse, AppError> {
+    let (_, auth_user_id) = auth_user(&req)?;
+    let (id, index) = path.into_inner();
+
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+
+    let attachment = find_own_attachment(&service, id, auth_user_id).await?;
+    let updated = service.put_chunk(&attachment, index, body.to_vec()).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "received_chunks": updated.received_chunks.len(),
+        "total_chunks": updated.total_chunks,
+        "complete": updated.is_fully_received(),
+    })))
+}
+
+#[post("/{id}/complete")]
+async fn complete_upload(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let (_, auth_user_id) = auth_user(&req)?;
+
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+
+    let attachment = find_own_attachment(&service, path.into_inner(), auth_user_id).await?;
+    let completed = service.complete(&attachment).await?;
+
+    Ok(HttpResponse::Ok().json(completed))
+}
+
+/// Cancel an upload or discard a file that was never sent in a message
+#[delete("/{id}")]
+async fn delete_attachment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let (_, auth_user_id) = auth_user(&req)?;
+
+    let db = get_database(&req, &state);
+    let service = MessageAttachmentService::new(&db);
+
+    let attachment = find_own_attachment(&service, path.into_inner(), auth_user_id).await?;
+    let attachment_id = attachment.id.ok_or_else(|| AppError {
+        message: "Attachment has no id".to_string(),
+    })?;
+
+    if !service.release_if_unreferenced(&attachment_id).await? {
+        return Err(AppError {
+            message: "Attachment is still referenced by a message".to_string(),
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "deleted": true })))
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(OptionalSchoolTokenMiddleware)
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .app_data(web::PayloadConfig::new(MAX_CHUNK_BYTES as usize))
+            .service(get_limits)
+            .service(update_limits)
+            .service(start_upload)
+            .service(get_attachment)
+            .service(upload_chunk)
+            .service(complete_upload)
+            .service(delete_attachment),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/m-attachments").configure(blueprint));
+}
@@ -1,18 +1,25 @@
-use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse};
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::Deserialize;
 
+use super::messaging_socket::WsMessage;
 use crate::{
     config::state::AppState,
     domain::{
-        common_details::Paginated,
+        common_details::{Paginated, UserRole},
         message::{Message, MessageType},
+        message_attachment::AttachmentStatus,
+        safeguarding::{MessageReport, ReportReason, ReportStatus},
     },
     errors::AppError,
     middleware::school_token_middleware::OptionalSchoolTokenMiddleware,
     models::id_model::IdType,
     schema::common_schema::ActorRef,
-    services::{conversation_service::ConversationService, message_service::MessageService},
+    services::{
+        conversation_service::ConversationService,
+        message_attachment_service::MessageAttachmentService, message_service::MessageService,
+        safeguarding_service::SafeguardingService,
+    },
     utils::db_utils::get_database,
 };
 
@@ -24,9 +31,30 @@ struct CreateMessageRequest {
     message_type: Option<MessageType>,
     file_url: Option<String>,
     file_public_id: Option<String>,
+    reply_to_message_id: Option<String>,
+    attachment_id: Option<String>,
     client_message_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct EditMessageRequest {
+    encrypted_payload: String,
+    nonce: String,
+    key_version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReactionRequest {
+    emoji: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportMessageRequest {
+    reason: ReportReason,
+    decrypted_content: String,
+    details: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct QueryParams {
     page: Option<i64>,
@@ -79,6 +107,62 @@ async fn create_message(
     // Ensure message school_id matches conversation school_id
     let message_school_id = conversation.school_id;
 
+    // Messages must be encrypted with an existing key version, new ones default to the latest
+    let key_version = body
+        .key_version
+        .unwrap_or(conversation.encryption_key_version);
+    if key_version < 1 || key_version > conversation.encryption_key_version {
+        return Err(AppError {
+            message: format!(
+                "Invalid key version {}. Current conversation key version is {}",
+                key_version, conversation.encryption_key_version
+            ),
+        });
+    }
+
+    let reply_to_message_id = match &body.reply_to_message_id {
+        Some(id) => Some(ObjectId::parse_str(id).map_err(|_| AppError {
+            message: "Invalid reply_to_message_id".to_string(),
+        })?),
+        None => None,
+    };
+
+    let message_type = body.message_type.clone().unwrap_or(MessageType::TEXT);
+    let mut file_url = body.file_url.clone();
+    let mut file_public_id = body.file_public_id.clone();
+
+    // Files uploaded through the attachment pipeline supply their own location
+    let attachment_id = match &body.attachment_id {
+        Some(id) => {
+            if message_type != MessageType::FILE {
+                return Err(AppError {
+                    message: "attachment_id is only allowed on FILE messages".to_string(),
+                });
+            }
+
+            let attachment = MessageAttachmentService::new(&db)
+                .find_one(&IdType::String(id.clone()))
+                .await?;
+            if attachment.conversation_id != conversation_id
+                || attachment.uploaded_by.id != auth_user_id
+            {
+                return Err(AppError {
+                    message: "Attachment does not belong to this conversation".to_string(),
+                });
+            }
+            if attachment.status != AttachmentStatus::Complete {
+                return Err(AppError {
+                    message: "Attachment upload is not complete".to_string(),
+                });
+            }
+
+            file_url = attachment.file_url.clone();
+            file_public_id = attachment.file_public_id.clone();
+            attachment.id
+        }
+        None => None,
+    };
+
     let message = Message {
         id: None,
         school_id: message_school_id, // Use conversation's school_id
@@ -89,18 +173,36 @@ async fn create_message(
         },
         encrypted_payload: body.encrypted_payload.clone(),
         nonce: body.nonce.clone(),
-        key_version: body.key_version.unwrap_or(1),
-        message_type: body.message_type.clone().unwrap_or(MessageType::TEXT),
-        file_url: body.file_url.clone(),
-        file_public_id: body.file_public_id.clone(),
+        key_version,
+        message_type,
+        file_url,
+        file_public_id,
+        reply_to_message_id,
+        attachment_id,
         read_by: vec![],
+        reactions: vec![],
+        edit_history: vec![],
         client_message_id: body.client_message_id.clone(),
+        edited_at: None,
         deleted_at: None,
         created_at: chrono::Utc::now(),
     };
 
     let created = msg_service.create(message).await?;
 
+    if let Some(message_id) = created.id {
+        state
+            .messaging_hub
+            .broadcast(
+                &conversation_id.to_hex(),
+                &WsMessage::MessageCreated {
+                    conversation_id: conversation_id.to_hex(),
+                    message_id: message_id.to_hex(),
+                },
+            )
+            .await;
+    }
+
     Ok(HttpResponse::Created().json(created))
 }
 
@@ -138,7 +240,7 @@ async fn get_messages(
     conv_service
         .find_one(
             Some(&IdType::ObjectId(conversation_id)),
-            Some(doc! { "participants.id": auth_user_id }),
+            Some(doc! { "$or": [{ "participants.id": auth_user_id }, { "observers.id": auth_user_id }] }),
         )
         .await
         .map_err(|_| AppError {
@@ -195,7 +297,7 @@ async fn get_files(
     conv_service
         .find_one(
             Some(&IdType::ObjectId(conversation_id)),
-            Some(doc! { "participants.id": auth_user_id }),
+            Some(doc! { "$or": [{ "participants.id": auth_user_id }, { "observers.id": auth_user_id }] }),
         )
         .await
         .map_err(|_| AppError {
@@ -267,12 +369,386 @@ async fn delete_message(
     }
 
     let deleted = msg_service
-        .soft_delete(&IdType::String(message_id_str))
+        .soft_delete(&IdType::String(message_id_str.clone()))
         .await?;
 
+    state
+        .messaging_hub
+        .broadcast(
+            &conversation_id.to_hex(),
+            &WsMessage::MessageDeleted {
+                message_id: message_id_str,
+            },
+        )
+        .await;
+
     Ok(HttpResponse::Ok().json(deleted))
 }
 
+/// Permanently delete a message. Once no message references its attachment
+/// the stored blob is removed as well.
+#[delete("/{conversation_id}/messages/{message_id}/permanent")]
+async fn purge_message(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<crate::domain::auth_user::AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let (conversation_id_str, message_id_str) = path.into_inner();
+    let conversation_id = ObjectId::parse_str(&conversation_id_str).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+    let message_id = ObjectId::parse_str(&message_id_str).map_err(|_| AppError {
+        message: "Invalid message ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let msg_service = MessageService::new(&db);
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    if !conv_service
+        .is_participant(conversation_id, auth_user_id)
+        .await?
+    {
+        return Err(AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        });
+    }
+
+    let deleted = msg_service
+        .hard_delete(message_id, conversation_id, auth_user_id)
+        .await?;
+
+    let mut attachment_released = false;
+    if let Some(attachment_id) = deleted.attachment_id {
+        attachment_released = MessageAttachmentService::new(&db)
+            .release_if_unreferenced(&attachment_id)
+            .await?;
+    }
+
+    if deleted.deleted_at.is_none() {
+        state
+            .messaging_hub
+            .broadcast(
+                &conversation_id.to_hex(),
+                &WsMessage::MessageDeleted {
+                    message_id: message_id_str,
+                },
+            )
+            .await;
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": deleted,
+        "attachment_released": attachment_released,
+    })))
+}
+
+#[put("/{conversation_id}/messages/{message_id}")]
+async fn edit_message(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<EditMessageRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<crate::domain::auth_user::AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let (conversation_id_str, message_id_str) = path.into_inner();
+    let conversation_id = ObjectId::parse_str(&conversation_id_str).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+    let message_id = ObjectId::parse_str(&message_id_str).map_err(|_| AppError {
+        message: "Invalid message ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let msg_service = MessageService::new(&db);
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    let conversation = conv_service
+        .find_one(
+            Some(&IdType::ObjectId(conversation_id)),
+            Some(doc! { "participants.id": auth_user_id }),
+        )
+        .await
+        .map_err(|_| AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        })?;
+
+    let message = msg_service
+        .find_one_in_conversation(message_id, conversation_id)
+        .await?;
+
+    if message.sender.id != auth_user_id {
+        return Err(AppError {
+            message: "You can only edit your own messages".to_string(),
+        });
+    }
+
+    // Edits are re-encrypted with the current key unless told otherwise
+    let key_version = body
+        .key_version
+        .unwrap_or(conversation.encryption_key_version);
+    if key_version < 1 || key_version > conversation.encryption_key_version {
+        return Err(AppError {
+            message: format!(
+                "Invalid key version {}. Current conversation key version is {}",
+                key_version, conversation.encryption_key_version
+            ),
+        });
+    }
+
+    let edited = msg_service
+        .edit(
+            &IdType::ObjectId(message_id),
+            body.encrypted_payload.clone(),
+            body.nonce.clone(),
+            key_version,
+        )
+        .await?;
+
+    state
+        .messaging_hub
+        .broadcast(
+            &conversation_id.to_hex(),
+            &WsMessage::MessageEdited {
+                conversation_id: conversation_id.to_hex(),
+                message_id: message_id.to_hex(),
+            },
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(edited))
+}
+
+#[post("/{conversation_id}/messages/{message_id}/reactions")]
+async fn add_reaction(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<ReactionRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<crate::domain::auth_user::AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let (conversation_id_str, message_id_str) = path.into_inner();
+    let conversation_id = ObjectId::parse_str(&conversation_id_str).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+    let message_id = ObjectId::parse_str(&message_id_str).map_err(|_| AppError {
+        message: "Invalid message ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let msg_service = MessageService::new(&db);
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    if !conv_service
+        .is_participant(conversation_id, auth_user_id)
+        .await?
+    {
+        return Err(AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        });
+    }
+
+    msg_service
+        .find_one_in_conversation(message_id, conversation_id)
+        .await?;
+
+    let user = ActorRef {
+        id: auth_user_id,
+        role: auth_user
+            .role
+            .clone()
+            .unwrap_or(crate::domain::common_details::UserRole::STUDENT),
+    };
+
+    let updated = msg_service
+        .add_reaction(&IdType::ObjectId(message_id), user, &body.emoji)
+        .await?;
+
+    state
+        .messaging_hub
+        .broadcast(
+            &conversation_id.to_hex(),
+            &WsMessage::ReactionAdded {
+                message_id: message_id.to_hex(),
+                user_id: auth_user_id.to_hex(),
+                emoji: body.emoji.trim().to_string(),
+            },
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+#[delete("/{conversation_id}/messages/{message_id}/reactions/{emoji}")]
+async fn remove_reaction(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<crate::domain::auth_user::AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let (conversation_id_str, message_id_str, emoji) = path.into_inner();
+    let conversation_id = ObjectId::parse_str(&conversation_id_str).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+    let message_id = ObjectId::parse_str(&message_id_str).map_err(|_| AppError {
+        message: "Invalid message ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let msg_service = MessageService::new(&db);
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    if !conv_service
+        .is_participant(conversation_id, auth_user_id)
+        .await?
+    {
+        return Err(AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        });
+    }
+
+    msg_service
+        .find_one_in_conversation(message_id, conversation_id)
+        .await?;
+
+    let updated = msg_service
+        .remove_reaction(&IdType::ObjectId(message_id), auth_user_id, &emoji)
+        .await?;
+
+    state
+        .messaging_hub
+        .broadcast(
+            &conversation_id.to_hex(),
+            &WsMessage::ReactionRemoved {
+                message_id: message_id.to_hex(),
+                user_id: auth_user_id.to_hex(),
+                emoji: emoji.trim().to_string(),
+            },
+        )
+        .await;
+
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+/// Report a message to the school's moderation queue. The reporter submits the
+/// plaintext they decrypted since the server cannot read message payloads.
+#[post("/{conversation_id}/messages/{message_id}/report")]
+async fn report_message(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    body: web::Json<ReportMessageRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = req
+        .extensions()
+        .get::<crate::domain::auth_user::AuthUserDto>()
+        .cloned()
+        .ok_or_else(|| AppError {
+            message: "User not authenticated".to_string(),
+        })?;
+
+    let (conversation_id_str, message_id_str) = path.into_inner();
+    let conversation_id = ObjectId::parse_str(&conversation_id_str).map_err(|_| AppError {
+        message: "Invalid conversation ID".to_string(),
+    })?;
+    let message_id = ObjectId::parse_str(&message_id_str).map_err(|_| AppError {
+        message: "Invalid message ID".to_string(),
+    })?;
+
+    let db = get_database(&req, &state);
+    let conv_service = ConversationService::new(&db);
+    let msg_service = MessageService::new(&db);
+
+    let auth_user_id = ObjectId::parse_str(&auth_user.id).map_err(|_| AppError {
+        message: "Invalid user ID".to_string(),
+    })?;
+
+    let conversation = conv_service
+        .find_one(
+            Some(&IdType::ObjectId(conversation_id)),
+            Some(doc! { "$or": [{ "participants.id": auth_user_id }, { "observers.id": auth_user_id }] }),
+        )
+        .await
+        .map_err(|_| AppError {
+            message: "You are not a participant in this conversation".to_string(),
+        })?;
+
+    let message = msg_service
+        .find_one_in_conversation(message_id, conversation_id)
+        .await?;
+
+    let report = MessageReport {
+        id: None,
+        school_id: conversation.school_id,
+        conversation_id,
+        message_id,
+        reporter: ActorRef {
+            id: auth_user_id,
+            role: auth_user.role.clone().unwrap_or(UserRole::STUDENT),
+        },
+        reported_user: message.sender.clone(),
+        reason: body.reason.clone(),
+        decrypted_content: body.decrypted_content.clone(),
+        details: body.details.clone(),
+        status: ReportStatus::Pending,
+        reviewed_by: None,
+        resolution_note: None,
+        reviewed_at: None,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    let created = SafeguardingService::new(&db).create_report(report).await?;
+
+    // No realtime event: school-wide broadcasts would expose the reported content
+    Ok(HttpResponse::Created().json(created))
+}
+
 fn blueprint(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("")
@@ -281,7 +757,12 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
             .service(create_message)
             .service(get_messages)
             .service(get_files)
-            .service(delete_message),
+            .service(delete_message)
+            .service(purge_message)
+            .service(edit_message)
+            .service(add_reaction)
+            .service(remove_reaction)
+            .service(report_message),
     );
 }
 
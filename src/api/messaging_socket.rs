@@ -9,10 +9,13 @@ use crate::config::state::AppState;
 // WebSocket message types
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
-enum WsMessage {
+pub(crate) enum WsMessage {
     MessageCreated { conversation_id: String, message_id: String },
     MessageRead { message_id: String, user_id: String },
     MessageDeleted { message_id: String },
+    MessageEdited { conversation_id: String, message_id: String },
+    ReactionAdded { message_id: String, user_id: String, emoji: String },
+    ReactionRemoved { message_id: String, user_id: String, emoji: String },
     ConversationCreated { conversation_id: String },
     ParticipantAdded { conversation_id: String, user_id: String },
     Error { message: String },
@@ -63,6 +66,18 @@ async fn websocket_handler(
 
     let (response, mut session, mut stream) = actix_ws::handle(&req, stream)?;
 
+    // Forward conversation events (edits, reactions, ...) to this socket
+    let hub = state.messaging_hub.clone();
+    let (socket_id, mut events) = hub.join(&conversation_id).await;
+    let mut event_session = session.clone();
+    actix_web::rt::spawn(async move {
+        while let Some(event) = events.next().await {
+            if event_session.text(event).await.is_err() {
+                break;
+            }
+        }
+    });
+
     // Spawn task to handle WebSocket messages
     actix_web::rt::spawn(async move {
         log::info!("WebSocket connection established for conversation: {}", conversation_id);
@@ -96,6 +111,7 @@ async fn websocket_handler(
             }
         }
 
+        hub.leave(&conversation_id, &socket_id).await;
         log::info!("WebSocket connection closed for conversation: {}", conversation_id);
     });
 
@@ -1,16 +1,21 @@
 use actix_web::web;
 
+mod academic_record_api;
+mod academic_year_api;
 mod analytics_api;
 mod announcement_api;
 mod assessment_category_api;
 mod assignment_api;
+mod assignment_group_api;
 mod audit_logs_api;
 mod auth_api;
 mod backups_api;
+mod chat;
 mod class_api;
 mod class_subject;
 mod class_timetable;
 mod comment_api;
+mod competency_api;
 mod conversations_api;
 mod database_status;
 mod education_year_api;
@@ -19,22 +24,35 @@ mod exam_api;
 mod grading_scale_api;
 mod join_school_request_api;
 mod learning_materials_api;
+mod learning_outcomes_api;
+mod lesson_plan_api;
 mod like_api;
 mod main_class_api;
+mod material_folder_api;
+mod message_attachments_api;
 mod messages_api;
 mod messaging_socket;
 mod messaging_users_api;
 mod parent_api;
+mod peer_review_api;
+mod promotion_api;
+mod quiz_api;
 mod ranking_api;
 mod recycle_bin_api;
+mod report_card_api;
+mod result_moderation_api;
 mod results_api;
 mod roles_api;
+mod rubric_api;
+mod safeguarding_api;
 mod school_api;
 mod school_collections;
 mod school_staff_api;
 mod score_api;
 mod sector_api;
 mod students_api;
+mod submission_similarity_api;
+mod syllabus_coverage_api;
 mod teachers_api;
 mod template_subject_api;
 mod trade_api;
@@ -57,6 +75,7 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
     teachers_api::init(cfg);
     school_staff_api::init(cfg);
     parent_api::init(cfg);
+    peer_review_api::init(cfg);
     join_school_request_api::init(cfg);
     school_collections::school_class_timetable::init(cfg);
     school_collections::school_timetable::init(cfg);
@@ -64,7 +83,9 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
     class_subject::init(cfg);
     class_timetable::init(cfg);
     education_year_api::init(cfg);
+    academic_year_api::init(cfg);
     announcement_api::init(cfg);
+    chat::init(cfg);
     comment_api::init(cfg);
     like_api::init(cfg);
     exam_api::init(cfg);
@@ -72,19 +93,34 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
     score_api::init(cfg);
     grading_scale_api::init(cfg);
     results_api::init(cfg);
+    result_moderation_api::init(cfg);
+    report_card_api::init(cfg);
+    competency_api::init(cfg);
     ranking_api::init(cfg);
+    academic_record_api::init(cfg);
+    promotion_api::init(cfg);
     assignment_api::init(cfg);
+    assignment_group_api::init(cfg);
+    quiz_api::init(cfg);
+    rubric_api::init(cfg);
+    submission_similarity_api::init(cfg);
     roles_api::init(cfg);
+    safeguarding_api::init(cfg);
     audit_logs_api::init(cfg);
     backups_api::init(cfg);
     recycle_bin_api::init(cfg);
     learning_materials_api::init(cfg);
+    material_folder_api::init(cfg);
+    learning_outcomes_api::init(cfg);
+    syllabus_coverage_api::init(cfg);
+    lesson_plan_api::init(cfg);
     analytics_api::init(cfg);
 
     // Messaging routes with /m prefix
     messaging_users_api::init(cfg);
     conversations_api::init(cfg);
     messages_api::init(cfg);
+    message_attachments_api::init(cfg);
 
     // WebSocket route
     messaging_socket::init(cfg);
@@ -0,0 +1,266 @@
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Database,
+};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        peer_review::{OverridePeerReview, StartPeerReview, SubmitPeerReview},
+    },
+    guards::role_guard::require_permission,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{peer_review_service::PeerReviewService, role_service::RoleService},
+    utils::{db_utils::get_database, object_id::parse_object_id_value},
+};
+
+/// Setting up, reading and correcting reviews is for whoever manages the assignment
+async fn ensure_manager(
+    req: &HttpRequest,
+    db: &Database,
+    user: &AuthUserDto,
+) -> Result<(), HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "message": "School ID required"
+        }))
+    })?;
+
+    require_permission(user, &school_id, "assignment.update", &RoleService::new(db))
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+/// Student record of the signed-in user
+async fn current_student_id(db: &Database, user: &AuthUserDto) -> Result<ObjectId, HttpResponse> {
+    let forbidden = || {
+        HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "Only students take part in peer review"
+        }))
+    };
+    if !matches!(user.role, Some(UserRole::STUDENT)) {
+        return Err(forbidden());
+    }
+    let user_oid = parse_object_id_value(&user.id).map_err(|_| forbidden())?;
+    db.collection::<Document>("students")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .and_then(|s| s.get_object_id("_id").ok())
+        .ok_or_else(|| {
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "message": "Student record not found"
+            }))
+        })
+}
+
+fn path_object_id(value: String) -> Result<ObjectId, HttpResponse> {
+    IdType::from_string(value)
+        .to_object_id()
+        .map_err(|e| HttpResponse::BadRequest().json(e))
+}
+
+#[post("/assignments/{id}")]
+async fn start_peer_review(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<StartPeerReview>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    if let Err(resp) = ensure_manager(&req, &db, &user).await {
+        return resp;
+    }
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    let created_by = parse_object_id_value(&user.id).ok();
+    match PeerReviewService::new(&db)
+        .start(assignment_id, data.into_inner(), created_by)
+        .await
+    {
+        Ok(round) => HttpResponse::Created().json(round),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/assignments/{id}")]
+async fn get_assignment_reviews(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    if let Err(resp) = ensure_manager(&req, &db, &user).await {
+        return resp;
+    }
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match PeerReviewService::new(&db)
+        .get_all_with_relations(assignment_id, query.limit, query.skip)
+        .await
+    {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(err) => HttpResponse::InternalServerError().json(err),
+    }
+}
+
+#[get("/assignments/{id}/summary")]
+async fn get_peer_review_summary(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    if let Err(resp) = ensure_manager(&req, &db, &user).await {
+        return resp;
+    }
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match PeerReviewService::new(&db).summary(assignment_id).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/assignments/{id}/finalize")]
+async fn finalize_peer_review(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    if let Err(resp) = ensure_manager(&req, &db, &user).await {
+        return resp;
+    }
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match PeerReviewService::new(&db).finalize(assignment_id).await {
+        Ok(summary) => HttpResponse::Ok().json(summary),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}/override")]
+async fn override_peer_review(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<OverridePeerReview>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    if let Err(resp) = ensure_manager(&req, &db, &user).await {
+        return resp;
+    }
+    let overridden_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let id = IdType::from_string(path.into_inner());
+    match PeerReviewService::new(&db)
+        .override_review(&id, data.into_inner(), overridden_by)
+        .await
+    {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/assignments/{id}/mine")]
+async fn get_my_peer_reviews(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+    let assignment_id = match path_object_id(path.into_inner()) {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    match PeerReviewService::new(&db)
+        .for_student(assignment_id, student_id)
+        .await
+    {
+        Ok(reviews) => HttpResponse::Ok().json(reviews),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn submit_peer_review(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SubmitPeerReview>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let student_id = match current_student_id(&db, &user).await {
+        Ok(id) => id,
+        Err(resp) => return resp,
+    };
+
+    let id = IdType::from_string(path.into_inner());
+    match PeerReviewService::new(&db)
+        .submit(&id, student_id, data.into_inner())
+        .await
+    {
+        // Reviewer and reviewee stay hidden from students
+        Ok(review) => HttpResponse::Ok().json(serde_json::json!({
+            "review_id": review.id.map(|id| id.to_hex()),
+            "status": review.status,
+            "score": review.score,
+            "criterion_scores": review.criterion_scores,
+            "comment": review.comment,
+            "submitted_at": review.submitted_at,
+        })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(start_peer_review)
+            .service(get_peer_review_summary)
+            .service(finalize_peer_review)
+            .service(get_my_peer_reviews)
+            .service(get_assignment_reviews)
+            .service(override_peer_review)
+            .service(submit_peer_review),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "peer-reviews", blueprint);
+}
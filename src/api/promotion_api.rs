@@ -0,0 +1,497 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        audit_log::AuditSeverity,
+        auth_user::AuthUserDto,
+        promotion::{
+            CreatePromotionBatch, OverridePromotionResult, PromotionRule, PromotionRulePartial,
+            PromotionStatus,
+        },
+    },
+    guards::role_guard::check_admin_or_staff,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{
+        audit_log_service::AuditLogService, event_service::EventService,
+        promotion_service::PromotionService,
+    },
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+fn school_object_id(req: &HttpRequest) -> Result<ObjectId, HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+    })?;
+    ObjectId::parse_str(&school_id).map_err(|_| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "Invalid school ID" }))
+    })
+}
+
+/// Promotions change class membership school-wide, so only admins and staff manage them
+fn ensure_manager(user: &AuthUserDto) -> Result<ObjectId, HttpResponse> {
+    check_admin_or_staff(user)
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))?;
+    parse_object_id_value(&user.id).map_err(|err| HttpResponse::BadRequest().json(err))
+}
+
+// =========================
+// RULES
+// =========================
+
+#[get("/rules")]
+async fn get_promotion_rules(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service
+        .get_rules(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/rules/{id}")]
+async fn get_promotion_rule(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.find_rule(&id).await {
+        Ok(rule) => HttpResponse::Ok().json(rule),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/rules")]
+async fn create_promotion_rule(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<PromotionRule>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match ensure_manager(&user) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    let mut rule = data.into_inner();
+    rule.school_id = Some(school_id);
+    rule.created_by = Some(user_id);
+
+    match service.create_rule(rule).await {
+        Ok(rule) => {
+            let rule_clone = rule.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rule_clone.id {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "promotion_rule",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rule_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Created().json(rule)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/rules/{id}")]
+async fn update_promotion_rule(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<PromotionRulePartial>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_manager(&user) {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.update_rule(&id, &data.into_inner()).await {
+        Ok(rule) => {
+            let rule_clone = rule.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rule_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "promotion_rule",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rule_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(rule)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/rules/{id}")]
+async fn delete_promotion_rule(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_manager(&user) {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.delete_rule(&id).await {
+        Ok(rule) => {
+            let rule_clone = rule.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rule_clone.id {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "promotion_rule",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rule_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(serde_json::json!({ "message": "Promotion rule deleted" }))
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+// =========================
+// BATCHES
+// =========================
+
+#[get("")]
+async fn get_promotion_batches(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_manager(&user) {
+        return res;
+    }
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let mut extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc.unwrap_or_default(),
+        Err(err) => return err,
+    };
+    extra_match.insert("school_id", school_id);
+    if let Some(class_id) = query.class_id.as_deref() {
+        match parse_object_id_value(class_id) {
+            Ok(id) => extra_match.insert("from_class_id", id),
+            Err(err) => return HttpResponse::BadRequest().json(err),
+        };
+    }
+
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service
+        .get_batches(query.limit, query.skip, Some(extra_match))
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Dry-run: evaluates the class against the rule and stores a draft batch
+#[post("/preview")]
+async fn preview_promotion(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<CreatePromotionBatch>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match ensure_manager(&user) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let from_class_id = match parse_object_id_value(&data.from_class_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let education_year_id = match parse_object_id_value(&data.education_year_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let to_class_id = match data.to_class_id.as_deref().map(parse_object_id_value) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(err)) => return HttpResponse::BadRequest().json(err),
+        None => None,
+    };
+    let rule_id = match data.rule_id.as_deref().map(parse_object_id_value) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(err)) => return HttpResponse::BadRequest().json(err),
+        None => None,
+    };
+
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service
+        .preview(
+            school_id,
+            education_year_id,
+            from_class_id,
+            to_class_id,
+            rule_id,
+            user_id,
+        )
+        .await
+    {
+        Ok(batch) => HttpResponse::Created().json(batch),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_promotion_batch(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_manager(&user) {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.find_batch(&id).await {
+        Ok(batch) => HttpResponse::Ok().json(batch),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[put("/{id}/students/{student_id}")]
+async fn override_promotion_result(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<(String, String)>,
+    data: web::Json<OverridePromotionResult>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match ensure_manager(&user) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let (batch_id, student_id) = path.into_inner();
+    let student_id = match parse_object_id_value(&student_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let batch_id = IdType::from_string(batch_id);
+
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service
+        .override_result(&batch_id, &student_id, &data, user_id)
+        .await
+    {
+        Ok(batch) => {
+            if let Some(id) = batch.id {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "promotion.override",
+                        "promotion_batch",
+                        id,
+                        Some(doc! {
+                            "student_id": student_id,
+                            "promotion_status": mongodb::bson::to_bson(&data.promotion_status).ok(),
+                            "reason": data.reason.clone(),
+                        }),
+                        None,
+                        Some(AuditSeverity::WARNING),
+                    )
+                    .await
+                    .ok();
+            }
+
+            HttpResponse::Ok().json(batch)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/execute")]
+async fn execute_promotion(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = match ensure_manager(&user) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.execute(&id, user_id).await {
+        Ok(batch) => {
+            let count = |status: PromotionStatus| {
+                batch
+                    .promotion_results
+                    .iter()
+                    .filter(|r| r.promotion_status == status)
+                    .count() as i64
+            };
+
+            if let Some(batch_id) = batch.id {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "promotion.execute",
+                        "promotion_batch",
+                        batch_id,
+                        Some(doc! {
+                            "from_class_id": batch.from_class_id,
+                            "to_class_id": batch.to_class_id,
+                            "promoted": count(PromotionStatus::Promoted),
+                            "repeated": count(PromotionStatus::Repeated),
+                            "graduated": count(PromotionStatus::Graduated),
+                        }),
+                        None,
+                        Some(AuditSeverity::WARNING),
+                    )
+                    .await
+                    .ok();
+            }
+
+            let batch_clone = batch.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = batch_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "promotion_batch",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &batch_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(batch)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/cancel")]
+async fn cancel_promotion(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_manager(&user) {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = PromotionService::new(&db);
+
+    match service.cancel(&id).await {
+        Ok(batch) => HttpResponse::Ok().json(batch),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_promotion_rules)
+        .service(get_promotion_rule)
+        .service(
+            web::scope("")
+                .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+                .service(create_promotion_rule)
+                .service(update_promotion_rule)
+                .service(delete_promotion_rule)
+                .service(get_promotion_batches)
+                .service(preview_promotion)
+                .service(get_promotion_batch)
+                .service(override_promotion_result)
+                .service(execute_promotion)
+                .service(cancel_promotion),
+        );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "promotions", blueprint);
+}
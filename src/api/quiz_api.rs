@@ -0,0 +1,451 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        quiz::{CreateQuizQuestion, SubmitQuizAttempt, UpdateQuizQuestion, UpsertQuiz},
+        student::Student,
+    },
+    guards::role_guard::{check_admin_staff_or_teacher, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{
+        assignment_service::AssignmentService, event_service::EventService,
+        quiz_service::QuizService, role_service::RoleService,
+    },
+    utils::{
+        api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
+    },
+};
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> QuizService {
+    QuizService::new(&get_database(req, state))
+}
+
+/// Quiz settings and the question bank are managed with assignment.update
+async fn require_quiz_editor(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+) -> Result<(), HttpResponse> {
+    let Some(school_id) = get_school_id_from_request(req) else {
+        return Err(
+            HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+        );
+    };
+    let role_service = RoleService::new(&get_database(req, state));
+    require_permission(user, &school_id, "assignment.update", &role_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+/// Student record of the signed-in user, enrolled in the assignment's class
+async fn current_student(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    assignment_id: ObjectId,
+) -> Result<Student, HttpResponse> {
+    if !matches!(user.role, Some(UserRole::STUDENT)) {
+        return Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({ "message": "Only students can take quizzes" })));
+    }
+
+    let db = get_database(req, state);
+    let user_oid =
+        parse_object_id_value(&user.id).map_err(|e| HttpResponse::BadRequest().json(e))?;
+    let student = db
+        .collection::<Student>("students")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .ok_or_else(|| {
+            HttpResponse::BadRequest()
+                .json(serde_json::json!({ "message": "Student record not found for this user" }))
+        })?;
+
+    let assignment = AssignmentService::new(&db)
+        .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+        .await
+        .map_err(|e| HttpResponse::NotFound().json(e))?;
+    if assignment.class_id.is_some() && assignment.class_id != student.class_id {
+        return Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({ "message": "You are not enrolled in this class" })));
+    }
+    Ok(student)
+}
+
+fn parse_assignment_id(raw: &str) -> Result<ObjectId, HttpResponse> {
+    parse_object_id_value(raw).map_err(|e| HttpResponse::BadRequest().json(e))
+}
+
+// =========================
+// QUESTION BANK
+// =========================
+
+#[get("/questions")]
+async fn get_questions(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_questions(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/questions/{id}")]
+async fn get_question(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_question(&id).await {
+        Ok(question) => HttpResponse::Ok().json(question),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/questions")]
+async fn create_question(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<CreateQuizQuestion>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let created_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+
+    match service(&req, &state)
+        .create_question(school_id, data.into_inner(), created_by)
+        .await
+    {
+        Ok(question) => HttpResponse::Created().json(question),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/questions/{id}")]
+async fn update_question(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpdateQuizQuestion>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state)
+        .update_question(&id, data.into_inner())
+        .await
+    {
+        Ok(question) => HttpResponse::Ok().json(question),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/questions/{id}")]
+async fn delete_question(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).delete_question(&id).await {
+        Ok(question) => HttpResponse::Ok().json(question),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+// =========================
+// QUIZ SETTINGS
+// =========================
+
+#[get("/assignments/{assignment_id}")]
+async fn get_quiz(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state).find_quiz(assignment_id).await {
+        Ok(quiz) => HttpResponse::Ok().json(quiz),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[put("/assignments/{assignment_id}")]
+async fn upsert_quiz(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpsertQuiz>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let created_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match service(&req, &state)
+        .upsert_quiz(assignment_id, data.into_inner(), created_by)
+        .await
+    {
+        Ok(quiz) => HttpResponse::Ok().json(quiz),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/assignments/{assignment_id}")]
+async fn delete_quiz(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state).delete_quiz(assignment_id).await {
+        Ok(quiz) => HttpResponse::Ok().json(quiz),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Grade every attempt again after an answer key was corrected
+#[post("/assignments/{assignment_id}/regrade")]
+async fn regrade_quiz(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = require_quiz_editor(&req, &state, &user).await {
+        return res;
+    }
+
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let actor = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    match service(&req, &state)
+        .regrade(assignment_id, None, actor)
+        .await
+    {
+        Ok(regraded) => HttpResponse::Ok().json(serde_json::json!({ "regraded": regraded })),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+// =========================
+// ATTEMPTS
+// =========================
+
+/// Students see their own attempts, staff and teachers everyone's
+#[get("/assignments/{assignment_id}/attempts")]
+async fn get_attempts(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let service = service(&req, &state);
+
+    if check_admin_staff_or_teacher(&user).is_ok() {
+        return match service.get_attempts(assignment_id, None).await {
+            Ok(attempts) => HttpResponse::Ok().json(attempts),
+            Err(err) => HttpResponse::BadRequest().json(err),
+        };
+    }
+
+    let student = match current_student(&req, &state, &user, assignment_id).await {
+        Ok(s) => s,
+        Err(res) => return res,
+    };
+    let quiz = match service.find_quiz(assignment_id).await {
+        Ok(q) => q,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+
+    let attempts = match service.get_attempts(assignment_id, student.id).await {
+        Ok(a) => a,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let mut views = Vec::with_capacity(attempts.len());
+    for attempt in attempts {
+        match service.view(attempt, quiz.show_correct_answers).await {
+            Ok(view) => views.push(view),
+            Err(err) => return HttpResponse::BadRequest().json(err),
+        }
+    }
+    HttpResponse::Ok().json(views)
+}
+
+/// Start an attempt, or resume the one still running
+#[post("/assignments/{assignment_id}/attempts")]
+async fn start_attempt(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let assignment_id = match parse_assignment_id(&path.into_inner()) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let student = match current_student(&req, &state, &user, assignment_id).await {
+        Ok(s) => s,
+        Err(res) => return res,
+    };
+    let (Some(student_id), Ok(actor)) = (student.id, parse_object_id_value(&user.id)) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "Invalid student record" }));
+    };
+
+    match service(&req, &state)
+        .start_attempt(assignment_id, student_id, actor)
+        .await
+    {
+        Ok(view) => HttpResponse::Ok().json(view),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/attempts/{id}/submit")]
+async fn submit_attempt(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SubmitQuizAttempt>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+
+    let attempt = match service.find_attempt(&id).await {
+        Ok(a) => a,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+    let student = match current_student(&req, &state, &user, attempt.assignment_id).await {
+        Ok(s) => s,
+        Err(res) => return res,
+    };
+    let (Some(student_id), Ok(actor)) = (student.id, parse_object_id_value(&user.id)) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "Invalid student record" }));
+    };
+
+    match service
+        .submit_attempt(&id, student_id, data.into_inner(), actor)
+        .await
+    {
+        Ok(view) => {
+            let view_clone = view.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = view_clone.attempt.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "quiz_attempt",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &view_clone.attempt,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(view)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_questions)
+            .service(create_question)
+            .service(get_question)
+            .service(update_question)
+            .service(delete_question)
+            .service(get_attempts)
+            .service(start_attempt)
+            .service(regrade_quiz)
+            .service(get_quiz)
+            .service(upsert_quiz)
+            .service(delete_quiz)
+            .service(submit_attempt),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "quizzes", blueprint);
+}
@@ -0,0 +1,280 @@
+use actix_web::{get, http::header, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::Deserialize;
+
+use crate::{
+    api::academic_record_api::authorize_student,
+    config::state::AppState,
+    domain::{
+        audit_log::AuditSeverity, auth_user::AuthUserDto, report_card::UpdateReportCardTemplate,
+    },
+    guards::role_guard::check_admin_or_staff,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{audit_log_service::AuditLogService, report_card_service::ReportCardService},
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+#[derive(Debug, Deserialize)]
+struct StartReportCardJob {
+    class_id: String,
+    exam_id: String,
+}
+
+fn school_object_id(req: &HttpRequest) -> Result<ObjectId, HttpResponse> {
+    get_school_id_from_request(req)
+        .and_then(|id| ObjectId::parse_str(&id).ok())
+        .ok_or_else(|| {
+            HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+        })
+}
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> ReportCardService {
+    ReportCardService::new(&get_database(req, state), &state.db.main_db())
+}
+
+/// Public check of the code printed on a report card
+#[get("/verify/{code}")]
+async fn verify_report_card(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    match service(&req, &state).verify(&path.into_inner()).await {
+        Ok(card) => HttpResponse::Ok().json(serde_json::json!({
+            "valid": true,
+            "report_card": card
+        })),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[get("/template")]
+async fn get_template(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state).get_template(school_id).await {
+        Ok(template) => HttpResponse::Ok().json(template),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/template")]
+async fn update_template(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<UpdateReportCardTemplate>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state).update_template(school_id, &data).await {
+        Ok(template) => HttpResponse::Ok().json(template),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// PDF report card of one student. Cards are only issued for finalized
+/// results; students and parents only get cards of published ones.
+#[get("/students/{student_id}/exams/{exam_id}")]
+async fn download_report_card(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (student_id, exam_id) = path.into_inner();
+
+    let (student, visibility) = match authorize_student(&req, &state, &user, &student_id).await {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    let exam_id = match parse_object_id_value(&exam_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+
+    match service(&req, &state)
+        .generate(school_id, &student, exam_id, visibility)
+        .await
+    {
+        Ok((card, pdf)) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"report_card_{}.pdf\"",
+                    card.verification_code
+                ),
+            ))
+            .body(pdf),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/jobs")]
+async fn get_jobs(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_jobs(query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Generate every card of a class in the background, packed in a zip
+#[post("/jobs")]
+async fn start_job(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<StartReportCardJob>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let school_id = match school_object_id(&req) {
+        Ok(id) => id,
+        Err(res) => return res,
+    };
+    let (class_id, exam_id, user_id) = match (
+        parse_object_id_value(&data.class_id),
+        parse_object_id_value(&data.exam_id),
+        parse_object_id_value(&user.id),
+    ) {
+        (Ok(c), Ok(e), Ok(u)) => (c, e, u),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            return HttpResponse::BadRequest().json(err)
+        }
+    };
+
+    match service(&req, &state)
+        .start_class_job(school_id, class_id, exam_id, user_id)
+        .await
+    {
+        Ok(job) => {
+            if let Some(id) = job.id {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "report_cards.generate",
+                        "report_card_job",
+                        id,
+                        Some(doc! { "class_id": class_id, "exam_id": exam_id }),
+                        None,
+                        Some(AuditSeverity::INFO),
+                    )
+                    .await
+                    .ok();
+            }
+
+            HttpResponse::Accepted().json(job)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/jobs/{id}")]
+async fn get_job(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_job(&id).await {
+        Ok(job) => HttpResponse::Ok().json(job),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[get("/jobs/{id}/download")]
+async fn download_job(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_or_staff(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let job = match service(&req, &state).find_job(&id).await {
+        Ok(job) => job,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+    let Some(file_path) = job.file_path else {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "message": "Report cards are not ready yet",
+            "status": job.status
+        }));
+    };
+
+    match std::fs::read(&file_path) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    file_path.rsplit('/').next().unwrap_or("report_cards.zip")
+                ),
+            ))
+            .body(bytes),
+        Err(e) => HttpResponse::NotFound().json(serde_json::json!({
+            "message": format!("Report card archive is missing: {}", e)
+        })),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(verify_report_card).service(get_template).service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(update_template)
+            .service(download_report_card)
+            .service(get_jobs)
+            .service(start_job)
+            .service(get_job)
+            .service(download_job),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "report-cards", blueprint);
+}
@@ -0,0 +1,466 @@
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        audit_log::AuditSeverity,
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        result_moderation::{
+            CreateReopenRequest, PublishResults, ReviewReopenRequest, SheetTransitionRequest,
+            SubmitResultSheet,
+        },
+    },
+    guards::role_guard::{check_admin_staff_or_teacher, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    schema::common_schema::ActorRef,
+    services::{
+        audit_log_service::AuditLogService, event_service::EventService,
+        result_moderation_service::ResultModerationService, role_service::RoleService,
+    },
+    utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
+};
+
+fn actor(user: &AuthUserDto) -> Result<ActorRef, HttpResponse> {
+    let id = parse_object_id_value(&user.id).map_err(|err| HttpResponse::BadRequest().json(err))?;
+    Ok(ActorRef {
+        id,
+        role: user.role.clone().unwrap_or(UserRole::TEACHER),
+    })
+}
+
+/// Head-of-studies steps are granted through roles, admins always pass
+async fn ensure_permission(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    permission: &str,
+) -> Result<(), HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+    })?;
+
+    let role_service = RoleService::new(&get_database(req, state));
+    require_permission(user, &school_id, permission, &role_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+async fn audit(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    action: &str,
+    entity_type: &str,
+    entity_id: Option<ObjectId>,
+    metadata: mongodb::bson::Document,
+) {
+    let school_id = get_school_id_from_request(req).and_then(|id| ObjectId::parse_str(&id).ok());
+    if let (Some(school_id), Some(entity_id)) = (school_id, entity_id) {
+        AuditLogService::new(&state.db.main_db())
+            .log_event(
+                school_id,
+                user,
+                action,
+                entity_type,
+                entity_id,
+                Some(metadata),
+                None,
+                Some(AuditSeverity::WARNING),
+            )
+            .await
+            .ok();
+    }
+}
+
+fn broadcast_sheet(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    sheet: &crate::domain::result_moderation::ResultSheet,
+) {
+    let sheet_clone = sheet.clone();
+    let state_clone = state.clone();
+    let school_id = get_school_id_from_request(req);
+    actix_rt::spawn(async move {
+        if let Some(id) = sheet_clone.id {
+            EventService::broadcast_updated(
+                &state_clone,
+                "result_sheet",
+                &id.to_hex(),
+                school_id,
+                &sheet_clone,
+            )
+            .await;
+        }
+    });
+}
+
+#[get("")]
+async fn get_result_sheets(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service.get_sheets(query.limit, query.skip, extra_match).await {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/reopen-requests")]
+async fn get_reopen_requests(
+    req: HttpRequest,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service
+        .get_reopen_requests(query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_result_sheet(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service.find_sheet(&id).await {
+        Ok(sheet) => HttpResponse::Ok().json(sheet),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+/// Teacher submits the scores of a class subject for moderation
+#[post("/submit")]
+async fn submit_result_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<SubmitResultSheet>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let exam_id = match parse_object_id_value(&data.exam_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let class_subject_id = match parse_object_id_value(&data.class_subject_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .submit(school_id, exam_id, class_subject_id, &actor, data.note.clone())
+        .await
+    {
+        Ok(sheet) => {
+            broadcast_sheet(&req, &state, &sheet);
+            HttpResponse::Ok().json(sheet)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/moderate")]
+async fn moderate_result_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SheetTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "results.moderate").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let sheet_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .moderate(sheet_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(sheet) => {
+            broadcast_sheet(&req, &state, &sheet);
+            HttpResponse::Ok().json(sheet)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Send a sheet back to the teacher, a note is required
+#[post("/{id}/return")]
+async fn return_result_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SheetTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "results.moderate").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let sheet_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .return_to_draft(sheet_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(sheet) => {
+            broadcast_sheet(&req, &state, &sheet);
+            HttpResponse::Ok().json(sheet)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/finalize")]
+async fn finalize_result_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<SheetTransitionRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "results.finalize").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let sheet_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .finalize(sheet_id, &actor, data.into_inner().note)
+        .await
+    {
+        Ok(sheet) => {
+            audit(
+                &req,
+                &state,
+                &user,
+                "results.finalize",
+                "result_sheet",
+                sheet.id,
+                doc! {
+                    "exam_id": sheet.exam_id,
+                    "class_subject_id": sheet.class_subject_id,
+                },
+            )
+            .await;
+            broadcast_sheet(&req, &state, &sheet);
+            HttpResponse::Ok().json(sheet)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Release the finalized results of a class to students and parents
+#[post("/publish")]
+async fn publish_results(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<PublishResults>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "results.finalize").await {
+        return res;
+    }
+
+    let exam_id = match parse_object_id_value(&data.exam_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let class_id = match parse_object_id_value(&data.class_id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service.publish(exam_id, class_id).await {
+        Ok(published) => {
+            audit(
+                &req,
+                &state,
+                &user,
+                "results.publish",
+                "exam",
+                Some(exam_id),
+                doc! { "class_id": class_id, "published": published as i64 },
+            )
+            .await;
+            HttpResponse::Ok().json(serde_json::json!({ "published": published }))
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[post("/{id}/reopen-requests")]
+async fn create_reopen_request(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<CreateReopenRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let sheet_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .request_reopen(sheet_id, actor, data.into_inner().reason)
+        .await
+    {
+        Ok(request) => HttpResponse::Created().json(request),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/reopen-requests/{id}")]
+async fn review_reopen_request(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<ReviewReopenRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(res) = ensure_permission(&req, &state, &user, "results.finalize").await {
+        return res;
+    }
+
+    let actor = match actor(&user) {
+        Ok(a) => a,
+        Err(res) => return res,
+    };
+    let id = IdType::from_string(path.into_inner());
+    let data = data.into_inner();
+
+    let db = get_database(&req, &state);
+    let service = ResultModerationService::new(&db);
+
+    match service
+        .review_reopen(&id, actor, data.approve, data.note)
+        .await
+    {
+        Ok(request) => {
+            audit(
+                &req,
+                &state,
+                &user,
+                if data.approve {
+                    "results.reopen_approved"
+                } else {
+                    "results.reopen_rejected"
+                },
+                "result_reopen_request",
+                request.id,
+                doc! {
+                    "result_sheet_id": request.result_sheet_id,
+                    "reason": request.reason.clone(),
+                },
+            )
+            .await;
+            HttpResponse::Ok().json(request)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_result_sheets)
+            .service(get_reopen_requests)
+            .service(submit_result_sheet)
+            .service(publish_results)
+            .service(review_reopen_request)
+            .service(get_result_sheet)
+            .service(moderate_result_sheet)
+            .service(return_result_sheet)
+            .service(finalize_result_sheet)
+            .service(create_reopen_request),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "result-sheets", blueprint);
+}
@@ -1,10 +1,14 @@
 use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
 
 use crate::{
+    api::academic_record_api::authorize_student,
     config::state::AppState,
     domain::auth_user::AuthUserDto,
+    guards::role_guard::check_admin_staff_or_teacher,
     models::api_request_model::RequestQuery,
-    services::gpa_calculation_service::GpaCalculationService,
+    services::{
+        academic_record_service::ResultVisibility, gpa_calculation_service::GpaCalculationService,
+    },
     utils::{db_utils::get_database, object_id::parse_object_id_value},
 };
 
@@ -79,18 +83,24 @@ async fn calculate_exam_results(
     }
 }
 
+/// Students and parents only see finalized, published results
 #[get("/student/{student_id}/term/{term_id}")]
 async fn get_student_term_results(
     req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
     path: web::Path<(String, String)>,
     query: web::Query<RequestQuery>,
     state: web::Data<AppState>,
 ) -> impl Responder {
     let (student_id_str, _term_id) = path.into_inner();
 
-    let student_id = match parse_object_id_value(&student_id_str) {
-        Ok(id) => id,
-        Err(err) => return HttpResponse::BadRequest().json(err),
+    let (student, visibility) =
+        match authorize_student(&req, &state, &user, &student_id_str).await {
+            Ok(v) => v,
+            Err(res) => return res,
+        };
+    let Some(student_id) = student.id else {
+        return HttpResponse::NotFound().json(serde_json::json!({ "message": "Student not found" }));
     };
 
     let exam_id = match query.exam_id.as_ref() {
@@ -108,7 +118,10 @@ async fn get_student_term_results(
     let db = get_database(&req, &state);
     let service = GpaCalculationService::new(&db);
 
-    match service.get_student_result(&student_id, &exam_id).await {
+    match service
+        .get_student_result(&student_id, &exam_id, visibility)
+        .await
+    {
         Ok(Some(result)) => HttpResponse::Ok().json(result),
         Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
             "message": "Result not found"
@@ -117,13 +130,18 @@ async fn get_student_term_results(
     }
 }
 
+/// Stored results of the whole class, for school staff and teachers
 #[get("/class/{class_id}/exam/{exam_id}")]
 async fn get_class_exam_results(
     req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
     path: web::Path<(String, String)>,
-    query: web::Query<RequestQuery>,
     state: web::Data<AppState>,
 ) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
     let (class_id_str, exam_id_str) = path.into_inner();
 
     let class_id = match parse_object_id_value(&class_id_str) {
@@ -136,41 +154,11 @@ async fn get_class_exam_results(
         Err(err) => return HttpResponse::BadRequest().json(err),
     };
 
-    let education_year_id = match query.education_year_id.as_ref() {
-        Some(id) => match parse_object_id_value(id) {
-            Ok(id) => id,
-            Err(err) => return HttpResponse::BadRequest().json(err),
-        },
-        None => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "message": "education_year_id is required"
-            }))
-        }
-    };
-
-    let school_id = match query.school_id.as_ref() {
-        Some(id) => match parse_object_id_value(id) {
-            Ok(id) => id,
-            Err(err) => return HttpResponse::BadRequest().json(err),
-        },
-        None => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "message": "school_id is required"
-            }))
-        }
-    };
-
     let db = get_database(&req, &state);
     let service = GpaCalculationService::new(&db);
 
     match service
-        .calculate_class_results(
-            &class_id,
-            &exam_id,
-            &education_year_id,
-            &school_id,
-            query.term_id.clone(),
-        )
+        .get_class_results(&class_id, &exam_id, ResultVisibility::All)
         .await
     {
         Ok(results) => HttpResponse::Ok().json(results),
@@ -179,13 +167,13 @@ async fn get_class_exam_results(
 }
 
 fn blueprint(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_student_term_results)
-        .service(get_class_exam_results)
-        .service(
-            web::scope("")
-                .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
-                .service(calculate_exam_results),
-        );
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(calculate_exam_results)
+            .service(get_student_term_results)
+            .service(get_class_exam_results),
+    );
 }
 
 pub fn init(cfg: &mut web::ServiceConfig) {
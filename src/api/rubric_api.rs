@@ -0,0 +1,274 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Database,
+};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        rubric::{Rubric, RubricPartial},
+    },
+    guards::role_guard::check_admin_staff_or_teacher,
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{event_service::EventService, rubric_service::RubricService},
+    utils::{
+        api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
+    },
+};
+
+/// Teacher record of the signed-in user, if they are a teacher
+async fn current_teacher_id(db: &Database, user: &AuthUserDto) -> Option<ObjectId> {
+    if !matches!(user.role, Some(UserRole::TEACHER)) {
+        return None;
+    }
+    let user_oid = parse_object_id_value(&user.id).ok()?;
+    db.collection::<Document>("teachers")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .and_then(|t| t.get_object_id("_id").ok())
+}
+
+/// Teachers only change their own rubrics; shared ones are managed by
+/// admins and staff
+async fn ensure_can_edit(
+    db: &Database,
+    user: &AuthUserDto,
+    rubric: &Rubric,
+) -> Result<(), HttpResponse> {
+    if !matches!(user.role, Some(UserRole::TEACHER)) {
+        return Ok(());
+    }
+    let teacher_id = current_teacher_id(db, user).await;
+    if teacher_id.is_none() || rubric.teacher_id != teacher_id {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You can only change your own rubrics"
+        })));
+    }
+    Ok(())
+}
+
+#[get("")]
+async fn get_all_rubrics(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let db = get_database(&req, &state);
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    // Teachers see school-wide rubrics and their own
+    let teacher_id = current_teacher_id(&db, &user).await;
+    if matches!(user.role, Some(UserRole::TEACHER)) && teacher_id.is_none() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "message": "Teacher record not found"
+        }));
+    }
+
+    match RubricService::new(&db)
+        .get_all(
+            query.filter.clone(),
+            query.limit,
+            query.skip,
+            extra_match,
+            teacher_id,
+        )
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/{id}")]
+async fn get_rubric_by_id(
+    req: HttpRequest,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+
+    match RubricService::new(&db).find_one(&id).await {
+        Ok(rubric) => HttpResponse::Ok().json(rubric),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("")]
+async fn create_rubric(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<Rubric>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let db = get_database(&req, &state);
+    let mut rubric = data.into_inner();
+
+    // A teacher's rubrics are their own; admins and staff share with the school
+    if matches!(user.role, Some(UserRole::TEACHER)) {
+        match current_teacher_id(&db, &user).await {
+            Some(teacher_id) => rubric.teacher_id = Some(teacher_id),
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "message": "Teacher record not found"
+                }));
+            }
+        }
+    }
+    if let Some(school_id) = get_school_id_from_request(&req) {
+        rubric.school_id = parse_object_id_value(&school_id).ok();
+    }
+    match parse_object_id_value(&user.id) {
+        Ok(user_id) => rubric.created_by = Some(user_id),
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    }
+
+    match RubricService::new(&db).create(rubric).await {
+        Ok(rubric) => {
+            let rubric_clone = rubric.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rubric_clone.id {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "rubric",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rubric_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Created().json(rubric)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/{id}")]
+async fn update_rubric(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<RubricPartial>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = RubricService::new(&db);
+
+    let existing = match service.find_one(&id).await {
+        Ok(rubric) => rubric,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+    if let Err(resp) = ensure_can_edit(&db, &user, &existing).await {
+        return resp;
+    }
+
+    match service.update(&id, &data.into_inner()).await {
+        Ok(rubric) => {
+            let rubric_clone = rubric.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rubric_clone.id {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "rubric",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rubric_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(rubric)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_rubric(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = RubricService::new(&db);
+
+    let existing = match service.find_one(&id).await {
+        Ok(rubric) => rubric,
+        Err(err) => return HttpResponse::NotFound().json(err),
+    };
+    if let Err(resp) = ensure_can_edit(&db, &user, &existing).await {
+        return resp;
+    }
+
+    match service.delete(&id).await {
+        Ok(rubric) => {
+            let rubric_clone = rubric.clone();
+            let state_clone = state.clone();
+            actix_rt::spawn(async move {
+                if let Some(id) = rubric_clone.id {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "rubric",
+                        &id.to_hex(),
+                        get_school_id_from_request(&req),
+                        &rubric_clone,
+                    )
+                    .await;
+                }
+            });
+
+            HttpResponse::Ok().json(rubric)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_all_rubrics)
+            .service(get_rubric_by_id)
+            .service(create_rubric)
+            .service(update_rubric)
+            .service(delete_rubric),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "rubrics", blueprint);
+}
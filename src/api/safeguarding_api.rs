@@ -0,0 +1,263 @@
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::Deserialize;
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        audit_log::AuditSeverity,
+        auth_user::AuthUserDto,
+        common_details::UserRole,
+        safeguarding::{ReportStatus, UpdateMessagingPolicy},
+    },
+    guards::role_guard::{check_admin_or_staff, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::id_model::IdType,
+    schema::common_schema::ActorRef,
+    services::{
+        audit_log_service::AuditLogService, role_service::RoleService,
+        safeguarding_service::SafeguardingService,
+    },
+    utils::db_utils::get_database,
+};
+
+#[derive(Debug, Deserialize)]
+struct ReportQuery {
+    status: Option<ReportStatus>,
+    limit: Option<i64>,
+    skip: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewReportRequest {
+    status: ReportStatus,
+    resolution_note: Option<String>,
+}
+
+fn school_object_id(req: &HttpRequest) -> Result<(String, ObjectId), HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "School ID required" }))
+    })?;
+    let school_oid = ObjectId::parse_str(&school_id).map_err(|_| {
+        HttpResponse::BadRequest().json(serde_json::json!({ "message": "Invalid school ID" }))
+    })?;
+    Ok((school_id, school_oid))
+}
+
+/// Moderation is limited to admins and staff holding the safeguarding permission
+async fn ensure_moderator(
+    user: &AuthUserDto,
+    school_id: &str,
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+) -> Result<(), HttpResponse> {
+    if let Err(e) = check_admin_or_staff(user) {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({ "message": e })));
+    }
+
+    let role_service = RoleService::new(&get_database(req, state));
+    require_permission(user, school_id, "safeguarding.moderate", &role_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+#[get("/messaging-policy")]
+async fn get_messaging_policy(req: HttpRequest, state: web::Data<AppState>) -> impl Responder {
+    let (_, school_oid) = match school_object_id(&req) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+
+    let db = get_database(&req, &state);
+    let service = SafeguardingService::new(&db);
+
+    match service.get_policy(&school_oid).await {
+        Ok(policy) => HttpResponse::Ok().json(policy),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/messaging-policy")]
+async fn update_messaging_policy(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<UpdateMessagingPolicy>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (school_id, school_oid) = match school_object_id(&req) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    if let Err(res) = ensure_moderator(&user, &school_id, &state, &req).await {
+        return res;
+    }
+
+    let user_id = match ObjectId::parse_str(&user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "message": "Invalid user ID" }))
+        }
+    };
+
+    let db = get_database(&req, &state);
+    let service = SafeguardingService::new(&db);
+
+    match service.update_policy(&school_oid, &data, user_id).await {
+        Ok(policy) => {
+            AuditLogService::new(&state.db.main_db())
+                .log_event(
+                    school_oid,
+                    &user,
+                    "safeguarding.policy_update",
+                    "messaging_policy",
+                    school_oid,
+                    Some(doc! {
+                        "students_contact_class_teachers_only": policy.students_contact_class_teachers_only,
+                        "allow_student_to_student": policy.allow_student_to_student,
+                        "parent_oversight": policy.parent_oversight,
+                    }),
+                    None,
+                    Some(AuditSeverity::WARNING),
+                )
+                .await
+                .ok();
+
+            HttpResponse::Ok().json(policy)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/reports")]
+async fn get_reports(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<ReportQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (school_id, school_oid) = match school_object_id(&req) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    if let Err(res) = ensure_moderator(&user, &school_id, &state, &req).await {
+        return res;
+    }
+
+    let mut extra_match = doc! { "school_id": school_oid };
+    if let Some(status) = &query.status {
+        extra_match.insert("status", mongodb::bson::to_bson(status).unwrap());
+    }
+
+    let db = get_database(&req, &state);
+    let service = SafeguardingService::new(&db);
+
+    match service
+        .get_reports(Some(extra_match), query.limit, query.skip)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/reports/{id}")]
+async fn get_report(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (school_id, _) = match school_object_id(&req) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    if let Err(res) = ensure_moderator(&user, &school_id, &state, &req).await {
+        return res;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = SafeguardingService::new(&db);
+
+    match service.find_report(&id).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[put("/reports/{id}/review")]
+async fn review_report(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<ReviewReportRequest>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let (school_id, school_oid) = match school_object_id(&req) {
+        Ok(v) => v,
+        Err(res) => return res,
+    };
+    if let Err(res) = ensure_moderator(&user, &school_id, &state, &req).await {
+        return res;
+    }
+
+    let reviewer_id = match ObjectId::parse_str(&user.id) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "message": "Invalid user ID" }))
+        }
+    };
+    let reviewer = ActorRef {
+        id: reviewer_id,
+        role: user.role.clone().unwrap_or(UserRole::SCHOOLSTAFF),
+    };
+
+    let id = IdType::from_string(path.into_inner());
+    let db = get_database(&req, &state);
+    let service = SafeguardingService::new(&db);
+
+    match service
+        .review_report(&id, data.status.clone(), reviewer, data.resolution_note.clone())
+        .await
+    {
+        Ok(report) => {
+            if let Some(report_id) = report.id {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_oid,
+                        &user,
+                        "safeguarding.report_review",
+                        "message_report",
+                        report_id,
+                        Some(doc! {
+                            "status": mongodb::bson::to_bson(&report.status).ok(),
+                            "message_id": report.message_id,
+                        }),
+                        None,
+                        Some(AuditSeverity::WARNING),
+                    )
+                    .await
+                    .ok();
+            }
+
+            HttpResponse::Ok().json(report)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_messaging_policy)
+            .service(update_messaging_policy)
+            .service(get_reports)
+            .service(get_report)
+            .service(review_report),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "safeguarding", blueprint);
+}
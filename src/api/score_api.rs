@@ -1,14 +1,25 @@
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_multipart::Multipart;
+use actix_web::{
+    delete, get, http::header, post, put, web, HttpRequest, HttpResponse, Responder,
+};
+use futures::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
 
 use crate::{
     config::state::AppState,
     domain::{
+        audit_log::AuditSeverity,
         auth_user::AuthUserDto,
         score::{Score, ScorePartial},
+        score_import::{MarkSheetFormat, MarkSheetQuery},
     },
+    guards::role_guard::check_admin_staff_or_teacher,
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
-    services::{event_service::EventService, score_service::ScoreService},
+    services::{
+        audit_log_service::AuditLogService, event_service::EventService,
+        score_import_service::ScoreImportService, score_service::ScoreService,
+    },
     utils::{api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value},
 };
 
@@ -169,6 +180,137 @@ async fn create_bulk_scores(
     }
 }
 
+/// Mark sheet for one exam, class subject and assessment category,
+/// pre-filled with the class roster
+#[get("/import/template")]
+async fn download_mark_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<MarkSheetQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let db = get_database(&req, &state);
+    let service = ScoreImportService::new(&db);
+
+    match service.template(&query).await {
+        Ok((bytes, format, file_name)) => HttpResponse::Ok()
+            .content_type(match format {
+                MarkSheetFormat::Csv => "text/csv",
+                MarkSheetFormat::Xlsx => {
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                }
+            })
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ))
+            .body(bytes),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Upload a filled CSV or XLSX mark sheet in the `file` field.
+/// With `dry_run=true` nothing is saved and the preview is returned.
+#[post("/import")]
+async fn import_mark_sheet(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<MarkSheetQuery>,
+    mut payload: Multipart,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let user_id = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+
+    let mut file_bytes: Option<Vec<u8>> = None;
+    let mut file_name: Option<String> = None;
+    while let Some(item) = payload.next().await {
+        let mut field = match item {
+            Ok(f) => f,
+            Err(e) => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "message": format!("Multipart error: {}", e) }))
+            }
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        file_name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(|s| s.to_string());
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            match chunk {
+                Ok(data) => bytes.extend_from_slice(&data),
+                Err(e) => {
+                    return HttpResponse::BadRequest()
+                        .json(serde_json::json!({ "message": format!("File read error: {}", e) }))
+                }
+            }
+        }
+        file_bytes = Some(bytes);
+    }
+
+    let Some(file_bytes) = file_bytes else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "Missing mark sheet file" }));
+    };
+
+    let db = get_database(&req, &state);
+    let service = ScoreImportService::new(&db);
+
+    match service
+        .import(&query, &file_bytes, file_name.as_deref(), user_id)
+        .await
+    {
+        Ok(report) => {
+            let school_id =
+                get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+            let exam_id = ObjectId::parse_str(&query.exam_id).ok();
+            if let (true, Some(school_id), Some(exam_id)) = (report.applied, school_id, exam_id) {
+                AuditLogService::new(&state.db.main_db())
+                    .log_event(
+                        school_id,
+                        &user,
+                        "scores.import",
+                        "exam",
+                        exam_id,
+                        Some(doc! {
+                            "class_subject_id": &query.class_subject_id,
+                            "assessment_category_id": &query.assessment_category_id,
+                            "file_name": file_name,
+                            "created": report.created as i64,
+                            "updated": report.updated as i64,
+                        }),
+                        None,
+                        Some(AuditSeverity::INFO),
+                    )
+                    .await
+                    .ok();
+            }
+
+            if report.errors.is_empty() {
+                HttpResponse::Ok().json(report)
+            } else {
+                HttpResponse::UnprocessableEntity().json(report)
+            }
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
 #[put("/{id}")]
 async fn update_score(
     req: HttpRequest,
@@ -256,6 +398,8 @@ fn blueprint(cfg: &mut web::ServiceConfig) {
                 .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
                 .service(create_score)
                 .service(create_bulk_scores)
+                .service(download_mark_sheet)
+                .service(import_mark_sheet)
                 .service(update_score)
                 .service(delete_score),
         );
@@ -1,4 +1,5 @@
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::oid::ObjectId;
 
 use crate::{
     config::state::AppState,
@@ -9,12 +10,19 @@ use crate::{
     guards::role_guard::check_admin_staff_or_teacher,
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
-    services::{event_service::EventService, student_service::StudentService},
+    services::{
+        chat_service::ChatService, event_service::EventService, student_service::StudentService,
+    },
     utils::{
         api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
     },
 };
 
+/// Classes whose chat channels list the student as a member
+fn student_classes(student: &Student) -> impl Iterator<Item = ObjectId> {
+    student.class_id.into_iter().chain(student.subclass_id)
+}
+
 #[get("")]
 async fn get_all_students(
     req: HttpRequest,
@@ -161,6 +169,8 @@ async fn create_student(
 
     match service.create(student, Some(&state)).await {
         Ok(student) => {
+            ChatService::refresh_classes(&db, student_classes(&student));
+
             let student_clone = student.clone();
             let state_clone = state.clone();
             actix_rt::spawn(async move {
@@ -201,8 +211,19 @@ async fn update_student(
     let db = get_database(&req, &state);
     let service = StudentService::new(&db);
 
+    // Moving the student drops them from the channels of the old class
+    let previous = service.find_one(Some(&id), None).await.ok();
+
     match service.update(&id, &data.into_inner()).await {
         Ok(student) => {
+            ChatService::refresh_classes(
+                &db,
+                previous
+                    .iter()
+                    .chain([&student])
+                    .flat_map(student_classes),
+            );
+
             let student_clone = student.clone();
             let state_clone = state.clone();
             actix_rt::spawn(async move {
@@ -249,6 +270,8 @@ async fn delete_student(
 
     match service.delete(&id, user_id).await {
         Ok(student) => {
+            ChatService::refresh_classes(&db, student_classes(&student));
+
             let student_clone = student.clone();
             let state_clone = state.clone();
             actix_rt::spawn(async move {
@@ -289,7 +312,10 @@ async fn restore_student(
     let service = StudentService::new(&db);
 
     match service.restore(&id).await {
-        Ok(student) => HttpResponse::Ok().json(student),
+        Ok(student) => {
+            ChatService::refresh_classes(&db, student_classes(&student));
+            HttpResponse::Ok().json(student)
+        }
         Err(err) => HttpResponse::BadRequest().json(err),
     }
 }
@@ -0,0 +1,113 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+
+use crate::{
+    config::state::AppState,
+    domain::{auth_user::AuthUserDto, submission_similarity::RunSimilarityCheck},
+    guards::role_guard::require_permission,
+    helpers::event_helpers::get_school_id_from_request,
+    models::id_model::IdType,
+    services::{
+        role_service::RoleService, submission_similarity_service::SubmissionSimilarityService,
+    },
+    utils::{db_utils::get_database, object_id::parse_object_id_value},
+};
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> SubmissionSimilarityService {
+    SubmissionSimilarityService::new(&get_database(req, state), &state.db.main_db())
+}
+
+/// Reports name students and quote their work, so only graders see them
+async fn ensure_grader(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+) -> Result<(), HttpResponse> {
+    let school_id = get_school_id_from_request(req).ok_or_else(|| {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "message": "School ID required"
+        }))
+    })?;
+
+    let role_service = RoleService::new(&get_database(req, state));
+    require_permission(user, &school_id, "submission.grade", &role_service)
+        .await
+        .map_err(|e| HttpResponse::Forbidden().json(serde_json::json!({ "message": e })))
+}
+
+#[post("/assignments/{id}")]
+async fn run_similarity_check(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: Option<web::Json<RunSimilarityCheck>>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = ensure_grader(&req, &state, &user).await {
+        return resp;
+    }
+
+    let assignment_id = IdType::from_string(path.into_inner());
+    let options = data.map(|d| d.into_inner()).unwrap_or_default();
+    let generated_by = parse_object_id_value(&user.id).ok();
+
+    match service(&req, &state)
+        .run(&assignment_id, options, generated_by)
+        .await
+    {
+        Ok(report) => HttpResponse::Created().json(report),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/assignments/{id}")]
+async fn get_latest_report(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = ensure_grader(&req, &state, &user).await {
+        return resp;
+    }
+
+    let assignment_id = IdType::from_string(path.into_inner());
+    match service(&req, &state)
+        .latest_for_assignment(&assignment_id)
+        .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[get("/reports/{id}")]
+async fn get_report_by_id(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(resp) = ensure_grader(&req, &state, &user).await {
+        return resp;
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_one(&id).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(run_similarity_check)
+            .service(get_latest_report)
+            .service(get_report_by_id),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "similarity", blueprint);
+}
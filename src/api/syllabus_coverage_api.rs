@@ -0,0 +1,346 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        auth_user::AuthUserDto,
+        class_subject::ClassSubject,
+        common_details::UserRole,
+        syllabus_coverage::{CreateLessonLog, LessonLog, UpdateLessonLog},
+    },
+    guards::role_guard::{check_admin_staff_or_teacher, require_permission},
+    helpers::event_helpers::get_school_id_from_request,
+    models::{api_request_model::RequestQuery, id_model::IdType},
+    services::{
+        event_service::EventService, role_service::RoleService,
+        syllabus_coverage_service::SyllabusCoverageService,
+    },
+    utils::{
+        api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
+    },
+};
+
+fn service(req: &HttpRequest, state: &web::Data<AppState>) -> SyllabusCoverageService {
+    SyllabusCoverageService::new(&get_database(req, state), &state.db.main_db())
+}
+
+fn broadcast_lesson(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    log: &LessonLog,
+    action: &'static str,
+) {
+    let log_clone = log.clone();
+    let state_clone = state.clone();
+    let school_id = get_school_id_from_request(req);
+    actix_rt::spawn(async move {
+        if let Some(id) = log_clone.id {
+            let id = id.to_hex();
+            match action {
+                "created" => {
+                    EventService::broadcast_created(
+                        &state_clone,
+                        "lesson_log",
+                        &id,
+                        school_id,
+                        &log_clone,
+                    )
+                    .await
+                }
+                "deleted" => {
+                    EventService::broadcast_deleted(
+                        &state_clone,
+                        "lesson_log",
+                        &id,
+                        school_id,
+                        &log_clone,
+                    )
+                    .await
+                }
+                _ => {
+                    EventService::broadcast_updated(
+                        &state_clone,
+                        "lesson_log",
+                        &id,
+                        school_id,
+                        &log_clone,
+                    )
+                    .await
+                }
+            }
+        }
+    });
+}
+
+/// Teachers only log lessons for subjects they teach
+async fn ensure_subject_teacher(
+    req: &HttpRequest,
+    state: &web::Data<AppState>,
+    user: &AuthUserDto,
+    subject: &ClassSubject,
+) -> Result<(), HttpResponse> {
+    if !matches!(user.role, Some(UserRole::TEACHER)) {
+        return Ok(());
+    }
+
+    let user_oid =
+        parse_object_id_value(&user.id).map_err(|e| HttpResponse::BadRequest().json(e))?;
+    let teacher = get_database(req, state)
+        .collection::<Document>("teachers")
+        .find_one(doc! { "user_id": user_oid })
+        .await
+        .ok()
+        .flatten()
+        .and_then(|t| t.get_object_id("_id").ok());
+
+    if teacher.is_none() || teacher != subject.teacher_id {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You can only log lessons for subjects you teach"
+        })));
+    }
+    Ok(())
+}
+
+/// Teachers only change lessons they logged themselves
+fn ensure_own_lesson(user: &AuthUserDto, log: &LessonLog) -> Result<(), HttpResponse> {
+    if matches!(user.role, Some(UserRole::TEACHER)) && log.logged_by.to_hex() != user.id {
+        return Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "message": "You can only change lessons you logged"
+        })));
+    }
+    Ok(())
+}
+
+#[get("/lessons")]
+async fn get_lessons(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let extra_match = match build_extra_match(&query) {
+        Ok(doc) => doc,
+        Err(err) => return err,
+    };
+
+    match service(&req, &state)
+        .get_all(query.filter.clone(), query.limit, query.skip, extra_match)
+        .await
+    {
+        Ok(data) => HttpResponse::Ok().json(data),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[get("/lessons/{id}")]
+async fn get_lesson(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    match service(&req, &state).find_one(&id).await {
+        Ok(log) => HttpResponse::Ok().json(log),
+        Err(err) => HttpResponse::NotFound().json(err),
+    }
+}
+
+#[post("/lessons")]
+async fn create_lesson(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    data: web::Json<CreateLessonLog>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let logged_by = match parse_object_id_value(&user.id) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let service = service(&req, &state);
+
+    let subject = match parse_object_id_value(&data.class_subject_id) {
+        Ok(id) => match service.find_subject(id).await {
+            Ok(subject) => subject,
+            Err(err) => return HttpResponse::NotFound().json(err),
+        },
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    if let Err(res) = ensure_subject_teacher(&req, &state, &user, &subject).await {
+        return res;
+    }
+
+    let school_id = get_school_id_from_request(&req).and_then(|id| ObjectId::parse_str(&id).ok());
+    match service
+        .create(school_id, data.into_inner(), logged_by)
+        .await
+    {
+        Ok(log) => {
+            broadcast_lesson(&req, &state, &log, "created");
+            HttpResponse::Created().json(log)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[put("/lessons/{id}")]
+async fn update_lesson(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    data: web::Json<UpdateLessonLog>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+    match service.find_one(&id).await {
+        Ok(log) => {
+            if let Err(res) = ensure_own_lesson(&user, &log) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service.update(&id, data.into_inner()).await {
+        Ok(log) => {
+            broadcast_lesson(&req, &state, &log, "updated");
+            HttpResponse::Ok().json(log)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+#[delete("/lessons/{id}")]
+async fn delete_lesson(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let id = IdType::from_string(path.into_inner());
+    let service = service(&req, &state);
+    match service.find_one(&id).await {
+        Ok(log) => {
+            if let Err(res) = ensure_own_lesson(&user, &log) {
+                return res;
+            }
+        }
+        Err(err) => return HttpResponse::NotFound().json(err),
+    }
+
+    match service.delete(&id).await {
+        Ok(log) => {
+            broadcast_lesson(&req, &state, &log, "deleted");
+            HttpResponse::Ok().json(log)
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Coverage of one class subject, optionally for `?education_year_id=`
+#[get("/subjects/{class_subject_id}")]
+async fn get_subject_coverage(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    path: web::Path<String>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    if let Err(e) = check_admin_staff_or_teacher(&user) {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let class_subject_id = match parse_object_id_value(&path.into_inner()) {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let year_id = query.education_year_id.clone().map(IdType::from_string);
+
+    match service(&req, &state)
+        .subject_coverage(class_subject_id, year_id.as_ref())
+        .await
+    {
+        Ok(coverage) => HttpResponse::Ok().json(coverage),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// School-wide coverage for heads of studies, subjects furthest behind first.
+/// Accepts `?education_year_id=` and `?class_id=`.
+#[get("/dashboard")]
+async fn get_dashboard(
+    req: HttpRequest,
+    user: web::ReqData<AuthUserDto>,
+    query: web::Query<RequestQuery>,
+    state: web::Data<AppState>,
+) -> impl Responder {
+    let Some(school_id) = get_school_id_from_request(&req) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "message": "School ID required" }));
+    };
+    let role_service = RoleService::new(&get_database(&req, &state));
+    if let Err(e) =
+        require_permission(&user, &school_id, "syllabus.view_coverage", &role_service).await
+    {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "message": e }));
+    }
+
+    let class_id = match query
+        .class_id
+        .as_deref()
+        .map(parse_object_id_value)
+        .transpose()
+    {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let year_id = query.education_year_id.clone().map(IdType::from_string);
+
+    match service(&req, &state)
+        .dashboard(year_id.as_ref(), class_id)
+        .await
+    {
+        Ok(dashboard) => HttpResponse::Ok().json(dashboard),
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+fn blueprint(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("")
+            .wrap(crate::middleware::jwt_middleware::JwtMiddleware)
+            .service(get_dashboard)
+            .service(get_lessons)
+            .service(create_lesson)
+            .service(get_lesson)
+            .service(update_lesson)
+            .service(delete_lesson)
+            .service(get_subject_coverage),
+    );
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    crate::utils::route_utils::mount_dual_routes(cfg, "syllabus-coverage", blueprint);
+}
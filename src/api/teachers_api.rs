@@ -9,7 +9,9 @@ use crate::{
     guards::role_guard::check_admin_or_staff,
     helpers::event_helpers::get_school_id_from_request,
     models::{api_request_model::RequestQuery, id_model::IdType},
-    services::{event_service::EventService, teacher_service::TeacherService},
+    services::{
+        chat_service::ChatService, event_service::EventService, teacher_service::TeacherService,
+    },
     utils::{
         api_utils::build_extra_match, db_utils::get_database, object_id::parse_object_id_value,
     },
@@ -205,8 +207,25 @@ async fn update_teacher(
     let db = get_database(&req, &state);
     let service = TeacherService::new(&db);
 
+    let previous_class_ids = service
+        .find_one(Some(&id), None)
+        .await
+        .ok()
+        .and_then(|t| t.class_ids)
+        .unwrap_or_default();
+
     match service.update(&id, &data.into_inner()).await {
         Ok(teacher) => {
+            if let Some(teacher_id) = teacher.id {
+                ChatService::refresh_member(
+                    &db,
+                    teacher_id,
+                    previous_class_ids
+                        .into_iter()
+                        .chain(teacher.class_ids.clone().unwrap_or_default()),
+                );
+            }
+
             let teacher_clone = teacher.clone();
             let state_clone = state.clone();
 
@@ -249,6 +268,10 @@ async fn delete_teacher(
 
     match service.delete(&id).await {
         Ok(teacher) => {
+            if let Some(teacher_id) = teacher.id {
+                ChatService::refresh_member(&db, teacher_id, []);
+            }
+
             let teacher_clone = teacher.clone();
             let state_clone = state.clone();
 
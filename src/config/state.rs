@@ -1,11 +1,13 @@
 use crate::config::mongo_manager::MongoManager;
 use crate::services::event_bus::EventBus;
+use crate::services::messaging_hub::MessagingHub;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: MongoManager, // new
     pub event_bus: Arc<EventBus>,
+    pub messaging_hub: Arc<MessagingHub>,
 }
 
 impl AppState {
@@ -13,6 +15,7 @@ impl AppState {
         Self {
             db,
             event_bus: Arc::new(EventBus::new()),
+            messaging_hub: Arc::new(MessagingHub::new()),
         }
     }
 }
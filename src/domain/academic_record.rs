@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{exam::ExamType, student_term_result::SubjectResult},
+    helpers::object_id_helpers,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExamTypeWeight {
+    pub exam_type: ExamType,
+    pub weight_percentage: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TermWeight {
+    pub term_id: String,
+    pub weight_percentage: f64,
+}
+
+/// How exam results roll up into a term result and terms into the year.
+/// Empty lists mean equal weights.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResultWeighting {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub education_year_id: ObjectId,
+
+    #[serde(default)]
+    pub exam_type_weights: Vec<ExamTypeWeight>,
+
+    #[serde(default)]
+    pub term_weights: Vec<TermWeight>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ResultWeighting {
+    pub fn new(school_id: Option<ObjectId>, education_year_id: ObjectId) -> Self {
+        Self {
+            id: None,
+            school_id,
+            education_year_id,
+            exam_type_weights: vec![],
+            term_weights: vec![],
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateResultWeighting {
+    pub education_year_id: String,
+    pub exam_type_weights: Option<Vec<ExamTypeWeight>>,
+    pub term_weights: Option<Vec<TermWeight>>,
+}
+
+/// One term, rolled up from the exams sat in it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TermRecord {
+    pub term_id: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_vec_oid",
+        deserialize_with = "object_id_helpers::deserialize_vec_oid"
+    )]
+    pub exam_ids: Vec<ObjectId>,
+
+    pub subject_results: Vec<SubjectResult>,
+    pub average_percentage: f64,
+    pub gpa: f64,
+    pub grade: String,
+    pub total_credits: Option<i32>,
+
+    /// True when every exam result behind this term is finalized
+    pub is_finalized: bool,
+}
+
+/// One education year, rolled up from its terms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnnualRecord {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub education_year_id: ObjectId,
+    pub education_year_label: Option<String>,
+    pub start_date: Option<DateTime<Utc>>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_id: Option<ObjectId>,
+
+    pub terms: Vec<TermRecord>,
+    pub subject_results: Vec<SubjectResult>,
+    pub average_percentage: f64,
+    pub gpa: f64,
+    pub max_gpa: f64,
+    pub grade: String,
+    pub total_credits: Option<i32>,
+    pub is_finalized: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CumulativeRecord {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+    pub years: Vec<AnnualRecord>,
+    pub cumulative_gpa: f64,
+    pub max_gpa: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TranscriptSchool {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub school_id: ObjectId,
+    pub school_name: Option<String>,
+    pub registration_number: Option<String>,
+    pub years: Vec<AnnualRecord>,
+}
+
+/// Finalized history of a student across every school they attended
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Transcript {
+    pub student_name: String,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub user_id: Option<ObjectId>,
+
+    pub schools: Vec<TranscriptSchool>,
+    pub cumulative_gpa: f64,
+    pub max_gpa: f64,
+    pub generated_at: DateTime<Utc>,
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{domain::education_year::Term, helpers::object_id_helpers};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RolloverStatus {
+    #[default]
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Steps run in this order; completed steps are skipped when a job is resumed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RolloverStep {
+    CreateEducationYear,
+    ClassStructure,
+    AssessmentCategories,
+    GradingScales,
+    ClassTimetables,
+    ArchiveExams,
+}
+
+impl RolloverStep {
+    pub const ALL: [RolloverStep; 6] = [
+        RolloverStep::CreateEducationYear,
+        RolloverStep::ClassStructure,
+        RolloverStep::AssessmentCategories,
+        RolloverStep::GradingScales,
+        RolloverStep::ClassTimetables,
+        RolloverStep::ArchiveExams,
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RolloverStepProgress {
+    pub step: RolloverStep,
+
+    #[serde(default)]
+    pub status: RolloverStatus,
+
+    #[serde(default)]
+    pub total: i64,
+
+    #[serde(default)]
+    pub processed: i64,
+
+    /// Items already present in the new year (e.g. from an earlier attempt)
+    #[serde(default)]
+    pub skipped: i64,
+
+    pub message: Option<String>,
+}
+
+impl RolloverStepProgress {
+    pub fn new(step: RolloverStep) -> Self {
+        Self {
+            step,
+            status: RolloverStatus::Pending,
+            total: 0,
+            processed: 0,
+            skipped: 0,
+            message: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AcademicYearRollover {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub from_education_year_id: ObjectId,
+
+    /// Set once the new year exists
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub to_education_year_id: Option<ObjectId>,
+
+    pub label: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub terms: Vec<Term>,
+
+    #[serde(default)]
+    pub status: RolloverStatus,
+
+    pub steps: Vec<RolloverStepProgress>,
+
+    pub error_message: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartRollover {
+    pub from_education_year_id: String,
+    pub label: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+
+    /// When empty, the previous year's terms are shifted to the new start date
+    #[serde(default)]
+    pub terms: Vec<Term>,
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// What a feedback provider gets to see of a submission
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedbackRequest {
+    pub assignment_title: String,
+    pub instructions: String,
+    pub rubric: Option<String>,
+    pub submission_text: String,
+    pub max_score: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedbackSuggestion {
+    pub feedback: String,
+    /// Provisional, between 0 and the assignment's max score
+    pub score: f64,
+    pub provider: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GenerateAiFeedback {
+    /// Overrides the rubric attached to the assignment
+    pub rubric: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AiFeedbackSettings {
+    pub enabled: bool,
+}
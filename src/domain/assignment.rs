@@ -1,8 +1,15 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
-use crate::{helpers::object_id_helpers, make_partial};
+use crate::{
+    domain::{
+        class::ClassworkRules,
+        rubric::{CriterionScore, CriterionScoreInput},
+    },
+    helpers::object_id_helpers,
+    make_partial,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum AssignmentStatus {
@@ -19,6 +26,34 @@ pub enum SubmissionStatus {
     Graded,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GroupFormation {
+    /// The teacher puts students into groups
+    #[default]
+    Teacher,
+    /// Students create and join groups themselves, up to the size limit
+    SelfEnroll,
+}
+
+/// Makes an assignment a group project with one shared submission per group
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupSettings {
+    #[serde(default)]
+    pub formation: GroupFormation,
+    pub max_group_size: i32,
+    /// Ask every member for a statement of what they contributed
+    #[serde(default)]
+    pub contribution_statements: bool,
+}
+
+/// AI suggestions stay drafts, hidden from students, until a teacher
+/// accepts them while grading
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AiFeedbackStatus {
+    Draft,
+    Accepted,
+}
+
 make_partial! {
     #[derive(Debug, Serialize, Deserialize, Clone)]
     pub struct Assignment {
@@ -55,6 +90,15 @@ make_partial! {
         )]
         pub subject_id: Option<ObjectId>,
 
+        /// Subject topic this belongs to, see `learning_outcome`
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub topic_id: Option<ObjectId>,
+
         #[serde(
             serialize_with = "object_id_helpers::serialize",
             deserialize_with = "object_id_helpers::deserialize",
@@ -63,6 +107,32 @@ make_partial! {
         )]
         pub teacher_id: Option<ObjectId>,
 
+        /// Rubric to grade against; graders then score each criterion
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub rubric_id: Option<ObjectId>,
+
+        /// Grades flow into `Score` under this category when set
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub assessment_category_id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub exam_id: Option<ObjectId>,
+
         pub title: String,
         pub description: Option<String>,
         pub instructions: Option<String>,
@@ -73,6 +143,13 @@ make_partial! {
         #[serde(default)]
         pub allow_late_submission: bool,
 
+        /// Overrides for the class `ClassworkRules`, see `SubmissionPolicy`
+        pub allow_resubmission: Option<bool>,
+        /// 0 refuses late work even when the class accepts it
+        pub max_late_days: Option<i64>,
+        pub late_penalty_per_day: Option<f64>,
+        pub max_late_penalty: Option<f64>,
+
         pub attachment_url: Option<String>,
         pub attachment_id: Option<String>,
 
@@ -82,6 +159,9 @@ make_partial! {
         #[serde(default)]
         pub auto_grade_enabled: bool,
 
+        /// Set for group projects; individual work otherwise
+        pub group_settings: Option<GroupSettings>,
+
         #[serde(default)]
         pub is_deleted: bool,
 
@@ -123,18 +203,45 @@ make_partial! {
         )]
         pub student_id: Option<ObjectId>,
 
+        /// Group the submission belongs to on group projects; `student_id`
+        /// is then the member who handed it in
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub group_id: Option<ObjectId>,
+
         pub file_url: Option<String>,
         pub file_id: Option<String>,
         pub comment: Option<String>,
 
+        /// Answer typed in online instead of, or next to, an uploaded file
+        pub text_content: Option<String>,
+
         #[serde(default)]
         pub is_late: bool,
+        pub late_days: Option<i64>,
+        /// Percent taken off the score for lateness when graded
+        pub late_penalty: Option<f64>,
 
         pub score: Option<f64>,
+        /// Score the grader gave, before the late penalty
+        pub raw_score: Option<f64>,
+        /// Per-criterion breakdown when the assignment has a rubric
+        pub rubric_scores: Option<Vec<CriterionScore>>,
         pub feedback: Option<String>,
         pub feedback_file_url: Option<String>,
         pub feedback_file_id: Option<String>,
 
+        /// What each group member says they contributed
+        #[serde(default)]
+        pub contributions: Vec<ContributionStatement>,
+        /// Every group member's grade; `score` is the group's grade
+        #[serde(default)]
+        pub member_grades: Vec<MemberGrade>,
+
         pub graded_at: Option<DateTime<Utc>>,
 
         #[serde(
@@ -149,7 +256,22 @@ make_partial! {
         pub status: SubmissionStatus,
 
         pub auto_grade_score: Option<f64>,
+        /// Why the grade could not be carried into the gradebook, e.g.
+        /// results already moderated; cleared once it goes through
+        pub score_sync_error: Option<String>,
+        /// Mean of the peer review scores once the review round is finalized
+        pub peer_review_score: Option<f64>,
         pub ai_feedback: Option<String>,
+        pub ai_suggested_score: Option<f64>,
+        pub ai_feedback_status: Option<AiFeedbackStatus>,
+        pub ai_feedback_generated_at: Option<DateTime<Utc>>,
+
+        /// Starts at 1 and goes up with every resubmission
+        #[serde(default = "default_version")]
+        pub version: i32,
+        /// Earlier versions, oldest first
+        #[serde(default)]
+        pub versions: Vec<SubmissionVersion>,
 
         #[serde(default)]
         pub is_deleted: bool,
@@ -164,6 +286,178 @@ make_partial! {
     } => SubmissionPartial
 }
 
+fn default_version() -> i32 {
+    1
+}
+
+/// Snapshot of a submission taken when the student resubmits
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmissionVersion {
+    pub version: i32,
+    pub file_url: Option<String>,
+    pub file_id: Option<String>,
+    pub comment: Option<String>,
+    pub text_content: Option<String>,
+    pub is_late: bool,
+    pub late_days: Option<i64>,
+    pub submitted_at: DateTime<Utc>,
+    pub score: Option<f64>,
+    pub feedback: Option<String>,
+    pub graded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContributionStatement {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    pub statement: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One member's share of a graded group submission
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemberGrade {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    /// Group score or the member's adjusted score, before the late penalty
+    pub raw_score: f64,
+    pub score: f64,
+    /// True when the grader set this member's score apart from the group's
+    #[serde(default)]
+    pub adjusted: bool,
+    pub feedback: Option<String>,
+}
+
+/// Submission rules in force for an assignment: its own settings, falling
+/// back to the class `ClassworkRules`. An assignment that does not allow
+/// late work closes at its due date whatever the class accepts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmissionPolicy {
+    pub due_date: DateTime<Utc>,
+    pub allow_resubmission: bool,
+    pub accepts_late: bool,
+    /// None with `accepts_late` means late work is taken at any time
+    pub max_late_days: Option<i64>,
+    /// Last moment a submission is accepted, if there is one
+    pub closes_at: Option<DateTime<Utc>>,
+    pub late_penalty_per_day: f64,
+    pub max_late_penalty: f64,
+}
+
+impl SubmissionPolicy {
+    pub fn resolve(assignment: &Assignment, rules: Option<&ClassworkRules>) -> Self {
+        let max_late_days = assignment
+            .max_late_days
+            .map(|d| d.max(0))
+            .or_else(|| rules.and_then(|r| r.max_late_days()));
+        let accepts_late =
+            assignment.allow_late_submission && max_late_days.is_none_or(|days| days > 0);
+
+        let closes_at = match (accepts_late, max_late_days) {
+            (false, _) => Some(assignment.due_date),
+            (true, Some(days)) => Some(assignment.due_date + Duration::days(days)),
+            (true, None) => None,
+        };
+
+        Self {
+            due_date: assignment.due_date,
+            allow_resubmission: assignment
+                .allow_resubmission
+                .or(rules.and_then(|r| r.allow_resubmission))
+                .unwrap_or(false),
+            accepts_late,
+            max_late_days: if accepts_late { max_late_days } else { Some(0) },
+            closes_at,
+            late_penalty_per_day: assignment
+                .late_penalty_per_day
+                .or(rules.and_then(|r| r.late_penalty_per_day))
+                .unwrap_or(0.0)
+                .clamp(0.0, 100.0),
+            max_late_penalty: assignment
+                .max_late_penalty
+                .or(rules.and_then(|r| r.max_late_penalty))
+                .unwrap_or(100.0)
+                .clamp(0.0, 100.0),
+        }
+    }
+
+    /// Started days past the due date; 0 when on time
+    pub fn late_days(&self, at: DateTime<Utc>) -> i64 {
+        let late = at - self.due_date;
+        if late <= Duration::zero() {
+            return 0;
+        }
+        let whole = late.num_days();
+        if late > Duration::days(whole) {
+            whole + 1
+        } else {
+            whole
+        }
+    }
+
+    /// Days late, or an error once the submission window has closed
+    pub fn check_deadline(&self, at: DateTime<Utc>) -> Result<i64, String> {
+        if self.closes_at.is_some_and(|closes_at| at > closes_at) {
+            return Err(if self.accepts_late {
+                format!(
+                    "Late submissions are only accepted up to {} day(s) after the deadline",
+                    self.max_late_days.unwrap_or(0)
+                )
+            } else {
+                "Submission deadline has passed and late submissions are not allowed".into()
+            });
+        }
+        Ok(self.late_days(at))
+    }
+
+    /// Percent taken off a score submitted this many days late
+    pub fn penalty_percent(&self, late_days: i64) -> f64 {
+        (late_days.max(0) as f64 * self.late_penalty_per_day).min(self.max_late_penalty)
+    }
+}
+
+impl Submission {
+    /// Strip an unaccepted AI suggestion before showing the submission to a
+    /// student or parent
+    pub fn hide_ai_draft(&mut self) {
+        if self.ai_feedback_status != Some(AiFeedbackStatus::Accepted) {
+            self.ai_feedback = None;
+            self.ai_suggested_score = None;
+            self.ai_feedback_status = None;
+            self.ai_feedback_generated_at = None;
+        }
+    }
+
+    /// Keep only the given member's grade on a group submission, so
+    /// members do not see each other's adjustments
+    pub fn keep_member_grade(&mut self, student_id: &ObjectId) {
+        self.member_grades.retain(|g| &g.student_id == student_id);
+    }
+
+    /// Final score for one student: their member grade on a group
+    /// submission, the submission score otherwise
+    pub fn score_for(&self, student_id: &ObjectId) -> Option<f64> {
+        if self.group_id.is_some() {
+            self.member_grades
+                .iter()
+                .find(|g| &g.student_id == student_id)
+                .map(|g| g.score)
+        } else if self.student_id.as_ref() == Some(student_id) {
+            self.score
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AssignmentWithRelations {
     #[serde(flatten)]
@@ -184,4 +478,155 @@ pub struct SubmissionWithRelations {
     pub student: Option<crate::domain::student::Student>,
     pub assignment: Option<Assignment>,
     pub graded_by_teacher: Option<crate::domain::teacher::Teacher>,
+    pub rubric: Option<crate::domain::rubric::Rubric>,
+    pub group: Option<crate::domain::assignment_group::AssignmentGroup>,
+}
+
+/// Grading input for `AssignmentService::grade_submission`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GradeSubmission {
+    /// Required unless it comes from criterion scores or an accepted AI draft
+    pub score: Option<f64>,
+    pub feedback: Option<String>,
+    pub feedback_file: Option<String>,
+    #[serde(default)]
+    pub accept_ai_feedback: bool,
+    /// One entry per rubric criterion; the total becomes the score
+    pub criterion_scores: Option<Vec<CriterionScoreInput>>,
+    /// Group submissions: members graded apart from the group; everyone
+    /// else gets the group score
+    pub member_scores: Option<Vec<MemberScoreInput>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemberScoreInput {
+    pub student_id: String,
+    pub score: f64,
+    pub feedback: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmitContribution {
+    pub statement: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn due() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 17, 0, 0).unwrap()
+    }
+
+    fn assignment(allow_late_submission: bool) -> Assignment {
+        serde_json::from_value(serde_json::json!({
+            "title": "Essay",
+            "due_date": due(),
+            "max_score": 20.0,
+            "allow_late_submission": allow_late_submission,
+        }))
+        .unwrap()
+    }
+
+    fn rules(max_late_days: &str) -> ClassworkRules {
+        ClassworkRules {
+            allow_resubmission: Some(true),
+            max_late_days: Some(max_late_days.into()),
+            late_penalty_per_day: Some(10.0),
+            max_late_penalty: Some(25.0),
+        }
+    }
+
+    #[test]
+    fn late_work_turned_off_on_the_assignment_beats_the_class() {
+        let policy = SubmissionPolicy::resolve(&assignment(false), Some(&rules("3")));
+
+        assert!(!policy.accepts_late);
+        assert_eq!(policy.closes_at, Some(due()));
+        assert_eq!(policy.max_late_days, Some(0));
+        assert!(policy.allow_resubmission);
+    }
+
+    #[test]
+    fn class_late_window_applies_when_the_assignment_allows_late_work() {
+        let policy = SubmissionPolicy::resolve(&assignment(true), Some(&rules("3")));
+
+        assert!(policy.accepts_late);
+        assert_eq!(policy.max_late_days, Some(3));
+        assert_eq!(policy.closes_at, Some(due() + Duration::days(3)));
+        assert_eq!(policy.late_penalty_per_day, 10.0);
+        assert_eq!(policy.max_late_penalty, 25.0);
+    }
+
+    #[test]
+    fn assignment_settings_override_the_class() {
+        let mut a = assignment(true);
+        a.max_late_days = Some(0);
+        let policy = SubmissionPolicy::resolve(&a, Some(&rules("3")));
+        assert!(!policy.accepts_late);
+        assert_eq!(policy.closes_at, Some(due()));
+
+        a.max_late_days = Some(5);
+        a.late_penalty_per_day = Some(150.0);
+        a.allow_resubmission = Some(false);
+        let policy = SubmissionPolicy::resolve(&a, Some(&rules("3")));
+        assert_eq!(policy.closes_at, Some(due() + Duration::days(5)));
+        assert_eq!(policy.late_penalty_per_day, 100.0);
+        assert!(!policy.allow_resubmission);
+    }
+
+    #[test]
+    fn late_work_without_a_limit_never_closes() {
+        let policy = SubmissionPolicy::resolve(&assignment(true), Some(&rules("soon")));
+
+        assert!(policy.accepts_late);
+        assert_eq!(policy.max_late_days, None);
+        assert_eq!(policy.closes_at, None);
+        assert_eq!(policy.late_penalty_per_day, 10.0);
+
+        let policy = SubmissionPolicy::resolve(&assignment(true), None);
+        assert_eq!(policy.closes_at, None);
+        assert_eq!(policy.late_penalty_per_day, 0.0);
+        assert_eq!(policy.max_late_penalty, 100.0);
+    }
+
+    #[test]
+    fn late_days_counts_started_days() {
+        let policy = SubmissionPolicy::resolve(&assignment(true), None);
+
+        assert_eq!(policy.late_days(due() - Duration::hours(1)), 0);
+        assert_eq!(policy.late_days(due()), 0);
+        assert_eq!(policy.late_days(due() + Duration::minutes(1)), 1);
+        assert_eq!(policy.late_days(due() + Duration::days(1)), 1);
+        assert_eq!(policy.late_days(due() + Duration::days(1) + Duration::seconds(1)), 2);
+    }
+
+    #[test]
+    fn check_deadline_refuses_after_the_window() {
+        let closed = SubmissionPolicy::resolve(&assignment(false), None);
+        assert_eq!(closed.check_deadline(due()), Ok(0));
+        assert!(closed
+            .check_deadline(due() + Duration::minutes(1))
+            .unwrap_err()
+            .contains("not allowed"));
+
+        let open = SubmissionPolicy::resolve(&assignment(true), Some(&rules("2")));
+        assert_eq!(open.check_deadline(due() + Duration::hours(30)), Ok(2));
+        assert_eq!(open.check_deadline(due() + Duration::days(2)), Ok(2));
+        assert!(open
+            .check_deadline(due() + Duration::days(2) + Duration::seconds(1))
+            .unwrap_err()
+            .contains("2 day(s)"));
+    }
+
+    #[test]
+    fn penalty_percent_is_capped() {
+        let policy = SubmissionPolicy::resolve(&assignment(true), Some(&rules("5")));
+
+        assert_eq!(policy.penalty_percent(0), 0.0);
+        assert_eq!(policy.penalty_percent(-2), 0.0);
+        assert_eq!(policy.penalty_percent(2), 20.0);
+        assert_eq!(policy.penalty_percent(4), 25.0);
+    }
 }
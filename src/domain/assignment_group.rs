@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+/// Students working together on a group assignment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssignmentGroup {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    pub name: String,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_vec_oid",
+        deserialize_with = "object_id_helpers::deserialize_vec_oid",
+        default
+    )]
+    pub member_ids: Vec<ObjectId>,
+
+    /// User who formed the group, a teacher or the student who started it
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssignmentGroupWithMembers {
+    #[serde(flatten)]
+    pub group: AssignmentGroup,
+
+    #[serde(default)]
+    pub members: Vec<crate::domain::student::Student>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateAssignmentGroup {
+    pub name: String,
+    /// Ignored when a student starts a group; they become its only member
+    #[serde(default)]
+    pub member_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateAssignmentGroup {
+    pub name: Option<String>,
+    pub member_ids: Option<Vec<String>>,
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::common_details::UserRole, helpers::object_id_helpers,
+    schema::common_schema::ActorRef,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChatChannelType {
+    #[default]
+    Class,
+    Subject,
+}
+
+/// A member of a managed channel. `id` is the school member id
+/// (student / teacher document id), not the global user id.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatChannelMember {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub user_id: Option<ObjectId>,
+
+    pub name: String,
+    pub role: UserRole,
+}
+
+/// Channel automatically managed for a class or a class subject.
+/// Membership is rebuilt from enrolled students and assigned teachers.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatChannel {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(default)]
+    pub channel_type: ChatChannelType,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_subject_id: Option<ObjectId>,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub members: Vec<ChatChannelMember>,
+
+    /// When true only teachers and school staff can post
+    #[serde(default)]
+    pub announcement_only: bool,
+
+    pub last_synced_at: Option<DateTime<Utc>>,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ChatChannel {
+    pub fn is_member(&self, member_id: &ObjectId) -> bool {
+        self.members.iter().any(|m| &m.id == member_id)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub channel_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    pub sender: ActorRef,
+    pub sender_name: Option<String>,
+    pub content: String,
+
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
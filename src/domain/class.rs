@@ -168,6 +168,22 @@ pub struct AttendanceRules {
 pub struct ClassworkRules {
     pub allow_resubmission: Option<bool>,
     pub max_late_days: Option<String>,
+    /// Percent of the score taken off per day late
+    #[serde(default)]
+    pub late_penalty_per_day: Option<f64>,
+    /// Cap on the total late penalty, in percent
+    #[serde(default)]
+    pub max_late_penalty: Option<f64>,
+}
+
+impl ClassworkRules {
+    /// `max_late_days` is stored as text; anything unparsable counts as unset
+    pub fn max_late_days(&self) -> Option<i64> {
+        self.max_late_days
+            .as_deref()
+            .and_then(|d| d.trim().parse::<i64>().ok())
+            .map(|d| d.max(0))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -273,6 +289,8 @@ impl ClassSettings {
                 classwork_rules: Some(ClassworkRules {
                     allow_resubmission: Some(true),
                     max_late_days: Some("3".to_string()),
+                    late_penalty_per_day: Some(10.0),
+                    max_late_penalty: Some(30.0),
                 }),
             }),
 
@@ -1,8 +1,11 @@
-use chrono::{NaiveTime, Weekday};
+use chrono::{DateTime, NaiveTime, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use crate::domain::{parent::Parent, school_staff::SchoolStaff, student::Student, teacher::Teacher, user::User};
+use crate::{
+    domain::{parent::Parent, school_staff::SchoolStaff, student::Student, teacher::Teacher, user::User},
+    schema::common_schema::ActorRef,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
@@ -1144,3 +1147,13 @@ impl fmt::Display for SubjectCategory {
         }
     }
 }
+
+/// One step of a document's approval workflow, kept in its `history`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusTransition<S> {
+    pub from: S,
+    pub to: S,
+    pub by: ActorRef,
+    pub note: Option<String>,
+    pub at: DateTime<Utc>,
+}
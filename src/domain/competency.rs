@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CompetencyResult {
+    Competent,
+    NotYetCompetent,
+}
+
+/// Signed-off assessments are locked and count towards reports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CompetencyAssessmentStatus {
+    #[default]
+    Draft,
+    SignedOff,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompetencyEvidence {
+    pub file_url: String,
+    pub file_public_id: String,
+    pub file_name: String,
+    pub description: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub uploaded_by: ObjectId,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// One judgement of a student against a learning outcome or topic of a
+/// TVET module. A Not Yet Competent student is reassessed with a new attempt.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompetencyAssessment {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_id: Option<ObjectId>,
+
+    /// The module being assessed
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub trade_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub education_year_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub learning_outcome_id: Option<ObjectId>,
+
+    /// `order` of the module's `TemplateTopic` when assessed by topic
+    pub topic_order: Option<String>,
+    pub topic_title: Option<String>,
+
+    pub attempt: i32,
+    pub result: CompetencyResult,
+    pub assessor_comment: Option<String>,
+
+    #[serde(default)]
+    pub evidence: Vec<CompetencyEvidence>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assessed_by: ObjectId,
+    pub assessed_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub status: CompetencyAssessmentStatus,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub signed_off_by: Option<ObjectId>,
+    pub signed_off_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateCompetencyAssessment {
+    pub student_id: String,
+    pub class_subject_id: String,
+    pub learning_outcome_id: Option<String>,
+    pub topic_order: Option<String>,
+    pub education_year_id: Option<String>,
+    pub result: CompetencyResult,
+    pub assessor_comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateCompetencyAssessment {
+    pub result: Option<CompetencyResult>,
+    pub assessor_comment: Option<String>,
+}
+
+/// Latest signed-off judgement of one outcome or topic
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutcomeCompetency {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub learning_outcome_id: Option<ObjectId>,
+    pub topic_order: Option<String>,
+    pub title: Option<String>,
+    /// `None` when the outcome has not been assessed yet
+    pub result: Option<CompetencyResult>,
+    pub attempts: i32,
+    pub assessed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModuleCompetencyReport {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+    pub module_name: String,
+    pub module_code: String,
+    pub credits: Option<i32>,
+    pub outcomes: Vec<OutcomeCompetency>,
+    pub total_outcomes: usize,
+    pub competent_outcomes: usize,
+    pub is_competent: bool,
+}
+
+/// Competency report of a student across every module of their trade
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TradeCompetencyReport {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+    pub student_name: String,
+    pub registration_number: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub trade_id: Option<ObjectId>,
+    pub trade_name: Option<String>,
+
+    pub modules: Vec<ModuleCompetencyReport>,
+    pub total_modules: usize,
+    pub competent_modules: usize,
+    pub credits_earned: i32,
+    pub is_competent: bool,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// One line per student of a class, for the trade-level overview
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClassCompetencySummary {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_id: ObjectId,
+    pub trade_name: Option<String>,
+    pub total_students: usize,
+    pub competent_students: usize,
+    pub students: Vec<TradeCompetencyReport>,
+}
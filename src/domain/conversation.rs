@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
@@ -26,6 +26,10 @@ make_partial! {
 
         pub participants: Vec<ActorRef>,
 
+        /// Read-only key holders, e.g. parents under the school's oversight policy
+        #[serde(default)]
+        pub observers: Vec<ActorRef>,
+
         #[serde(default)]
         pub is_group: bool,
 
@@ -34,6 +38,9 @@ make_partial! {
         #[serde(default = "default_key_version")]
         pub encryption_key_version: i32,
 
+        pub key_rotation_interval_days: Option<i32>,
+        pub last_key_rotated_at: Option<DateTime<Utc>>,
+
         #[serde(default = "Utc::now")]
         pub created_at: DateTime<Utc>,
 
@@ -46,6 +53,29 @@ fn default_key_version() -> i32 {
     1
 }
 
+impl Conversation {
+    /// Everyone who must receive a copy of the conversation key
+    pub fn key_holders(&self) -> Vec<ActorRef> {
+        self.participants
+            .iter()
+            .chain(self.observers.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// A rotation is due once the configured interval has elapsed since the
+    /// last rotation (or since creation if the key was never rotated).
+    pub fn is_key_rotation_due(&self) -> bool {
+        match self.key_rotation_interval_days {
+            Some(days) if days > 0 => {
+                let since = self.last_key_rotated_at.unwrap_or(self.created_at);
+                Utc::now() >= since + Duration::days(days as i64)
+            }
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConversationWithRelations {
     #[serde(flatten)]
@@ -80,6 +110,56 @@ pub struct ConversationKey {
 
     pub encrypted_key_for_user: String,
 
+    #[serde(default = "default_key_version")]
+    pub key_version: i32,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum KeyRotationTrigger {
+    #[default]
+    Manual,
+    Scheduled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationKeyRotation {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub conversation_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    pub from_version: i32,
+    pub to_version: i32,
+
+    pub rotated_by: ActorRef,
+
+    #[serde(default)]
+    pub trigger: KeyRotationTrigger,
+
+    pub reason: Option<String>,
+
     #[serde(default = "Utc::now")]
     pub created_at: DateTime<Utc>,
 }
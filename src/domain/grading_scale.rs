@@ -52,6 +52,14 @@ make_partial! {
         pub grading_type: GradingType,
         pub grade_boundaries: Vec<GradeBoundary>,
 
+        /// Top of the GPA scale, e.g. 4.0, 5.0 or 20.0
+        #[serde(default = "default_max_gpa")]
+        pub max_gpa: f64,
+
+        /// Bumped on every change so results can name the exact scale they used
+        #[serde(default = "default_version")]
+        pub version: i32,
+
         #[serde(default)]
         pub is_active: bool,
 
@@ -74,6 +82,101 @@ make_partial! {
     } => GradingScalePartial
 }
 
+fn default_max_gpa() -> f64 {
+    4.0
+}
+
+fn default_version() -> i32 {
+    1
+}
+
+impl GradingScale {
+    /// Built-in A–F scale used when a school has no active scale. Boundaries
+    /// carry no gpa_value, so points are proportional to the percentage.
+    pub fn fallback() -> Self {
+        let boundary = |grade: &str, min_score: f64, max_score: f64| GradeBoundary {
+            grade: grade.to_string(),
+            min_score,
+            max_score,
+            gpa_value: None,
+            description: None,
+        };
+
+        Self {
+            id: None,
+            school_id: None,
+            education_year_id: None,
+            name: "Default".to_string(),
+            grading_type: GradingType::Letter,
+            grade_boundaries: vec![
+                boundary("A", 90.0, 100.0),
+                boundary("B", 80.0, 90.0),
+                boundary("C", 70.0, 80.0),
+                boundary("D", 60.0, 70.0),
+                boundary("E", 50.0, 60.0),
+                boundary("F", 0.0, 50.0),
+            ],
+            max_gpa: default_max_gpa(),
+            version: default_version(),
+            is_active: false,
+            created_by: None,
+            created_at: None,
+            updated_at: None,
+            is_deleted: false,
+        }
+    }
+
+    /// The boundary with the highest `min_score` the percentage reaches, so
+    /// gaps such as 89.5 between 80–89 and 90–100 still resolve
+    pub fn boundary_for(&self, percentage: f64) -> Option<&GradeBoundary> {
+        self.grade_boundaries
+            .iter()
+            .filter(|b| percentage >= b.min_score)
+            .max_by(|a, b| a.min_score.total_cmp(&b.min_score))
+    }
+
+    pub fn grade_for(&self, percentage: f64) -> String {
+        self.boundary_for(percentage)
+            .map(|b| b.grade.clone())
+            .unwrap_or_else(|| "N/A".to_string())
+    }
+
+    /// Grade points on this scale; boundaries without a gpa_value fall back
+    /// to a proportional share of `max_gpa`
+    pub fn grade_points(&self, percentage: f64) -> f64 {
+        let proportional = percentage.clamp(0.0, 100.0) / 100.0 * self.max_gpa;
+        self.boundary_for(percentage)
+            .and_then(|b| b.gpa_value)
+            .unwrap_or(proportional)
+            .clamp(0.0, self.max_gpa)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_gpa <= 0.0 {
+            return Err("max_gpa must be greater than 0".into());
+        }
+        if self.grade_boundaries.is_empty() {
+            return Err("At least one grade boundary is required".into());
+        }
+
+        for boundary in &self.grade_boundaries {
+            if boundary.min_score > boundary.max_score {
+                return Err(format!("{}: min_score is above max_score", boundary.grade));
+            }
+            if let Some(points) = boundary.gpa_value {
+                if !(0.0..=self.max_gpa).contains(&points) {
+                    return Err(format!(
+                        "{}: gpa_value must be between 0 and {}",
+                        boundary.grade, self.max_gpa
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GradingScaleWithRelations {
     #[serde(flatten)]
@@ -58,6 +58,28 @@ make_partial! {
         )]
         pub subject_id: Option<ObjectId>,
 
+        /// Subject topic this belongs to, see `learning_outcome`
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub topic_id: Option<ObjectId>,
+
+        /// Module or folder within the subject; unfiled when empty
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub folder_id: Option<ObjectId>,
+
+        /// Order within the folder, lowest first
+        #[serde(default)]
+        pub position: i32,
+
         pub title: String,
         pub description: Option<String>,
 
@@ -68,6 +90,13 @@ make_partial! {
         pub file_public_id: Option<String>,
         pub video_url: Option<String>,
 
+        /// Goes up each time the file is replaced
+        #[serde(default = "default_version")]
+        pub version: i32,
+        /// Replaced files, oldest first; their Cloudinary files are kept
+        #[serde(default)]
+        pub versions: Vec<MaterialVersion>,
+
         #[serde(
             serialize_with = "object_id_helpers::serialize",
             deserialize_with = "object_id_helpers::deserialize",
@@ -79,6 +108,18 @@ make_partial! {
         #[serde(default)]
         pub is_published: bool,
 
+        /// Hidden from students until this date
+        pub release_at: Option<DateTime<Utc>>,
+
+        /// Material a student has to complete before this one appears
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub prerequisite_id: Option<ObjectId>,
+
         pub deleted_at: Option<DateTime<Utc>>,
 
         #[serde(default = "Utc::now")]
@@ -89,6 +130,275 @@ make_partial! {
     } => LearningMaterialPartial
 }
 
+fn default_version() -> i32 {
+    1
+}
+
+/// A file that was replaced by a newer upload
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterialVersion {
+    pub version: i32,
+    pub file_url: Option<String>,
+    pub file_public_id: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub uploaded_by: Option<ObjectId>,
+
+    pub replaced_at: DateTime<Utc>,
+}
+
+impl LearningMaterial {
+    /// Published, past its release date and, when there is a prerequisite,
+    /// the student has completed it
+    pub fn is_available(&self, completed: &[ObjectId]) -> Result<(), String> {
+        if !self.is_published {
+            return Err("This material is not published".into());
+        }
+        if let Some(release_at) = self.release_at {
+            if release_at > Utc::now() {
+                return Err(format!("This material is released on {}", release_at.to_rfc3339()));
+            }
+        }
+        if let Some(prerequisite_id) = self.prerequisite_id {
+            if !completed.contains(&prerequisite_id) {
+                return Err("Complete the prerequisite material first".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+make_partial! {
+    /// Ordered module or folder of materials within a class subject
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct MaterialFolder {
+        #[serde(
+            rename = "_id",
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub school_id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub subject_id: Option<ObjectId>,
+
+        /// Enclosing folder for nested modules
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub parent_id: Option<ObjectId>,
+
+        pub name: String,
+        pub description: Option<String>,
+
+        /// Order among its siblings, lowest first
+        #[serde(default)]
+        pub position: i32,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub created_by: Option<ObjectId>,
+
+        #[serde(default = "Utc::now")]
+        pub created_at: DateTime<Utc>,
+
+        #[serde(default = "Utc::now")]
+        pub updated_at: DateTime<Utc>,
+    } => MaterialFolderPartial
+}
+
+/// Folder with its materials and subfolders, in order
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterialFolderTree {
+    #[serde(flatten)]
+    pub folder: MaterialFolder,
+
+    pub materials: Vec<LearningMaterial>,
+    pub folders: Vec<MaterialFolderTree>,
+}
+
+/// A subject's materials arranged by folder
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubjectMaterialTree {
+    pub folders: Vec<MaterialFolderTree>,
+    /// Materials outside any folder
+    pub materials: Vec<LearningMaterial>,
+}
+
+/// New order for folders or materials, by id
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReorderItems {
+    pub ids: Vec<String>,
+}
+
+/// A student marking a material as done; unlocks materials that have it as
+/// their prerequisite
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterialCompletion {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub material_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MaterialEventType {
+    #[serde(rename = "VIEW")]
+    View,
+    #[serde(rename = "DOWNLOAD")]
+    Download,
+    #[serde(rename = "COMPLETE")]
+    Complete,
+}
+
+/// Something a student did with a material, sent by the client
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecordMaterialEvent {
+    pub event_type: MaterialEventType,
+    /// Seconds of video watched since the last event; only counted on videos
+    pub seconds: Option<i64>,
+}
+
+/// Running totals of one student's use of one material
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterialEngagement {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub material_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub subject_id: Option<ObjectId>,
+
+    #[serde(default)]
+    pub view_count: i64,
+    #[serde(default)]
+    pub download_count: i64,
+    #[serde(default)]
+    pub video_seconds: i64,
+
+    pub first_opened_at: DateTime<Utc>,
+    pub last_opened_at: DateTime<Utc>,
+}
+
+/// A student's engagement with a material, for the teacher report
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StudentMaterialEngagement {
+    pub student: crate::domain::student::Student,
+    pub engagement: Option<MaterialEngagement>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Who in the class has and has not opened a material
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterialEngagementReport {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub material_id: ObjectId,
+    pub title: String,
+    pub total_students: i64,
+    pub completed_count: i64,
+    pub engaged: Vec<StudentMaterialEngagement>,
+    pub not_engaged: Vec<crate::domain::student::Student>,
+}
+
+/// How far a student is through the released materials of a subject
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubjectMaterialProgress {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub subject_id: Option<ObjectId>,
+    pub subject_name: Option<String>,
+    pub total_materials: i64,
+    pub opened_materials: i64,
+    pub completed_materials: i64,
+    /// Completed out of total, 0 to 100
+    pub progress_percentage: f64,
+}
+
+/// Engagement overview of one student, shown on the parent dashboard
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LearningEngagementSummary {
+    pub total_materials: i64,
+    pub opened_materials: i64,
+    pub completed_materials: i64,
+    pub video_minutes: i64,
+    pub last_active_at: Option<DateTime<Utc>>,
+    pub subjects: Vec<SubjectMaterialProgress>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LearningMaterialWithRelations {
     #[serde(flatten)]
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::object_id_helpers, make_partial};
+
+/// Template outcomes live in the main database next to the template
+/// subjects, class subject outcomes in the school database
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OutcomeSubjectType {
+    TemplateSubject,
+    #[default]
+    ClassSubject,
+}
+
+make_partial! {
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct LearningOutcome {
+        #[serde(
+            rename = "_id",
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub school_id: Option<ObjectId>,
+
+        #[serde(default)]
+        pub subject_type: OutcomeSubjectType,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub subject_id: Option<ObjectId>,
+
+        pub title: String,
+        pub description: Option<String>,
+
+        #[serde(default)]
+        pub order: i32,
+
+        pub estimated_hours: Option<i32>,
+        pub credits: Option<i32>,
+
+        /// Template outcome this one was copied from
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub source_outcome_id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub created_by: Option<ObjectId>,
+
+        #[serde(default = "Utc::now")]
+        pub created_at: DateTime<Utc>,
+
+        #[serde(default = "Utc::now")]
+        pub updated_at: DateTime<Utc>,
+    } => LearningOutcomePartial
+}
+
+make_partial! {
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct SubjectTopic {
+        #[serde(
+            rename = "_id",
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub learning_outcome_id: Option<ObjectId>,
+
+        /// Set on sub-topics, top-level topics hang off the outcome directly
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub parent_topic_id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub subject_id: Option<ObjectId>,
+
+        pub title: String,
+        pub description: Option<String>,
+
+        #[serde(default)]
+        pub order: i32,
+
+        pub estimated_hours: Option<i32>,
+
+        #[serde(default = "Utc::now")]
+        pub created_at: DateTime<Utc>,
+
+        #[serde(default = "Utc::now")]
+        pub updated_at: DateTime<Utc>,
+    } => SubjectTopicPartial
+}
+
+/// Materials and assignments to attach to, or detach from, a topic
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TopicLinks {
+    #[serde(default)]
+    pub learning_material_ids: Vec<String>,
+    #[serde(default)]
+    pub assignment_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TopicLinkResult {
+    pub learning_materials: u64,
+    pub assignments: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubjectTopicWithResources {
+    #[serde(flatten)]
+    pub topic: SubjectTopic,
+
+    #[serde(default)]
+    pub learning_materials: Vec<crate::domain::learning_material::LearningMaterial>,
+    #[serde(default)]
+    pub assignments: Vec<crate::domain::assignment::Assignment>,
+    #[serde(default)]
+    pub sub_topics: Vec<SubjectTopicWithResources>,
+}
+
+/// One branch of a subject's outcome tree, built by `learning_outcome_pipeline`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LearningOutcomeWithTopics {
+    #[serde(flatten)]
+    pub learning_outcome: LearningOutcome,
+
+    #[serde(default)]
+    pub topics: Vec<SubjectTopicWithResources>,
+}
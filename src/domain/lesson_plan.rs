@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{domain::common_details::StatusTransition, helpers::object_id_helpers};
+
+/// Draft → Submitted (teacher) → Approved (head of studies). A plan sent
+/// back goes to Draft with a note; only drafts can be edited.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LessonPlanStatus {
+    #[default]
+    Draft,
+    Submitted,
+    Approved,
+}
+
+pub type LessonPlanTransition = StatusTransition<LessonPlanStatus>;
+
+/// What students should be able to do after the lesson, on one topic
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonObjective {
+    /// `order` of the `TemplateTopic` or sub-topic, e.g. "2" or "2.1"
+    pub topic_order: String,
+    pub topic_title: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonActivity {
+    pub title: String,
+    pub description: Option<String>,
+    pub duration_minutes: Option<i32>,
+}
+
+/// Something used in the lesson, either an uploaded material or a free
+/// description such as "wall map"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonResource {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub learning_material_id: Option<ObjectId>,
+    pub title: String,
+    pub note: Option<String>,
+}
+
+/// A teacher's plan for one lesson of a class subject
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonPlan {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    /// Teacher of the subject when the plan was written
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub teacher_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub education_year_id: Option<ObjectId>,
+    pub term_order: Option<i32>,
+
+    pub lesson_date: DateTime<Utc>,
+
+    /// Timetable period the lesson is planned for
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub period_id: Option<ObjectId>,
+
+    pub title: String,
+
+    #[serde(default)]
+    pub objectives: Vec<LessonObjective>,
+    #[serde(default)]
+    pub activities: Vec<LessonActivity>,
+    #[serde(default)]
+    pub resources: Vec<LessonResource>,
+    pub assessment_method: Option<String>,
+
+    #[serde(default)]
+    pub status: LessonPlanStatus,
+
+    #[serde(default)]
+    pub history: Vec<LessonPlanTransition>,
+
+    /// Plan of another class this one was copied from
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub copied_from_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub created_by: ObjectId,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonObjectiveInput {
+    pub topic_order: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonResourceInput {
+    pub learning_material_id: Option<String>,
+    /// Defaults to the material's title
+    pub title: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateLessonPlan {
+    pub class_subject_id: String,
+    pub lesson_date: DateTime<Utc>,
+    pub period_id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub objectives: Vec<LessonObjectiveInput>,
+    #[serde(default)]
+    pub activities: Vec<LessonActivity>,
+    #[serde(default)]
+    pub resources: Vec<LessonResourceInput>,
+    pub assessment_method: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateLessonPlan {
+    pub lesson_date: Option<DateTime<Utc>>,
+    pub period_id: Option<String>,
+    pub title: Option<String>,
+    pub objectives: Option<Vec<LessonObjectiveInput>>,
+    pub activities: Option<Vec<LessonActivity>>,
+    pub resources: Option<Vec<LessonResourceInput>>,
+    pub assessment_method: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonPlanTransitionRequest {
+    pub note: Option<String>,
+}
+
+/// Copy a plan to the same subject in other classes of its `MainClass`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CopyLessonPlan {
+    pub class_ids: Vec<String>,
+    /// Keeps the original date when empty
+    pub lesson_date: Option<DateTime<Utc>>,
+}
+
+/// One timetabled lesson of a scheme of work
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemeLesson {
+    pub date: DateTime<Utc>,
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub period_id: ObjectId,
+    pub duration_minutes: i32,
+    /// Empty once every topic has its planned time
+    pub topic_order: Option<String>,
+    pub topic_title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemeWeek {
+    pub week: i32,
+    pub start_date: DateTime<Utc>,
+    pub lessons: Vec<SchemeLesson>,
+}
+
+/// Topics of a class subject spread over its timetable slots for a term
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemeOfWork {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+    pub subject_name: String,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub education_year_id: Option<ObjectId>,
+    pub term_name: String,
+    pub term_order: i32,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+
+    pub weekly_minutes: i32,
+    pub available_hours: f64,
+    pub planned_hours: f64,
+
+    pub weeks: Vec<SchemeWeek>,
+    /// Topics that do not get all their planned time in the term, by order
+    pub unscheduled_topics: Vec<String>,
+}
+
+/// `?education_year_id=&term_order=`; defaults to the current year and term
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemeOfWorkQuery {
+    pub education_year_id: Option<String>,
+    pub term_order: Option<i32>,
+}
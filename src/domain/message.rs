@@ -1,8 +1,14 @@
 use chrono::{DateTime, Utc};
-use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
 use serde::{Deserialize, Serialize};
 
-use crate::{domain::common_details::RelatedUser, helpers::object_id_helpers, schema::common_schema::ActorRef};
+use crate::{
+    domain::{common_details::RelatedUser, message_attachment::MessageAttachment},
+    errors::AppError,
+    helpers::object_id_helpers,
+    schema::common_schema::ActorRef,
+    utils::mongo_utils::to_stored_document,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
@@ -51,11 +57,34 @@ pub struct Message {
     pub file_url: Option<String>,
     pub file_public_id: Option<String>,
 
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub reply_to_message_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub attachment_id: Option<ObjectId>,
+
     #[serde(default)]
     pub read_by: Vec<RelatedUser>,
 
+    #[serde(default)]
+    pub reactions: Vec<MessageReaction>,
+
+    #[serde(default)]
+    pub edit_history: Vec<MessageEdit>,
+
     pub client_message_id: String,
 
+    pub edited_at: Option<DateTime<Utc>>,
     pub deleted_at: Option<DateTime<Utc>>,
 
     #[serde(default = "Utc::now")]
@@ -66,6 +95,24 @@ fn default_key_version() -> i32 {
     1
 }
 
+/// Previous ciphertext of an edited message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageEdit {
+    pub encrypted_payload: String,
+    pub nonce: String,
+    pub key_version: i32,
+    pub edited_at: DateTime<Utc>,
+}
+
+/// A user may react with a given emoji only once per message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageReaction {
+    pub emoji: String,
+    pub user: ActorRef,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
 impl Default for MessageType {
     fn default() -> Self {
         MessageType::TEXT
@@ -77,12 +124,25 @@ pub struct MessageWithRelations {
     #[serde(flatten)]
     pub message: Message,
     pub sender_user: Option<RelatedUser>,
+    pub reply_to: Option<Message>,
+}
+
+/// A FILE message together with its upload pipeline metadata
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageFile {
+    #[serde(flatten)]
+    pub message: Message,
+    pub attachment: Option<MessageAttachment>,
 }
 
 impl Message {
-    pub fn to_document(&self) -> Result<mongodb::bson::Document, crate::errors::AppError> {
-        mongodb::bson::to_document(self).map_err(|e| crate::errors::AppError {
-            message: format!("Failed to convert Message to document: {}", e),
-        })
+    /// Stored form of the message. Ids stay ObjectIds so conversation, reply
+    /// and attachment filters match; unset fields are left out so new
+    /// messages satisfy `deleted_at: { $exists: false }`.
+    pub fn to_document(&self) -> Result<Document, AppError> {
+        Ok(to_stored_document(self)?
+            .into_iter()
+            .filter(|(_, value)| *value != Bson::Null)
+            .collect())
     }
 }
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::object_id_helpers, schema::common_schema::ActorRef};
+
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: i64 = 25 * 1024 * 1024;
+pub const DEFAULT_CHUNK_BYTES: i64 = 1024 * 1024;
+pub const MAX_CHUNK_BYTES: i64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AttachmentStatus {
+    #[default]
+    Uploading,
+    Complete,
+    Deleted,
+}
+
+/// A file uploaded in chunks for a FILE message.
+/// Encrypted uploads are opaque blobs; the file key travels inside the
+/// encrypted message payload, so the server never sees the content.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageAttachment {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub conversation_id: ObjectId,
+
+    pub uploaded_by: ActorRef,
+
+    pub file_name: String,
+    pub mime_type: String,
+    pub total_size: i64,
+    pub chunk_size: i64,
+    pub total_chunks: i32,
+
+    #[serde(default)]
+    pub received_chunks: Vec<i32>,
+
+    #[serde(default = "default_encrypted")]
+    pub encrypted: bool,
+
+    #[serde(default)]
+    pub status: AttachmentStatus,
+
+    pub file_url: Option<String>,
+    pub file_public_id: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub thumbnail_public_id: Option<String>,
+
+    pub completed_at: Option<DateTime<Utc>>,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_encrypted() -> bool {
+    true
+}
+
+impl MessageAttachment {
+    pub fn is_fully_received(&self) -> bool {
+        (0..self.total_chunks).all(|i| self.received_chunks.contains(&i))
+    }
+}
+
+/// Per-school upload limits for messaging attachments
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttachmentLimits {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub school_id: ObjectId,
+
+    pub max_file_size: i64,
+
+    /// Allowed MIME types; `type/*` entries match a whole family
+    pub allowed_mime_types: Vec<String>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AttachmentLimits {
+    pub fn new(school_id: ObjectId) -> Self {
+        Self {
+            school_id,
+            max_file_size: DEFAULT_MAX_ATTACHMENT_BYTES,
+            allowed_mime_types: vec![
+                "image/*".to_string(),
+                "audio/*".to_string(),
+                "video/*".to_string(),
+                "application/pdf".to_string(),
+                "text/plain".to_string(),
+                "application/msword".to_string(),
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                    .to_string(),
+                "application/vnd.ms-excel".to_string(),
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".to_string(),
+                "application/vnd.ms-powerpoint".to_string(),
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+                    .to_string(),
+            ],
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn allows_mime_type(&self, mime_type: &str) -> bool {
+        let mime_type = mime_type.to_ascii_lowercase();
+        self.allowed_mime_types.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_suffix("/*") {
+                Some(family) => mime_type
+                    .split_once('/')
+                    .is_some_and(|(f, _)| f == family),
+                None => allowed == mime_type,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateAttachmentLimits {
+    pub max_file_size: Option<i64>,
+    pub allowed_mime_types: Option<Vec<String>>,
+}
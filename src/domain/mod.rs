@@ -1,16 +1,22 @@
+pub mod academic_record;
+pub mod academic_year_rollover;
+pub mod ai_feedback;
 pub mod analytics;
 pub mod announcement;
 pub mod assessment_category;
 pub mod assignment;
+pub mod assignment_group;
 pub mod audit_log;
 pub mod auth;
 pub mod auth_user;
 pub mod backup;
+pub mod chat;
 pub mod class;
 pub mod class_subject;
 pub mod class_timetable;
 pub mod comment;
 pub mod common_details;
+pub mod competency;
 pub mod conversation;
 pub mod database_status;
 pub mod education_year;
@@ -19,19 +25,31 @@ pub mod grading_scale;
 pub mod guardian;
 pub mod join_school_request;
 pub mod learning_material;
+pub mod learning_outcome;
+pub mod lesson_plan;
 pub mod like;
 pub mod main_class;
 pub mod message;
+pub mod message_attachment;
 pub mod parent;
+pub mod peer_review;
 pub mod promotion;
+pub mod quiz;
+pub mod report_card;
+pub mod result_moderation;
 pub mod role;
+pub mod rubric;
+pub mod safeguarding;
 pub mod school;
 pub mod school_staff;
 pub mod school_timetable;
 pub mod score;
+pub mod score_import;
 pub mod sector;
 pub mod student;
 pub mod student_term_result;
+pub mod submission_similarity;
+pub mod syllabus_coverage;
 pub mod teacher;
 pub mod template_subject;
 pub mod trade;
@@ -103,6 +103,8 @@ pub struct ChildSummary {
     pub attendance_percentage: f64,
     pub current_term_gpa: f64,
     pub outstanding_fees: f64,
+    /// Use of learning materials across the child's subjects
+    pub learning_engagement: Option<crate::domain::learning_material::LearningEngagementSummary>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
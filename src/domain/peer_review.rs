@@ -0,0 +1,265 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::rubric::{CriterionScore, CriterionScoreInput},
+    helpers::object_id_helpers,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PeerReviewRoundStatus {
+    Open,
+    /// Peer scores have been aggregated onto the submissions
+    Finalized,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PeerReviewStatus {
+    Pending,
+    Submitted,
+}
+
+/// Peer review set up for one assignment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerReviewRound {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    /// Rubric every reviewer scores against
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub rubric_id: ObjectId,
+
+    pub reviews_per_submission: i32,
+    pub review_deadline: DateTime<Utc>,
+    pub status: PeerReviewRoundStatus,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    pub created_at: DateTime<Utc>,
+    pub finalized_at: Option<DateTime<Utc>>,
+}
+
+/// Teacher correction of a peer review; replaces the reviewer's score
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerReviewOverride {
+    pub score: f64,
+    pub criterion_scores: Option<Vec<CriterionScore>>,
+    pub comment: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub overridden_by: ObjectId,
+
+    pub overridden_at: DateTime<Utc>,
+}
+
+/// One reviewer's review of one submission
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerReview {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub round_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub reviewee_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub reviewer_id: ObjectId,
+
+    pub status: PeerReviewStatus,
+    pub criterion_scores: Option<Vec<CriterionScore>>,
+    /// Rubric total scaled to the assignment's max score
+    pub score: Option<f64>,
+    pub comment: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+
+    pub teacher_override: Option<PeerReviewOverride>,
+
+    pub created_at: DateTime<Utc>,
+}
+
+impl PeerReview {
+    /// Score that counts: the teacher's override, else the reviewer's
+    pub fn effective_score(&self) -> Option<f64> {
+        self.teacher_override
+            .as_ref()
+            .map(|o| o.score)
+            .or(self.score)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerReviewWithRelations {
+    #[serde(flatten)]
+    pub review: PeerReview,
+
+    pub reviewer: Option<crate::domain::student::Student>,
+    pub reviewee: Option<crate::domain::student::Student>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartPeerReview {
+    pub reviews_per_submission: i32,
+    pub review_deadline: DateTime<Utc>,
+    /// Defaults to the assignment's rubric
+    pub rubric_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmitPeerReview {
+    pub criterion_scores: Vec<CriterionScoreInput>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverridePeerReview {
+    /// Either a score or criterion scores
+    pub score: Option<f64>,
+    pub criterion_scores: Option<Vec<CriterionScoreInput>>,
+    pub comment: Option<String>,
+}
+
+/// Review a student has to write; the author stays anonymous
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewTask {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub review_id: ObjectId,
+
+    pub status: PeerReviewStatus,
+    pub review_deadline: DateTime<Utc>,
+    pub file_url: Option<String>,
+    pub text_content: Option<String>,
+    pub criterion_scores: Option<Vec<CriterionScore>>,
+    pub comment: Option<String>,
+}
+
+/// Review a student received; the reviewer stays anonymous
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReceivedReview {
+    pub score: Option<f64>,
+    pub criterion_scores: Option<Vec<CriterionScore>>,
+    pub comment: Option<String>,
+    pub overridden: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StudentPeerReviews {
+    pub round: PeerReviewRound,
+    pub rubric: crate::domain::rubric::Rubric,
+    pub to_review: Vec<ReviewTask>,
+    /// Shown once the review deadline has passed
+    pub received: Vec<ReceivedReview>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmissionPeerScore {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    pub reviews_assigned: usize,
+    pub reviews_received: usize,
+    /// Mean of the effective scores
+    pub peer_score: Option<f64>,
+    pub scores: Vec<f64>,
+}
+
+/// How closely a reviewer's scores track the rest: the teacher's grade where
+/// there is one, otherwise the other reviewers' mean
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewerReliability {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub reviewer_id: ObjectId,
+
+    pub assigned: usize,
+    pub completed: usize,
+    pub overridden: usize,
+    /// Mean distance from the reference score, in points
+    pub mean_deviation: Option<f64>,
+    /// 1 minus the mean deviation as a share of the max score
+    pub reliability: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerReviewSummary {
+    pub round: PeerReviewRound,
+    pub submissions: Vec<SubmissionPeerScore>,
+    pub reviewers: Vec<ReviewerReliability>,
+}
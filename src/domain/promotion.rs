@@ -12,6 +12,16 @@ pub enum PromotionStatus {
     Pending,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum PromotionBatchStatus {
+    #[default]
+    Draft,
+    /// Students are being moved; running `execute` again picks up from here
+    Executing,
+    Executed,
+    Cancelled,
+}
+
 make_partial! {
     #[derive(Debug, Serialize, Deserialize, Clone)]
     pub struct PromotionRule {
@@ -89,6 +99,28 @@ pub struct PromotionResult {
         default
     )]
     pub promoted_to_class_id: Option<ObjectId>,
+
+    #[serde(default)]
+    pub attendance_percentage: Option<f64>,
+
+    /// Required subjects the student did not pass
+    #[serde(default)]
+    pub failed_subjects: Vec<String>,
+
+    /// Set when staff replaced the rule's decision
+    #[serde(default)]
+    pub is_overridden: bool,
+
+    #[serde(default)]
+    pub override_reason: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub overridden_by: Option<ObjectId>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -132,7 +164,18 @@ pub struct PromotionBatch {
         skip_serializing_if = "Option::is_none",
         default
     )]
-    pub to_class_id: Option<ObjectId>,
+    pub to_class_id: Option<ObjectId>, // None when the class graduates
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub rule_id: Option<ObjectId>,
+
+    #[serde(default)]
+    pub status: PromotionBatchStatus,
 
     pub promotion_results: Vec<PromotionResult>,
 
@@ -146,4 +189,34 @@ pub struct PromotionBatch {
 
     #[serde(default)]
     pub executed_at: Option<DateTime<Utc>>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreatePromotionBatch {
+    pub from_class_id: String,
+    pub to_class_id: Option<String>,
+    pub education_year_id: String,
+    /// Falls back to the latest rule of the education year
+    pub rule_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverridePromotionResult {
+    pub promotion_status: PromotionStatus,
+    pub reason: String,
+    pub promoted_to_class_id: Option<String>,
 }
@@ -0,0 +1,385 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuestionType {
+    MultipleChoice,
+    MultiSelect,
+    TrueFalse,
+    Numeric,
+    ShortAnswer,
+}
+
+/// How a short answer is compared with the accepted answers
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ShortAnswerMatch {
+    /// Equal after trimming and collapsing whitespace
+    #[default]
+    Exact,
+    /// Accepted answers are regular expressions matched against the whole answer
+    Pattern,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuizGradingPolicy {
+    #[default]
+    Highest,
+    Latest,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuizAttemptStatus {
+    InProgress,
+    Graded,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuestionOption {
+    pub id: String,
+    pub text: String,
+}
+
+/// Question bank entry, owned by a class subject and optionally a topic
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizQuestion {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub topic_id: Option<ObjectId>,
+
+    pub question_type: QuestionType,
+    pub prompt: String,
+    pub points: f64,
+
+    /// Choices for multiple choice and multi-select questions
+    #[serde(default)]
+    pub options: Vec<QuestionOption>,
+    #[serde(default)]
+    pub correct_option_ids: Vec<String>,
+    /// Multi-select: correct picks minus wrong picks, instead of all or nothing
+    #[serde(default)]
+    pub partial_credit: bool,
+
+    pub correct_boolean: Option<bool>,
+
+    pub numeric_answer: Option<f64>,
+    pub numeric_tolerance: Option<f64>,
+
+    #[serde(default)]
+    pub accepted_answers: Vec<String>,
+    #[serde(default)]
+    pub answer_match: ShortAnswerMatch,
+    #[serde(default)]
+    pub case_sensitive: bool,
+
+    /// Shown to students after grading when the quiz allows it
+    pub explanation: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default)]
+    pub is_deleted: bool,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateQuizQuestion {
+    pub class_subject_id: String,
+    pub topic_id: Option<String>,
+    pub question_type: QuestionType,
+    pub prompt: String,
+    pub points: Option<f64>,
+    #[serde(default)]
+    pub options: Vec<QuestionOption>,
+    #[serde(default)]
+    pub correct_option_ids: Vec<String>,
+    #[serde(default)]
+    pub partial_credit: bool,
+    pub correct_boolean: Option<bool>,
+    pub numeric_answer: Option<f64>,
+    pub numeric_tolerance: Option<f64>,
+    #[serde(default)]
+    pub accepted_answers: Vec<String>,
+    #[serde(default)]
+    pub answer_match: ShortAnswerMatch,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateQuizQuestion {
+    pub topic_id: Option<String>,
+    pub prompt: Option<String>,
+    pub points: Option<f64>,
+    pub options: Option<Vec<QuestionOption>>,
+    pub correct_option_ids: Option<Vec<String>>,
+    pub partial_credit: Option<bool>,
+    pub correct_boolean: Option<bool>,
+    pub numeric_answer: Option<f64>,
+    pub numeric_tolerance: Option<f64>,
+    pub accepted_answers: Option<Vec<String>>,
+    pub answer_match: Option<ShortAnswerMatch>,
+    pub case_sensitive: Option<bool>,
+    pub explanation: Option<String>,
+}
+
+/// Quiz settings of an auto-graded assignment. Marks are scaled to the
+/// assignment's `max_score`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Quiz {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_vec_oid",
+        deserialize_with = "object_id_helpers::deserialize_vec_oid",
+        default
+    )]
+    pub question_ids: Vec<ObjectId>,
+
+    #[serde(default)]
+    pub shuffle_questions: bool,
+    #[serde(default)]
+    pub shuffle_options: bool,
+    pub time_limit_minutes: Option<i32>,
+    /// Unlimited when not set
+    pub max_attempts: Option<i32>,
+    #[serde(default)]
+    pub grading_policy: QuizGradingPolicy,
+    #[serde(default)]
+    pub show_correct_answers: bool,
+
+    /// Marks flow into `Score` under this category when set
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub assessment_category_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub exam_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpsertQuiz {
+    pub question_ids: Vec<String>,
+    #[serde(default)]
+    pub shuffle_questions: bool,
+    #[serde(default)]
+    pub shuffle_options: bool,
+    pub time_limit_minutes: Option<i32>,
+    pub max_attempts: Option<i32>,
+    #[serde(default)]
+    pub grading_policy: QuizGradingPolicy,
+    #[serde(default)]
+    pub show_correct_answers: bool,
+    pub assessment_category_id: Option<String>,
+    pub exam_id: Option<String>,
+}
+
+/// A question as it was laid out for one attempt
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttemptQuestion {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub question_id: ObjectId,
+    #[serde(default)]
+    pub option_ids: Vec<String>,
+}
+
+/// A student's answer. Which field is read depends on the question type.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizAnswer {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub question_id: ObjectId,
+    #[serde(default)]
+    pub selected_option_ids: Vec<String>,
+    pub boolean_answer: Option<bool>,
+    pub numeric_answer: Option<f64>,
+    pub text_answer: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GradedAnswer {
+    #[serde(flatten)]
+    pub answer: QuizAnswer,
+    pub is_correct: bool,
+    pub points_awarded: f64,
+    pub points_possible: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizAttempt {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub quiz_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+
+    pub attempt_number: i32,
+    pub questions: Vec<AttemptQuestion>,
+
+    pub started_at: DateTime<Utc>,
+    /// Start plus the time limit, answers arriving later are not graded
+    pub expires_at: Option<DateTime<Utc>>,
+    pub submitted_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub answers: Vec<GradedAnswer>,
+    pub raw_score: Option<f64>,
+    pub raw_max_score: Option<f64>,
+    /// Raw score scaled to the assignment's max score
+    pub score: Option<f64>,
+
+    pub status: QuizAttemptStatus,
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmitQuizAttempt {
+    #[serde(default)]
+    pub answers: Vec<QuizAnswer>,
+}
+
+/// Question without its answer key, in attempt order
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StudentQuizQuestion {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub question_id: ObjectId,
+    pub question_type: QuestionType,
+    pub prompt: String,
+    pub points: f64,
+    #[serde(default)]
+    pub options: Vec<QuestionOption>,
+    pub result: Option<GradedAnswer>,
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuizAttemptView {
+    #[serde(flatten)]
+    pub attempt: QuizAttempt,
+    pub questions_view: Vec<StudentQuizQuestion>,
+}
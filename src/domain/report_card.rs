@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+fn default_title() -> String {
+    "Student Report Card".to_string()
+}
+
+fn default_primary_color() -> String {
+    "#1F3A93".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-school layout of the printed report card
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportCardTemplate {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(default = "default_title")]
+    pub title: String,
+
+    /// Hex colour of the header band and table heading, e.g. "#1F3A93"
+    #[serde(default = "default_primary_color")]
+    pub primary_color: String,
+
+    #[serde(default = "default_true")]
+    pub show_logo: bool,
+
+    #[serde(default = "default_true")]
+    pub show_category_scores: bool,
+
+    #[serde(default = "default_true")]
+    pub show_rank: bool,
+
+    #[serde(default = "default_true")]
+    pub show_attendance: bool,
+
+    #[serde(default = "default_true")]
+    pub show_remarks: bool,
+
+    pub principal_name: Option<String>,
+    pub footer_text: Option<String>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ReportCardTemplate {
+    pub fn new(school_id: Option<ObjectId>) -> Self {
+        Self {
+            id: None,
+            school_id,
+            title: default_title(),
+            primary_color: default_primary_color(),
+            show_logo: true,
+            show_category_scores: true,
+            show_rank: true,
+            show_attendance: true,
+            show_remarks: true,
+            principal_name: None,
+            footer_text: None,
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateReportCardTemplate {
+    pub title: Option<String>,
+    pub primary_color: Option<String>,
+    pub show_logo: Option<bool>,
+    pub show_category_scores: Option<bool>,
+    pub show_rank: Option<bool>,
+    pub show_attendance: Option<bool>,
+    pub show_remarks: Option<bool>,
+    pub principal_name: Option<String>,
+    pub footer_text: Option<String>,
+}
+
+/// Public record behind a report card's verification code. Lives in the main
+/// database so a code can be checked without knowing the school.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportCardVerification {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    pub verification_code: String,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub school_id: ObjectId,
+    pub school_name: String,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+    pub student_name: String,
+    pub registration_number: Option<String>,
+    pub class_name: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub exam_id: ObjectId,
+    pub exam_name: String,
+    pub education_year_label: Option<String>,
+    pub term_id: Option<String>,
+
+    pub average_percentage: f64,
+    pub gpa: f64,
+    pub grade: String,
+    pub rank_in_class: Option<i32>,
+    pub total_students: Option<i32>,
+
+    pub issued_at: DateTime<Utc>,
+
+    /// Set once the result was reissued with different marks; the printed
+    /// card no longer matches the school's records
+    #[serde(default)]
+    pub superseded: bool,
+    pub superseded_by_code: Option<String>,
+    pub superseded_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ReportCardVerification {
+    /// Whether both records vouch for the same marks
+    pub fn same_result(&self, other: &Self) -> bool {
+        self.term_id == other.term_id
+            && self.average_percentage == other.average_percentage
+            && self.gpa == other.gpa
+            && self.grade == other.grade
+            && self.rank_in_class == other.rank_in_class
+            && self.total_students == other.total_students
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReportCardJobStatus {
+    #[default]
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportCardFailure {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+    pub student_name: String,
+    pub reason: String,
+}
+
+/// Background generation of every report card of a class, packed in a zip
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportCardJob {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub school_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub exam_id: ObjectId,
+
+    #[serde(default)]
+    pub status: ReportCardJobStatus,
+
+    #[serde(default)]
+    pub total: i64,
+
+    #[serde(default)]
+    pub generated: i64,
+
+    #[serde(default)]
+    pub failures: Vec<ReportCardFailure>,
+
+    pub file_path: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub error_message: Option<String>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub created_by: Option<ObjectId>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+
+    pub completed_at: Option<DateTime<Utc>>,
+}
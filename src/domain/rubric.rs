@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::object_id_helpers, make_partial};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RubricLevel {
+    pub label: String, // "Excellent", "Developing"
+    pub points: f64,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RubricCriterion {
+    /// Stable key that criterion scores refer to; generated when left empty
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub levels: Vec<RubricLevel>,
+}
+
+impl RubricCriterion {
+    pub fn max_points(&self) -> f64 {
+        self.levels.iter().map(|l| l.points).fold(0.0, f64::max)
+    }
+}
+
+make_partial! {
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct Rubric {
+        #[serde(
+            rename = "_id",
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub id: Option<ObjectId>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub school_id: Option<ObjectId>,
+
+        /// Owning teacher; rubrics without one are shared across the school
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub teacher_id: Option<ObjectId>,
+
+        pub title: String,
+        pub description: Option<String>,
+        pub criteria: Vec<RubricCriterion>,
+
+        #[serde(
+            serialize_with = "object_id_helpers::serialize",
+            deserialize_with = "object_id_helpers::deserialize",
+            skip_serializing_if = "Option::is_none",
+            default
+        )]
+        pub created_by: Option<ObjectId>,
+
+        #[serde(default)]
+        pub is_deleted: bool,
+
+        #[serde(default)]
+        pub created_at: Option<DateTime<Utc>>,
+
+        #[serde(default)]
+        pub updated_at: Option<DateTime<Utc>>,
+    } => RubricPartial
+}
+
+/// Score given for one criterion while grading
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CriterionScoreInput {
+    pub criterion_id: String,
+    /// Level label to award; its points are used unless `points` is given
+    pub level: Option<String>,
+    pub points: Option<f64>,
+    pub comment: Option<String>,
+}
+
+/// Graded criterion as stored on the submission. Title and maximum are
+/// copied so the breakdown still reads right if the rubric is edited later.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CriterionScore {
+    pub criterion_id: String,
+    pub criterion_title: String,
+    pub level: Option<String>,
+    pub points: f64,
+    pub max_points: f64,
+    pub comment: Option<String>,
+}
+
+impl Rubric {
+    pub fn max_points(&self) -> f64 {
+        self.criteria.iter().map(|c| c.max_points()).sum()
+    }
+
+    /// Criterion points as a score out of `max_score`, to 2 decimals
+    pub fn scaled_total(&self, scores: &[CriterionScore], max_score: f64) -> f64 {
+        let earned: f64 = scores.iter().map(|s| s.points).sum();
+        (earned / self.max_points() * max_score * 100.0).round() / 100.0
+    }
+
+    /// Fill in missing criterion ids and check the rubric can be graded with
+    pub fn normalize(&mut self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err("Rubric title is required".into());
+        }
+        if self.criteria.is_empty() {
+            return Err("At least one criterion is required".into());
+        }
+
+        let mut ids = HashSet::new();
+        for criterion in &mut self.criteria {
+            if criterion.id.trim().is_empty() {
+                criterion.id = ObjectId::new().to_hex();
+            }
+            if !ids.insert(criterion.id.clone()) {
+                return Err(format!("Duplicate criterion id '{}'", criterion.id));
+            }
+            if criterion.levels.is_empty() {
+                return Err(format!(
+                    "{}: at least one level is required",
+                    criterion.title
+                ));
+            }
+
+            let mut labels = HashSet::new();
+            for level in &criterion.levels {
+                if level.points < 0.0 {
+                    return Err(format!(
+                        "{}: level '{}' has negative points",
+                        criterion.title, level.label
+                    ));
+                }
+                if !labels.insert(level.label.to_lowercase()) {
+                    return Err(format!(
+                        "{}: duplicate level '{}'",
+                        criterion.title, level.label
+                    ));
+                }
+            }
+        }
+
+        if self.max_points() <= 0.0 {
+            return Err("Rubric must be worth more than 0 points".into());
+        }
+        Ok(())
+    }
+
+    /// Turn per-criterion input into stored scores; every criterion must be
+    /// scored exactly once
+    pub fn grade(&self, input: &[CriterionScoreInput]) -> Result<Vec<CriterionScore>, String> {
+        let mut seen = HashSet::new();
+        for score in input {
+            if !self.criteria.iter().any(|c| c.id == score.criterion_id) {
+                return Err(format!("Unknown criterion '{}'", score.criterion_id));
+            }
+            if !seen.insert(score.criterion_id.as_str()) {
+                return Err(format!("Criterion '{}' scored twice", score.criterion_id));
+            }
+        }
+
+        self.criteria
+            .iter()
+            .map(|criterion| {
+                let score = input
+                    .iter()
+                    .find(|s| s.criterion_id == criterion.id)
+                    .ok_or(format!("{}: score is required", criterion.title))?;
+
+                let level = match &score.level {
+                    Some(label) => Some(
+                        criterion
+                            .levels
+                            .iter()
+                            .find(|l| l.label.eq_ignore_ascii_case(label))
+                            .ok_or(format!("{}: unknown level '{}'", criterion.title, label))?,
+                    ),
+                    None => None,
+                };
+                let points = score
+                    .points
+                    .or(level.map(|l| l.points))
+                    .ok_or(format!("{}: give a level or points", criterion.title))?;
+
+                let max_points = criterion.max_points();
+                if !(0.0..=max_points).contains(&points) {
+                    return Err(format!(
+                        "{}: points must be between 0 and {}",
+                        criterion.title, max_points
+                    ));
+                }
+
+                Ok(CriterionScore {
+                    criterion_id: criterion.id.clone(),
+                    criterion_title: criterion.title.clone(),
+                    level: level.map(|l| l.label.clone()),
+                    points,
+                    max_points,
+                    comment: score.comment.clone(),
+                })
+            })
+            .collect()
+    }
+}
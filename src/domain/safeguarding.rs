@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::object_id_helpers, schema::common_schema::ActorRef};
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-school messaging rules protecting students.
+/// Defaults keep messaging open so existing schools are unaffected until they opt in.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessagingPolicy {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub school_id: ObjectId,
+
+    /// Students may only start conversations with teachers of their class
+    #[serde(default)]
+    pub students_contact_class_teachers_only: bool,
+
+    /// Conversations made up only of students
+    #[serde(default = "default_true")]
+    pub allow_student_to_student: bool,
+
+    /// Linked parents receive a read-only key for student/teacher conversations
+    #[serde(default)]
+    pub parent_oversight: bool,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub updated_by: Option<ObjectId>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MessagingPolicy {
+    pub fn new(school_id: ObjectId) -> Self {
+        Self {
+            school_id,
+            students_contact_class_teachers_only: false,
+            allow_student_to_student: true,
+            parent_oversight: false,
+            updated_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateMessagingPolicy {
+    pub students_contact_class_teachers_only: Option<bool>,
+    pub allow_student_to_student: Option<bool>,
+    pub parent_oversight: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReportReason {
+    Bullying,
+    Harassment,
+    InappropriateContent,
+    SelfHarm,
+    Grooming,
+    Spam,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ReportStatus {
+    #[default]
+    Pending,
+    Reviewing,
+    Resolved,
+    Dismissed,
+}
+
+/// A reported message. Messages are end-to-end encrypted, so the reporter
+/// submits the plaintext they decrypted on their device.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageReport {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub conversation_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub message_id: ObjectId,
+
+    pub reporter: ActorRef,
+    pub reported_user: ActorRef,
+    pub reason: ReportReason,
+    pub decrypted_content: String,
+    pub details: Option<String>,
+
+    #[serde(default)]
+    pub status: ReportStatus,
+
+    pub reviewed_by: Option<ActorRef>,
+    pub resolution_note: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
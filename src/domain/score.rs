@@ -135,6 +135,15 @@ pub struct ScoreAuditLog {
 
     pub change_reason: Option<String>,
 
+    /// Set when the change was made on a re-opened finalized sheet
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub reopen_request_id: Option<ObjectId>,
+
     #[serde(default)]
     pub changed_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,99 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+/// Columns of a mark sheet, in order
+pub const MARK_SHEET_COLUMNS: [&str; 6] = [
+    "student_id",
+    "registration_number",
+    "student_name",
+    "score",
+    "max_score",
+    "remarks",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkSheetFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+impl MarkSheetFormat {
+    /// Decide from the uploaded file name, falling back to the zip signature
+    /// every XLSX file starts with
+    pub fn detect(file_name: Option<&str>, bytes: &[u8]) -> Self {
+        let by_name = file_name.map(|name| name.to_lowercase());
+        match by_name.as_deref() {
+            Some(name) if name.ends_with(".xlsx") => MarkSheetFormat::Xlsx,
+            Some(name) if name.ends_with(".csv") => MarkSheetFormat::Csv,
+            _ if bytes.starts_with(b"PK") => MarkSheetFormat::Xlsx,
+            _ => MarkSheetFormat::Csv,
+        }
+    }
+}
+
+/// Which scores a mark sheet covers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MarkSheetQuery {
+    pub exam_id: String,
+    pub class_subject_id: String,
+    pub assessment_category_id: String,
+    /// Template only, defaults to CSV
+    pub format: Option<MarkSheetFormat>,
+    /// Template only, pre-filled for students without a score yet
+    pub max_score: Option<f64>,
+    /// Upload only, validate and preview without saving
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ScoreImportAction {
+    Create,
+    Update,
+    Unchanged,
+    /// Score cell left blank
+    Skip,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoreImportRow {
+    /// Spreadsheet row number, the header being row 1
+    pub row: usize,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub student_id: ObjectId,
+    pub student_name: String,
+    pub score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub previous_score: Option<f64>,
+    pub remarks: Option<String>,
+    pub action: ScoreImportAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScoreImportRowError {
+    pub row: usize,
+    pub student: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScoreImportReport {
+    pub dry_run: bool,
+    /// False when the sheet had errors, nothing is saved in that case
+    pub applied: bool,
+    pub total_rows: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub rows: Vec<ScoreImportRow>,
+    pub errors: Vec<ScoreImportRowError>,
+}
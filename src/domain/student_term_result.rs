@@ -34,6 +34,10 @@ pub struct SubjectResult {
     pub percentage: f64,
     pub grade: String,
     pub credits: Option<i32>,
+
+    /// Points for this subject on the scale that produced the result
+    #[serde(default)]
+    pub grade_points: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -98,6 +102,21 @@ pub struct StudentTermResult {
     pub total_credits: Option<i32>,
     pub grade: String, // Based on grading scale
 
+    /// Scale that produced this result; None means the built-in fallback
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub grading_scale_id: Option<ObjectId>,
+
+    #[serde(default)]
+    pub grading_scale_version: Option<i32>,
+
+    #[serde(default)]
+    pub max_gpa: Option<f64>,
+
     pub rank_in_class: Option<i32>,
     pub total_students: Option<i32>,
 
@@ -106,6 +125,13 @@ pub struct StudentTermResult {
 
     #[serde(default)]
     pub is_finalized: bool,
+
+    /// Families only see results that are finalized and published
+    #[serde(default)]
+    pub is_published: bool,
+
+    #[serde(default)]
+    pub published_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+/// One run of the similarity check over an assignment's submissions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarityReport {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub assignment_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_subject_id: Option<ObjectId>,
+
+    /// Pairs below this Jaccard similarity are left out
+    pub threshold: f64,
+    /// Words per shingle
+    pub shingle_size: usize,
+
+    pub submissions_checked: usize,
+    pub prior_submissions_checked: usize,
+    #[serde(default)]
+    pub skipped: Vec<SkippedSubmission>,
+    /// Highest similarity first
+    #[serde(default)]
+    pub pairs: Vec<SimilarityPair>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub generated_by: Option<ObjectId>,
+
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedSubmission {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub student_id: Option<ObjectId>,
+
+    pub reason: String,
+}
+
+/// Two submissions that share text. `b` may come from an earlier year.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarityPair {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_a_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub student_a_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_b_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub student_b_id: Option<ObjectId>,
+
+    /// Set when `b` belongs to an assignment from a prior year
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub prior_assignment_id: Option<ObjectId>,
+
+    /// MinHash estimate used to pick the pair
+    pub estimated_similarity: f64,
+    /// Exact Jaccard similarity of the shingle sets
+    pub similarity: f64,
+    /// Share of each text covered by the passages
+    pub coverage_a: f64,
+    pub coverage_b: f64,
+
+    pub passages: Vec<OverlapPassage>,
+}
+
+/// Matching run of text; offsets are character positions in each
+/// submission's extracted text, for highlighting
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlapPassage {
+    pub a_start: usize,
+    pub a_end: usize,
+    pub b_start: usize,
+    pub b_end: usize,
+    pub text: String,
+}
+
+/// Extracted text and MinHash signature of a submission version, so
+/// files are only downloaded and parsed once
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubmissionFingerprint {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub submission_id: ObjectId,
+
+    pub version: i32,
+    pub shingle_size: usize,
+    pub text: String,
+    pub signature: Vec<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RunSimilarityCheck {
+    /// Defaults to 0.3
+    pub threshold: Option<f64>,
+    /// Also compare with submissions from earlier years; on by default
+    pub include_prior_years: Option<bool>,
+}
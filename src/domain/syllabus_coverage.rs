@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::object_id_helpers;
+
+/// A lesson a teacher gave on one topic of a class subject
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LessonLog {
+    #[serde(
+        rename = "_id",
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub school_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub education_year_id: Option<ObjectId>,
+    pub term_order: Option<i32>,
+
+    /// `order` of the `TemplateTopic` or sub-topic taught, e.g. "2" or "2.1"
+    pub topic_order: String,
+    pub topic_title: String,
+
+    pub lesson_date: DateTime<Utc>,
+
+    /// Timetable period the lesson took place in
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub period_id: Option<ObjectId>,
+    pub duration_minutes: i32,
+    pub note: Option<String>,
+
+    /// Teacher of the subject when the lesson was logged
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub teacher_id: Option<ObjectId>,
+
+    /// User who logged the lesson
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub logged_by: ObjectId,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateLessonLog {
+    pub class_subject_id: String,
+    pub topic_order: String,
+    pub lesson_date: DateTime<Utc>,
+    /// Duration is taken from the period when given
+    pub period_id: Option<String>,
+    pub duration_minutes: Option<i32>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateLessonLog {
+    pub topic_order: Option<String>,
+    pub duration_minutes: Option<i32>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TopicCoverage {
+    pub order: String,
+    pub title: String,
+    pub planned_hours: f64,
+    pub taught_hours: f64,
+    pub lessons: usize,
+    pub coverage_percentage: f64,
+    pub last_taught_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubjectCoverage {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize_oid",
+        deserialize_with = "object_id_helpers::deserialize_oid"
+    )]
+    pub class_subject_id: ObjectId,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub class_id: Option<ObjectId>,
+
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub teacher_id: Option<ObjectId>,
+
+    pub subject_name: String,
+    pub subject_code: String,
+
+    pub planned_hours: f64,
+    pub taught_hours: f64,
+    pub coverage_percentage: f64,
+    /// Where coverage should be by now, from the share of term days elapsed
+    pub expected_percentage: f64,
+    pub lessons: usize,
+    pub last_lesson_at: Option<DateTime<Utc>>,
+    /// Teaching hours per week needed to finish by the end of the last term
+    pub weekly_hours_needed: Option<f64>,
+    pub is_behind: bool,
+
+    pub topics: Vec<TopicCoverage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CoverageDashboard {
+    #[serde(
+        serialize_with = "object_id_helpers::serialize",
+        deserialize_with = "object_id_helpers::deserialize",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub education_year_id: Option<ObjectId>,
+    pub education_year_label: String,
+    pub term_name: Option<String>,
+    pub term_order: Option<i32>,
+    pub days_remaining_in_term: i64,
+
+    pub total_subjects: usize,
+    pub behind_subjects: usize,
+    pub average_coverage: f64,
+
+    /// Furthest behind first
+    pub subjects: Vec<SubjectCoverage>,
+    pub generated_at: DateTime<Utc>,
+}
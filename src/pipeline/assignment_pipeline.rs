@@ -207,8 +207,99 @@ pub fn submission_with_relations_pipeline(match_stage: Document) -> Vec<Document
                 "preserveNullAndEmptyArrays": true
             }
         },
+        // Lookup the assignment's rubric for the score breakdown
+        doc! {
+            "$lookup": {
+                "from": "rubrics",
+                "localField": "assignment.rubric_id",
+                "foreignField": "_id",
+                "as": "rubric"
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$rubric",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
+        // Lookup group on group assignments
+        doc! {
+            "$lookup": {
+                "from": "assignment_groups",
+                "localField": "group_id",
+                "foreignField": "_id",
+                "as": "group"
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$group",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
         doc! {
             "$sort": { "submitted_at": -1 }
         },
     ]
 }
+
+pub fn peer_review_with_students_pipeline(match_stage: Document) -> Vec<Document> {
+    vec![
+        doc! {
+            "$match": match_stage
+        },
+        // Lookup reviewer
+        doc! {
+            "$lookup": {
+                "from": "students",
+                "localField": "reviewer_id",
+                "foreignField": "_id",
+                "as": "reviewer"
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$reviewer",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
+        // Lookup reviewee
+        doc! {
+            "$lookup": {
+                "from": "students",
+                "localField": "reviewee_id",
+                "foreignField": "_id",
+                "as": "reviewee"
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$reviewee",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
+        doc! {
+            "$sort": { "submission_id": 1, "created_at": 1 }
+        },
+    ]
+}
+
+pub fn assignment_group_with_members_pipeline(match_stage: Document) -> Vec<Document> {
+    vec![
+        doc! {
+            "$match": match_stage
+        },
+        // Lookup members
+        doc! {
+            "$lookup": {
+                "from": "students",
+                "localField": "member_ids",
+                "foreignField": "_id",
+                "as": "members"
+            }
+        },
+        doc! {
+            "$sort": { "name": 1 }
+        },
+    ]
+}
@@ -1,14 +1,44 @@
 use mongodb::bson::{doc, Document};
 
-pub fn learning_outcome_with_topics_pipeline(match_stage: Document) -> Vec<Document> {
+/// Outcomes with their topics, sub-topics and the learning materials and
+/// assignments linked to each topic. `published_only` hides drafts and
+/// materials not yet released from students and parents.
+pub fn learning_outcome_with_topics_pipeline(
+    match_stage: Document,
+    published_only: bool,
+) -> Vec<Document> {
+    let mut material_match = vec![
+        doc! { "$in": ["$topic_id", "$$all_topic_ids"] },
+        doc! { "$eq": [{ "$ifNull": ["$deleted_at", null] }, null] },
+    ];
+    let mut assignment_match = vec![
+        doc! { "$in": ["$topic_id", "$$all_topic_ids"] },
+        doc! { "$ne": ["$is_deleted", true] },
+    ];
+    if published_only {
+        material_match.push(doc! { "$eq": ["$is_published", true] });
+        material_match.push(doc! { "$lte": [{ "$ifNull": ["$release_at", "$$NOW"] }, "$$NOW"] });
+        assignment_match.push(doc! { "$eq": ["$status", "Published"] });
+    }
+
     vec![
         doc! { "$match": match_stage },
-        // 🔹 Lookup all topics for the learning outcome
+        // 🔹 Lookup top-level topics for the learning outcome
         doc! {
             "$lookup": {
                 "from": "subject_topics",
-                "localField": "_id",
-                "foreignField": "learning_outcome_id",
+                "let": { "outcome_id": "$_id" },
+                "pipeline": [
+                    { "$match": {
+                        "$expr": {
+                            "$and": [
+                                { "$eq": ["$learning_outcome_id", "$$outcome_id"] },
+                                { "$eq": [{ "$ifNull": ["$parent_topic_id", null] }, null] }
+                            ]
+                        }
+                    }},
+                    { "$sort": { "order": 1 } }
+                ],
                 "as": "topics"
             }
         },
@@ -29,24 +59,32 @@ pub fn learning_outcome_with_topics_pipeline(match_stage: Document) -> Vec<Docum
         // 🔹 Lookup learning materials linked to all topics (main + sub)
         doc! {
             "$lookup": {
-                "from": "subject_learning_materials",
+                "from": "learning_materials",
                 "let": {
                     "all_topic_ids": { "$concatArrays": ["$topics._id", "$sub_topics_flat._id"] }
                 },
                 "pipeline": [
-                    { "$match": {
-                        "$expr": {
-                            "$and": [
-                                { "$in": ["$reference_id", "$$all_topic_ids"] },
-                                { "$eq": ["$role", "SubjectTopic"] }
-                            ]
-                        }
-                    }}
+                    { "$match": { "$expr": { "$and": material_match } } },
+                    { "$sort": { "created_at": 1 } }
                 ],
                 "as": "materials_flat"
             }
         },
-        // 🔹 Combine main topics, attach materials + subtopics
+        // 🔹 Lookup assignments linked to all topics (main + sub)
+        doc! {
+            "$lookup": {
+                "from": "assignments",
+                "let": {
+                    "all_topic_ids": { "$concatArrays": ["$topics._id", "$sub_topics_flat._id"] }
+                },
+                "pipeline": [
+                    { "$match": { "$expr": { "$and": assignment_match } } },
+                    { "$sort": { "due_date": 1 } }
+                ],
+                "as": "assignments_flat"
+            }
+        },
+        // 🔹 Combine main topics, attach materials, assignments + subtopics
         doc! {
             "$addFields": {
                 "topics": {
@@ -61,7 +99,14 @@ pub fn learning_outcome_with_topics_pipeline(match_stage: Document) -> Vec<Docum
                                         "$filter": {
                                             "input": "$materials_flat",
                                             "as": "m",
-                                            "cond": { "$eq": ["$$m.reference_id", "$$t._id"] }
+                                            "cond": { "$eq": ["$$m.topic_id", "$$t._id"] }
+                                        }
+                                    },
+                                    "assignments": {
+                                        "$filter": {
+                                            "input": "$assignments_flat",
+                                            "as": "a",
+                                            "cond": { "$eq": ["$$a.topic_id", "$$t._id"] }
                                         }
                                     },
                                     "sub_topics": {
@@ -82,7 +127,14 @@ pub fn learning_outcome_with_topics_pipeline(match_stage: Document) -> Vec<Docum
                                                             "$filter": {
                                                                 "input": "$materials_flat",
                                                                 "as": "m2",
-                                                                "cond": { "$eq": ["$$m2.reference_id", "$$sub_t._id"] }
+                                                                "cond": { "$eq": ["$$m2.topic_id", "$$sub_t._id"] }
+                                                            }
+                                                        },
+                                                        "assignments": {
+                                                            "$filter": {
+                                                                "input": "$assignments_flat",
+                                                                "as": "a2",
+                                                                "cond": { "$eq": ["$$a2.topic_id", "$$sub_t._id"] }
                                                             }
                                                         }
                                                     }
@@ -97,7 +149,7 @@ pub fn learning_outcome_with_topics_pipeline(match_stage: Document) -> Vec<Docum
                 }
             }
         },
-        doc! { "$project": { "materials_flat": 0, "sub_topics_flat": 0 } },
+        doc! { "$project": { "materials_flat": 0, "assignments_flat": 0, "sub_topics_flat": 0 } },
         doc! { "$sort": { "order": 1 } },
     ]
 }
@@ -116,6 +116,61 @@ pub fn message_pipeline(match_stage: Document) -> Vec<Document> {
                 "sender_parent": 0
             }
         },
+        doc! {
+            "$lookup": {
+                "from": "messages",
+                // Messages written before ids were stored as ObjectIds hold a hex string
+                "let": {
+                    "reply_id": {
+                        "$cond": [
+                            { "$eq": [{ "$type": "$reply_to_message_id" }, "string"] },
+                            { "$toObjectId": "$reply_to_message_id" },
+                            "$reply_to_message_id"
+                        ]
+                    }
+                },
+                "pipeline": [
+                    { "$match": { "$expr": { "$eq": ["$_id", "$$reply_id"] } } },
+                    // Hide the content of replied messages that were deleted since
+                    {
+                        "$addFields": {
+                            "encrypted_payload": {
+                                "$cond": [{ "$ifNull": ["$deleted_at", false] }, "", "$encrypted_payload"]
+                            }
+                        }
+                    },
+                    { "$project": { "edit_history": 0, "reactions": 0, "read_by": 0 } }
+                ],
+                "as": "reply_to"
+            }
+        },
+        doc! { "$addFields": { "reply_to": { "$first": "$reply_to" } } },
+        doc! { "$sort": { "created_at": -1 } },
+    ]
+}
+
+/// FILE messages with the attachment record produced by the upload pipeline
+pub fn message_files_pipeline(match_stage: Document) -> Vec<Document> {
+    vec![
+        doc! { "$match": match_stage },
+        doc! {
+            "$lookup": {
+                "from": "message_attachments",
+                "let": {
+                    "attachment_id": {
+                        "$cond": [
+                            { "$eq": [{ "$type": "$attachment_id" }, "string"] },
+                            { "$toObjectId": "$attachment_id" },
+                            "$attachment_id"
+                        ]
+                    }
+                },
+                "pipeline": [{ "$match": { "$expr": { "$eq": ["$_id", "$$attachment_id"] } } }],
+                "as": "attachment"
+            }
+        },
+        doc! { "$addFields": { "attachment": { "$first": "$attachment" } } },
+        doc! { "$project": { "edit_history": 0 } },
         doc! { "$sort": { "created_at": -1 } },
     ]
 }
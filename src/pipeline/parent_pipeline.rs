@@ -187,7 +187,9 @@ pub fn student_results_pipeline(
 ) -> Vec<Document> {
     let mut match_doc = doc! {
         "student_id": { "$toObjectId": student_id },
-        "school_id": { "$toObjectId": school_id }
+        "school_id": { "$toObjectId": school_id },
+        "is_finalized": true,
+        "is_published": true
     };
 
     if let Some(year_id) = education_year_id {
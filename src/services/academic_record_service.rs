@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    config::state::AppState,
+    domain::{
+        academic_record::{
+            AnnualRecord, CumulativeRecord, ResultWeighting, TermRecord, Transcript,
+            TranscriptSchool, UpdateResultWeighting,
+        },
+        education_year::EducationYear,
+        exam::{Exam, ExamType},
+        grading_scale::GradingScale,
+        student::Student,
+        student_term_result::{StudentTermResult, SubjectResult},
+    },
+    errors::AppError,
+    models::mongo_model::IndexDef,
+    repositories::base_repo::BaseRepository,
+    services::{
+        gpa_calculation_service::credit_weighted_gpa, grading_scale_service::GradingScaleService,
+    },
+};
+
+/// Which results a caller may see
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultVisibility {
+    All,
+    /// Locked results, used for transcripts
+    Finalized,
+    /// Locked and released to students and families
+    Published,
+}
+
+impl ResultVisibility {
+    pub(crate) fn apply(self, filter: &mut Document) {
+        match self {
+            ResultVisibility::All => {}
+            ResultVisibility::Finalized => {
+                filter.insert("is_finalized", true);
+            }
+            ResultVisibility::Published => {
+                filter.insert("is_finalized", true);
+                filter.insert("is_published", true);
+            }
+        }
+    }
+
+    /// Official documents never show draft results, whoever asks
+    pub(crate) fn finalized_or_stricter(self) -> Self {
+        match self {
+            ResultVisibility::All => ResultVisibility::Finalized,
+            other => other,
+        }
+    }
+}
+
+pub struct AcademicRecordService {
+    pub result_collection: Collection<StudentTermResult>,
+    pub weighting_collection: Collection<ResultWeighting>,
+    pub db: Database,
+}
+
+impl AcademicRecordService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            result_collection: db.collection::<StudentTermResult>("student_term_results"),
+            weighting_collection: db.collection::<ResultWeighting>("result_weightings"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![IndexDef::compound(
+            vec![("school_id", 1), ("education_year_id", 1)],
+            true,
+        )];
+
+        let repo =
+            BaseRepository::new(self.weighting_collection.clone().clone_with_type::<Document>());
+        repo.ensure_indexes(&indexes).await?;
+        Ok(())
+    }
+
+    // =========================
+    // WEIGHTING
+    // =========================
+
+    pub async fn get_weighting(
+        &self,
+        school_id: Option<ObjectId>,
+        education_year_id: &ObjectId,
+    ) -> Result<ResultWeighting, AppError> {
+        let weighting = self
+            .weighting_collection
+            .find_one(doc! { "school_id": school_id, "education_year_id": education_year_id })
+            .await?;
+
+        Ok(weighting.unwrap_or_else(|| ResultWeighting::new(school_id, *education_year_id)))
+    }
+
+    pub async fn update_weighting(
+        &self,
+        school_id: Option<ObjectId>,
+        update: &UpdateResultWeighting,
+    ) -> Result<ResultWeighting, AppError> {
+        self.ensure_indexes().await?;
+
+        let education_year_id =
+            ObjectId::parse_str(&update.education_year_id).map_err(|_| AppError {
+                message: "Invalid education_year_id".into(),
+            })?;
+
+        let mut set = doc! { "updated_at": bson::to_bson(&Utc::now()).unwrap() };
+
+        if let Some(weights) = &update.exam_type_weights {
+            validate_weights(weights.iter().map(|w| w.weight_percentage), "Exam type")?;
+            for (i, weight) in weights.iter().enumerate() {
+                if weights[..i].iter().any(|w| w.exam_type == weight.exam_type) {
+                    return Err(AppError {
+                        message: format!("{:?} is weighted more than once", weight.exam_type),
+                    });
+                }
+            }
+            set.insert(
+                "exam_type_weights",
+                bson::to_bson(weights).map_err(|e| AppError {
+                    message: format!("Failed to serialize weights: {}", e),
+                })?,
+            );
+        }
+
+        if let Some(weights) = &update.term_weights {
+            validate_weights(weights.iter().map(|w| w.weight_percentage), "Term")?;
+            set.insert(
+                "term_weights",
+                bson::to_bson(weights).map_err(|e| AppError {
+                    message: format!("Failed to serialize weights: {}", e),
+                })?,
+            );
+        }
+
+        self.weighting_collection
+            .find_one_and_update(
+                doc! { "school_id": school_id, "education_year_id": education_year_id },
+                doc! { "$set": set },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Failed to update result weighting".into(),
+            })
+    }
+
+    // =========================
+    // RECORDS
+    // =========================
+
+    /// Term and annual roll-up of one education year
+    pub async fn annual_record(
+        &self,
+        student_id: &ObjectId,
+        education_year_id: &ObjectId,
+        visibility: ResultVisibility,
+    ) -> Result<Option<AnnualRecord>, AppError> {
+        let mut filter = doc! {
+            "student_id": student_id,
+            "education_year_id": education_year_id
+        };
+        visibility.apply(&mut filter);
+
+        let mut cursor = self.result_collection.find(filter).await?;
+        let mut results: Vec<StudentTermResult> = Vec::new();
+        while cursor.advance().await? {
+            results.push(cursor.deserialize_current()?);
+        }
+
+        if results.is_empty() {
+            return Ok(None);
+        }
+
+        let school_id = results.iter().find_map(|r| r.school_id);
+        let weighting = self.get_weighting(school_id, education_year_id).await?;
+        let scale = match school_id {
+            Some(school_id) => GradingScaleService::new(&self.db)
+                .get_active_scale(&school_id, education_year_id)
+                .await?,
+            None => None,
+        }
+        .unwrap_or_else(GradingScale::fallback);
+        let year = self
+            .db
+            .collection::<EducationYear>("education_years")
+            .find_one(doc! { "_id": education_year_id })
+            .await?;
+        let exam_types = self.exam_types(&results).await?;
+
+        // Group exam results by term, keeping the year's term order
+        let mut by_term: Vec<(Option<String>, Vec<StudentTermResult>)> = Vec::new();
+        for result in results {
+            match by_term.iter_mut().find(|(t, _)| *t == result.term_id) {
+                Some((_, group)) => group.push(result),
+                None => by_term.push((result.term_id.clone(), vec![result])),
+            }
+        }
+        by_term.sort_by_key(|(term_id, _)| term_order(year.as_ref(), term_id.as_deref()));
+
+        let class_id = by_term
+            .iter()
+            .flat_map(|(_, group)| group.iter())
+            .max_by_key(|r| r.calculated_at)
+            .and_then(|r| r.class_id);
+
+        let terms: Vec<TermRecord> = by_term
+            .into_iter()
+            .map(|(term_id, group)| term_record(term_id, &group, &exam_types, &weighting, &scale))
+            .collect();
+
+        let term_weights: Vec<f64> = if weighting.term_weights.is_empty() {
+            vec![1.0; terms.len()]
+        } else {
+            terms
+                .iter()
+                .map(|t| {
+                    weighting
+                        .term_weights
+                        .iter()
+                        .find(|w| Some(&w.term_id) == t.term_id.as_ref())
+                        .map(|w| w.weight_percentage)
+                        .unwrap_or(0.0)
+                })
+                .collect()
+        };
+        let sources: Vec<(f64, &[SubjectResult])> = terms
+            .iter()
+            .zip(equal_if_unweighted(term_weights))
+            .map(|(t, w)| (w, t.subject_results.as_slice()))
+            .collect();
+
+        let subject_results = combine_subjects(&sources, &scale);
+        let (average_percentage, gpa, grade, total_credits) = summarize(&subject_results, &scale);
+
+        Ok(Some(AnnualRecord {
+            education_year_id: *education_year_id,
+            start_date: year.as_ref().map(|y| y.start_date),
+            education_year_label: year.map(|y| y.label),
+            class_id,
+            is_finalized: terms.iter().all(|t| t.is_finalized),
+            terms,
+            subject_results,
+            average_percentage,
+            gpa,
+            max_gpa: scale.max_gpa,
+            grade,
+            total_credits,
+        }))
+    }
+
+    /// Every year the student has results for in this school, oldest first
+    pub async fn cumulative_record(
+        &self,
+        student_id: &ObjectId,
+        visibility: ResultVisibility,
+    ) -> Result<CumulativeRecord, AppError> {
+        let mut filter = doc! { "student_id": student_id };
+        visibility.apply(&mut filter);
+
+        let year_ids: Vec<ObjectId> = self
+            .result_collection
+            .distinct("education_year_id", filter)
+            .await?
+            .into_iter()
+            .filter_map(|v| v.as_object_id())
+            .collect();
+
+        let mut years = Vec::new();
+        for year_id in year_ids {
+            if let Some(record) = self
+                .annual_record(student_id, &year_id, visibility)
+                .await?
+            {
+                years.push(record);
+            }
+        }
+
+        years.sort_by_key(|y| y.start_date);
+
+        let (cumulative_gpa, max_gpa) = cumulative_gpa(&years);
+        Ok(CumulativeRecord {
+            student_id: *student_id,
+            years,
+            cumulative_gpa,
+            max_gpa,
+        })
+    }
+
+    /// Finalized history across every school the student's account belongs
+    /// to, limited further to published results for students and families
+    pub async fn transcript(
+        &self,
+        state: &AppState,
+        student: &Student,
+        current_school_id: Option<ObjectId>,
+        visibility: ResultVisibility,
+    ) -> Result<Transcript, AppError> {
+        let visibility = visibility.finalized_or_stricter();
+        let main_db = state.db.main_db();
+
+        let mut school_ids: Vec<ObjectId> = current_school_id.into_iter().collect();
+        if let Some(user_id) = student.user_id {
+            let user = main_db
+                .collection::<Document>("users")
+                .find_one(doc! { "_id": user_id })
+                .await?;
+            if let Some(Ok(schools)) = user.as_ref().map(|u| u.get_array("schools")) {
+                for id in schools.iter().filter_map(|v| v.as_object_id()) {
+                    if !school_ids.contains(&id) {
+                        school_ids.push(id);
+                    }
+                }
+            }
+        }
+
+        let mut schools = Vec::new();
+        for school_id in school_ids {
+            // The current school's record is known; elsewhere we match on the account
+            let (service, school_student) = if Some(school_id) == current_school_id {
+                (AcademicRecordService::new(&self.db), Some(student.clone()))
+            } else {
+                let Some(user_id) = student.user_id else {
+                    continue;
+                };
+                let db = state
+                    .db
+                    .get_db(&state.db.school_db_name_from_id(&school_id.to_hex()));
+                let found = db
+                    .collection::<Student>("students")
+                    .find_one(doc! { "user_id": user_id })
+                    .await?;
+                (AcademicRecordService::new(&db), found)
+            };
+
+            let Some(school_student) = school_student else {
+                continue;
+            };
+            let Some(student_id) = school_student.id else {
+                continue;
+            };
+
+            let record = service.cumulative_record(&student_id, visibility).await?;
+            if record.years.is_empty() {
+                continue;
+            }
+
+            let school_name = main_db
+                .collection::<Document>("schools")
+                .find_one(doc! { "_id": school_id })
+                .await?
+                .and_then(|s| s.get_str("name").ok().map(|n| n.to_string()));
+
+            schools.push(TranscriptSchool {
+                school_id,
+                school_name,
+                registration_number: school_student.registration_number.clone(),
+                years: record.years,
+            });
+        }
+
+        let mut all_years: Vec<AnnualRecord> = schools
+            .iter()
+            .flat_map(|s| s.years.iter().cloned())
+            .collect();
+        all_years.sort_by_key(|y| y.start_date);
+        let (cumulative_gpa, max_gpa) = cumulative_gpa(&all_years);
+
+        Ok(Transcript {
+            student_name: student.name.clone(),
+            user_id: student.user_id,
+            schools,
+            cumulative_gpa,
+            max_gpa,
+            generated_at: Utc::now(),
+        })
+    }
+
+    async fn exam_types(
+        &self,
+        results: &[StudentTermResult],
+    ) -> Result<HashMap<ObjectId, ExamType>, AppError> {
+        let exam_ids: Vec<ObjectId> = results.iter().filter_map(|r| r.exam_id).collect();
+
+        let mut cursor = self
+            .db
+            .collection::<Exam>("exams")
+            .find(doc! { "_id": { "$in": exam_ids } })
+            .await?;
+
+        let mut types = HashMap::new();
+        while cursor.advance().await? {
+            let exam: Exam = cursor.deserialize_current()?;
+            if let Some(id) = exam.id {
+                types.insert(id, exam.exam_type);
+            }
+        }
+        Ok(types)
+    }
+}
+
+fn validate_weights(weights: impl Iterator<Item = f64>, label: &str) -> Result<(), AppError> {
+    let weights: Vec<f64> = weights.collect();
+    if weights.iter().any(|w| *w < 0.0) {
+        return Err(AppError {
+            message: format!("{} weights cannot be negative", label),
+        });
+    }
+    if !weights.is_empty() && (weights.iter().sum::<f64>() - 100.0).abs() > 0.01 {
+        return Err(AppError {
+            message: format!("{} weights must add up to 100", label),
+        });
+    }
+    Ok(())
+}
+
+/// Fall back to equal weights when nothing present was given a weight
+fn equal_if_unweighted(weights: Vec<f64>) -> Vec<f64> {
+    if weights.iter().sum::<f64>() > 0.0 {
+        weights
+    } else {
+        vec![1.0; weights.len()]
+    }
+}
+
+fn term_order(year: Option<&EducationYear>, term_id: Option<&str>) -> (i32, String) {
+    let order = year
+        .zip(term_id)
+        .and_then(|(year, term_id)| {
+            year.terms
+                .iter()
+                .find(|t| t.name == term_id || t.order.to_string() == term_id)
+                .map(|t| t.order)
+        })
+        .unwrap_or(i32::MAX);
+    (order, term_id.unwrap_or_default().to_string())
+}
+
+/// A type's weight is shared by all exams of that type in the term,
+/// so three CATs split the CAT weight between them
+fn term_record(
+    term_id: Option<String>,
+    results: &[StudentTermResult],
+    exam_types: &HashMap<ObjectId, ExamType>,
+    weighting: &ResultWeighting,
+    scale: &GradingScale,
+) -> TermRecord {
+    let type_of = |r: &StudentTermResult| r.exam_id.and_then(|id| exam_types.get(&id));
+
+    let weights: Vec<f64> = results
+        .iter()
+        .map(|r| {
+            let Some(exam_type) = type_of(r) else {
+                return 0.0;
+            };
+            let same_type = results.iter().filter(|o| type_of(o) == Some(exam_type)).count();
+            weighting
+                .exam_type_weights
+                .iter()
+                .find(|w| &w.exam_type == exam_type)
+                .map(|w| w.weight_percentage / same_type as f64)
+                .unwrap_or(0.0)
+        })
+        .collect();
+
+    let sources: Vec<(f64, &[SubjectResult])> = results
+        .iter()
+        .zip(equal_if_unweighted(weights))
+        .map(|(r, w)| (w, r.subject_results.as_slice()))
+        .collect();
+
+    let subject_results = combine_subjects(&sources, scale);
+    let (average_percentage, gpa, grade, total_credits) = summarize(&subject_results, scale);
+
+    TermRecord {
+        term_id,
+        exam_ids: results.iter().filter_map(|r| r.exam_id).collect(),
+        subject_results,
+        average_percentage,
+        gpa,
+        grade,
+        total_credits,
+        is_finalized: results.iter().all(|r| r.is_finalized),
+    }
+}
+
+/// Weighted average of each subject over the sources it appears in
+fn combine_subjects(sources: &[(f64, &[SubjectResult])], scale: &GradingScale) -> Vec<SubjectResult> {
+    let mut combined: Vec<(SubjectResult, f64, f64)> = Vec::new();
+
+    for (weight, subjects) in sources {
+        for subject in subjects.iter() {
+            let existing = combined.iter_mut().find(|(s, _, _)| match subject.class_subject_id {
+                Some(id) => s.class_subject_id == Some(id),
+                None => s.class_subject_id.is_none() && s.subject_name == subject.subject_name,
+            });
+
+            match existing {
+                Some((_, weighted, total_weight)) => {
+                    *weighted += subject.percentage * weight;
+                    *total_weight += weight;
+                }
+                None => combined.push((subject.clone(), subject.percentage * weight, *weight)),
+            }
+        }
+    }
+
+    combined
+        .into_iter()
+        .filter(|(_, _, total_weight)| *total_weight > 0.0)
+        .map(|(subject, weighted, total_weight)| {
+            let percentage = weighted / total_weight;
+            SubjectResult {
+                class_subject_id: subject.class_subject_id,
+                subject_name: subject.subject_name,
+                category_scores: vec![],
+                weighted_score: percentage,
+                percentage,
+                grade: scale.grade_for(percentage),
+                credits: subject.credits,
+                grade_points: Some(scale.grade_points(percentage)),
+            }
+        })
+        .collect()
+}
+
+fn summarize(subjects: &[SubjectResult], scale: &GradingScale) -> (f64, f64, String, Option<i32>) {
+    let average_percentage = if subjects.is_empty() {
+        0.0
+    } else {
+        subjects.iter().map(|s| s.percentage).sum::<f64>() / subjects.len() as f64
+    };
+    let total_credits: i32 = subjects.iter().filter_map(|s| s.credits).sum();
+
+    (
+        average_percentage,
+        credit_weighted_gpa(subjects),
+        scale.grade_for(average_percentage),
+        (total_credits > 0).then_some(total_credits),
+    )
+}
+
+/// Credit-weighted across years. Years graded on a different scale are
+/// converted to the latest year's scale first.
+fn cumulative_gpa(years: &[AnnualRecord]) -> (f64, f64) {
+    let Some(max_gpa) = years.last().map(|y| y.max_gpa) else {
+        return (0.0, 0.0);
+    };
+
+    let (points, weights) = years.iter().fold((0.0, 0.0), |(p, w), year| {
+        let weight = year.total_credits.filter(|c| *c > 0).unwrap_or(1) as f64;
+        let gpa = if year.max_gpa > 0.0 {
+            year.gpa / year.max_gpa * max_gpa
+        } else {
+            0.0
+        };
+        (p + gpa * weight, w + weight)
+    });
+
+    let cumulative = if weights > 0.0 { points / weights } else { 0.0 };
+    (cumulative, max_gpa)
+}
@@ -0,0 +1,668 @@
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        academic_year_rollover::{
+            AcademicYearRollover, RolloverStatus, RolloverStep, RolloverStepProgress,
+            StartRollover,
+        },
+        common_details::Paginated,
+        education_year::{EducationYear, Term},
+        exam::ExamStatus,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::education_year_service::EducationYearService,
+    utils::mongo_utils::{id_match, to_stored_document},
+};
+
+/// Filter value for a key copied from a source document. Ids may be stored
+/// as ObjectIds or hex strings, so either form matches.
+fn key_match(value: Option<&Bson>) -> Bson {
+    match value {
+        Some(Bson::ObjectId(id)) => Bson::Document(id_match(id)),
+        Some(Bson::String(s)) => match ObjectId::parse_str(s) {
+            Ok(id) => Bson::Document(id_match(&id)),
+            Err(_) => Bson::String(s.clone()),
+        },
+        Some(other) => other.clone(),
+        None => Bson::Null,
+    }
+}
+
+/// Counters reported back for a finished step
+struct StepOutcome {
+    total: i64,
+    processed: i64,
+    skipped: i64,
+    message: Option<String>,
+}
+
+pub struct AcademicYearService {
+    pub collection: Collection<AcademicYearRollover>,
+    pub db: Database,
+}
+
+impl AcademicYearService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<AcademicYearRollover>("academic_year_rollovers"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("school_id", 1), ("from_education_year_id", 1)], false),
+            IndexDef::single("status", false),
+        ];
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.ensure_indexes(&indexes).await?;
+        Ok(())
+    }
+
+    // =========================
+    // START / RESUME
+    // =========================
+
+    /// Record a rollover job and run it in the background
+    pub async fn start_rollover(
+        &self,
+        school_id: Option<ObjectId>,
+        input: StartRollover,
+        created_by: ObjectId,
+    ) -> Result<AcademicYearRollover, AppError> {
+        self.ensure_indexes().await?;
+
+        let from_education_year_id =
+            ObjectId::parse_str(&input.from_education_year_id).map_err(|_| AppError {
+                message: "Invalid from_education_year_id".into(),
+            })?;
+
+        if input.label.trim().is_empty() {
+            return Err(AppError {
+                message: "label is required".into(),
+            });
+        }
+        if input.end_date <= input.start_date {
+            return Err(AppError {
+                message: "end_date must be after start_date".into(),
+            });
+        }
+
+        let previous = EducationYearService::new(&self.db)
+            .find_one(Some(&IdType::ObjectId(from_education_year_id)), None)
+            .await?;
+        if input.start_date <= previous.start_date {
+            return Err(AppError {
+                message: "The new year must start after the year it rolls over from".into(),
+            });
+        }
+
+        let existing = self
+            .collection
+            .find_one(doc! {
+                "from_education_year_id": from_education_year_id,
+                "status": { "$ne": bson::to_bson(&RolloverStatus::Failed).unwrap() }
+            })
+            .await?;
+        if let Some(existing) = existing {
+            return Err(AppError {
+                message: match existing.status {
+                    RolloverStatus::Completed => {
+                        format!("{} has already been rolled over", previous.label)
+                    }
+                    _ => format!("A rollover of {} is already in progress", previous.label),
+                },
+            });
+        }
+
+        // Earlier failed attempts must be resumed, not restarted, so nothing is cloned twice
+        let failed = self
+            .collection
+            .count_documents(doc! { "from_education_year_id": from_education_year_id })
+            .await?;
+        if failed > 0 {
+            return Err(AppError {
+                message: "A failed rollover exists for this year; resume it instead".into(),
+            });
+        }
+
+        let terms = if input.terms.is_empty() {
+            let offset = input.start_date - previous.start_date;
+            previous
+                .terms
+                .iter()
+                .map(|t| Term {
+                    name: t.name.clone(),
+                    order: t.order,
+                    start_date: t.start_date + offset,
+                    end_date: t.end_date + offset,
+                })
+                .collect()
+        } else {
+            input.terms
+        };
+
+        if let Some(term) = terms
+            .iter()
+            .find(|t| t.start_date < input.start_date || t.end_date > input.end_date)
+        {
+            return Err(AppError {
+                message: format!("{} falls outside the new academic year", term.name),
+            });
+        }
+
+        let rollover = AcademicYearRollover {
+            id: None,
+            school_id,
+            from_education_year_id,
+            to_education_year_id: None,
+            label: input.label.trim().to_string(),
+            start_date: input.start_date,
+            end_date: input.end_date,
+            terms,
+            status: RolloverStatus::Pending,
+            steps: RolloverStep::ALL
+                .iter()
+                .map(|s| RolloverStepProgress::new(*s))
+                .collect(),
+            error_message: None,
+            created_by: Some(created_by),
+            created_at: None,
+            updated_at: None,
+            completed_at: None,
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&rollover)?;
+        let created = repo.create::<AcademicYearRollover>(doc, None).await?;
+
+        self.spawn_run(&created)?;
+        Ok(created)
+    }
+
+    /// Pick a failed job back up from its first unfinished step
+    pub async fn resume_rollover(&self, id: &IdType) -> Result<AcademicYearRollover, AppError> {
+        let rollover = self.find_one(id).await?;
+
+        if rollover.status != RolloverStatus::Failed {
+            return Err(AppError {
+                message: "Only failed rollovers can be resumed".into(),
+            });
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let updated = repo
+            .update_one_and_fetch::<AcademicYearRollover>(
+                id,
+                doc! {
+                    "status": bson::to_bson(&RolloverStatus::Pending).unwrap(),
+                    "error_message": Bson::Null,
+                },
+            )
+            .await?;
+
+        self.spawn_run(&updated)?;
+        Ok(updated)
+    }
+
+    fn spawn_run(&self, rollover: &AcademicYearRollover) -> Result<(), AppError> {
+        let rollover_id = rollover.id.ok_or(AppError {
+            message: "Rollover ID not found".into(),
+        })?;
+        let db = self.db.clone();
+
+        actix_rt::spawn(async move {
+            let service = AcademicYearService::new(&db);
+            if let Err(e) = service.run(rollover_id).await {
+                service
+                    .collection
+                    .update_one(
+                        doc! { "_id": rollover_id },
+                        doc! { "$set": {
+                            "status": bson::to_bson(&RolloverStatus::Failed).unwrap(),
+                            "error_message": e.message,
+                            "updated_at": bson::to_bson(&Utc::now()).unwrap(),
+                        } },
+                    )
+                    .await
+                    .ok();
+            }
+        });
+
+        Ok(())
+    }
+
+    // =========================
+    // RUN
+    // =========================
+
+    async fn run(&self, rollover_id: ObjectId) -> Result<(), AppError> {
+        let id = IdType::ObjectId(rollover_id);
+        let mut rollover = self.find_one(&id).await?;
+
+        self.set_fields(
+            &rollover_id,
+            doc! { "status": bson::to_bson(&RolloverStatus::Running).unwrap() },
+        )
+        .await?;
+
+        for index in 0..rollover.steps.len() {
+            if rollover.steps[index].status == RolloverStatus::Completed {
+                continue;
+            }
+
+            let step = rollover.steps[index].step;
+            rollover.steps[index].status = RolloverStatus::Running;
+            self.save_steps(&rollover_id, &rollover.steps).await?;
+
+            match self.run_step(step, &mut rollover).await {
+                Ok(outcome) => {
+                    let progress = &mut rollover.steps[index];
+                    progress.status = RolloverStatus::Completed;
+                    progress.total = outcome.total;
+                    progress.processed = outcome.processed;
+                    progress.skipped = outcome.skipped;
+                    progress.message = outcome.message;
+                    self.save_steps(&rollover_id, &rollover.steps).await?;
+                }
+                Err(e) => {
+                    rollover.steps[index].status = RolloverStatus::Failed;
+                    rollover.steps[index].message = Some(e.message.clone());
+                    self.save_steps(&rollover_id, &rollover.steps).await?;
+                    return Err(e);
+                }
+            }
+        }
+
+        self.set_fields(
+            &rollover_id,
+            doc! {
+                "status": bson::to_bson(&RolloverStatus::Completed).unwrap(),
+                "completed_at": bson::to_bson(&Utc::now()).unwrap(),
+            },
+        )
+        .await
+    }
+
+    async fn run_step(
+        &self,
+        step: RolloverStep,
+        rollover: &mut AcademicYearRollover,
+    ) -> Result<StepOutcome, AppError> {
+        let from = rollover.from_education_year_id;
+        let to = || {
+            rollover.to_education_year_id.ok_or(AppError {
+                message: "The new education year has not been created".into(),
+            })
+        };
+
+        match step {
+            RolloverStep::CreateEducationYear => self.create_education_year(rollover).await,
+            RolloverStep::ClassStructure => self.check_class_structure().await,
+            RolloverStep::AssessmentCategories => {
+                self.clone_year_documents(
+                    "assessment_categories",
+                    doc! { "is_deleted": false },
+                    &["class_subject_id", "code"],
+                    &from,
+                    &to()?,
+                )
+                .await
+            }
+            RolloverStep::GradingScales => {
+                self.clone_year_documents(
+                    "grading_scales",
+                    doc! { "is_deleted": false },
+                    &["school_id", "name"],
+                    &from,
+                    &to()?,
+                )
+                .await
+            }
+            RolloverStep::ClassTimetables => {
+                // Timetables for terms the new year does not have are left behind
+                let term_count = rollover.terms.len() as i32;
+                self.clone_year_documents(
+                    "class_timetables",
+                    doc! { "term_order": { "$lte": term_count } },
+                    &["class_id", "term_order"],
+                    &from,
+                    &to()?,
+                )
+                .await
+            }
+            RolloverStep::ArchiveExams => self.archive_exams(&from).await,
+        }
+    }
+
+    async fn create_education_year(
+        &self,
+        rollover: &mut AcademicYearRollover,
+    ) -> Result<StepOutcome, AppError> {
+        let year_service = EducationYearService::new(&self.db);
+        let previous = year_service
+            .find_one(Some(&IdType::ObjectId(rollover.from_education_year_id)), None)
+            .await?;
+
+        // A previous attempt may have created the year before failing
+        let (year, skipped) = match year_service
+            .find_by_label_and_curriculum(&rollover.label, previous.curriculum_id)
+            .await
+        {
+            Ok(existing) => (existing, 1),
+            Err(_) => {
+                let created = year_service
+                    .create(EducationYear {
+                        id: None,
+                        curriculum_id: previous.curriculum_id,
+                        label: rollover.label.clone(),
+                        start_date: rollover.start_date,
+                        end_date: rollover.end_date,
+                        terms: rollover.terms.clone(),
+                        created_by: rollover.created_by,
+                        created_at: None,
+                        updated_at: None,
+                    })
+                    .await?;
+                (created, 0)
+            }
+        };
+
+        let year_id = year.id.ok_or(AppError {
+            message: "Created education year has no ID".into(),
+        })?;
+        rollover.to_education_year_id = Some(year_id);
+
+        let rollover_id = rollover.id.ok_or(AppError {
+            message: "Rollover ID not found".into(),
+        })?;
+        self.set_fields(&rollover_id, doc! { "to_education_year_id": year_id })
+            .await?;
+
+        Ok(StepOutcome {
+            total: 1,
+            processed: 1 - skipped,
+            skipped,
+            message: Some(format!("{} with {} term(s)", year.label, year.terms.len())),
+        })
+    }
+
+    /// Classes and their subject assignments are not tied to a year, so they
+    /// carry over as they are; this step reports what moves forward
+    async fn check_class_structure(&self) -> Result<StepOutcome, AppError> {
+        let classes = self
+            .db
+            .collection::<Document>("classes")
+            .count_documents(doc! { "is_active": { "$ne": false } })
+            .await?;
+
+        let subjects = self.db.collection::<Document>("class_subjects");
+        let subject_filter = doc! { "disable": { "$ne": true } };
+        let subject_count = subjects.count_documents(subject_filter.clone()).await?;
+
+        let mut unassigned_filter = subject_filter;
+        unassigned_filter.insert("teacher_id", Bson::Null);
+        let unassigned = subjects.count_documents(unassigned_filter).await?;
+
+        Ok(StepOutcome {
+            total: (classes + subject_count) as i64,
+            processed: (classes + subject_count) as i64,
+            skipped: 0,
+            message: Some(format!(
+                "{} classes and {} subject assignments carried over; {} subjects have no teacher",
+                classes, subject_count, unassigned
+            )),
+        })
+    }
+
+    /// Copy every document of a year-scoped collection into the new year.
+    /// `unique_keys` identify a copy that already exists, which keeps the step
+    /// safe to re-run after a partial failure.
+    async fn clone_year_documents(
+        &self,
+        collection_name: &str,
+        extra_filter: Document,
+        unique_keys: &[&str],
+        from: &ObjectId,
+        to: &ObjectId,
+    ) -> Result<StepOutcome, AppError> {
+        let collection = self.db.collection::<Document>(collection_name);
+
+        let mut filter = extra_filter;
+        filter.insert("education_year_id", id_match(from));
+
+        let total = collection.count_documents(filter.clone()).await? as i64;
+        let mut processed = 0;
+        let mut skipped = 0;
+
+        let mut cursor = collection.find(filter).await?;
+        while cursor.advance().await? {
+            let mut item: Document = cursor.deserialize_current()?;
+
+            let mut existing_filter = doc! { "education_year_id": id_match(to) };
+            for key in unique_keys {
+                existing_filter.insert(*key, key_match(item.get(*key)));
+            }
+            if collection.count_documents(existing_filter).await? > 0 {
+                skipped += 1;
+                continue;
+            }
+
+            let now = bson::to_bson(&Utc::now()).unwrap();
+            // Keep the id form the owning service stores and queries
+            let year = match item.get("education_year_id") {
+                Some(Bson::String(_)) => Bson::String(to.to_hex()),
+                _ => Bson::ObjectId(*to),
+            };
+            item.remove("_id");
+            item.insert("education_year_id", year);
+            item.insert("created_at", now.clone());
+            item.insert("updated_at", now);
+
+            collection.insert_one(item).await.map_err(|e| AppError {
+                message: format!("Failed to copy {}: {}", collection_name, e),
+            })?;
+            processed += 1;
+        }
+
+        Ok(StepOutcome {
+            total,
+            processed,
+            skipped,
+            message: None,
+        })
+    }
+
+    async fn archive_exams(&self, from: &ObjectId) -> Result<StepOutcome, AppError> {
+        let archived = bson::to_bson(&ExamStatus::Archived).unwrap();
+        let exams = self.db.collection::<Document>("exams");
+
+        let total = exams
+            .count_documents(doc! { "education_year_id": id_match(from) })
+            .await? as i64;
+
+        let result = exams
+            .update_many(
+                doc! {
+                    "education_year_id": id_match(from),
+                    "status": { "$ne": archived.clone() }
+                },
+                doc! { "$set": {
+                    "status": archived,
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap(),
+                } },
+            )
+            .await?;
+
+        let processed = result.modified_count as i64;
+        Ok(StepOutcome {
+            total,
+            processed,
+            skipped: total - processed,
+            message: None,
+        })
+    }
+
+    async fn save_steps(
+        &self,
+        rollover_id: &ObjectId,
+        steps: &[RolloverStepProgress],
+    ) -> Result<(), AppError> {
+        let steps = bson::to_bson(steps).map_err(|e| AppError {
+            message: format!("Failed to serialize rollover steps: {}", e),
+        })?;
+        self.set_fields(rollover_id, doc! { "steps": steps }).await
+    }
+
+    async fn set_fields(&self, rollover_id: &ObjectId, mut fields: Document) -> Result<(), AppError> {
+        fields.insert("updated_at", bson::to_bson(&Utc::now()).unwrap());
+        self.collection
+            .update_one(doc! { "_id": rollover_id }, doc! { "$set": fields })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to update rollover progress: {}", e),
+            })?;
+        Ok(())
+    }
+
+    // =========================
+    // QUERIES
+    // =========================
+
+    pub async fn find_one(&self, id: &IdType) -> Result<AcademicYearRollover, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<AcademicYearRollover>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Rollover not found".into(),
+            })
+    }
+
+    pub async fn get_all(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<AcademicYearRollover>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<AcademicYearRollover>(None, &["label", "status"], limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            assessment_category::AssessmentCategory,
+            exam::{Exam, ExamType},
+        },
+        services::{assessment_category_service::AssessmentCategoryService, exam_service::ExamService},
+        utils::test_utils::test_database,
+    };
+
+    #[test]
+    fn copied_keys_match_either_id_form() {
+        let id = ObjectId::new();
+        let both = Bson::Document(doc! { "$in": [id, id.to_hex()] });
+
+        assert_eq!(key_match(Some(&Bson::ObjectId(id))), both);
+        assert_eq!(key_match(Some(&Bson::String(id.to_hex()))), both);
+        assert_eq!(key_match(Some(&Bson::String("CAT".into()))), Bson::String("CAT".into()));
+        assert_eq!(key_match(Some(&Bson::Int32(2))), Bson::Int32(2));
+        assert_eq!(key_match(None), Bson::Null);
+    }
+
+    #[actix_rt::test]
+    #[ignore = "needs MongoDB, see TEST_MONGODB_URI"]
+    async fn rollover_copies_and_archives_documents_of_the_existing_services() {
+        let db = test_database("test_academic_year").await;
+        let (school_id, subject_id) = (ObjectId::new(), ObjectId::new());
+        let (from, to) = (ObjectId::new(), ObjectId::new());
+
+        AssessmentCategoryService::new(&db)
+            .create(AssessmentCategory {
+                id: None,
+                school_id: Some(school_id),
+                class_subject_id: Some(subject_id),
+                education_year_id: Some(from),
+                name: "Exam".into(),
+                code: "EX".into(),
+                weight_percentage: 60.0,
+                description: None,
+                created_by: None,
+                created_at: None,
+                updated_at: None,
+                is_deleted: false,
+            })
+            .await
+            .unwrap();
+        ExamService::new(&db)
+            .create(Exam {
+                id: None,
+                school_id: Some(school_id),
+                education_year_id: Some(from),
+                term_id: None,
+                class_id: None,
+                name: "Term 3".into(),
+                description: None,
+                exam_type: ExamType::Final,
+                status: ExamStatus::Completed,
+                start_date: Utc::now(),
+                end_date: Utc::now(),
+                created_by: None,
+                created_at: None,
+                updated_at: None,
+                is_deleted: false,
+            })
+            .await
+            .unwrap();
+
+        let service = AcademicYearService::new(&db);
+        for (processed, skipped) in [(1, 0), (0, 1)] {
+            // Re-running skips what was already copied
+            let outcome = service
+                .clone_year_documents(
+                    "assessment_categories",
+                    doc! { "is_deleted": false },
+                    &["class_subject_id", "code"],
+                    &from,
+                    &to,
+                )
+                .await
+                .unwrap();
+            assert_eq!((outcome.processed, outcome.skipped), (processed, skipped));
+        }
+
+        let copies = AssessmentCategoryService::new(&db)
+            .collection
+            .count_documents(doc! { "education_year_id": id_match(&to) })
+            .await
+            .unwrap();
+        assert_eq!(copies, 1);
+
+        let archived = service.archive_exams(&from).await.unwrap();
+        assert_eq!((archived.total, archived.processed), (1, 1));
+
+        db.drop().await.unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+use std::{collections::HashSet, env, time::Duration};
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+use serde_json::json;
+
+use crate::{
+    domain::{
+        ai_feedback::{FeedbackRequest, FeedbackSuggestion},
+        assignment::{AiFeedbackStatus, Submission},
+    },
+    errors::AppError,
+    models::id_model::IdType,
+    services::assignment_service::AssignmentService,
+};
+
+/// Feature flag, off unless a school turns it on
+pub const AI_FEEDBACK_FEATURE: &str = "assignments.ai_feedback";
+
+const DEFAULT_HOURLY_LIMIT: i64 = 30;
+
+/// Anything that can turn a submission into suggested feedback and a score
+pub trait FeedbackProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn suggest<'a>(
+        &'a self,
+        request: &'a FeedbackRequest,
+    ) -> BoxFuture<'a, Result<FeedbackSuggestion, AppError>>;
+}
+
+/// `AI_FEEDBACK_PROVIDER=http` talks to an OpenAI-compatible endpoint,
+/// anything else uses the offline mock
+pub fn provider_from_env() -> Box<dyn FeedbackProvider> {
+    match env::var("AI_FEEDBACK_PROVIDER").as_deref() {
+        Ok("http") => Box::new(HttpFeedbackProvider::from_env()),
+        _ => Box::new(MockFeedbackProvider),
+    }
+}
+
+// =========================
+// MOCK PROVIDER
+// =========================
+
+/// Offline provider for development and tests. Scores on how many key terms
+/// of the instructions and rubric the answer uses, and on its length.
+pub struct MockFeedbackProvider;
+
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "their", "there", "these", "those", "which", "would", "should", "could",
+    "where", "while", "write", "answer", "question", "explain", "describe", "students", "points",
+];
+
+impl MockFeedbackProvider {
+    fn key_terms(text: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        text.split(|c: char| !c.is_alphanumeric())
+            .map(|w| w.to_lowercase())
+            .filter(|w| w.chars().count() > 4 && !STOP_WORDS.contains(&w.as_str()))
+            .filter(|w| seen.insert(w.clone()))
+            .collect()
+    }
+}
+
+impl FeedbackProvider for MockFeedbackProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn suggest<'a>(
+        &'a self,
+        request: &'a FeedbackRequest,
+    ) -> BoxFuture<'a, Result<FeedbackSuggestion, AppError>> {
+        Box::pin(async move {
+            let mut source = request.instructions.clone();
+            if let Some(rubric) = &request.rubric {
+                source.push(' ');
+                source.push_str(rubric);
+            }
+            let terms = Self::key_terms(&source);
+            let answer: HashSet<String> = request
+                .submission_text
+                .split(|c: char| !c.is_alphanumeric())
+                .map(|w| w.to_lowercase())
+                .collect();
+            let missing: Vec<&String> = terms.iter().filter(|t| !answer.contains(*t)).collect();
+
+            let coverage = if terms.is_empty() {
+                1.0
+            } else {
+                (terms.len() - missing.len()) as f64 / terms.len() as f64
+            };
+            let words = request.submission_text.split_whitespace().count();
+            let length = (words as f64 / 150.0).min(1.0);
+            let score = ((0.6 * coverage + 0.4 * length) * request.max_score * 10.0).round() / 10.0;
+
+            let mut feedback = vec![format!(
+                "The answer uses {} of {} key terms from the task.",
+                terms.len() - missing.len(),
+                terms.len()
+            )];
+            if !missing.is_empty() {
+                let listed: Vec<&str> = missing.iter().take(5).map(|t| t.as_str()).collect();
+                feedback.push(format!("Consider addressing: {}.", listed.join(", ")));
+            }
+            if words < 150 {
+                feedback.push(format!(
+                    "At {} words the answer is brief, develop the main points further.",
+                    words
+                ));
+            }
+
+            Ok(FeedbackSuggestion {
+                feedback: feedback.join(" "),
+                score,
+                provider: self.name().into(),
+            })
+        })
+    }
+}
+
+// =========================
+// HTTP PROVIDER
+// =========================
+
+/// Chat completions client for any OpenAI-compatible server, such as a
+/// local Ollama, llama.cpp or vLLM instance
+pub struct HttpFeedbackProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl HttpFeedbackProvider {
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("AI_FEEDBACK_BASE_URL").unwrap_or_else(|_| "http://localhost:11434/v1".into()),
+            env::var("AI_FEEDBACK_MODEL").unwrap_or_else(|_| "llama3.1".into()),
+            env::var("AI_FEEDBACK_API_KEY").ok(),
+        )
+    }
+
+    pub fn new(base_url: String, model: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(90))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+        }
+    }
+
+    fn prompt(request: &FeedbackRequest) -> String {
+        format!(
+            "Assignment: {}\n\nInstructions:\n{}\n\nRubric:\n{}\n\nMaximum score: {}\n\n\
+             Student submission:\n{}\n\n\
+             Reply with only a JSON object {{\"feedback\": string, \"score\": number}}. \
+             The feedback is addressed to the student, constructive and under 200 words.",
+            request.assignment_title,
+            request.instructions,
+            request.rubric.as_deref().unwrap_or("None given"),
+            request.max_score,
+            request.submission_text
+        )
+    }
+}
+
+impl FeedbackProvider for HttpFeedbackProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn suggest<'a>(
+        &'a self,
+        request: &'a FeedbackRequest,
+    ) -> BoxFuture<'a, Result<FeedbackSuggestion, AppError>> {
+        Box::pin(async move {
+            let body = json!({
+                "model": self.model,
+                "temperature": 0.2,
+                "messages": [
+                    {
+                        "role": "system",
+                        "content": "You are a teacher's assistant drafting feedback on student work."
+                    },
+                    { "role": "user", "content": Self::prompt(request) }
+                ]
+            });
+
+            let mut call = self
+                .client
+                .post(format!("{}/chat/completions", self.base_url))
+                .json(&body);
+            if let Some(key) = &self.api_key {
+                call = call.bearer_auth(key);
+            }
+
+            let response = call.send().await.map_err(|e| AppError {
+                message: format!("AI feedback provider unreachable: {}", e),
+            })?;
+            if !response.status().is_success() {
+                return Err(AppError {
+                    message: format!("AI feedback provider returned {}", response.status()),
+                });
+            }
+            let payload: serde_json::Value = response.json().await.map_err(|e| AppError {
+                message: format!("Invalid AI feedback response: {}", e),
+            })?;
+
+            let content = payload["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap_or_default();
+            // Models like to wrap JSON in prose or code fences
+            let parsed = match (content.find('{'), content.rfind('}')) {
+                (Some(start), Some(end)) if start < end => {
+                    serde_json::from_str::<serde_json::Value>(&content[start..=end]).ok()
+                }
+                _ => None,
+            }
+            .ok_or(AppError {
+                message: "AI feedback provider did not return JSON".into(),
+            })?;
+
+            let feedback = parsed["feedback"]
+                .as_str()
+                .filter(|f| !f.trim().is_empty())
+                .ok_or(AppError {
+                    message: "AI feedback provider returned no feedback".into(),
+                })?;
+            let score = parsed["score"].as_f64().unwrap_or(0.0);
+
+            Ok(FeedbackSuggestion {
+                feedback: feedback.trim().to_string(),
+                score: score.clamp(0.0, request.max_score),
+                provider: self.name().into(),
+            })
+        })
+    }
+}
+
+// =========================
+// SERVICE
+// =========================
+
+pub struct AiFeedbackService {
+    pub usage_collection: Collection<Document>,
+    pub db: Database,
+    pub provider: Box<dyn FeedbackProvider>,
+}
+
+impl AiFeedbackService {
+    pub fn new(db: &Database) -> Self {
+        Self::with_provider(db, provider_from_env())
+    }
+
+    pub fn with_provider(db: &Database, provider: Box<dyn FeedbackProvider>) -> Self {
+        Self {
+            usage_collection: db.collection::<Document>("ai_feedback_usage"),
+            db: db.clone(),
+            provider,
+        }
+    }
+
+    /// Count a request against the school's hourly allowance
+    /// (`AI_FEEDBACK_HOURLY_LIMIT`, 30 by default)
+    async fn consume_quota(&self, school_id: Option<ObjectId>) -> Result<(), AppError> {
+        let limit = env::var("AI_FEEDBACK_HOURLY_LIMIT")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_HOURLY_LIMIT);
+        let window = Utc::now().format("%Y-%m-%dT%H").to_string();
+
+        let usage = self
+            .usage_collection
+            .find_one_and_update(
+                doc! { "school_id": school_id, "window": &window },
+                doc! {
+                    "$inc": { "count": 1_i64 },
+                    "$setOnInsert": { "created_at": bson::to_bson(&Utc::now()).unwrap() }
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?;
+
+        let used = usage
+            .and_then(|u| {
+                u.get("count")
+                    .and_then(|c| c.as_i64().or(c.as_i32().map(i64::from)))
+            })
+            .unwrap_or(0);
+        if used > limit {
+            return Err(AppError {
+                message: format!(
+                    "AI feedback limit of {} requests per hour reached for this school",
+                    limit
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Ask the provider for feedback and store it on the submission as a
+    /// draft. Generating again replaces an unaccepted draft.
+    pub async fn generate(
+        &self,
+        submission_id: &IdType,
+        school_id: Option<ObjectId>,
+        rubric: Option<String>,
+    ) -> Result<Submission, AppError> {
+        let assignment_service = AssignmentService::new(&self.db);
+        let submission = assignment_service
+            .find_one_submission(Some(submission_id), None)
+            .await?;
+        if submission.ai_feedback_status == Some(AiFeedbackStatus::Accepted) {
+            return Err(AppError {
+                message: "AI feedback on this submission was already accepted".into(),
+            });
+        }
+
+        let submission_text = submission
+            .text_content
+            .clone()
+            .or(submission.comment.clone())
+            .filter(|t| !t.trim().is_empty())
+            .ok_or(AppError {
+                message: "Submission has no text to give feedback on".into(),
+            })?;
+        let assignment_id = submission.assignment_id.ok_or(AppError {
+            message: "Submission has no assignment".into(),
+        })?;
+        let assignment = assignment_service
+            .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+            .await?;
+
+        self.consume_quota(school_id.or(assignment.school_id))
+            .await?;
+
+        let request = FeedbackRequest {
+            assignment_title: assignment.title.clone(),
+            instructions: [
+                assignment.description.clone(),
+                assignment.instructions.clone(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+            rubric,
+            submission_text,
+            max_score: assignment.max_score,
+        };
+        let suggestion = self.provider.suggest(&request).await?;
+
+        let repo_id = IdType::to_object_id(submission_id)?;
+        assignment_service
+            .submission_collection
+            .find_one_and_update(
+                doc! { "_id": repo_id },
+                doc! { "$set": {
+                    "ai_feedback": &suggestion.feedback,
+                    "ai_suggested_score": suggestion.score,
+                    "ai_feedback_status": bson::to_bson(&AiFeedbackStatus::Draft).unwrap(),
+                    "ai_feedback_generated_at": bson::to_bson(&Utc::now()).unwrap(),
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Submission not found".into(),
+            })
+    }
+}
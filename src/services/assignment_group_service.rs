@@ -0,0 +1,363 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        assignment::{Assignment, GroupFormation, GroupSettings},
+        assignment_group::{
+            AssignmentGroup, AssignmentGroupWithMembers, CreateAssignmentGroup,
+            UpdateAssignmentGroup,
+        },
+        common_details::Paginated,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    pipeline::assignment_pipeline::assignment_group_with_members_pipeline,
+    repositories::base_repo::BaseRepository,
+    services::assignment_service::AssignmentService,
+    utils::mongo_utils::to_stored_document,
+};
+
+pub struct AssignmentGroupService {
+    pub collection: Collection<AssignmentGroup>,
+    pub db: Database,
+}
+
+impl AssignmentGroupService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<AssignmentGroup>("assignment_groups"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::single("assignment_id", false),
+            IndexDef::compound(vec![("assignment_id", 1), ("member_ids", 1)], false),
+        ];
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    /// The assignment and its group settings; fails for individual work
+    async fn group_assignment(
+        &self,
+        assignment_id: ObjectId,
+    ) -> Result<(Assignment, GroupSettings), AppError> {
+        let assignment = AssignmentService::new(&self.db)
+            .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+            .await?;
+        let settings = assignment.group_settings.clone().ok_or(AppError {
+            message: "This is not a group assignment".into(),
+        })?;
+        Ok((assignment, settings))
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<AssignmentGroup, AppError> {
+        self.collection
+            .find_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?
+            .ok_or(AppError {
+                message: "Group not found".into(),
+            })
+    }
+
+    pub async fn get_all_with_members(
+        &self,
+        assignment_id: ObjectId,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Paginated<AssignmentGroupWithMembers>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let pipeline =
+            assignment_group_with_members_pipeline(doc! { "assignment_id": assignment_id });
+        repo.aggregate_with_paginate::<AssignmentGroupWithMembers>(pipeline, limit, skip)
+            .await
+    }
+
+    /// Group the student is in for this assignment, if any
+    pub async fn find_for_student(
+        &self,
+        assignment_id: ObjectId,
+        student_id: ObjectId,
+    ) -> Result<Option<AssignmentGroup>, AppError> {
+        Ok(self
+            .collection
+            .find_one(doc! { "assignment_id": assignment_id, "member_ids": student_id })
+            .await?)
+    }
+
+    async fn has_submission(&self, group_id: ObjectId) -> Result<bool, AppError> {
+        Ok(AssignmentService::new(&self.db)
+            .submission_collection
+            .find_one(doc! { "group_id": group_id, "is_deleted": false })
+            .await?
+            .is_some())
+    }
+
+    /// Members must be distinct students of the assignment's class, fit the
+    /// size limit and not already be in another group for the assignment
+    async fn validate_members(
+        &self,
+        assignment: &Assignment,
+        settings: &GroupSettings,
+        member_ids: &[ObjectId],
+        group_id: Option<ObjectId>,
+    ) -> Result<(), AppError> {
+        let distinct: HashSet<&ObjectId> = member_ids.iter().collect();
+        if distinct.len() != member_ids.len() {
+            return Err(AppError {
+                message: "A student is listed twice".into(),
+            });
+        }
+        if member_ids.len() > settings.max_group_size.max(1) as usize {
+            return Err(AppError {
+                message: format!(
+                    "Groups can have at most {} member(s)",
+                    settings.max_group_size
+                ),
+            });
+        }
+        if member_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut student_filter = doc! { "_id": { "$in": member_ids } };
+        if let Some(class_id) = assignment.class_id {
+            student_filter.insert("class_id", class_id);
+        }
+        let found = self
+            .db
+            .collection::<Document>("students")
+            .count_documents(student_filter)
+            .await?;
+        if found as usize != member_ids.len() {
+            return Err(AppError {
+                message: "Every member must be a student of the assignment's class".into(),
+            });
+        }
+
+        let mut taken_filter = doc! {
+            "assignment_id": assignment.id,
+            "member_ids": { "$in": member_ids }
+        };
+        if let Some(group_id) = group_id {
+            taken_filter.insert("_id", doc! { "$ne": group_id });
+        }
+        if let Some(other) = self.collection.find_one(taken_filter).await? {
+            return Err(AppError {
+                message: format!("A student is already in group \"{}\"", other.name),
+            });
+        }
+        Ok(())
+    }
+
+    fn parse_members(member_ids: &[String]) -> Result<Vec<ObjectId>, AppError> {
+        member_ids
+            .iter()
+            .map(|id| IdType::from_string(id.clone()).to_object_id())
+            .collect()
+    }
+
+    /// Teacher-formed group, or with `student_id` a group a student starts
+    /// on a self-enrolment assignment
+    pub async fn create(
+        &self,
+        assignment_id: ObjectId,
+        dto: CreateAssignmentGroup,
+        student_id: Option<ObjectId>,
+        created_by: Option<ObjectId>,
+    ) -> Result<AssignmentGroup, AppError> {
+        self.ensure_indexes().await?;
+        let (assignment, settings) = self.group_assignment(assignment_id).await?;
+
+        if dto.name.trim().is_empty() {
+            return Err(AppError {
+                message: "Group name is required".into(),
+            });
+        }
+
+        let member_ids = match student_id {
+            Some(student_id) => {
+                if settings.formation != GroupFormation::SelfEnroll {
+                    return Err(AppError {
+                        message: "Groups for this assignment are formed by the teacher".into(),
+                    });
+                }
+                vec![student_id]
+            }
+            None => Self::parse_members(&dto.member_ids)?,
+        };
+        self.validate_members(&assignment, &settings, &member_ids, None)
+            .await?;
+
+        let group = AssignmentGroup {
+            id: None,
+            school_id: assignment.school_id,
+            assignment_id,
+            name: dto.name.trim().to_string(),
+            member_ids,
+            created_by,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .create::<AssignmentGroup>(to_stored_document(&group)?, None)
+            .await
+    }
+
+    /// Rename a group or replace its members. Membership is fixed once the
+    /// group has submitted, so grades reach the people who did the work.
+    pub async fn update(
+        &self,
+        id: &IdType,
+        dto: UpdateAssignmentGroup,
+    ) -> Result<AssignmentGroup, AppError> {
+        let group = self.find_one(id).await?;
+        let group_id = group.id.unwrap_or_default();
+        let mut set = doc! { "updated_at": bson::to_bson(&Utc::now()).unwrap() };
+
+        if let Some(name) = dto.name {
+            if name.trim().is_empty() {
+                return Err(AppError {
+                    message: "Group name is required".into(),
+                });
+            }
+            set.insert("name", name.trim());
+        }
+
+        if let Some(member_ids) = dto.member_ids {
+            if self.has_submission(group_id).await? {
+                return Err(AppError {
+                    message: "Members cannot change after the group has submitted".into(),
+                });
+            }
+            let (assignment, settings) = self.group_assignment(group.assignment_id).await?;
+            let member_ids = Self::parse_members(&member_ids)?;
+            self.validate_members(&assignment, &settings, &member_ids, Some(group_id))
+                .await?;
+            set.insert("member_ids", member_ids);
+        }
+
+        self.collection
+            .update_one(doc! { "_id": group_id }, doc! { "$set": set })
+            .await?;
+        self.find_one(id).await
+    }
+
+    pub async fn delete(&self, id: &IdType) -> Result<AssignmentGroup, AppError> {
+        let group = self.find_one(id).await?;
+        if self.has_submission(group.id.unwrap_or_default()).await? {
+            return Err(AppError {
+                message: "A group that has submitted cannot be deleted".into(),
+            });
+        }
+        self.collection.delete_one(doc! { "_id": group.id }).await?;
+        Ok(group)
+    }
+
+    /// Self-enrolment: join a group that still has room
+    pub async fn join(
+        &self,
+        id: &IdType,
+        student_id: ObjectId,
+    ) -> Result<AssignmentGroup, AppError> {
+        let group = self.find_one(id).await?;
+        let (assignment, settings) = self.group_assignment(group.assignment_id).await?;
+        if settings.formation != GroupFormation::SelfEnroll {
+            return Err(AppError {
+                message: "Groups for this assignment are formed by the teacher".into(),
+            });
+        }
+        if Utc::now() > assignment.due_date {
+            return Err(AppError {
+                message: "Groups are closed after the due date".into(),
+            });
+        }
+        if self.has_submission(group.id.unwrap_or_default()).await? {
+            return Err(AppError {
+                message: "This group has already submitted".into(),
+            });
+        }
+
+        let mut member_ids = group.member_ids.clone();
+        member_ids.push(student_id);
+        self.validate_members(&assignment, &settings, &member_ids, group.id)
+            .await?;
+
+        // The size guard sits in the filter so concurrent joins cannot
+        // overfill the group between the check above and the update
+        let last_slot = format!("member_ids.{}", settings.max_group_size.max(1) - 1);
+        let joined = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": group.id,
+                    "member_ids": { "$ne": student_id },
+                    last_slot: { "$exists": false }
+                },
+                doc! {
+                    "$push": { "member_ids": student_id },
+                    "$set": { "updated_at": bson::to_bson(&Utc::now()).unwrap() }
+                },
+            )
+            .await?;
+        if joined.matched_count == 0 {
+            return Err(AppError {
+                message: "This group is full".into(),
+            });
+        }
+        self.find_one(id).await
+    }
+
+    /// Self-enrolment: leave a group; the last member out removes it
+    pub async fn leave(
+        &self,
+        id: &IdType,
+        student_id: ObjectId,
+    ) -> Result<AssignmentGroup, AppError> {
+        let group = self.find_one(id).await?;
+        let (_, settings) = self.group_assignment(group.assignment_id).await?;
+        if settings.formation != GroupFormation::SelfEnroll {
+            return Err(AppError {
+                message: "Groups for this assignment are formed by the teacher".into(),
+            });
+        }
+        if !group.member_ids.contains(&student_id) {
+            return Err(AppError {
+                message: "You are not in this group".into(),
+            });
+        }
+        if self.has_submission(group.id.unwrap_or_default()).await? {
+            return Err(AppError {
+                message: "Members cannot change after the group has submitted".into(),
+            });
+        }
+
+        if group.member_ids.len() == 1 {
+            self.collection.delete_one(doc! { "_id": group.id }).await?;
+            return Ok(AssignmentGroup {
+                member_ids: Vec::new(),
+                ..group
+            });
+        }
+
+        self.collection
+            .update_one(
+                doc! { "_id": group.id },
+                doc! {
+                    "$pull": { "member_ids": student_id },
+                    "$set": { "updated_at": bson::to_bson(&Utc::now()).unwrap() }
+                },
+            )
+            .await?;
+        self.find_one(id).await
+    }
+}
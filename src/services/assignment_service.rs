@@ -1,16 +1,23 @@
 use chrono::Utc;
+use futures::TryStreamExt;
 use mongodb::{
-    bson::{doc, Document},
+    bson::{self, doc, oid::ObjectId, Document},
     Collection, Database,
 };
 
 use crate::{
     domain::{
         assignment::{
-            Assignment, AssignmentPartial, AssignmentStatus, AssignmentWithRelations, Submission,
-            SubmissionPartial, SubmissionStatus, SubmissionWithRelations,
+            AiFeedbackStatus, Assignment, AssignmentPartial, AssignmentStatus,
+            AssignmentWithRelations, ContributionStatement, GradeSubmission, GroupSettings,
+            MemberGrade, MemberScoreInput, Submission, SubmissionPartial, SubmissionPolicy,
+            SubmissionStatus, SubmissionVersion, SubmissionWithRelations,
         },
+        assessment_category::AssessmentCategory,
+        class::ClassworkRules,
+        rubric::Rubric,
         common_details::Paginated,
+        score::{Score, ScorePartial},
     },
     errors::AppError,
     models::{
@@ -21,8 +28,14 @@ use crate::{
         assignment_with_teacher_pipeline, submission_with_relations_pipeline,
     },
     repositories::base_repo::BaseRepository,
-    services::cloudinary_service::CloudinaryService,
-    utils::mongo_utils::{build_search_filter, extract_valid_fields},
+    services::{
+        assignment_group_service::AssignmentGroupService, cloudinary_service::CloudinaryService,
+        quiz_service::QuizService, rubric_service::RubricService, score_service::ScoreService,
+    },
+    utils::{
+        math_utils::round2,
+        mongo_utils::{build_search_filter, extract_valid_fields},
+    },
 };
 
 pub struct AssignmentService {
@@ -57,6 +70,8 @@ impl AssignmentService {
         let submission_indexes = vec![
             IndexDef::single("assignment_id", false),
             IndexDef::single("student_id", false),
+            IndexDef::single("group_id", false),
+            IndexDef::single("member_grades.student_id", false),
             IndexDef::single("graded_by", false),
             IndexDef::single("submitted_at", false),
             IndexDef::single("status", false),
@@ -117,6 +132,19 @@ impl AssignmentService {
             }
         }
 
+        if let Some(rubric_id) = dto.rubric_id {
+            self.validate_rubric(rubric_id, dto.teacher_id).await?;
+        }
+
+        if let Some(settings) = &dto.group_settings {
+            Self::validate_group_settings(settings)?;
+        }
+
+        if let Some(category_id) = dto.assessment_category_id {
+            self.validate_assessment_category(category_id, dto.subject_id)
+                .await?;
+        }
+
         let mut assignment = dto;
 
         // Handle attachment upload
@@ -268,6 +296,40 @@ impl AssignmentService {
             }
         }
 
+        if let Some(Some(rubric_id)) = update.rubric_id {
+            if Some(rubric_id) != existing.rubric_id {
+                let teacher_id = update.teacher_id.unwrap_or(existing.teacher_id);
+                self.validate_rubric(rubric_id, teacher_id).await?;
+            }
+        }
+
+        if let Some(settings) = &update.group_settings {
+            // Switching between group and individual work would orphan
+            // submissions already handed in
+            let was_group = existing.group_settings.is_some();
+            if was_group != settings.is_some() {
+                let submitted = self
+                    .submission_collection
+                    .find_one(doc! { "assignment_id": existing.id, "is_deleted": false })
+                    .await?;
+                if submitted.is_some() {
+                    return Err(AppError {
+                        message: "Group work cannot be switched on or off after submissions"
+                            .into(),
+                    });
+                }
+            }
+            if let Some(settings) = settings {
+                Self::validate_group_settings(settings)?;
+            }
+        }
+
+        if let Some(Some(category_id)) = update.assessment_category_id {
+            let subject_id = update.subject_id.unwrap_or(existing.subject_id);
+            self.validate_assessment_category(category_id, subject_id)
+                .await?;
+        }
+
         let mut update_data = update.clone();
 
         // Handle attachment update
@@ -296,6 +358,58 @@ impl AssignmentService {
         .await
     }
 
+    /// A rubric can be attached if it is shared school-wide or belongs to
+    /// the assignment's teacher
+    async fn validate_rubric(
+        &self,
+        rubric_id: ObjectId,
+        teacher_id: Option<ObjectId>,
+    ) -> Result<Rubric, AppError> {
+        let rubric = RubricService::new(&self.db)
+            .find_one(&IdType::ObjectId(rubric_id))
+            .await?;
+        if rubric.teacher_id.is_some() && rubric.teacher_id != teacher_id {
+            return Err(AppError {
+                message: "Rubric belongs to another teacher".into(),
+            });
+        }
+        Ok(rubric)
+    }
+
+    fn validate_group_settings(settings: &GroupSettings) -> Result<(), AppError> {
+        if settings.max_group_size < 2 {
+            return Err(AppError {
+                message: "Groups need room for at least 2 members".into(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Grades can only feed a category of the assignment's own class subject
+    async fn validate_assessment_category(
+        &self,
+        category_id: ObjectId,
+        subject_id: Option<ObjectId>,
+    ) -> Result<(), AppError> {
+        let category = self
+            .db
+            .collection::<AssessmentCategory>("assessment_categories")
+            .find_one(doc! { "_id": category_id, "is_deleted": { "$ne": true } })
+            .await?
+            .ok_or(AppError {
+                message: "Assessment category not found".into(),
+            })?;
+        if let (Some(category_subject), Some(subject_id)) = (category.class_subject_id, subject_id)
+        {
+            if category_subject != subject_id {
+                return Err(AppError {
+                    message: "Assessment category belongs to another class subject".into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub async fn delete_assignment(&self, id: &IdType) -> Result<Assignment, AppError> {
         let assignment = self.find_one_assignment(Some(id), None).await?;
 
@@ -338,21 +452,43 @@ impl AssignmentService {
     // SUBMISSION CRUD
     // =========================
 
-    pub async fn validate_submission_deadline(
+    /// The assignment's submission rules, falling back to its class's
+    /// `ClassworkRules`
+    pub async fn submission_policy(
         &self,
         assignment: &Assignment,
-    ) -> Result<bool, AppError> {
-        let now = Utc::now();
-        let is_late = now > assignment.due_date;
-
-        if is_late && !assignment.allow_late_submission {
-            return Err(AppError {
-                message: "Submission deadline has passed and late submissions are not allowed"
-                    .into(),
-            });
+    ) -> Result<SubmissionPolicy, AppError> {
+        let mut rules = None;
+        if let Some(class_id) = assignment.class_id {
+            let class = self
+                .db
+                .collection::<Document>("classes")
+                .find_one(doc! { "_id": class_id })
+                .await?;
+            rules = class
+                .and_then(|c| {
+                    c.get_document("settings")
+                        .and_then(|s| s.get_document("students"))
+                        .and_then(|s| s.get_document("classwork_rules"))
+                        .ok()
+                        .cloned()
+                })
+                .and_then(|r| bson::from_document::<ClassworkRules>(r).ok());
         }
 
-        Ok(is_late)
+        Ok(SubmissionPolicy::resolve(assignment, rules.as_ref()))
+    }
+
+    /// Days late a submission made now would be, or an error once the
+    /// policy no longer accepts it
+    pub async fn validate_submission_deadline(
+        &self,
+        assignment: &Assignment,
+    ) -> Result<i64, AppError> {
+        self.submission_policy(assignment)
+            .await?
+            .check_deadline(Utc::now())
+            .map_err(|message| AppError { message })
     }
 
     pub async fn create_submission(
@@ -381,34 +517,70 @@ impl AssignmentService {
         }
 
         // Validate deadline
-        let is_late = self.validate_submission_deadline(&assignment).await?;
+        let policy = self.submission_policy(&assignment).await?;
+        let late_days = policy
+            .check_deadline(Utc::now())
+            .map_err(|message| AppError { message })?;
+
+        let mut dto = dto;
+
+        // Group work is handed in once for the whole group
+        if assignment.group_settings.is_some() {
+            let (Some(assignment_id), Some(student_id)) = (dto.assignment_id, dto.student_id)
+            else {
+                return Err(AppError {
+                    message: "Student ID is required".into(),
+                });
+            };
+            let group = AssignmentGroupService::new(&self.db)
+                .find_for_student(assignment_id, student_id)
+                .await?
+                .ok_or(AppError {
+                    message: "Join a group before submitting this assignment".into(),
+                })?;
+            dto.group_id = group.id;
+        } else {
+            dto.group_id = None;
+        }
+        dto.contributions = Vec::new();
+        dto.member_grades = Vec::new();
 
         // Check for existing submission
+        let mut existing = None;
         if let (Some(assignment_id), Some(student_id)) = (dto.assignment_id, dto.student_id) {
-            let existing = self
+            let filter = match dto.group_id {
+                Some(group_id) => doc! {
+                    "assignment_id": assignment_id,
+                    "group_id": group_id,
+                    "is_deleted": false
+                },
+                None => doc! {
+                    "assignment_id": assignment_id,
+                    "student_id": student_id,
+                    "is_deleted": false
+                },
+            };
+            existing = self
                 .submission_collection
-                .find_one(
-                    doc! {
-                        "assignment_id": assignment_id,
-                        "student_id": student_id,
-                        "is_deleted": false
-                    }
-                )
+                .find_one(filter)
                 .await
                 .map_err(|e| AppError {
                     message: format!("Failed to check existing submission: {}", e),
                 })?;
 
-            if existing.is_some() {
+            if existing.is_some() && !policy.allow_resubmission {
                 return Err(AppError {
-                    message: "You have already submitted this assignment. Use update instead."
-                        .into(),
+                    message: if dto.group_id.is_some() {
+                        "Your group has already submitted this assignment and resubmission is not allowed."
+                    } else {
+                        "You have already submitted this assignment and resubmission is not allowed."
+                    }
+                    .into(),
                 });
             }
         }
 
         let mut submission = dto;
-        submission.is_late = is_late;
 
         // Handle file upload
         if let Some(file_data) = submission.file_url.clone() {
@@ -419,12 +591,114 @@ impl AssignmentService {
             submission.file_url = Some(cloud_res.secure_url);
         }
 
+        if let Some(existing) = existing {
+            return self.resubmit(existing, submission, late_days).await;
+        }
+
+        submission.is_late = late_days > 0;
+        submission.late_days = (late_days > 0).then_some(late_days);
+        submission.version = 1;
+        submission.versions = Vec::new();
+
+        // Grading belongs to the teacher, quiz and AI flows, never the student
+        submission.status = SubmissionStatus::Submitted;
+        submission.late_penalty = None;
+        submission.score = None;
+        submission.raw_score = None;
+        submission.rubric_scores = None;
+        submission.feedback = None;
+        submission.feedback_file_url = None;
+        submission.feedback_file_id = None;
+        submission.graded_at = None;
+        submission.graded_by = None;
+        submission.auto_grade_score = None;
+        submission.score_sync_error = None;
+        submission.peer_review_score = None;
+        submission.ai_feedback = None;
+        submission.ai_suggested_score = None;
+        submission.ai_feedback_status = None;
+        submission.ai_feedback_generated_at = None;
+
         let repo =
             BaseRepository::new(self.submission_collection.clone().clone_with_type::<Document>());
         repo.create::<Submission>(extract_valid_fields(submission.to_document()?), None)
             .await
     }
 
+    /// Replace the work on an existing submission, keeping the previous
+    /// version and its grade in `versions`. The new version needs grading again.
+    async fn resubmit(
+        &self,
+        existing: Submission,
+        new: Submission,
+        late_days: i64,
+    ) -> Result<Submission, AppError> {
+        let id = existing.id.ok_or(AppError {
+            message: "Submission has no id".into(),
+        })?;
+
+        let previous = SubmissionVersion {
+            version: existing.version,
+            file_url: existing.file_url,
+            file_id: existing.file_id,
+            comment: existing.comment,
+            text_content: existing.text_content,
+            is_late: existing.is_late,
+            late_days: existing.late_days,
+            submitted_at: existing.submitted_at,
+            score: existing.score,
+            feedback: existing.feedback,
+            graded_at: existing.graded_at,
+        };
+        let previous = bson::to_bson(&previous).map_err(|e| AppError {
+            message: format!("Failed to serialize submission version: {}", e),
+        })?;
+        let now = bson::to_bson(&Utc::now()).unwrap();
+
+        self.submission_collection
+            .update_one(
+                doc! { "_id": id },
+                doc! {
+                    "$set": {
+                        // On group work the member who resubmits becomes the submitter
+                        "student_id": new.student_id.or(existing.student_id),
+                        "file_url": new.file_url,
+                        "file_id": new.file_id,
+                        "comment": new.comment,
+                        "text_content": new.text_content,
+                        "is_late": late_days > 0,
+                        "late_days": (late_days > 0).then_some(late_days),
+                        "status": bson::to_bson(&SubmissionStatus::Submitted).unwrap(),
+                        "version": existing.version + 1,
+                        "submitted_at": now.clone(),
+                        "updated_at": now,
+                    },
+                    "$unset": {
+                        "score": "",
+                        "raw_score": "",
+                        "late_penalty": "",
+                        "rubric_scores": "",
+                        "member_grades": "",
+                        "feedback": "",
+                        "feedback_file_url": "",
+                        "feedback_file_id": "",
+                        "graded_at": "",
+                        "graded_by": "",
+                        "auto_grade_score": "",
+                        "ai_feedback": "",
+                        "ai_suggested_score": "",
+                        "ai_feedback_status": "",
+                        "ai_feedback_generated_at": "",
+                    },
+                    "$push": { "versions": previous },
+                },
+            )
+            .await?;
+
+        self.find_one_submission(Some(&IdType::ObjectId(id)), None)
+            .await
+    }
+
     pub async fn find_one_submission(
         &self,
         id: Option<&IdType>,
@@ -584,22 +858,68 @@ impl AssignmentService {
         .await
     }
 
+    /// Grade a submission. With a rubric the criterion scores make up the
+    /// score; with `accept_ai_feedback` the AI draft is published, filling in
+    /// the score and feedback the teacher left out.
     pub async fn grade_submission(
         &self,
         submission_id: &IdType,
-        score: f64,
-        feedback: Option<String>,
-        feedback_file: Option<String>,
-        graded_by: mongodb::bson::oid::ObjectId,
+        grade: GradeSubmission,
+        graded_by: ObjectId,
     ) -> Result<Submission, AppError> {
         let submission = self.find_one_submission(Some(submission_id), None).await?;
+        let assignment = match submission.assignment_id {
+            Some(assignment_id) => Some(
+                self.find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+                    .await?,
+            ),
+            None => None,
+        };
 
-        // Validate score against assignment max_score
-        if let Some(assignment_id) = submission.assignment_id {
-            let assignment = self
-                .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+        let GradeSubmission {
+            mut score,
+            mut feedback,
+            feedback_file,
+            accept_ai_feedback,
+            criterion_scores,
+            member_scores,
+        } = grade;
+
+        // Rubric total, scaled to the assignment's max score
+        let mut rubric_scores = None;
+        if let Some(input) = criterion_scores {
+            let assignment = assignment.as_ref().ok_or(AppError {
+                message: "Submission has no assignment".into(),
+            })?;
+            let rubric_id = assignment.rubric_id.ok_or(AppError {
+                message: "Assignment has no rubric".into(),
+            })?;
+            let rubric = RubricService::new(&self.db)
+                .find_one(&IdType::ObjectId(rubric_id))
                 .await?;
+            let scores = rubric.grade(&input).map_err(|message| AppError { message })?;
+
+            score = Some(rubric.scaled_total(&scores, assignment.max_score));
+            rubric_scores = Some(scores);
+        }
 
+        if accept_ai_feedback {
+            if submission.ai_feedback_status.is_none() {
+                return Err(AppError {
+                    message: "There is no AI feedback to accept on this submission".into(),
+                });
+            }
+            score = score.or(submission.ai_suggested_score);
+            feedback = feedback.or(submission.ai_feedback.clone());
+        }
+        let score = score.ok_or(AppError {
+            message: "Score is required".into(),
+        })?;
+
+        // Validate score against assignment max_score
+        let mut late_penalty = None;
+        let mut final_score = score;
+        if let Some(assignment) = &assignment {
             if score > assignment.max_score {
                 return Err(AppError {
                     message: format!(
@@ -608,10 +928,46 @@ impl AssignmentService {
                     ),
                 });
             }
+
+            // Late penalty from the submission policy, on top of the given score
+            if submission.is_late {
+                let policy = self.submission_policy(assignment).await?;
+                let late_days = submission
+                    .late_days
+                    .unwrap_or_else(|| policy.late_days(submission.submitted_at));
+                let percent = policy.penalty_percent(late_days);
+                if percent > 0.0 {
+                    final_score = round2(score * (1.0 - percent / 100.0));
+                    late_penalty = Some(percent);
+                }
+            }
+        }
+
+        // Group work: every member gets the group score unless graded apart
+        let mut group_members = None;
+        if let Some(group_id) = submission.group_id {
+            let group = AssignmentGroupService::new(&self.db)
+                .find_one(&IdType::ObjectId(group_id))
+                .await?;
+            let max_score = assignment.as_ref().map_or(f64::MAX, |a| a.max_score);
+            group_members = Some(Self::member_grades(
+                &group.member_ids,
+                score,
+                late_penalty.unwrap_or(0.0),
+                member_scores.unwrap_or_default(),
+                max_score,
+            )?);
+        } else if member_scores.is_some() {
+            return Err(AppError {
+                message: "Member scores only apply to group submissions".into(),
+            });
         }
 
         let mut update = SubmissionPartial {
-            score: Some(Some(score)),
+            score: Some(Some(final_score)),
+            raw_score: Some(Some(score)),
+            late_penalty: Some(late_penalty),
+            rubric_scores: Some(rubric_scores),
             feedback: Some(feedback),
             graded_by: Some(Some(graded_by)),
             graded_at: Some(Some(Utc::now())),
@@ -623,9 +979,247 @@ impl AssignmentService {
             update.feedback_file_url = Some(Some(file));
         }
 
+        if accept_ai_feedback {
+            update.ai_feedback_status = Some(Some(AiFeedbackStatus::Accepted));
+        }
+
+        let students: Vec<ObjectId> = match &group_members {
+            Some(grades) => grades.iter().map(|g| g.student_id).collect(),
+            None => submission.student_id.into_iter().collect(),
+        };
+        if group_members.is_some() {
+            update.member_grades = group_members;
+        }
+
+        let mut graded = self.update_submission(submission_id, &update).await?;
+
+        if let (Some(assignment), Some(id)) = (assignment, graded.id) {
+            graded.score_sync_error = self
+                .record_score_sync(&assignment, id, &students, graded_by)
+                .await?;
+        }
+        Ok(graded)
+    }
+
+    /// Grades for every group member, with the late penalty applied to
+    /// each. Adjusted members must belong to the group.
+    fn member_grades(
+        member_ids: &[ObjectId],
+        group_score: f64,
+        penalty_percent: f64,
+        adjustments: Vec<MemberScoreInput>,
+        max_score: f64,
+    ) -> Result<Vec<MemberGrade>, AppError> {
+        let mut adjusted = Vec::with_capacity(adjustments.len());
+        for input in adjustments {
+            let student_id = IdType::from_string(input.student_id).to_object_id()?;
+            if !member_ids.contains(&student_id) {
+                return Err(AppError {
+                    message: format!("Student {} is not in this group", student_id.to_hex()),
+                });
+            }
+            if !(0.0..=max_score).contains(&input.score) {
+                return Err(AppError {
+                    message: format!("Score cannot exceed max score of {}", max_score),
+                });
+            }
+            adjusted.push((student_id, input.score, input.feedback));
+        }
+
+        Ok(member_ids
+            .iter()
+            .map(|student_id| {
+                let adjustment = adjusted.iter().find(|(id, _, _)| id == student_id);
+                let raw_score = adjustment.map_or(group_score, |(_, score, _)| *score);
+                MemberGrade {
+                    student_id: *student_id,
+                    raw_score,
+                    score: round2(raw_score * (1.0 - penalty_percent / 100.0)),
+                    adjusted: adjustment.is_some(),
+                    feedback: adjustment.and_then(|(_, _, feedback)| feedback.clone()),
+                }
+            })
+            .collect())
+    }
+
+    /// A group member's statement of what they did on the shared submission
+    pub async fn set_contribution(
+        &self,
+        submission_id: &IdType,
+        student_id: ObjectId,
+        statement: String,
+    ) -> Result<Submission, AppError> {
+        let submission = self.find_one_submission(Some(submission_id), None).await?;
+        let group_id = submission.group_id.ok_or(AppError {
+            message: "Contribution statements are only for group submissions".into(),
+        })?;
+        let group = AssignmentGroupService::new(&self.db)
+            .find_one(&IdType::ObjectId(group_id))
+            .await?;
+        if !group.member_ids.contains(&student_id) {
+            return Err(AppError {
+                message: "You are not in this group".into(),
+            });
+        }
+        if statement.trim().is_empty() {
+            return Err(AppError {
+                message: "Statement cannot be empty".into(),
+            });
+        }
+
+        let mut contributions = submission.contributions;
+        contributions.retain(|c| c.student_id != student_id);
+        contributions.push(ContributionStatement {
+            student_id,
+            statement: statement.trim().to_string(),
+            updated_at: Utc::now(),
+        });
+
+        let update = SubmissionPartial {
+            contributions: Some(contributions),
+            ..Default::default()
+        };
         self.update_submission(submission_id, &update).await
     }
 
+    // =========================
+    // GRADEBOOK
+    // =========================
+
+    /// Carry grades into `Score` after a submission is graded. The grade
+    /// stands when the gradebook refuses it, e.g. once results are
+    /// moderated; the reason is kept as the submission's `score_sync_error`.
+    pub async fn record_score_sync(
+        &self,
+        assignment: &Assignment,
+        submission_id: ObjectId,
+        student_ids: &[ObjectId],
+        actor: ObjectId,
+    ) -> Result<Option<String>, AppError> {
+        let error = self
+            .sync_scores(assignment, student_ids, actor)
+            .await
+            .err()
+            .map(|e| e.message);
+        let update = match &error {
+            None => doc! { "$unset": { "score_sync_error": "" } },
+            Some(message) => doc! { "$set": { "score_sync_error": message } },
+        };
+        self.submission_collection
+            .update_one(doc! { "_id": submission_id }, update)
+            .await?;
+        Ok(error)
+    }
+
+    /// Carry assignment and quiz grades into `Score`. All graded assignments
+    /// of the subject feeding the same exam and category make up one score
+    /// per student; group members are scored by their own member grade.
+    pub async fn sync_scores(
+        &self,
+        assignment: &Assignment,
+        student_ids: &[ObjectId],
+        actor: ObjectId,
+    ) -> Result<(), AppError> {
+        let (Some(category_id), Some(class_subject_id)) =
+            (assignment.assessment_category_id, assignment.subject_id)
+        else {
+            return Ok(());
+        };
+
+        let assignments: Vec<Assignment> = self
+            .collection
+            .find(doc! {
+                "subject_id": class_subject_id,
+                "exam_id": assignment.exam_id,
+                "assessment_category_id": category_id,
+                "is_deleted": false,
+                "status": { "$ne": bson::to_bson(&AssignmentStatus::Draft).unwrap() }
+            })
+            .await?
+            .try_collect()
+            .await?;
+        let assignment_ids: Vec<ObjectId> = assignments.iter().filter_map(|a| a.id).collect();
+        let max_score: f64 = assignments.iter().map(|a| a.max_score).sum();
+
+        let education_year_id = self
+            .db
+            .collection::<AssessmentCategory>("assessment_categories")
+            .find_one(doc! { "_id": category_id })
+            .await?
+            .and_then(|c| c.education_year_id);
+
+        let score_service = ScoreService::new(&self.db);
+        for student_id in student_ids {
+            let submissions: Vec<Submission> = self
+                .submission_collection
+                .find(doc! {
+                    "assignment_id": { "$in": &assignment_ids },
+                    "is_deleted": false,
+                    "status": bson::to_bson(&SubmissionStatus::Graded).unwrap(),
+                    "$or": [
+                        { "student_id": student_id },
+                        { "member_grades.student_id": student_id }
+                    ]
+                })
+                .await?
+                .try_collect()
+                .await?;
+            let score: f64 = submissions.iter().filter_map(|s| s.score_for(student_id)).sum();
+            let score = round2(score);
+
+            let existing = score_service
+                .collection
+                .find_one(doc! {
+                    "student_id": student_id,
+                    "class_subject_id": class_subject_id,
+                    "exam_id": assignment.exam_id,
+                    "assessment_category_id": category_id,
+                    "is_deleted": false
+                })
+                .await?;
+
+            match existing.and_then(|s| s.id) {
+                Some(id) => {
+                    let update = ScorePartial {
+                        score: Some(score),
+                        max_score: Some(max_score),
+                        ..Default::default()
+                    };
+                    score_service
+                        .update(
+                            &IdType::ObjectId(id),
+                            &update,
+                            &actor,
+                            Some("Assignment grading".into()),
+                        )
+                        .await?;
+                }
+                None => {
+                    score_service
+                        .create(Score {
+                            id: None,
+                            school_id: assignment.school_id,
+                            student_id: Some(*student_id),
+                            class_subject_id: Some(class_subject_id),
+                            exam_id: assignment.exam_id,
+                            assessment_category_id: Some(category_id),
+                            education_year_id,
+                            score,
+                            max_score,
+                            percentage: 0.0,
+                            remarks: Some("Assignment grading".into()),
+                            entered_by: Some(actor),
+                            created_at: None,
+                            updated_at: None,
+                            is_deleted: false,
+                        })
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub async fn delete_submission(&self, id: &IdType) -> Result<Submission, AppError> {
         let submission = self.find_one_submission(Some(id), None).await?;
 
@@ -642,15 +1236,37 @@ impl AssignmentService {
     }
 
     // =========================
-    // PLACEHOLDER FOR FUTURE AI AUTO-GRADING
+    // AUTO-GRADING
     // =========================
-    #[allow(dead_code)]
-    pub async fn trigger_auto_grading(&self, _submission_id: &IdType) -> Result<(), AppError> {
-        // Placeholder for future AI integration
-        // This would:
-        // 1. Fetch submission and assignment
-        // 2. Send to AI service for grading
-        // 3. Update submission with auto_grade_score and ai_feedback
-        Ok(())
+
+    /// Grade the student's quiz attempts for this submission again and
+    /// refresh `auto_grade_score`
+    pub async fn trigger_auto_grading(
+        &self,
+        submission_id: &IdType,
+        actor: mongodb::bson::oid::ObjectId,
+    ) -> Result<Submission, AppError> {
+        let submission = self.find_one_submission(Some(submission_id), None).await?;
+        let (Some(assignment_id), Some(student_id)) =
+            (submission.assignment_id, submission.student_id)
+        else {
+            return Err(AppError {
+                message: "Submission is missing its assignment or student".into(),
+            });
+        };
+
+        let assignment = self
+            .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+            .await?;
+        if !assignment.auto_grade_enabled {
+            return Err(AppError {
+                message: "Auto-grading is not enabled for this assignment".into(),
+            });
+        }
+
+        QuizService::new(&self.db)
+            .regrade(assignment_id, Some(student_id), actor)
+            .await?;
+        self.find_one_submission(Some(submission_id), None).await
     }
 }
@@ -0,0 +1,673 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        chat::{ChatChannel, ChatChannelMember, ChatChannelType, ChatMessage},
+        class::{Class, ClassSettings, ClassStudentSettings, StudentPermissions},
+        class_subject::ClassSubject,
+        common_details::{Paginated, UserRole},
+        student::Student,
+        teacher::Teacher,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    schema::common_schema::ActorRef,
+    services::{class_service::ClassService, class_subject_service::ClassSubjectService},
+    utils::mongo_utils::to_stored_document,
+};
+
+const MAX_CHAT_MESSAGE_LEN: usize = 4000;
+
+pub struct ChatService {
+    pub collection: Collection<ChatChannel>,
+    pub message_collection: Collection<ChatMessage>,
+    pub db: Database,
+}
+
+impl ChatService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<ChatChannel>("chat_channels"),
+            message_collection: db.collection::<ChatMessage>("chat_messages"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let channel_indexes = vec![
+            IndexDef::compound(
+                vec![("channel_type", 1), ("class_id", 1), ("class_subject_id", 1)],
+                true,
+            ),
+            IndexDef::single("school_id", false),
+            IndexDef::single("members.id", false),
+        ];
+
+        let message_indexes = vec![
+            IndexDef::compound(vec![("channel_id", 1), ("created_at", -1)], false),
+            IndexDef::single("sender.id", false),
+        ];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&channel_indexes)
+            .await?;
+        BaseRepository::new(self.message_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&message_indexes)
+            .await?;
+
+        Ok(())
+    }
+
+    // =========================
+    // MEMBERSHIP SYNC
+    // =========================
+
+    /// Rebuild the class channel and every subject channel of the class
+    pub async fn sync_class_channels(&self, class_id: &ObjectId) -> Result<Vec<ChatChannel>, AppError> {
+        self.ensure_indexes().await?;
+
+        let class = ClassService::new(&self.db)
+            .find_one(Some(&IdType::ObjectId(*class_id)), None)
+            .await?;
+
+        let subjects = self.class_subjects(class_id).await?;
+        let students = self.student_members(class_id).await?;
+
+        // Class channel: class teacher, teachers attached to the class and subject teachers
+        let mut teacher_ids: Vec<ObjectId> = class.class_teacher_id.into_iter().collect();
+        teacher_ids.extend(subjects.iter().filter_map(|s| s.teacher_id));
+        let mut class_teachers = self.teacher_members(&teacher_ids).await?;
+        for teacher in self.teachers_of_class(class_id).await? {
+            if !class_teachers.iter().any(|m| m.id == teacher.id) {
+                class_teachers.push(teacher);
+            }
+        }
+
+        let mut members = class_teachers;
+        members.extend(students.iter().cloned());
+
+        let mut channels = vec![
+            self.upsert_channel(&class, None, class.name.clone(), members)
+                .await?,
+        ];
+
+        for subject in subjects {
+            let subject_teacher_ids: Vec<ObjectId> = subject
+                .teacher_id
+                .into_iter()
+                .chain(class.class_teacher_id)
+                .collect();
+
+            let mut members = self.teacher_members(&subject_teacher_ids).await?;
+            members.extend(students.iter().cloned());
+
+            let name = format!("{} - {}", class.name, subject.name);
+            channels.push(self.upsert_channel(&class, subject.id, name, members).await?);
+        }
+
+        Ok(channels)
+    }
+
+    /// Bring the existing channels of these classes back in line after
+    /// enrolment or a teaching assignment changed. Runs in the background;
+    /// classes whose chat was never opened are left alone.
+    pub fn refresh_classes(db: &Database, class_ids: impl IntoIterator<Item = ObjectId>) {
+        let class_ids: HashSet<ObjectId> = class_ids.into_iter().collect();
+        if class_ids.is_empty() {
+            return;
+        }
+
+        let service = ChatService::new(db);
+        actix_rt::spawn(async move {
+            service.refresh(class_ids).await;
+        });
+    }
+
+    /// Like `refresh_classes`, also covering every class whose channels
+    /// already list the member, e.g. a teacher who was deactivated
+    pub fn refresh_member(
+        db: &Database,
+        member_id: ObjectId,
+        class_ids: impl IntoIterator<Item = ObjectId>,
+    ) {
+        let mut class_ids: HashSet<ObjectId> = class_ids.into_iter().collect();
+
+        let service = ChatService::new(db);
+        actix_rt::spawn(async move {
+            match service.find_channels(doc! { "members.id": member_id }).await {
+                Ok(channels) => class_ids.extend(channels.into_iter().map(|c| c.class_id)),
+                Err(e) => eprintln!("⚠️ Failed to look up chat channels of {}: {}", member_id, e.message),
+            }
+            service.refresh(class_ids).await;
+        });
+    }
+
+    async fn refresh(&self, class_ids: HashSet<ObjectId>) {
+        for class_id in class_ids {
+            match self
+                .collection
+                .count_documents(doc! { "class_id": class_id })
+                .await
+            {
+                Ok(0) => {}
+                Ok(_) => {
+                    if let Err(e) = self.sync_class_channels(&class_id).await {
+                        eprintln!("⚠️ Failed to sync chat channels for class {}: {}", class_id, e.message);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("⚠️ Failed to look up chat channels for class {}: {}", class_id, e);
+                }
+            }
+        }
+    }
+
+    /// Channels of the class, created on first use
+    pub async fn class_channels(&self, class_id: &ObjectId) -> Result<Vec<ChatChannel>, AppError> {
+        let channels = self.find_channels(doc! { "class_id": class_id }).await?;
+        if channels.is_empty() {
+            return self.sync_class_channels(class_id).await;
+        }
+        Ok(channels)
+    }
+
+    /// Channels of the member, creating those of their classes that do not
+    /// exist yet. Existing channels are kept current by `refresh_classes`.
+    pub async fn member_channels(
+        &self,
+        member_id: &ObjectId,
+        role: &UserRole,
+    ) -> Result<Vec<ChatChannel>, AppError> {
+        let mut class_ids: HashSet<ObjectId> = HashSet::new();
+
+        match role {
+            UserRole::STUDENT => {
+                if let Some(student) = self
+                    .db
+                    .collection::<Student>("students")
+                    .find_one(doc! { "_id": member_id })
+                    .await
+                    .map_err(|e| AppError {
+                        message: format!("Failed to fetch student: {}", e),
+                    })?
+                {
+                    class_ids.extend(student.class_id);
+                    class_ids.extend(student.subclass_id);
+                }
+            }
+            UserRole::TEACHER => {
+                if let Some(teacher) = self
+                    .db
+                    .collection::<Teacher>("teachers")
+                    .find_one(doc! { "_id": member_id })
+                    .await
+                    .map_err(|e| AppError {
+                        message: format!("Failed to fetch teacher: {}", e),
+                    })?
+                {
+                    class_ids.extend(teacher.class_ids.unwrap_or_default());
+                }
+
+                let mut cursor = self
+                    .db
+                    .collection::<Document>("class_subjects")
+                    .find(doc! { "teacher_id": member_id })
+                    .await
+                    .map_err(|e| AppError {
+                        message: format!("Failed to fetch subjects: {}", e),
+                    })?;
+                while cursor.advance().await.map_err(|e| AppError {
+                    message: format!("Failed to iterate subjects: {}", e),
+                })? {
+                    let subject = cursor.deserialize_current().map_err(|e| AppError {
+                        message: format!("Failed to deserialize subject: {}", e),
+                    })?;
+                    if let Ok(class_id) = subject.get_object_id("class_id") {
+                        class_ids.insert(class_id);
+                    }
+                }
+
+                let mut cursor = self
+                    .db
+                    .collection::<Document>("classes")
+                    .find(doc! { "class_teacher_id": member_id })
+                    .await
+                    .map_err(|e| AppError {
+                        message: format!("Failed to fetch classes: {}", e),
+                    })?;
+                while cursor.advance().await.map_err(|e| AppError {
+                    message: format!("Failed to iterate classes: {}", e),
+                })? {
+                    let class = cursor.deserialize_current().map_err(|e| AppError {
+                        message: format!("Failed to deserialize class: {}", e),
+                    })?;
+                    if let Ok(class_id) = class.get_object_id("_id") {
+                        class_ids.insert(class_id);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for class_id in &class_ids {
+            // A dangling class reference should not hide the remaining channels
+            if let Err(e) = self.class_channels(class_id).await {
+                eprintln!("⚠️ Failed to sync chat channels for class {}: {}", class_id, e.message);
+            }
+        }
+
+        self.find_channels(doc! { "members.id": member_id }).await
+    }
+
+    async fn upsert_channel(
+        &self,
+        class: &Class,
+        class_subject_id: Option<ObjectId>,
+        name: String,
+        members: Vec<ChatChannelMember>,
+    ) -> Result<ChatChannel, AppError> {
+        let class_id = class.id.ok_or(AppError {
+            message: "Class has no id".into(),
+        })?;
+
+        let channel_type = if class_subject_id.is_some() {
+            ChatChannelType::Subject
+        } else {
+            ChatChannelType::Class
+        };
+
+        let filter = doc! {
+            "channel_type": bson::to_bson(&channel_type).unwrap(),
+            "class_id": class_id,
+            "class_subject_id": class_subject_id,
+        };
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let mut set = doc! {
+            "name": name,
+            "members": members
+                .iter()
+                .map(to_stored_document)
+                .collect::<Result<Vec<_>, _>>()?,
+            "last_synced_at": now.clone(),
+            "updated_at": now.clone(),
+        };
+        if let Some(school_id) = class.school_id {
+            set.insert("school_id", school_id);
+        }
+
+        self.collection
+            .find_one_and_update(
+                filter,
+                doc! {
+                    "$set": set,
+                    "$setOnInsert": { "announcement_only": false, "created_at": now },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to sync chat channel: {}", e),
+            })?
+            .ok_or(AppError {
+                message: "Failed to sync chat channel".into(),
+            })
+    }
+
+    async fn class_subjects(&self, class_id: &ObjectId) -> Result<Vec<ClassSubject>, AppError> {
+        let mut cursor = ClassSubjectService::new(&self.db)
+            .collection
+            .find(doc! { "class_id": class_id, "disable": { "$ne": true } })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch class subjects: {}", e),
+            })?;
+
+        let mut subjects = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate class subjects: {}", e),
+        })? {
+            subjects.push(cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize class subject: {}", e),
+            })?);
+        }
+
+        Ok(subjects)
+    }
+
+    async fn student_members(&self, class_id: &ObjectId) -> Result<Vec<ChatChannelMember>, AppError> {
+        let filter = doc! {
+            "$or": [{ "class_id": class_id }, { "subclass_id": class_id }],
+            "is_active": true,
+            "deleted_at": { "$in": [null, ""] },
+        };
+
+        let mut cursor = self
+            .db
+            .collection::<Student>("students")
+            .find(filter)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch students: {}", e),
+            })?;
+
+        let mut members = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate students: {}", e),
+        })? {
+            let student = cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize student: {}", e),
+            })?;
+            if let Some(id) = student.id {
+                members.push(ChatChannelMember {
+                    id,
+                    user_id: student.user_id,
+                    name: student.name,
+                    role: UserRole::STUDENT,
+                });
+            }
+        }
+
+        Ok(members)
+    }
+
+    async fn teacher_members(&self, ids: &[ObjectId]) -> Result<Vec<ChatChannelMember>, AppError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.fetch_teachers(doc! { "_id": { "$in": ids }, "is_active": true })
+            .await
+    }
+
+    async fn teachers_of_class(&self, class_id: &ObjectId) -> Result<Vec<ChatChannelMember>, AppError> {
+        self.fetch_teachers(doc! { "class_ids": class_id, "is_active": true })
+            .await
+    }
+
+    async fn fetch_teachers(&self, filter: Document) -> Result<Vec<ChatChannelMember>, AppError> {
+        let mut cursor = self
+            .db
+            .collection::<Teacher>("teachers")
+            .find(filter)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch teachers: {}", e),
+            })?;
+
+        let mut members = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate teachers: {}", e),
+        })? {
+            let teacher = cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize teacher: {}", e),
+            })?;
+            if let Some(id) = teacher.id {
+                members.push(ChatChannelMember {
+                    id,
+                    user_id: teacher.user_id,
+                    name: teacher.name,
+                    role: UserRole::TEACHER,
+                });
+            }
+        }
+
+        Ok(members)
+    }
+
+    // =========================
+    // CHANNELS
+    // =========================
+
+    pub async fn find_channel(&self, id: &IdType) -> Result<ChatChannel, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<ChatChannel>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Chat channel not found".into(),
+            })
+    }
+
+    pub async fn find_channels(&self, filter: Document) -> Result<Vec<ChatChannel>, AppError> {
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "channel_type": 1, "name": 1 })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch chat channels: {}", e),
+            })?;
+
+        let mut channels = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate chat channels: {}", e),
+        })? {
+            channels.push(cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize chat channel: {}", e),
+            })?);
+        }
+
+        Ok(channels)
+    }
+
+    pub async fn set_announcement_only(
+        &self,
+        id: &IdType,
+        enabled: bool,
+    ) -> Result<ChatChannel, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<ChatChannel>(id, doc! { "announcement_only": enabled })
+            .await
+    }
+
+    /// Toggle `settings.students.permissions.can_chat` on the class
+    pub async fn set_student_chat(&self, class_id: &ObjectId, enabled: bool) -> Result<Class, AppError> {
+        let class_service = ClassService::new(&self.db);
+        let id = IdType::ObjectId(*class_id);
+        let class = class_service.find_one(Some(&id), None).await?;
+
+        let mut settings = class.settings.unwrap_or_else(ClassSettings::default);
+        let students = settings.students.get_or_insert(ClassStudentSettings {
+            auto_enroll_subclasses: None,
+            student_visibility: None,
+            permissions: None,
+            attendance_rules: None,
+            classwork_rules: None,
+        });
+        students
+            .permissions
+            .get_or_insert(StudentPermissions {
+                can_chat: None,
+                can_upload_homework: None,
+                can_comment: None,
+                can_view_all_students: None,
+            })
+            .can_chat = Some(enabled);
+
+        let settings_doc = bson::to_bson(&settings).map_err(|e| AppError {
+            message: format!("Failed to serialize class settings: {}", e),
+        })?;
+
+        let repo = BaseRepository::new(class_service.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<Class>(&id, doc! { "settings": settings_doc })
+            .await
+    }
+
+    /// Students may post unless the channel is announcement-only or the
+    /// class has `can_chat` switched off. Teachers must be channel members.
+    pub async fn ensure_can_post(
+        &self,
+        channel: &ChatChannel,
+        member_id: &ObjectId,
+        role: &UserRole,
+    ) -> Result<(), AppError> {
+        match role {
+            UserRole::ADMIN | UserRole::SCHOOLSTAFF => Ok(()),
+            UserRole::TEACHER if channel.is_member(member_id) => Ok(()),
+            UserRole::STUDENT if channel.is_member(member_id) => {
+                if channel.announcement_only {
+                    return Err(AppError {
+                        message: "This channel is in announcement-only mode".into(),
+                    });
+                }
+
+                let class = ClassService::new(&self.db)
+                    .find_one(Some(&IdType::ObjectId(channel.class_id)), None)
+                    .await?;
+
+                let can_chat = class
+                    .settings
+                    .and_then(|s| s.students)
+                    .and_then(|s| s.permissions)
+                    .and_then(|p| p.can_chat)
+                    .unwrap_or(true);
+
+                if !can_chat {
+                    return Err(AppError {
+                        message: "Student chat is disabled for this class".into(),
+                    });
+                }
+
+                Ok(())
+            }
+            _ => Err(AppError {
+                message: "You are not a member of this channel".into(),
+            }),
+        }
+    }
+
+    // =========================
+    // MESSAGES
+    // =========================
+
+    pub async fn post_message(
+        &self,
+        channel: &ChatChannel,
+        sender: ActorRef,
+        sender_name: Option<String>,
+        content: &str,
+    ) -> Result<ChatMessage, AppError> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(AppError {
+                message: "Message content is required".into(),
+            });
+        }
+        if content.len() > MAX_CHAT_MESSAGE_LEN {
+            return Err(AppError {
+                message: format!("Message exceeds {} characters", MAX_CHAT_MESSAGE_LEN),
+            });
+        }
+
+        let channel_id = channel.id.ok_or(AppError {
+            message: "Chat channel has no id".into(),
+        })?;
+
+        let message = ChatMessage {
+            id: None,
+            channel_id,
+            school_id: channel.school_id,
+            sender,
+            sender_name,
+            content: content.to_string(),
+            deleted_at: None,
+            created_at: Utc::now(),
+        };
+
+        let result = self
+            .message_collection
+            .insert_one(&message)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to send chat message: {}", e),
+            })?;
+
+        Ok(ChatMessage {
+            id: result.inserted_id.as_object_id(),
+            ..message
+        })
+    }
+
+    pub async fn get_messages(
+        &self,
+        channel_id: &ObjectId,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Paginated<ChatMessage>, AppError> {
+        let filter = doc! { "channel_id": channel_id, "deleted_at": null };
+        let limit = limit.unwrap_or(50).max(1);
+        let skip = skip.unwrap_or(0).max(0);
+
+        let total = self
+            .message_collection
+            .count_documents(filter.clone())
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to count chat messages: {}", e),
+            })? as i64;
+
+        // Newest first; the generic paginator sorts by updated_at which messages don't have
+        let mut cursor = self
+            .message_collection
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .skip(skip as u64)
+            .limit(limit)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch chat messages: {}", e),
+            })?;
+
+        let mut data = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate chat messages: {}", e),
+        })? {
+            data.push(cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize chat message: {}", e),
+            })?);
+        }
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages: (total + limit - 1) / limit,
+            current_page: skip / limit + 1,
+        })
+    }
+
+    pub async fn find_message(
+        &self,
+        channel_id: &ObjectId,
+        message_id: &ObjectId,
+    ) -> Result<ChatMessage, AppError> {
+        let repo = BaseRepository::new(self.message_collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<ChatMessage>(
+            doc! { "_id": message_id, "channel_id": channel_id, "deleted_at": null },
+            None,
+        )
+        .await?
+        .ok_or(AppError {
+            message: "Chat message not found".into(),
+        })
+    }
+
+    pub async fn delete_message(&self, message_id: &ObjectId) -> Result<(), AppError> {
+        let repo = BaseRepository::new(self.message_collection.clone().clone_with_type::<Document>());
+        repo.update_one_raw(
+            &IdType::ObjectId(*message_id),
+            doc! { "$set": { "deleted_at": bson::to_bson(&Utc::now()).unwrap() } },
+        )
+        .await
+    }
+}
@@ -0,0 +1,720 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class::Class,
+        class_subject::ClassSubject,
+        common_details::Paginated,
+        competency::{
+            ClassCompetencySummary, CompetencyAssessment, CompetencyAssessmentStatus,
+            CompetencyEvidence, CompetencyResult, CreateCompetencyAssessment,
+            ModuleCompetencyReport, OutcomeCompetency, TradeCompetencyReport,
+            UpdateCompetencyAssessment,
+        },
+        student::Student,
+        trade::Trade,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::cloudinary_service::CloudinaryService,
+    utils::{mongo_utils::to_stored_document, object_id::parse_object_id_value},
+};
+
+/// Outcome or topic an assessment is about. Attempts and reports are keyed
+/// on it, learning outcomes taking precedence over topics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum UnitKey {
+    Outcome(ObjectId),
+    Topic(String),
+}
+
+impl UnitKey {
+    fn of(assessment: &CompetencyAssessment) -> Option<Self> {
+        match (&assessment.learning_outcome_id, &assessment.topic_order) {
+            (Some(id), _) => Some(UnitKey::Outcome(*id)),
+            (None, Some(order)) => Some(UnitKey::Topic(order.clone())),
+            _ => None,
+        }
+    }
+}
+
+pub struct CompetencyService {
+    pub collection: Collection<CompetencyAssessment>,
+    pub db: Database,
+    pub main_db: Database,
+}
+
+impl CompetencyService {
+    pub fn new(db: &Database, main_db: &Database) -> Self {
+        Self {
+            collection: db.collection::<CompetencyAssessment>("competency_assessments"),
+            db: db.clone(),
+            main_db: main_db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("student_id", 1), ("class_subject_id", 1)], false),
+            IndexDef::compound(vec![("class_id", 1), ("status", 1)], false),
+            IndexDef::single("learning_outcome_id", false),
+            IndexDef::single("assessed_by", false),
+        ];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    // =========================
+    // ASSESSMENTS
+    // =========================
+
+    pub async fn find_one(&self, id: &IdType) -> Result<CompetencyAssessment, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.find_one::<CompetencyAssessment>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Competency assessment not found".into(),
+            })
+    }
+
+    pub async fn get_all(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<CompetencyAssessment>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let searchable = [
+            "_id",
+            "student_id",
+            "class_id",
+            "class_subject_id",
+            "learning_outcome_id",
+            "topic_title",
+            "result",
+            "status",
+        ];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<CompetencyAssessment>(None, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Record a judgement. Reassessing the same outcome or topic opens a new
+    /// attempt, earlier attempts stay as history.
+    pub async fn create(
+        &self,
+        school_id: Option<ObjectId>,
+        dto: CreateCompetencyAssessment,
+        assessor_id: ObjectId,
+    ) -> Result<CompetencyAssessment, AppError> {
+        self.ensure_indexes().await?;
+
+        let student_id = parse_object_id_value(&dto.student_id)?;
+        let class_subject_id = parse_object_id_value(&dto.class_subject_id)?;
+        let learning_outcome_id = dto
+            .learning_outcome_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+        let education_year_id = dto
+            .education_year_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+
+        let student = self
+            .db
+            .collection::<Student>("students")
+            .find_one(doc! { "_id": student_id })
+            .await?
+            .ok_or(AppError {
+                message: "Student not found".into(),
+            })?;
+        let module = self.find_module(class_subject_id).await?;
+        if module.class_id.is_some() && module.class_id != student.class_id {
+            return Err(AppError {
+                message: "Student is not in the class of this module".into(),
+            });
+        }
+
+        if let Some(outcome_id) = learning_outcome_id {
+            let outcome_exists = self
+                .db
+                .collection::<Document>("learning_outcomes")
+                .count_documents(doc! { "_id": outcome_id, "subject_id": class_subject_id })
+                .await?;
+            if outcome_exists == 0 {
+                return Err(AppError {
+                    message: "Learning outcome does not belong to this module".into(),
+                });
+            }
+        }
+
+        // Reports count one kind of unit per module, so only that kind is assessed
+        if learning_outcome_id.is_none() {
+            let module_outcomes = self
+                .db
+                .collection::<Document>("learning_outcomes")
+                .count_documents(doc! { "subject_id": class_subject_id })
+                .await?;
+            if module_outcomes > 0 {
+                return Err(AppError {
+                    message: "This module is assessed against its learning outcomes".into(),
+                });
+            }
+        }
+
+        let (topic_order, topic_title) = match (&learning_outcome_id, dto.topic_order) {
+            (Some(_), _) => (None, None),
+            (None, Some(order)) => {
+                let topic = module
+                    .topics
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .find(|t| t.order == order)
+                    .ok_or(AppError {
+                        message: format!("Module has no topic {}", order),
+                    })?;
+                (Some(topic.order.clone()), Some(topic.title.clone()))
+            }
+            (None, None) => {
+                return Err(AppError {
+                    message: "A learning outcome or topic is required".into(),
+                })
+            }
+        };
+
+        let trade_id = match module.class_id {
+            Some(class_id) => self
+                .db
+                .collection::<Class>("classes")
+                .find_one(doc! { "_id": class_id })
+                .await?
+                .and_then(|c| c.trade_id),
+            None => None,
+        };
+
+        let mut unit_filter = doc! {
+            "student_id": student_id,
+            "class_subject_id": class_subject_id,
+        };
+        match (&learning_outcome_id, &topic_order) {
+            (Some(id), _) => unit_filter.insert("learning_outcome_id", id),
+            (None, Some(order)) => unit_filter.insert("topic_order", order),
+            _ => None,
+        };
+
+        // A competent outcome is not reassessed, an unsigned draft is edited instead
+        let mut previous = self
+            .collection
+            .find(unit_filter)
+            .sort(doc! { "attempt": -1 })
+            .limit(1)
+            .await?;
+        let attempt = match previous.try_next().await? {
+            Some(last) if last.status == CompetencyAssessmentStatus::Draft => {
+                return Err(AppError {
+                    message: "An unsigned assessment already exists for this outcome, update it instead"
+                        .into(),
+                })
+            }
+            Some(last) if last.result == CompetencyResult::Competent => {
+                return Err(AppError {
+                    message: "Student is already competent in this outcome".into(),
+                })
+            }
+            Some(last) => last.attempt + 1,
+            None => 1,
+        };
+
+        let now = Utc::now();
+        let assessment = CompetencyAssessment {
+            id: None,
+            school_id: school_id.or(module.school_id),
+            student_id,
+            class_id: module.class_id.or(student.class_id),
+            class_subject_id,
+            trade_id,
+            education_year_id,
+            learning_outcome_id,
+            topic_order,
+            topic_title,
+            attempt,
+            result: dto.result,
+            assessor_comment: dto.assessor_comment,
+            evidence: vec![],
+            assessed_by: assessor_id,
+            assessed_at: now,
+            status: CompetencyAssessmentStatus::Draft,
+            signed_off_by: None,
+            signed_off_at: None,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&assessment)?;
+        repo.create::<CompetencyAssessment>(doc, None).await
+    }
+
+    pub async fn update(
+        &self,
+        id: &IdType,
+        dto: UpdateCompetencyAssessment,
+    ) -> Result<CompetencyAssessment, AppError> {
+        let assessment = self.find_one(id).await?;
+        Self::ensure_draft(&assessment)?;
+
+        let mut set = doc! {
+            "assessed_at": bson::to_bson(&Utc::now()).unwrap(),
+        };
+        if let Some(result) = dto.result {
+            set.insert("result", bson::to_bson(&result).unwrap());
+        }
+        if let Some(comment) = dto.assessor_comment {
+            set.insert("assessor_comment", comment);
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<CompetencyAssessment>(id, set)
+            .await
+    }
+
+    pub async fn delete(&self, id: &IdType) -> Result<CompetencyAssessment, AppError> {
+        let assessment = self.find_one(id).await?;
+        Self::ensure_draft(&assessment)?;
+
+        for evidence in &assessment.evidence {
+            CloudinaryService::delete_file(&evidence.file_public_id)
+                .await
+                .ok();
+        }
+
+        self.collection
+            .delete_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?;
+        Ok(assessment)
+    }
+
+    fn ensure_draft(assessment: &CompetencyAssessment) -> Result<(), AppError> {
+        if assessment.status == CompetencyAssessmentStatus::SignedOff {
+            return Err(AppError {
+                message: "Signed-off assessments cannot be changed, record a new attempt".into(),
+            });
+        }
+        Ok(())
+    }
+
+    // =========================
+    // EVIDENCE & SIGN-OFF
+    // =========================
+
+    pub async fn add_evidence(
+        &self,
+        id: &IdType,
+        file_bytes: Vec<u8>,
+        file_name: &str,
+        description: Option<String>,
+        uploaded_by: ObjectId,
+    ) -> Result<CompetencyAssessment, AppError> {
+        let assessment = self.find_one(id).await?;
+        Self::ensure_draft(&assessment)?;
+
+        let folder = format!(
+            "space-together/{}/competency/{}",
+            assessment
+                .school_id
+                .map(|id| id.to_hex())
+                .unwrap_or_else(|| "shared".into()),
+            assessment.student_id.to_hex()
+        );
+        let upload = CloudinaryService::upload_file(file_bytes, file_name, &folder)
+            .await
+            .map_err(|e| AppError { message: e })?;
+
+        let evidence = CompetencyEvidence {
+            file_url: upload.url,
+            file_public_id: upload.public_id,
+            file_name: file_name.to_string(),
+            description,
+            uploaded_by,
+            uploaded_at: Utc::now(),
+        };
+
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "_id": IdType::to_object_id(id)?,
+                    "status": bson::to_bson(&CompetencyAssessmentStatus::Draft).unwrap()
+                },
+                doc! {
+                    "$push": { "evidence": to_stored_document(&evidence)? },
+                    "$set": { "updated_at": bson::to_bson(&Utc::now()).unwrap() }
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Assessment was signed off while uploading".into(),
+            })
+    }
+
+    /// Lock the judgement. Competent needs at least one piece of evidence.
+    pub async fn sign_off(
+        &self,
+        id: &IdType,
+        signer_id: ObjectId,
+    ) -> Result<CompetencyAssessment, AppError> {
+        let assessment = self.find_one(id).await?;
+        Self::ensure_draft(&assessment)?;
+
+        if assessment.result == CompetencyResult::Competent && assessment.evidence.is_empty() {
+            return Err(AppError {
+                message: "Upload evidence before signing off a competent result".into(),
+            });
+        }
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "_id": IdType::to_object_id(id)?,
+                    "status": bson::to_bson(&CompetencyAssessmentStatus::Draft).unwrap()
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&CompetencyAssessmentStatus::SignedOff).unwrap(),
+                    "signed_off_by": signer_id,
+                    "signed_off_at": now.clone(),
+                    "updated_at": now
+                } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Assessment was already signed off".into(),
+            })
+    }
+
+    // =========================
+    // REPORTS
+    // =========================
+
+    async fn find_module(&self, class_subject_id: ObjectId) -> Result<ClassSubject, AppError> {
+        self.db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(doc! { "_id": class_subject_id })
+            .await?
+            .ok_or(AppError {
+                message: "Module not found".into(),
+            })
+    }
+
+    /// Roll signed-off assessments up to one module. The required units are
+    /// the module's learning outcomes when it defines any, its topics
+    /// otherwise; assessments against the other kind do not count.
+    fn module_report(
+        module: &ClassSubject,
+        assessments: &[CompetencyAssessment],
+        outcomes: &[(ObjectId, String)],
+    ) -> ModuleCompetencyReport {
+        let units: Vec<(UnitKey, Option<String>)> = if outcomes.is_empty() {
+            module
+                .topics
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(|t| (UnitKey::Topic(t.order.clone()), Some(t.title.clone())))
+                .collect()
+        } else {
+            outcomes
+                .iter()
+                .map(|(id, title)| (UnitKey::Outcome(*id), Some(title.clone())))
+                .collect()
+        };
+
+        let mut latest: HashMap<UnitKey, (&CompetencyAssessment, i32)> = HashMap::new();
+        for assessment in assessments
+            .iter()
+            .filter(|a| a.status == CompetencyAssessmentStatus::SignedOff)
+        {
+            let Some(key) = UnitKey::of(assessment) else {
+                continue;
+            };
+            if !units.iter().any(|(k, _)| k == &key) {
+                continue;
+            }
+            let entry = latest.entry(key).or_insert((assessment, 0));
+            entry.1 += 1;
+            if assessment.attempt > entry.0.attempt {
+                entry.0 = assessment;
+            }
+        }
+
+        let outcomes: Vec<OutcomeCompetency> = units
+            .into_iter()
+            .map(|(key, title)| {
+                let found = latest.get(&key);
+                let (learning_outcome_id, topic_order) = match key {
+                    UnitKey::Outcome(id) => (Some(id), None),
+                    UnitKey::Topic(order) => (None, Some(order)),
+                };
+                OutcomeCompetency {
+                    learning_outcome_id,
+                    topic_order,
+                    title: title.or_else(|| found.and_then(|(a, _)| a.topic_title.clone())),
+                    result: found.map(|(a, _)| a.result),
+                    attempts: found.map(|(_, n)| *n).unwrap_or(0),
+                    assessed_at: found.map(|(a, _)| a.assessed_at),
+                }
+            })
+            .collect();
+
+        let competent_outcomes = outcomes
+            .iter()
+            .filter(|o| o.result == Some(CompetencyResult::Competent))
+            .count();
+
+        ModuleCompetencyReport {
+            class_subject_id: module.id.unwrap_or_default(),
+            module_name: module.name.clone(),
+            module_code: module.code.clone(),
+            credits: module.credits,
+            total_outcomes: outcomes.len(),
+            competent_outcomes,
+            is_competent: !outcomes.is_empty() && competent_outcomes == outcomes.len(),
+            outcomes,
+        }
+    }
+
+    /// Learning outcomes defined for each module, in order
+    async fn module_outcomes(
+        &self,
+        module_ids: &[ObjectId],
+    ) -> Result<HashMap<ObjectId, Vec<(ObjectId, String)>>, AppError> {
+        if module_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>("learning_outcomes")
+            .find(doc! { "subject_id": { "$in": module_ids } })
+            .sort(doc! { "order": 1 })
+            .projection(doc! { "subject_id": 1, "title": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut outcomes: HashMap<ObjectId, Vec<(ObjectId, String)>> = HashMap::new();
+        for d in docs {
+            let (Ok(id), Ok(module_id), Ok(title)) = (
+                d.get_object_id("_id"),
+                d.get_object_id("subject_id"),
+                d.get_str("title"),
+            ) else {
+                continue;
+            };
+            outcomes
+                .entry(module_id)
+                .or_default()
+                .push((id, title.to_string()));
+        }
+        Ok(outcomes)
+    }
+
+    async fn trade_name(&self, trade_id: Option<ObjectId>) -> Result<Option<String>, AppError> {
+        let Some(trade_id) = trade_id else {
+            return Ok(None);
+        };
+        Ok(self
+            .main_db
+            .collection::<Trade>("trades")
+            .find_one(doc! { "_id": trade_id })
+            .await?
+            .map(|t| t.name))
+    }
+
+    async fn class_modules(&self, class_id: ObjectId) -> Result<Vec<ClassSubject>, AppError> {
+        Ok(self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find(doc! { "class_id": class_id, "disable": { "$ne": true } })
+            .sort(doc! { "code": 1 })
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    fn student_report(
+        student: &Student,
+        modules: &[ClassSubject],
+        assessments: &[CompetencyAssessment],
+        module_outcomes: &HashMap<ObjectId, Vec<(ObjectId, String)>>,
+        trade_id: Option<ObjectId>,
+        trade_name: Option<String>,
+    ) -> TradeCompetencyReport {
+        let modules: Vec<ModuleCompetencyReport> = modules
+            .iter()
+            .map(|module| {
+                let module_assessments: Vec<CompetencyAssessment> = assessments
+                    .iter()
+                    .filter(|a| Some(a.class_subject_id) == module.id)
+                    .cloned()
+                    .collect();
+                let outcomes = module
+                    .id
+                    .and_then(|id| module_outcomes.get(&id))
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                Self::module_report(module, &module_assessments, outcomes)
+            })
+            .collect();
+
+        let competent_modules = modules.iter().filter(|m| m.is_competent).count();
+        let credits_earned = modules
+            .iter()
+            .filter(|m| m.is_competent)
+            .filter_map(|m| m.credits)
+            .sum();
+
+        TradeCompetencyReport {
+            student_id: student.id.unwrap_or_default(),
+            student_name: student.name.clone(),
+            registration_number: student.registration_number.clone(),
+            trade_id,
+            trade_name,
+            total_modules: modules.len(),
+            competent_modules,
+            credits_earned,
+            is_competent: !modules.is_empty() && competent_modules == modules.len(),
+            modules,
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Competency of one student across every module of their class' trade
+    pub async fn student_report_for(
+        &self,
+        student: &Student,
+    ) -> Result<TradeCompetencyReport, AppError> {
+        let class_id = student.class_id.ok_or(AppError {
+            message: "Student is not assigned to a class".into(),
+        })?;
+        let class = self
+            .db
+            .collection::<Class>("classes")
+            .find_one(doc! { "_id": class_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class not found".into(),
+            })?;
+
+        let modules = self.class_modules(class_id).await?;
+        let assessments: Vec<CompetencyAssessment> = self
+            .collection
+            .find(doc! { "student_id": student.id })
+            .await?
+            .try_collect()
+            .await?;
+        let module_ids: Vec<ObjectId> = modules.iter().filter_map(|m| m.id).collect();
+        let module_outcomes = self.module_outcomes(&module_ids).await?;
+        let trade_name = self.trade_name(class.trade_id).await?;
+
+        Ok(Self::student_report(
+            student,
+            &modules,
+            &assessments,
+            &module_outcomes,
+            class.trade_id,
+            trade_name,
+        ))
+    }
+
+    pub async fn class_report(&self, class_id: ObjectId) -> Result<ClassCompetencySummary, AppError> {
+        let class = self
+            .db
+            .collection::<Class>("classes")
+            .find_one(doc! { "_id": class_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class not found".into(),
+            })?;
+
+        let modules = self.class_modules(class_id).await?;
+        let students: Vec<Student> = self
+            .db
+            .collection::<Student>("students")
+            .find(doc! { "class_id": class_id, "is_active": true })
+            .sort(doc! { "name": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        let assessments: Vec<CompetencyAssessment> = self
+            .collection
+            .find(doc! {
+                "class_id": class_id,
+                "status": bson::to_bson(&CompetencyAssessmentStatus::SignedOff).unwrap()
+            })
+            .await?
+            .try_collect()
+            .await?;
+        let module_ids: Vec<ObjectId> = modules.iter().filter_map(|m| m.id).collect();
+        let module_outcomes = self.module_outcomes(&module_ids).await?;
+        let trade_name = self.trade_name(class.trade_id).await?;
+
+        let mut by_student: HashMap<ObjectId, Vec<CompetencyAssessment>> = HashMap::new();
+        for assessment in assessments {
+            by_student
+                .entry(assessment.student_id)
+                .or_default()
+                .push(assessment);
+        }
+
+        let reports: Vec<TradeCompetencyReport> = students
+            .iter()
+            .map(|student| {
+                let assessments = student
+                    .id
+                    .and_then(|id| by_student.get(&id))
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                Self::student_report(
+                    student,
+                    &modules,
+                    assessments,
+                    &module_outcomes,
+                    class.trade_id,
+                    trade_name.clone(),
+                )
+            })
+            .collect();
+
+        Ok(ClassCompetencySummary {
+            class_id,
+            trade_name,
+            total_students: reports.len(),
+            competent_students: reports.iter().filter(|r| r.is_competent).count(),
+            students: reports,
+        })
+    }
+}
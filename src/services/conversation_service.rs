@@ -1,7 +1,10 @@
 use crate::{
     domain::{
         common_details::Paginated,
-        conversation::{Conversation, ConversationKey, ConversationWithRelations},
+        conversation::{
+            Conversation, ConversationKey, ConversationKeyRotation, ConversationWithRelations,
+            KeyRotationTrigger,
+        },
     },
     errors::AppError,
     models::{
@@ -10,16 +13,20 @@ use crate::{
     },
     pipeline::conversation_pipeline::conversation_pipeline,
     repositories::base_repo::BaseRepository,
-    utils::mongo_utils::extract_valid_fields,
+    schema::common_schema::ActorRef,
+    utils::mongo_utils::{extract_valid_fields, to_stored_document},
 };
+use chrono::Utc;
 use mongodb::{
     bson::{doc, oid::ObjectId, Document},
+    options::ReturnDocument,
     Collection, Database,
 };
 
 pub struct ConversationService {
     pub collection: Collection<Conversation>,
     pub keys_collection: Collection<ConversationKey>,
+    pub rotations_collection: Collection<ConversationKeyRotation>,
 }
 
 impl ConversationService {
@@ -27,6 +34,8 @@ impl ConversationService {
         Self {
             collection: db.collection::<Conversation>("conversations"),
             keys_collection: db.collection::<ConversationKey>("conversation_keys"),
+            rotations_collection: db
+                .collection::<ConversationKeyRotation>("conversation_key_rotations"),
         }
     }
 
@@ -42,15 +51,32 @@ impl ConversationService {
         );
         repo.ensure_indexes(&indexes).await?;
 
-        let key_indexes = vec![
-            IndexDef::compound(vec![("conversation_id", 1), ("user_id", 1)], true),
-        ];
+        // Keys used to be unique per (conversation, user); rotation keeps one key per version
+        self.keys_collection
+            .drop_index("conversation_id_1_user_id_1")
+            .await
+            .ok();
+
+        let key_indexes = vec![IndexDef::compound(
+            vec![("conversation_id", 1), ("user_id", 1), ("key_version", -1)],
+            true,
+        )];
 
         let key_repo = BaseRepository::new(
             self.keys_collection.clone().clone_with_type::<Document>(),
         );
         key_repo.ensure_indexes(&key_indexes).await?;
 
+        let rotation_indexes = vec![IndexDef::compound(
+            vec![("conversation_id", 1), ("to_version", -1)],
+            true,
+        )];
+
+        let rotation_repo = BaseRepository::new(
+            self.rotations_collection.clone().clone_with_type::<Document>(),
+        );
+        rotation_repo.ensure_indexes(&rotation_indexes).await?;
+
         Ok(())
     }
 
@@ -62,11 +88,7 @@ impl ConversationService {
 
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
 
-        let doc = mongodb::bson::to_document(&dto).map_err(|e| AppError {
-            message: format!("Failed to serialize conversation: {}", e),
-        })?;
-
-        repo.create::<Conversation>(extract_valid_fields(doc), None)
+        repo.create::<Conversation>(extract_valid_fields(to_stored_document(&dto)?), None)
             .await
     }
 
@@ -132,9 +154,7 @@ impl ConversationService {
             self.keys_collection.clone().clone_with_type::<Document>(),
         );
 
-        let doc = mongodb::bson::to_document(&key).map_err(|e| AppError {
-            message: format!("Failed to serialize conversation key: {}", e),
-        })?;
+        let doc = to_stored_document(&key)?;
 
         repo.create::<ConversationKey>(
             extract_valid_fields(doc),
@@ -143,23 +163,248 @@ impl ConversationService {
         .await
     }
 
+    /// Get the caller's key for a specific version, or the latest one when no version is given
     pub async fn get_conversation_key(
         &self,
         conversation_id: ObjectId,
         user_id: ObjectId,
+        version: Option<i32>,
     ) -> Result<ConversationKey, AppError> {
-        let repo = BaseRepository::new(
+        let mut filter = doc! { "conversation_id": conversation_id, "user_id": user_id };
+
+        match version {
+            // Keys stored before rotation existed have no key_version and belong to version 1
+            Some(1) => {
+                filter.insert(
+                    "$or",
+                    vec![
+                        doc! { "key_version": 1 },
+                        doc! { "key_version": { "$exists": false } },
+                    ],
+                );
+            }
+            Some(v) => {
+                filter.insert("key_version", v);
+            }
+            None => {}
+        }
+
+        self.keys_collection
+            .find_one(filter)
+            .sort(doc! { "key_version": -1 })
+            .await?
+            .ok_or(AppError {
+                message: "Conversation key not found".into(),
+            })
+    }
+
+    /// Get every key version the user holds for a conversation, oldest first
+    pub async fn get_conversation_keys(
+        &self,
+        conversation_id: ObjectId,
+        user_id: ObjectId,
+    ) -> Result<Vec<ConversationKey>, AppError> {
+        let mut cursor = self
+            .keys_collection
+            .find(doc! { "conversation_id": conversation_id, "user_id": user_id })
+            .sort(doc! { "key_version": 1 })
+            .await?;
+
+        let mut keys = Vec::new();
+        while cursor.advance().await? {
+            keys.push(cursor.deserialize_current()?);
+        }
+
+        Ok(keys)
+    }
+
+    // =========================
+    // KEY ROTATION
+    // =========================
+
+    /// Store the new per-member encrypted keys, then bump the conversation key
+    /// version. Old key versions are kept so history stays decryptable.
+    pub async fn rotate_key(
+        &self,
+        conversation: &Conversation,
+        encrypted_keys: Vec<ConversationKey>,
+        rotated_by: ActorRef,
+        trigger: KeyRotationTrigger,
+        reason: Option<String>,
+    ) -> Result<(Conversation, ConversationKeyRotation), AppError> {
+        self.ensure_indexes().await?;
+
+        let conversation_id = conversation.id.ok_or(AppError {
+            message: "Conversation ID is required".into(),
+        })?;
+
+        let from_version = conversation.encryption_key_version;
+        let to_version = from_version + 1;
+        let already_rotated = || AppError {
+            message: "Conversation key was already rotated. Fetch the latest version and try again".into(),
+        };
+
+        // The rotation record is unique per version, so it claims the version
+        // before anything else is written
+        let mut rotation = ConversationKeyRotation {
+            id: None,
+            conversation_id,
+            school_id: conversation.school_id,
+            from_version,
+            to_version,
+            rotated_by,
+            trigger,
+            reason,
+            created_at: Utc::now(),
+        };
+        rotation.id = match self.rotations_collection.insert_one(&rotation).await {
+            Ok(inserted) => inserted.inserted_id.as_object_id(),
+            Err(e) if e.to_string().contains("E11000") => return Err(already_rotated()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let bumped = self
+            .store_rotated_keys(conversation_id, from_version, to_version, encrypted_keys)
+            .await;
+        match bumped {
+            Ok(Some(updated)) => Ok((updated, rotation)),
+            Ok(None) => {
+                self.discard_rotation(conversation_id, to_version).await?;
+                Err(already_rotated())
+            }
+            Err(e) => {
+                self.discard_rotation(conversation_id, to_version).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Insert the keys of `to_version` and only then make it current, provided
+    /// nobody rotated in the meantime
+    async fn store_rotated_keys(
+        &self,
+        conversation_id: ObjectId,
+        from_version: i32,
+        to_version: i32,
+        encrypted_keys: Vec<ConversationKey>,
+    ) -> Result<Option<Conversation>, AppError> {
+        let mut docs = Vec::with_capacity(encrypted_keys.len());
+        for mut key in encrypted_keys {
+            key.id = None;
+            key.conversation_id = conversation_id;
+            key.key_version = to_version;
+            key.created_at = Utc::now();
+
+            docs.push(extract_valid_fields(to_stored_document(&key)?));
+        }
+
+        let key_repo = BaseRepository::new(
             self.keys_collection.clone().clone_with_type::<Document>(),
         );
+        key_repo.create_many::<ConversationKey>(docs, None).await?;
 
-        repo.find_one::<ConversationKey>(
-            doc! { "conversation_id": conversation_id, "user_id": user_id },
-            None,
-        )
-        .await?
-        .ok_or(AppError {
-            message: "Conversation key not found".into(),
-        })
+        let now = mongodb::bson::to_bson(&Utc::now()).unwrap();
+        Ok(self
+            .collection
+            .find_one_and_update(
+                doc! { "_id": conversation_id, "encryption_key_version": from_version },
+                doc! {
+                    "$set": {
+                        "encryption_key_version": to_version,
+                        "last_key_rotated_at": now.clone(),
+                        "updated_at": now,
+                    }
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await?)
+    }
+
+    /// Undo a rotation that never became current
+    async fn discard_rotation(
+        &self,
+        conversation_id: ObjectId,
+        to_version: i32,
+    ) -> Result<(), AppError> {
+        self.keys_collection
+            .delete_many(doc! { "conversation_id": conversation_id, "key_version": to_version })
+            .await?;
+        self.rotations_collection
+            .delete_one(doc! { "conversation_id": conversation_id, "to_version": to_version })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_key_rotations(
+        &self,
+        conversation_id: ObjectId,
+    ) -> Result<Vec<ConversationKeyRotation>, AppError> {
+        let mut cursor = self
+            .rotations_collection
+            .find(doc! { "conversation_id": conversation_id })
+            .sort(doc! { "to_version": -1 })
+            .await?;
+
+        let mut rotations = Vec::new();
+        while cursor.advance().await? {
+            rotations.push(cursor.deserialize_current()?);
+        }
+
+        Ok(rotations)
+    }
+
+    pub async fn update_key_rotation_interval(
+        &self,
+        conversation_id: ObjectId,
+        interval_days: Option<i32>,
+    ) -> Result<Conversation, AppError> {
+        if let Some(days) = interval_days {
+            if !(1..=365).contains(&days) {
+                return Err(AppError {
+                    message: "Key rotation interval must be between 1 and 365 days".into(),
+                });
+            }
+        }
+
+        let now = mongodb::bson::to_bson(&Utc::now()).unwrap();
+        let update = match interval_days {
+            Some(days) => doc! { "$set": { "key_rotation_interval_days": days, "updated_at": now } },
+            None => doc! {
+                "$unset": { "key_rotation_interval_days": "" },
+                "$set": { "updated_at": now }
+            },
+        };
+
+        self.collection
+            .find_one_and_update(doc! { "_id": conversation_id }, update)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Conversation not found".into(),
+            })
+    }
+
+    /// Conversations of a participant whose scheduled key rotation is due
+    pub async fn get_rotation_due(
+        &self,
+        user_id: ObjectId,
+        extra_match: Option<Document>,
+    ) -> Result<Vec<Conversation>, AppError> {
+        let mut filter = extra_match.unwrap_or_default();
+        filter.insert("participants.id", user_id);
+        filter.insert("key_rotation_interval_days", doc! { "$gt": 0 });
+
+        let mut cursor = self.collection.find(filter).await?;
+
+        let mut due = Vec::new();
+        while cursor.advance().await? {
+            let conversation: Conversation = cursor.deserialize_current()?;
+            if conversation.is_key_rotation_due() {
+                due.push(conversation);
+            }
+        }
+
+        Ok(due)
     }
 
     // =========================
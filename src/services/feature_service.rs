@@ -24,6 +24,18 @@ impl FeatureService {
         &self,
         school_id: &IdType,
         feature_name: &str,
+    ) -> Result<bool, AppError> {
+        self.is_feature_enabled_or(school_id, feature_name, true)
+            .await
+    }
+
+    /// Like `is_feature_enabled`, for features that should stay off until a
+    /// school opts in
+    pub async fn is_feature_enabled_or(
+        &self,
+        school_id: &IdType,
+        feature_name: &str,
+        default: bool,
     ) -> Result<bool, AppError> {
         let school_oid = IdType::to_object_id(school_id)?;
 
@@ -36,9 +48,9 @@ impl FeatureService {
             })?;
 
         if let Some(features) = features {
-            Ok(*features.features.get(feature_name).unwrap_or(&true))
+            Ok(*features.features.get(feature_name).unwrap_or(&default))
         } else {
-            Ok(true)
+            Ok(default)
         }
     }
 
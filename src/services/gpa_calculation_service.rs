@@ -7,15 +7,34 @@ use crate::{
     domain::{
         assessment_category::AssessmentCategory,
         class_subject::ClassSubject,
+        grading_scale::{GradingScale, GradingType},
         score::Score,
         student_term_result::{CategoryScore, StudentTermResult, SubjectResult},
     },
     errors::AppError,
     models::{id_model::IdType, mongo_model::IndexDef},
     repositories::base_repo::BaseRepository,
-    services::grading_scale_service::GradingScaleService,
+    services::{
+        academic_record_service::ResultVisibility, grading_scale_service::GradingScaleService,
+    },
+    utils::mongo_utils::{id_match, to_stored_document},
 };
 
+/// Credit-weighted average of subject grade points; subjects without
+/// credits count once
+pub fn credit_weighted_gpa(subject_results: &[SubjectResult]) -> f64 {
+    let (points, weights) = subject_results.iter().fold((0.0, 0.0), |(p, w), s| {
+        let weight = s.credits.filter(|c| *c > 0).unwrap_or(1) as f64;
+        (p + s.grade_points.unwrap_or(0.0) * weight, w + weight)
+    });
+
+    if weights > 0.0 {
+        points / weights
+    } else {
+        0.0
+    }
+}
+
 pub struct GpaCalculationService {
     pub score_collection: Collection<Score>,
     pub result_collection: Collection<StudentTermResult>,
@@ -97,6 +116,19 @@ impl GpaCalculationService {
             }
         }
 
+        // Subject grades and points come from the school's active scale
+        let scale = self
+            .grading_service
+            .get_active_scale(school_id, education_year_id)
+            .await?
+            .unwrap_or_else(GradingScale::fallback);
+        if scale.grading_type == GradingType::Competency {
+            return Err(AppError {
+                message: "This school grades by competency, use competency reports instead of GPA"
+                    .into(),
+            });
+        }
+
         // Calculate subject results
         let mut subject_results = Vec::new();
         let mut total_weighted_score = 0.0;
@@ -104,7 +136,7 @@ impl GpaCalculationService {
 
         for (subject_id, scores) in subject_scores {
             let subject_result = self
-                .calculate_subject_result(&subject_id, scores, education_year_id)
+                .calculate_subject_result(&subject_id, scores, education_year_id, &scale)
                 .await?;
 
             total_weighted_score += subject_result.weighted_score;
@@ -115,7 +147,6 @@ impl GpaCalculationService {
             subject_results.push(subject_result);
         }
 
-        // Calculate overall GPA
         let subject_count = subject_results.len() as f64;
         let average_percentage = if subject_count > 0.0 {
             total_weighted_score / subject_count
@@ -123,20 +154,9 @@ impl GpaCalculationService {
             0.0
         };
 
-        // Calculate GPA (simple average for now, can be credit-based)
-        let gpa = average_percentage / 25.0; // Convert to 4.0 scale (100/25 = 4.0)
+        let gpa = credit_weighted_gpa(&subject_results);
 
-        // Get grading scale and determine grade
-        let grading_scale = self
-            .grading_service
-            .get_active_scale(school_id, education_year_id)
-            .await?;
-
-        let grade = if let Some(scale) = grading_scale {
-            self.grading_service.calculate_grade(&scale, average_percentage)
-        } else {
-            "N/A".to_string()
-        };
+        let grade = self.grading_service.calculate_grade(&scale, average_percentage);
 
         // Create result
         let result = StudentTermResult {
@@ -158,10 +178,15 @@ impl GpaCalculationService {
                 None
             },
             grade,
+            grading_scale_id: scale.id,
+            grading_scale_version: scale.id.map(|_| scale.version),
+            max_gpa: Some(scale.max_gpa),
             rank_in_class: None, // Will be calculated separately
             total_students: None,
             calculated_at: Some(chrono::Utc::now()),
             is_finalized: false,
+            is_published: false,
+            published_at: None,
         };
 
         // Save or update result
@@ -173,6 +198,7 @@ impl GpaCalculationService {
         subject_id: &ObjectId,
         scores: Vec<Score>,
         education_year_id: &ObjectId,
+        scale: &GradingScale,
     ) -> Result<SubjectResult, AppError> {
         // Fetch subject details
         let subject = self
@@ -183,10 +209,11 @@ impl GpaCalculationService {
                 message: "Subject not found".to_string(),
             })?;
 
-        // Fetch assessment categories for this subject
+        // Fetch assessment categories for this subject; they are stored
+        // with hex ids
         let category_filter = doc! {
-            "class_subject_id": subject_id,
-            "education_year_id": education_year_id,
+            "class_subject_id": id_match(subject_id),
+            "education_year_id": id_match(education_year_id),
             "is_deleted": false
         };
 
@@ -221,7 +248,7 @@ impl GpaCalculationService {
         }
 
         // Determine grade based on weighted percentage
-        let grade = self.determine_grade(weighted_total);
+        let grade = self.grading_service.calculate_grade(scale, weighted_total);
 
         Ok(SubjectResult {
             class_subject_id: Some(*subject_id),
@@ -231,30 +258,20 @@ impl GpaCalculationService {
             percentage: weighted_total,
             grade,
             credits: subject.credits,
+            grade_points: Some(scale.grade_points(weighted_total)),
         })
     }
 
-    fn determine_grade(&self, percentage: f64) -> String {
-        // Simple grading logic (can be replaced with grading scale)
-        if percentage >= 90.0 {
-            "A".to_string()
-        } else if percentage >= 80.0 {
-            "B".to_string()
-        } else if percentage >= 70.0 {
-            "C".to_string()
-        } else if percentage >= 60.0 {
-            "D".to_string()
-        } else if percentage >= 50.0 {
-            "E".to_string()
-        } else {
-            "F".to_string()
-        }
-    }
-
     async fn save_result(&self, result: &StudentTermResult) -> Result<StudentTermResult, AppError> {
+        let (Some(student_id), Some(exam_id)) = (result.student_id, result.exam_id) else {
+            return Err(AppError {
+                message: "Result needs a student and an exam".into(),
+            });
+        };
+        // Results used to be stored with hex ids
         let filter = doc! {
-            "student_id": result.student_id,
-            "exam_id": result.exam_id
+            "student_id": id_match(&student_id),
+            "exam_id": id_match(&exam_id)
         };
 
         let repo = BaseRepository::new(
@@ -265,10 +282,16 @@ impl GpaCalculationService {
 
         // Check if result already exists
         if let Some(existing) = repo.find_one::<StudentTermResult>(filter.clone(), None).await? {
+            // Finalized results only change through a re-opened result sheet
+            if existing.is_finalized {
+                return Err(AppError {
+                    message: "Result is finalized and cannot be recalculated".into(),
+                });
+            }
+
             // Update existing result
-            let update_doc = mongodb::bson::to_document(result).map_err(|e| AppError {
-                message: format!("Failed to serialize result: {}", e),
-            })?;
+            let mut update_doc = to_stored_document(result)?;
+            update_doc.remove("_id");
             let id = existing.id.ok_or(AppError {
                 message: "Existing result has no ID".into(),
             })?;
@@ -278,9 +301,7 @@ impl GpaCalculationService {
                 .await
         } else {
             // Insert new result
-            let doc = mongodb::bson::to_document(result).map_err(|e| AppError {
-                message: format!("Failed to serialize result: {}", e),
-            })?;
+            let doc = to_stored_document(result)?;
             repo.create::<StudentTermResult>(doc, None).await
         }
     }
@@ -332,11 +353,13 @@ impl GpaCalculationService {
         &self,
         student_id: &ObjectId,
         exam_id: &ObjectId,
+        visibility: ResultVisibility,
     ) -> Result<Option<StudentTermResult>, AppError> {
-        let filter = doc! {
-            "student_id": student_id,
-            "exam_id": exam_id
+        let mut filter = doc! {
+            "student_id": id_match(student_id),
+            "exam_id": id_match(exam_id)
         };
+        visibility.apply(&mut filter);
 
         let repo = BaseRepository::new(
             self.result_collection
@@ -346,4 +369,30 @@ impl GpaCalculationService {
 
         repo.find_one::<StudentTermResult>(filter, None).await
     }
+
+    /// Stored results of a class for an exam, best ranked first
+    pub async fn get_class_results(
+        &self,
+        class_id: &ObjectId,
+        exam_id: &ObjectId,
+        visibility: ResultVisibility,
+    ) -> Result<Vec<StudentTermResult>, AppError> {
+        let mut filter = doc! {
+            "class_id": id_match(class_id),
+            "exam_id": id_match(exam_id)
+        };
+        visibility.apply(&mut filter);
+
+        let mut cursor = self
+            .result_collection
+            .find(filter)
+            .sort(doc! { "rank_in_class": 1, "average_percentage": -1 })
+            .await?;
+
+        let mut results = Vec::new();
+        while cursor.advance().await? {
+            results.push(cursor.deserialize_current()?);
+        }
+        Ok(results)
+    }
 }
@@ -14,7 +14,7 @@ use crate::{
         mongo_model::IndexDef,
     },
     repositories::base_repo::BaseRepository,
-    utils::mongo_utils::extract_valid_fields,
+    utils::mongo_utils::{extract_valid_fields, id_match, to_stored_document},
 };
 
 pub struct GradingScaleService {
@@ -44,12 +44,12 @@ impl GradingScaleService {
 
     pub async fn create(&self, scale: GradingScale) -> Result<GradingScale, AppError> {
         self.ensure_indexes().await?;
+        scale.validate().map_err(|message| AppError { message })?;
 
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
-        let mut doc = extract_valid_fields(mongodb::bson::to_document(&scale).map_err(|e| AppError {
-            message: format!("Failed to serialize grading scale: {}", e),
-        })?);
+        let mut doc = extract_valid_fields(to_stored_document(&scale)?);
         doc.insert("is_deleted", false);
+        doc.insert("version", 1);
 
         repo.create::<GradingScale>(doc, None).await
     }
@@ -105,10 +105,20 @@ impl GradingScaleService {
         id: &IdType,
         update: &GradingScalePartial,
     ) -> Result<GradingScale, AppError> {
+        let existing = self.find_one(id).await?;
+
+        let mut merged = existing.clone();
+        if let Some(boundaries) = &update.grade_boundaries {
+            merged.grade_boundaries = boundaries.clone();
+        }
+        if let Some(max_gpa) = update.max_gpa {
+            merged.max_gpa = max_gpa;
+        }
+        merged.validate().map_err(|message| AppError { message })?;
+
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
-        let update_doc = extract_valid_fields(mongodb::bson::to_document(update).map_err(|e| AppError {
-            message: format!("Failed to serialize update: {}", e),
-        })?);
+        let mut update_doc = extract_valid_fields(to_stored_document(update)?);
+        update_doc.insert("version", existing.version + 1);
 
         repo.update_one_and_fetch::<GradingScale>(id, update_doc)
             .await
@@ -128,8 +138,8 @@ impl GradingScaleService {
 
         // Deactivate all other scales for this school and education year
         let deactivate_filter = doc! {
-            "school_id": school_id,
-            "education_year_id": education_year_id,
+            "school_id": id_match(&school_id),
+            "education_year_id": id_match(&education_year_id),
             "is_deleted": false,
             "_id": { "$ne": IdType::to_object_id(id)? }
         };
@@ -151,9 +161,10 @@ impl GradingScaleService {
         school_id: &mongodb::bson::oid::ObjectId,
         education_year_id: &mongodb::bson::oid::ObjectId,
     ) -> Result<Option<GradingScale>, AppError> {
+        // Scales used to be stored with hex ids
         let filter = doc! {
-            "school_id": school_id,
-            "education_year_id": education_year_id,
+            "school_id": id_match(school_id),
+            "education_year_id": id_match(education_year_id),
             "is_active": true,
             "is_deleted": false
         };
@@ -168,11 +179,6 @@ impl GradingScaleService {
     }
 
     pub fn calculate_grade(&self, scale: &GradingScale, percentage: f64) -> String {
-        for boundary in &scale.grade_boundaries {
-            if percentage >= boundary.min_score && percentage <= boundary.max_score {
-                return boundary.grade.clone();
-            }
-        }
-        "N/A".to_string()
+        scale.grade_for(percentage)
     }
 }
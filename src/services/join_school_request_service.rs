@@ -30,7 +30,7 @@ use crate::{
     pipeline::join_school_request_pipeline::join_school_request_pipeline,
     repositories::{base_repo::BaseRepository, user_repo::UserRepo},
     services::{
-        class_service::ClassService, school_service::SchoolService,
+        chat_service::ChatService, class_service::ClassService, school_service::SchoolService,
         school_staff_service::SchoolStaffService, student_service::StudentService,
         teacher_service::TeacherService, user_service::UserService,
     },
@@ -469,6 +469,7 @@ impl JoinSchoolRequestService {
                     .find_one(None, Some(doc! {"email": user.email.clone()}))
                     .await
                 {
+                    let previous_class_id = student.class_id;
                     let update_student = StudentPartial {
                         id: None,               // StudentPartial.id is Option<ObjectId>
                         user_id: Some(user.id), // Assuming user.id is ObjectId
@@ -510,6 +511,10 @@ impl JoinSchoolRequestService {
                             &update_student,
                         )
                         .await?;
+                    ChatService::refresh_classes(
+                        &school_db,
+                        previous_class_id.into_iter().chain(request.class_id),
+                    );
                 } else {
                     let new_student = Student {
                         id: None,
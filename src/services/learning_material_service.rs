@@ -1,5 +1,6 @@
 use chrono::Utc;
-use mongodb::{bson::{doc, Document}, Collection, Database};
+use futures::TryStreamExt;
+use mongodb::{bson::{self, doc, oid::ObjectId, Document}, Collection, Database};
 
 use crate::{
     config::state::AppState,
@@ -7,7 +8,7 @@ use crate::{
         audit_log::AuditSeverity,
         auth_user::AuthUserDto,
         common_details::Paginated,
-        learning_material::{LearningMaterial, LearningMaterialPartial, LearningMaterialWithRelations, MaterialType},
+        learning_material::{LearningMaterial, LearningMaterialPartial, LearningMaterialWithRelations, MaterialCompletion, MaterialFolder, MaterialType, MaterialVersion},
     },
     errors::AppError,
     models::{id_model::IdType, mongo_model::{CountDoc, IndexDef}},
@@ -19,12 +20,16 @@ use crate::{
 
 pub struct LearningMaterialService {
     pub collection: Collection<LearningMaterial>,
+    pub folder_collection: Collection<MaterialFolder>,
+    pub completion_collection: Collection<MaterialCompletion>,
 }
 
 impl LearningMaterialService {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection::<LearningMaterial>("learning_materials"),
+            folder_collection: db.collection::<MaterialFolder>("material_folders"),
+            completion_collection: db.collection::<MaterialCompletion>("material_completions"),
         }
     }
 
@@ -39,10 +44,44 @@ impl LearningMaterialService {
             IndexDef::single("created_at", false),
             IndexDef::single("deleted_at", false),
             IndexDef::compound(vec![("school_id", 1), ("subject_id", 1), ("created_at", -1)], false),
+            IndexDef::compound(vec![("folder_id", 1), ("position", 1)], false),
+            IndexDef::single("prerequisite_id", false),
         ];
 
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
         repo.ensure_indexes(&indexes).await?;
+
+        let completion_indexes = vec![IndexDef::compound(vec![("material_id", 1), ("student_id", 1)], true), IndexDef::single("student_id", false)];
+        let completion_repo = BaseRepository::new(self.completion_collection.clone().clone_with_type::<Document>());
+        completion_repo.ensure_indexes(&completion_indexes).await?;
+        Ok(())
+    }
+
+    /// A folder must be in the material's subject
+    async fn validate_folder(&self, folder_id: ObjectId, subject_id: Option<ObjectId>) -> Result<(), AppError> {
+        let folder = self.folder_collection.find_one(doc! { "_id": folder_id }).await?.ok_or(AppError { message: "Folder not found".into() })?;
+        if folder.subject_id != subject_id {
+            return Err(AppError { message: "Folder belongs to another subject".into() });
+        }
+        Ok(())
+    }
+
+    /// A prerequisite must be another material of the same subject and must
+    /// not lead back to this one
+    async fn validate_prerequisite(&self, prerequisite_id: ObjectId, material_id: Option<ObjectId>, subject_id: Option<ObjectId>) -> Result<(), AppError> {
+        let mut next = Some(prerequisite_id);
+        let mut seen = Vec::new();
+        while let Some(id) = next {
+            if Some(id) == material_id || seen.contains(&id) {
+                return Err(AppError { message: "Prerequisites cannot form a loop".into() });
+            }
+            let prerequisite = self.find_one(Some(&IdType::ObjectId(id)), Some(doc! { "deleted_at": null })).await.map_err(|_| AppError { message: "Prerequisite material not found".into() })?;
+            if id == prerequisite_id && prerequisite.subject_id != subject_id {
+                return Err(AppError { message: "Prerequisite belongs to another subject".into() });
+            }
+            seen.push(id);
+            next = prerequisite.prerequisite_id;
+        }
         Ok(())
     }
 
@@ -76,6 +115,15 @@ impl LearningMaterialService {
             return Err(AppError { message: "Video URL is required for VIDEO type".into() });
         }
 
+        if let Some(folder_id) = material.folder_id {
+            self.validate_folder(folder_id, material.subject_id).await?;
+        }
+        if let Some(prerequisite_id) = material.prerequisite_id {
+            self.validate_prerequisite(prerequisite_id, None, material.subject_id).await?;
+        }
+        material.version = 1;
+        material.versions = Vec::new();
+
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
         let created_material = repo.create::<LearningMaterial>(extract_valid_fields(material.to_document()?), None).await?;
 
@@ -106,10 +154,29 @@ impl LearningMaterialService {
     pub async fn update(&self, id: &IdType, update: &LearningMaterialPartial, file_bytes: Option<Vec<u8>>, file_name: Option<String>, user: &AuthUserDto, state: &AppState) -> Result<LearningMaterial, AppError> {
         let existing = self.find_one(Some(id), None).await?;
         let mut update_data = update.clone();
+        update_data.version = None;
+        update_data.versions = None;
+
+        if let Some(Some(folder_id)) = update.folder_id {
+            self.validate_folder(folder_id, update.subject_id.unwrap_or(existing.subject_id)).await?;
+        }
+        if let Some(Some(prerequisite_id)) = update.prerequisite_id {
+            self.validate_prerequisite(prerequisite_id, existing.id, update.subject_id.unwrap_or(existing.subject_id)).await?;
+        }
 
         if let (Some(bytes), Some(name)) = (file_bytes, file_name) {
-            if let Some(old_public_id) = existing.file_public_id.clone() {
-                CloudinaryService::delete_file(&old_public_id).await.ok();
+            // Keep the replaced file so earlier versions stay downloadable
+            if existing.file_url.is_some() || existing.file_public_id.is_some() {
+                let mut versions = existing.versions.clone();
+                versions.push(MaterialVersion {
+                    version: existing.version,
+                    file_url: existing.file_url.clone(),
+                    file_public_id: existing.file_public_id.clone(),
+                    uploaded_by: existing.uploaded_by,
+                    replaced_at: Utc::now(),
+                });
+                update_data.version = Some(existing.version + 1);
+                update_data.versions = Some(versions);
             }
 
             let school_id = existing.school_id.ok_or(AppError { message: "School ID is required".into() })?;
@@ -166,6 +233,48 @@ impl LearningMaterialService {
         repo.aggregate_one::<LearningMaterialWithRelations>(learning_material_pipeline(match_stage), None).await?.ok_or(AppError { message: "Learning material not found".into() })
     }
 
+    // =========================
+    // RELEASE & COMPLETION
+    // =========================
+
+    /// Materials the student has marked complete
+    pub async fn completed_ids(&self, student_id: ObjectId) -> Result<Vec<ObjectId>, AppError> {
+        let completions: Vec<MaterialCompletion> = self.completion_collection.find(doc! { "student_id": student_id }).await?.try_collect().await?;
+        Ok(completions.into_iter().map(|c| c.material_id).collect())
+    }
+
+    /// Match stage hiding drafts and materials not released yet. With a
+    /// student's completions, materials locked behind a prerequisite are
+    /// hidden too.
+    pub fn visible_filter(completed: Option<&[ObjectId]>) -> Document {
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let mut conditions = vec![
+            doc! { "is_published": true },
+            doc! { "$or": [{ "release_at": null }, { "release_at": { "$lte": now } }] },
+        ];
+        if let Some(completed) = completed {
+            conditions.push(doc! { "$or": [{ "prerequisite_id": null }, { "prerequisite_id": { "$in": completed } }] });
+        }
+        doc! { "$and": conditions }
+    }
+
+    pub async fn mark_complete(&self, id: &IdType, student_id: ObjectId) -> Result<MaterialCompletion, AppError> {
+        self.ensure_indexes().await?;
+        let material = self.find_one(Some(id), Some(doc! { "deleted_at": null })).await?;
+        let material_id = material.id.ok_or(AppError { message: "Learning material not found".into() })?;
+        material.is_available(&self.completed_ids(student_id).await?).map_err(|message| AppError { message })?;
+
+        let completion = self.completion_collection
+            .find_one_and_update(
+                doc! { "material_id": material_id, "student_id": student_id },
+                doc! { "$setOnInsert": { "completed_at": bson::to_bson(&Utc::now()).unwrap() } },
+            )
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await?;
+        completion.ok_or(AppError { message: "Failed to record completion".into() })
+    }
+
     pub async fn count_materials(&self, filter: Option<String>, extra_match: Document) -> Result<CountDoc, AppError> {
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
         let searchable = ["title", "description", "material_type", "school_id", "class_id", "subject_id"];
@@ -0,0 +1,565 @@
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        common_details::Paginated,
+        learning_outcome::{
+            LearningOutcome, LearningOutcomePartial, LearningOutcomeWithTopics,
+            OutcomeSubjectType, SubjectTopic, SubjectTopicPartial, TopicLinkResult, TopicLinks,
+        },
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    pipeline::learning_outcome_pipeline::learning_outcome_with_topics_pipeline,
+    repositories::base_repo::BaseRepository,
+    utils::{mongo_utils::extract_valid_fields, object_id::parse_object_id_value},
+};
+
+/// Works on whichever database it is given: the main database holds the
+/// outcomes of template subjects, a school database those of its class subjects.
+pub struct LearningOutcomeService {
+    pub collection: Collection<LearningOutcome>,
+    pub topic_collection: Collection<SubjectTopic>,
+    pub db: Database,
+}
+
+impl LearningOutcomeService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<LearningOutcome>("learning_outcomes"),
+            topic_collection: db.collection::<SubjectTopic>("subject_topics"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let outcome_indexes = vec![
+            IndexDef::compound(vec![("subject_id", 1), ("order", 1)], false),
+            IndexDef::single("school_id", false),
+            IndexDef::single("source_outcome_id", false),
+        ];
+        let topic_indexes = vec![
+            IndexDef::compound(vec![("learning_outcome_id", 1), ("order", 1)], false),
+            IndexDef::single("parent_topic_id", false),
+            IndexDef::single("subject_id", false),
+        ];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&outcome_indexes)
+            .await?;
+        BaseRepository::new(self.topic_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&topic_indexes)
+            .await?;
+        Ok(())
+    }
+
+    /// Which kind of subject the id belongs to in this database
+    async fn subject_type(&self, subject_id: ObjectId) -> Result<OutcomeSubjectType, AppError> {
+        let filter = doc! { "_id": subject_id };
+        if self
+            .db
+            .collection::<Document>("class_subjects")
+            .count_documents(filter.clone())
+            .await?
+            > 0
+        {
+            return Ok(OutcomeSubjectType::ClassSubject);
+        }
+        if self
+            .db
+            .collection::<Document>("template_subjects")
+            .count_documents(filter)
+            .await?
+            > 0
+        {
+            return Ok(OutcomeSubjectType::TemplateSubject);
+        }
+        Err(AppError {
+            message: "Subject not found".into(),
+        })
+    }
+
+    // =========================
+    // LEARNING OUTCOMES
+    // =========================
+
+    pub async fn create_outcome(&self, dto: LearningOutcome) -> Result<LearningOutcome, AppError> {
+        self.ensure_indexes().await?;
+
+        if dto.title.trim().is_empty() {
+            return Err(AppError {
+                message: "Learning outcome title is required".into(),
+            });
+        }
+        let subject_id = dto.subject_id.ok_or(AppError {
+            message: "subject_id is required".into(),
+        })?;
+
+        let mut outcome = dto;
+        outcome.subject_type = self.subject_type(subject_id).await?;
+        if outcome.order <= 0 {
+            let existing = self
+                .collection
+                .count_documents(doc! { "subject_id": subject_id })
+                .await?;
+            outcome.order = existing as i32 + 1;
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.create::<LearningOutcome>(extract_valid_fields(outcome.to_document()?), None)
+            .await
+    }
+
+    pub async fn find_outcome(&self, id: &IdType) -> Result<LearningOutcome, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.find_one::<LearningOutcome>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Learning outcome not found".into(),
+            })
+    }
+
+    pub async fn get_outcomes(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<LearningOutcome>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let searchable = ["_id", "subject_id", "title", "description"];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<LearningOutcome>(filter, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn update_outcome(
+        &self,
+        id: &IdType,
+        update: &LearningOutcomePartial,
+    ) -> Result<LearningOutcome, AppError> {
+        self.find_outcome(id).await?;
+
+        let mut update_doc = extract_valid_fields(bson::to_document(update).map_err(|e| AppError {
+            message: format!("Failed to serialize update: {}", e),
+        })?);
+        // An outcome stays with the subject it was created for
+        for field in ["subject_id", "subject_type", "school_id", "source_outcome_id", "created_by"] {
+            update_doc.remove(field);
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<LearningOutcome>(id, update_doc)
+            .await
+    }
+
+    /// Removes the outcome with all its topics; linked materials and
+    /// assignments are kept but detached
+    pub async fn delete_outcome(&self, id: &IdType) -> Result<LearningOutcome, AppError> {
+        let outcome = self.find_outcome(id).await?;
+        let outcome_id = IdType::to_object_id(id)?;
+
+        let topic_ids: Vec<ObjectId> = self
+            .topic_collection
+            .distinct("_id", doc! { "learning_outcome_id": outcome_id })
+            .await?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        self.detach_topics(&topic_ids).await?;
+        self.topic_collection
+            .delete_many(doc! { "learning_outcome_id": outcome_id })
+            .await?;
+        self.collection.delete_one(doc! { "_id": outcome_id }).await?;
+
+        Ok(outcome)
+    }
+
+    /// A subject's outcomes with their topics, sub-topics and the materials
+    /// and assignments linked to them
+    pub async fn get_outcome_tree(
+        &self,
+        subject_id: ObjectId,
+        published_only: bool,
+    ) -> Result<Vec<LearningOutcomeWithTopics>, AppError> {
+        let pipeline =
+            learning_outcome_with_topics_pipeline(doc! { "subject_id": subject_id }, published_only);
+
+        let docs: Vec<Document> = self
+            .collection
+            .clone_with_type::<Document>()
+            .aggregate(pipeline)
+            .await?
+            .try_collect()
+            .await?;
+
+        docs.into_iter()
+            .map(|doc| {
+                bson::from_document::<LearningOutcomeWithTopics>(doc).map_err(|e| AppError {
+                    message: format!("Failed to read learning outcome tree: {}", e),
+                })
+            })
+            .collect()
+    }
+
+    /// Copy a template subject's outcomes and topics into a class subject.
+    /// Outcomes copied earlier are skipped, so this can be re-run after the
+    /// template changes.
+    pub async fn copy_from_template(
+        &self,
+        template: &LearningOutcomeService,
+        template_subject_id: ObjectId,
+        class_subject_id: ObjectId,
+        school_id: Option<ObjectId>,
+        created_by: Option<ObjectId>,
+    ) -> Result<Vec<LearningOutcome>, AppError> {
+        if self.subject_type(class_subject_id).await? != OutcomeSubjectType::ClassSubject {
+            return Err(AppError {
+                message: "Outcomes can only be copied into a class subject".into(),
+            });
+        }
+
+        let source_outcomes: Vec<LearningOutcome> = template
+            .collection
+            .find(doc! { "subject_id": template_subject_id })
+            .sort(doc! { "order": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        let already_copied: Vec<ObjectId> = self
+            .collection
+            .distinct("source_outcome_id", doc! { "subject_id": class_subject_id })
+            .await?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+
+        let mut created = Vec::new();
+        for source in source_outcomes {
+            let Some(source_id) = source.id else {
+                continue;
+            };
+            if already_copied.contains(&source_id) {
+                continue;
+            }
+
+            let outcome = self
+                .create_outcome(LearningOutcome {
+                    id: None,
+                    school_id,
+                    subject_type: OutcomeSubjectType::ClassSubject,
+                    subject_id: Some(class_subject_id),
+                    title: source.title.clone(),
+                    description: source.description.clone(),
+                    order: source.order,
+                    estimated_hours: source.estimated_hours,
+                    credits: source.credits,
+                    source_outcome_id: Some(source_id),
+                    created_by,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                })
+                .await?;
+            let Some(outcome_id) = outcome.id else {
+                continue;
+            };
+
+            let source_topics: Vec<SubjectTopic> = template
+                .topic_collection
+                .find(doc! { "learning_outcome_id": source_id })
+                .sort(doc! { "order": 1 })
+                .await?
+                .try_collect()
+                .await?;
+
+            // Parents first, so sub-topics can point at the copied parent
+            let mut copied_ids: Vec<(ObjectId, ObjectId)> = Vec::new();
+            let (parents, children): (Vec<_>, Vec<_>) = source_topics
+                .into_iter()
+                .partition(|t| t.parent_topic_id.is_none());
+            for topic in parents.into_iter().chain(children) {
+                let parent_topic_id = match topic.parent_topic_id {
+                    Some(parent) => match copied_ids.iter().find(|(from, _)| *from == parent) {
+                        Some((_, to)) => Some(*to),
+                        None => continue,
+                    },
+                    None => None,
+                };
+                let copy = self
+                    .insert_topic(SubjectTopic {
+                        id: None,
+                        learning_outcome_id: Some(outcome_id),
+                        parent_topic_id,
+                        subject_id: Some(class_subject_id),
+                        title: topic.title,
+                        description: topic.description,
+                        order: topic.order,
+                        estimated_hours: topic.estimated_hours,
+                        created_at: Utc::now(),
+                        updated_at: Utc::now(),
+                    })
+                    .await?;
+                if let (Some(from), Some(to)) = (topic.id, copy.id) {
+                    copied_ids.push((from, to));
+                }
+            }
+
+            created.push(outcome);
+        }
+
+        Ok(created)
+    }
+
+    // =========================
+    // TOPICS
+    // =========================
+
+    async fn insert_topic(&self, topic: SubjectTopic) -> Result<SubjectTopic, AppError> {
+        let repo = BaseRepository::new(self.topic_collection.clone().clone_with_type::<Document>());
+        repo.create::<SubjectTopic>(extract_valid_fields(topic.to_document()?), None)
+            .await
+    }
+
+    /// Topics belong to an outcome; sub-topics to a top-level topic of the
+    /// same outcome. Trees are two levels deep.
+    pub async fn create_topic(&self, dto: SubjectTopic) -> Result<SubjectTopic, AppError> {
+        self.ensure_indexes().await?;
+
+        if dto.title.trim().is_empty() {
+            return Err(AppError {
+                message: "Topic title is required".into(),
+            });
+        }
+
+        let mut topic = dto;
+        if let Some(parent_id) = topic.parent_topic_id {
+            let parent = self.find_topic(&IdType::ObjectId(parent_id)).await?;
+            if parent.parent_topic_id.is_some() {
+                return Err(AppError {
+                    message: "Sub-topics cannot have sub-topics of their own".into(),
+                });
+            }
+            if topic.learning_outcome_id.is_some()
+                && topic.learning_outcome_id != parent.learning_outcome_id
+            {
+                return Err(AppError {
+                    message: "Sub-topic must belong to the same learning outcome as its parent"
+                        .into(),
+                });
+            }
+            topic.learning_outcome_id = parent.learning_outcome_id;
+        }
+
+        let outcome_id = topic.learning_outcome_id.ok_or(AppError {
+            message: "learning_outcome_id is required".into(),
+        })?;
+        let outcome = self.find_outcome(&IdType::ObjectId(outcome_id)).await?;
+        topic.subject_id = outcome.subject_id;
+
+        if topic.order <= 0 {
+            let existing = self
+                .topic_collection
+                .count_documents(doc! {
+                    "learning_outcome_id": outcome_id,
+                    "parent_topic_id": topic.parent_topic_id
+                })
+                .await?;
+            topic.order = existing as i32 + 1;
+        }
+
+        self.insert_topic(topic).await
+    }
+
+    pub async fn find_topic(&self, id: &IdType) -> Result<SubjectTopic, AppError> {
+        let repo = BaseRepository::new(self.topic_collection.clone().clone_with_type::<Document>());
+        repo.find_one::<SubjectTopic>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Topic not found".into(),
+            })
+    }
+
+    pub async fn get_topics(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<SubjectTopic>, AppError> {
+        let repo = BaseRepository::new(self.topic_collection.clone().clone_with_type::<Document>());
+        let searchable = [
+            "_id",
+            "learning_outcome_id",
+            "parent_topic_id",
+            "subject_id",
+            "title",
+            "description",
+        ];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<SubjectTopic>(filter, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn update_topic(
+        &self,
+        id: &IdType,
+        update: &SubjectTopicPartial,
+    ) -> Result<SubjectTopic, AppError> {
+        self.find_topic(id).await?;
+
+        let mut update_doc = extract_valid_fields(bson::to_document(update).map_err(|e| AppError {
+            message: format!("Failed to serialize update: {}", e),
+        })?);
+        // Moving a topic to another outcome or parent is done by re-creating it
+        for field in ["learning_outcome_id", "parent_topic_id", "subject_id"] {
+            update_doc.remove(field);
+        }
+
+        let repo = BaseRepository::new(self.topic_collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<SubjectTopic>(id, update_doc)
+            .await
+    }
+
+    /// Removes the topic and its sub-topics, detaching linked resources
+    pub async fn delete_topic(&self, id: &IdType) -> Result<SubjectTopic, AppError> {
+        let topic = self.find_topic(id).await?;
+        let topic_id = IdType::to_object_id(id)?;
+
+        let mut topic_ids: Vec<ObjectId> = self
+            .topic_collection
+            .distinct("_id", doc! { "parent_topic_id": topic_id })
+            .await?
+            .into_iter()
+            .filter_map(|id| id.as_object_id())
+            .collect();
+        topic_ids.push(topic_id);
+
+        self.detach_topics(&topic_ids).await?;
+        self.topic_collection
+            .delete_many(doc! { "_id": { "$in": &topic_ids } })
+            .await?;
+
+        Ok(topic)
+    }
+
+    // =========================
+    // LINKED RESOURCES
+    // =========================
+
+    fn parse_ids(ids: &[String]) -> Result<Vec<ObjectId>, AppError> {
+        ids.iter().map(|id| parse_object_id_value(id)).collect()
+    }
+
+    /// Attach materials and assignments of the topic's subject to the topic.
+    /// Anything from another subject is left alone and not counted.
+    pub async fn link_resources(
+        &self,
+        topic_id: &IdType,
+        links: &TopicLinks,
+    ) -> Result<TopicLinkResult, AppError> {
+        let topic = self.find_topic(topic_id).await?;
+        let topic_oid = IdType::to_object_id(topic_id)?;
+        let now = bson::to_bson(&Utc::now()).unwrap();
+
+        let materials = self
+            .db
+            .collection::<Document>("learning_materials")
+            .update_many(
+                doc! {
+                    "_id": { "$in": Self::parse_ids(&links.learning_material_ids)? },
+                    "subject_id": topic.subject_id
+                },
+                doc! { "$set": { "topic_id": topic_oid, "updated_at": now.clone() } },
+            )
+            .await?;
+        let assignments = self
+            .db
+            .collection::<Document>("assignments")
+            .update_many(
+                doc! {
+                    "_id": { "$in": Self::parse_ids(&links.assignment_ids)? },
+                    "subject_id": topic.subject_id
+                },
+                doc! { "$set": { "topic_id": topic_oid, "updated_at": now } },
+            )
+            .await?;
+
+        Ok(TopicLinkResult {
+            learning_materials: materials.matched_count,
+            assignments: assignments.matched_count,
+        })
+    }
+
+    pub async fn unlink_resources(
+        &self,
+        topic_id: &IdType,
+        links: &TopicLinks,
+    ) -> Result<TopicLinkResult, AppError> {
+        let topic_oid = IdType::to_object_id(topic_id)?;
+
+        let materials = self
+            .db
+            .collection::<Document>("learning_materials")
+            .update_many(
+                doc! {
+                    "_id": { "$in": Self::parse_ids(&links.learning_material_ids)? },
+                    "topic_id": topic_oid
+                },
+                doc! { "$unset": { "topic_id": "" } },
+            )
+            .await?;
+        let assignments = self
+            .db
+            .collection::<Document>("assignments")
+            .update_many(
+                doc! {
+                    "_id": { "$in": Self::parse_ids(&links.assignment_ids)? },
+                    "topic_id": topic_oid
+                },
+                doc! { "$unset": { "topic_id": "" } },
+            )
+            .await?;
+
+        Ok(TopicLinkResult {
+            learning_materials: materials.modified_count,
+            assignments: assignments.modified_count,
+        })
+    }
+
+    async fn detach_topics(&self, topic_ids: &[ObjectId]) -> Result<(), AppError> {
+        if topic_ids.is_empty() {
+            return Ok(());
+        }
+        for collection in ["learning_materials", "assignments"] {
+            self.db
+                .collection::<Document>(collection)
+                .update_many(
+                    doc! { "topic_id": { "$in": topic_ids } },
+                    doc! { "$unset": { "topic_id": "" } },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc};
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class::Class,
+        class_subject::ClassSubject,
+        class_timetable::ClassTimetable,
+        common_details::Paginated,
+        learning_material::LearningMaterial,
+        lesson_plan::{
+            CopyLessonPlan, CreateLessonPlan, LessonActivity, LessonObjective,
+            LessonObjectiveInput, LessonPlan, LessonPlanStatus, LessonPlanTransition,
+            LessonResource, LessonResourceInput, SchemeLesson, SchemeOfWork, SchemeWeek,
+            UpdateLessonPlan,
+        },
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    schema::common_schema::ActorRef,
+    services::{
+        education_year_service::EducationYearService,
+        syllabus_coverage_service::{planned_topic_hours, SyllabusCoverageService},
+    },
+    utils::{
+        math_utils::round2,
+        mongo_utils::{id_match, to_stored_document},
+        object_id::parse_object_id_value,
+        status_utils::transition_status,
+    },
+};
+
+pub struct LessonPlanService {
+    pub collection: Collection<LessonPlan>,
+    pub db: Database,
+    pub main_db: Database,
+}
+
+impl LessonPlanService {
+    pub fn new(db: &Database, main_db: &Database) -> Self {
+        Self {
+            collection: db.collection::<LessonPlan>("lesson_plans"),
+            db: db.clone(),
+            main_db: main_db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("class_subject_id", 1), ("lesson_date", 1)], false),
+            IndexDef::compound(vec![("status", 1), ("lesson_date", 1)], false),
+            IndexDef::single("class_id", false),
+            IndexDef::single("teacher_id", false),
+            IndexDef::single("created_by", false),
+        ];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    fn coverage(&self) -> SyllabusCoverageService {
+        SyllabusCoverageService::new(&self.db, &self.main_db)
+    }
+
+    pub async fn find_subject(&self, class_subject_id: ObjectId) -> Result<ClassSubject, AppError> {
+        self.coverage().find_subject(class_subject_id).await
+    }
+
+    // =========================
+    // LESSON PLANS
+    // =========================
+
+    pub async fn find_one(&self, id: &IdType) -> Result<LessonPlan, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.find_one::<LessonPlan>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Lesson plan not found".into(),
+            })
+    }
+
+    pub async fn get_all(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<LessonPlan>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let searchable = [
+            "_id",
+            "class_id",
+            "class_subject_id",
+            "teacher_id",
+            "status",
+            "title",
+        ];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<LessonPlan>(filter, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    fn objectives(
+        subject: &ClassSubject,
+        inputs: &[LessonObjectiveInput],
+    ) -> Result<Vec<LessonObjective>, AppError> {
+        inputs
+            .iter()
+            .map(|o| {
+                if o.description.trim().is_empty() {
+                    return Err(AppError {
+                        message: "Every objective needs a description".into(),
+                    });
+                }
+                let topic = SyllabusCoverageService::find_topic(subject, &o.topic_order)?;
+                Ok(LessonObjective {
+                    topic_order: o.topic_order.clone(),
+                    topic_title: topic.title.clone(),
+                    description: o.description.trim().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn validate_activities(activities: &[LessonActivity]) -> Result<(), AppError> {
+        for activity in activities {
+            if activity.title.trim().is_empty() {
+                return Err(AppError {
+                    message: "Every activity needs a title".into(),
+                });
+            }
+            if let Some(minutes) = activity.duration_minutes {
+                if !(1..=600).contains(&minutes) {
+                    return Err(AppError {
+                        message: "Activity duration_minutes must be between 1 and 600".into(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Linked materials must exist; their title is used unless one is given
+    async fn resources(
+        &self,
+        inputs: &[LessonResourceInput],
+    ) -> Result<Vec<LessonResource>, AppError> {
+        let mut resources = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let title = input
+                .title
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty());
+            let resource = match input.learning_material_id.as_deref() {
+                Some(material_id) => {
+                    let material_id = parse_object_id_value(material_id)?;
+                    let material = self
+                        .db
+                        .collection::<LearningMaterial>("learning_materials")
+                        .find_one(doc! { "_id": material_id, "deleted_at": null })
+                        .await?
+                        .ok_or(AppError {
+                            message: "Learning material not found".into(),
+                        })?;
+                    LessonResource {
+                        learning_material_id: Some(material_id),
+                        title: title.map(str::to_string).unwrap_or(material.title),
+                        note: input.note.clone(),
+                    }
+                }
+                None => LessonResource {
+                    learning_material_id: None,
+                    title: title
+                        .ok_or(AppError {
+                            message: "A resource needs a title or a learning material".into(),
+                        })?
+                        .to_string(),
+                    note: input.note.clone(),
+                },
+            };
+            resources.push(resource);
+        }
+        Ok(resources)
+    }
+
+    /// Education year and term of the lesson date, and a check that the
+    /// period is on the class timetable for that day
+    async fn schedule(
+        &self,
+        subject: &ClassSubject,
+        lesson_date: DateTime<Utc>,
+        period_id: Option<ObjectId>,
+    ) -> Result<(Option<ObjectId>, Option<i32>), AppError> {
+        let (year, term) = EducationYearService::new(&self.main_db)
+            .get_current_year_and_term(Some(lesson_date))
+            .await?;
+        let term_order = term.as_ref().map(|t| t.order);
+
+        if let Some(period_id) = period_id {
+            self.coverage()
+                .period_duration(subject, year.id, term_order, period_id, lesson_date)
+                .await?;
+        }
+        Ok((year.id, term_order))
+    }
+
+    /// New draft plan for a lesson of a class subject
+    pub async fn create(
+        &self,
+        school_id: Option<ObjectId>,
+        dto: CreateLessonPlan,
+        created_by: ObjectId,
+    ) -> Result<LessonPlan, AppError> {
+        self.ensure_indexes().await?;
+
+        if dto.title.trim().is_empty() {
+            return Err(AppError {
+                message: "Lesson plan title is required".into(),
+            });
+        }
+        let class_subject_id = parse_object_id_value(&dto.class_subject_id)?;
+        let subject = self.coverage().find_subject(class_subject_id).await?;
+        let objectives = Self::objectives(&subject, &dto.objectives)?;
+        Self::validate_activities(&dto.activities)?;
+        let resources = self.resources(&dto.resources).await?;
+
+        let period_id = dto
+            .period_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+        let (education_year_id, term_order) =
+            self.schedule(&subject, dto.lesson_date, period_id).await?;
+
+        let now = Utc::now();
+        let plan = LessonPlan {
+            id: None,
+            school_id: school_id.or(subject.school_id),
+            class_id: subject.class_id,
+            class_subject_id,
+            teacher_id: subject.teacher_id,
+            education_year_id,
+            term_order,
+            lesson_date: dto.lesson_date,
+            period_id,
+            title: dto.title.trim().to_string(),
+            objectives,
+            activities: dto.activities,
+            resources,
+            assessment_method: dto.assessment_method,
+            status: LessonPlanStatus::Draft,
+            history: Vec::new(),
+            copied_from_id: None,
+            created_by,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.create::<LessonPlan>(to_stored_document(&plan)?, None)
+            .await
+    }
+
+    fn ensure_draft(plan: &LessonPlan) -> Result<(), AppError> {
+        if plan.status != LessonPlanStatus::Draft {
+            return Err(AppError {
+                message: format!(
+                    "Lesson plan is {:?}; only drafts can be changed",
+                    plan.status
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    pub async fn update(&self, id: &IdType, dto: UpdateLessonPlan) -> Result<LessonPlan, AppError> {
+        let plan = self.find_one(id).await?;
+        Self::ensure_draft(&plan)?;
+        let subject = self.coverage().find_subject(plan.class_subject_id).await?;
+
+        let mut set = doc! { "updated_at": bson::to_bson(&Utc::now()).unwrap() };
+        if let Some(title) = dto.title {
+            if title.trim().is_empty() {
+                return Err(AppError {
+                    message: "Lesson plan title is required".into(),
+                });
+            }
+            set.insert("title", title.trim());
+        }
+        if let Some(objectives) = dto.objectives {
+            let objectives = Self::objectives(&subject, &objectives)?;
+            set.insert("objectives", bson::to_bson(&objectives).unwrap());
+        }
+        if let Some(activities) = dto.activities {
+            Self::validate_activities(&activities)?;
+            set.insert("activities", bson::to_bson(&activities).unwrap());
+        }
+        if let Some(resources) = dto.resources {
+            let resources = self.resources(&resources).await?;
+            let resources = resources
+                .iter()
+                .map(to_stored_document)
+                .collect::<Result<Vec<_>, _>>()?;
+            set.insert("resources", resources);
+        }
+        if let Some(method) = dto.assessment_method {
+            set.insert("assessment_method", method);
+        }
+
+        if dto.lesson_date.is_some() || dto.period_id.is_some() {
+            let lesson_date = dto.lesson_date.unwrap_or(plan.lesson_date);
+            let period_id = match dto.period_id.as_deref() {
+                Some(period_id) => Some(parse_object_id_value(period_id)?),
+                None => plan.period_id,
+            };
+            let (education_year_id, term_order) =
+                self.schedule(&subject, lesson_date, period_id).await?;
+            set.insert("lesson_date", bson::to_bson(&lesson_date).unwrap());
+            set.insert("period_id", period_id);
+            set.insert("education_year_id", education_year_id);
+            set.insert("term_order", term_order);
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<LessonPlan>(id, set).await
+    }
+
+    /// Only drafts can be removed; submitted and approved plans are records
+    pub async fn delete(&self, id: &IdType) -> Result<LessonPlan, AppError> {
+        let plan = self.find_one(id).await?;
+        Self::ensure_draft(&plan)?;
+        self.collection
+            .delete_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?;
+        Ok(plan)
+    }
+
+    // =========================
+    // APPROVAL
+    // =========================
+
+    /// Move a plan between states atomically, recording who did it
+    async fn transition(
+        &self,
+        plan_id: ObjectId,
+        from: &[LessonPlanStatus],
+        to: LessonPlanStatus,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<LessonPlan, AppError> {
+        let current = self.find_one(&IdType::ObjectId(plan_id)).await?;
+        let entry = LessonPlanTransition {
+            from: current.status,
+            to,
+            by: actor.clone(),
+            note,
+            at: Utc::now(),
+        };
+        transition_status(&self.collection, plan_id, from, entry, "Lesson plan").await
+    }
+
+    /// Teacher hands the plan to the head of studies
+    pub async fn submit(
+        &self,
+        plan_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<LessonPlan, AppError> {
+        let plan = self.find_one(&IdType::ObjectId(plan_id)).await?;
+        if plan.objectives.is_empty() {
+            return Err(AppError {
+                message: "Add at least one objective before submitting".into(),
+            });
+        }
+
+        self.transition(
+            plan_id,
+            &[LessonPlanStatus::Draft],
+            LessonPlanStatus::Submitted,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    /// Head of studies signs the plan off; its author and the subject's
+    /// teacher cannot approve it themselves
+    pub async fn approve(
+        &self,
+        plan_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<LessonPlan, AppError> {
+        let plan = self.find_one(&IdType::ObjectId(plan_id)).await?;
+        let is_teacher = match plan.teacher_id {
+            Some(teacher_id) => self
+                .db
+                .collection::<Document>("teachers")
+                .count_documents(doc! { "_id": teacher_id, "user_id": actor.id })
+                .await?
+                > 0,
+            None => false,
+        };
+        if plan.created_by == actor.id || is_teacher {
+            return Err(AppError {
+                message: "You cannot approve your own lesson plan".into(),
+            });
+        }
+
+        self.transition(
+            plan_id,
+            &[LessonPlanStatus::Submitted],
+            LessonPlanStatus::Approved,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    /// Send a plan back to the teacher for changes
+    pub async fn return_to_draft(
+        &self,
+        plan_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<LessonPlan, AppError> {
+        if note
+            .as_deref()
+            .map(str::trim)
+            .unwrap_or_default()
+            .is_empty()
+        {
+            return Err(AppError {
+                message: "A note is required when returning a lesson plan".into(),
+            });
+        }
+
+        self.transition(
+            plan_id,
+            &[LessonPlanStatus::Submitted, LessonPlanStatus::Approved],
+            LessonPlanStatus::Draft,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    // =========================
+    // COPYING
+    // =========================
+
+    /// Same subject in another class: same template subject when known,
+    /// otherwise the same subject code
+    async fn matching_subject(
+        &self,
+        source: &ClassSubject,
+        class_id: ObjectId,
+    ) -> Result<ClassSubject, AppError> {
+        let mut filter = doc! { "class_id": class_id };
+        match source.main_subject_id {
+            Some(main_subject_id) => filter.insert("main_subject_id", main_subject_id),
+            None => filter.insert("code", &source.code),
+        };
+        self.db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(filter)
+            .await?
+            .ok_or(AppError {
+                message: format!("Class {} does not take {}", class_id.to_hex(), source.name),
+            })
+    }
+
+    /// Copy a plan as new drafts for the same subject in other classes of
+    /// the same `MainClass`. Every class is checked first and the copies
+    /// are written in one batch, so a class that does not fit copies nothing.
+    pub async fn copy(
+        &self,
+        id: &IdType,
+        dto: CopyLessonPlan,
+        created_by: ObjectId,
+    ) -> Result<Vec<LessonPlan>, AppError> {
+        let plan = self.find_one(id).await?;
+        let source_subject = self.coverage().find_subject(plan.class_subject_id).await?;
+
+        let classes = self.db.collection::<Class>("classes");
+        let source_class = classes
+            .find_one(doc! { "_id": plan.class_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class of the lesson plan not found".into(),
+            })?;
+        let main_class_id = source_class.main_class_id.ok_or(AppError {
+            message: "The plan's class is not part of a main class".into(),
+        })?;
+
+        let class_ids: Vec<ObjectId> = dto
+            .class_ids
+            .iter()
+            .map(|id| parse_object_id_value(id))
+            .collect::<Result<_, _>>()?;
+        let distinct: HashSet<&ObjectId> = class_ids.iter().collect();
+        if class_ids.is_empty() || distinct.len() != class_ids.len() {
+            return Err(AppError {
+                message: "Give each class to copy to once".into(),
+            });
+        }
+
+        let lesson_date = dto.lesson_date.unwrap_or(plan.lesson_date);
+        let objective_inputs: Vec<LessonObjectiveInput> = plan
+            .objectives
+            .iter()
+            .map(|o| LessonObjectiveInput {
+                topic_order: o.topic_order.clone(),
+                description: o.description.clone(),
+            })
+            .collect();
+
+        let now = Utc::now();
+        let mut copies = Vec::with_capacity(class_ids.len());
+        for class_id in class_ids {
+            if Some(class_id) == source_class.id {
+                return Err(AppError {
+                    message: "A plan cannot be copied to its own class".into(),
+                });
+            }
+            let class = classes
+                .find_one(doc! { "_id": class_id })
+                .await?
+                .ok_or(AppError {
+                    message: "Class not found".into(),
+                })?;
+            if class.main_class_id != Some(main_class_id) {
+                return Err(AppError {
+                    message: format!("{} is not in the same main class", class.name),
+                });
+            }
+
+            let subject = self.matching_subject(&source_subject, class_id).await?;
+            let objectives = Self::objectives(&subject, &objective_inputs)?;
+            // Periods differ between classes, so the copy is not timetabled
+            let (education_year_id, term_order) =
+                self.schedule(&subject, lesson_date, None).await?;
+
+            copies.push(LessonPlan {
+                id: None,
+                school_id: plan.school_id,
+                class_id: Some(class_id),
+                class_subject_id: subject.id.unwrap_or_default(),
+                teacher_id: subject.teacher_id,
+                education_year_id,
+                term_order,
+                lesson_date,
+                period_id: None,
+                title: plan.title.clone(),
+                objectives,
+                activities: plan.activities.clone(),
+                resources: plan.resources.clone(),
+                assessment_method: plan.assessment_method.clone(),
+                status: LessonPlanStatus::Draft,
+                history: Vec::new(),
+                copied_from_id: plan.id,
+                created_by,
+                created_at: Some(now),
+                updated_at: Some(now),
+            });
+        }
+
+        let docs = copies
+            .iter()
+            .map(to_stored_document)
+            .collect::<Result<Vec<_>, _>>()?;
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let mut created = repo.create_many::<LessonPlan>(docs, None).await?;
+        created.sort_by_key(|c| copies.iter().position(|p| p.class_id == c.class_id));
+        Ok(created)
+    }
+
+    // =========================
+    // SCHEMES OF WORK
+    // =========================
+
+    /// Spread the subject's top-level topics, in order, over its timetable
+    /// slots for the term. Each topic takes lessons until its planned hours
+    /// are used; a topic with no hours still gets one lesson.
+    pub async fn scheme_of_work(
+        &self,
+        class_subject_id: ObjectId,
+        education_year_id: Option<&IdType>,
+        term_order: Option<i32>,
+    ) -> Result<SchemeOfWork, AppError> {
+        let coverage = self.coverage();
+        let subject = coverage.find_subject(class_subject_id).await?;
+        let class_id = subject.class_id.ok_or(AppError {
+            message: "Subject is not assigned to a class".into(),
+        })?;
+
+        let (year, current_term) = coverage.resolve_year(education_year_id).await?;
+        let term = match term_order {
+            Some(order) => year.terms.iter().find(|t| t.order == order).cloned(),
+            None => current_term,
+        }
+        .ok_or(AppError {
+            message: "Term not found; pass term_order outside term time".into(),
+        })?;
+
+        let timetable = self
+            .db
+            .collection::<ClassTimetable>("class_timetables")
+            .find_one(doc! {
+                // Timetables are stored with hex ids
+                "class_id": id_match(&class_id),
+                "education_year_id": year.id.as_ref().map(id_match),
+                "term_order": term.order
+            })
+            .await?
+            .ok_or(AppError {
+                message: "Class timetable not found for this term".into(),
+            })?;
+
+        // (weekday, start time, period, minutes) of each weekly slot
+        let mut slots = Vec::new();
+        for day in timetable.weekly_schedule.iter().filter(|d| !d.is_holiday) {
+            let day_start = day
+                .start_on
+                .as_deref()
+                .and_then(|s| NaiveTime::parse_from_str(s, "%H:%M").ok())
+                .unwrap_or_default();
+            for period in &day.periods {
+                if period.subject_id != subject.id || period.enabled == Some(false) {
+                    continue;
+                }
+                let start = day_start + Duration::minutes(period.start_offset as i64);
+                slots.push((day.day, start, period.period_id, period.duration_minutes));
+            }
+        }
+        if slots.is_empty() {
+            return Err(AppError {
+                message: "The subject has no periods on the class timetable".into(),
+            });
+        }
+        slots.sort_by_key(|(_, start, _, _)| *start);
+        let weekly_minutes: i32 = slots.iter().map(|(_, _, _, minutes)| minutes).sum();
+
+        let topics = subject.topics.as_deref().unwrap_or_default();
+        let planned = planned_topic_hours(&subject);
+        let mut remaining: Vec<f64> = planned.iter().map(|h| h * 60.0).collect();
+        let mut current = 0;
+
+        let first_monday = term.start_date.date_naive()
+            - Duration::days(term.start_date.weekday().num_days_from_monday() as i64);
+        let mut weeks: Vec<SchemeWeek> = Vec::new();
+        let mut total_minutes = 0;
+        let mut date = term.start_date.date_naive();
+        while date <= term.end_date.date_naive() {
+            for (_, start, period_id, minutes) in
+                slots.iter().filter(|(day, _, _, _)| *day == date.weekday())
+            {
+                total_minutes += minutes;
+                let topic = topics.get(current);
+                if topic.is_some() {
+                    remaining[current] -= *minutes as f64;
+                    if remaining[current] <= 0.0 {
+                        current += 1;
+                    }
+                }
+
+                let week = ((date - first_monday).num_days() / 7) as i32 + 1;
+                if weeks.last().map(|w| w.week) != Some(week) {
+                    let monday = first_monday + Duration::days((week as i64 - 1) * 7);
+                    weeks.push(SchemeWeek {
+                        week,
+                        start_date: monday
+                            .max(term.start_date.date_naive())
+                            .and_time(NaiveTime::MIN)
+                            .and_utc(),
+                        lessons: Vec::new(),
+                    });
+                }
+                if let Some(week) = weeks.last_mut() {
+                    week.lessons.push(SchemeLesson {
+                        date: date.and_time(*start).and_utc(),
+                        period_id: *period_id,
+                        duration_minutes: *minutes,
+                        topic_order: topic.map(|t| t.order.clone()),
+                        topic_title: topic.map(|t| t.title.clone()),
+                    });
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        // Topics before `current` got all their time
+        let unscheduled_topics = topics
+            .iter()
+            .skip(current)
+            .map(|t| t.order.clone())
+            .collect();
+
+        Ok(SchemeOfWork {
+            class_subject_id,
+            subject_name: subject.name.clone(),
+            education_year_id: year.id,
+            term_name: term.name.clone(),
+            term_order: term.order,
+            start_date: term.start_date,
+            end_date: term.end_date,
+            weekly_minutes,
+            available_hours: round2(total_minutes as f64 / 60.0),
+            planned_hours: round2(planned.iter().sum()),
+            weeks,
+            unscheduled_topics,
+        })
+    }
+}
+
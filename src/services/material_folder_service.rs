@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::learning_material::{
+        LearningMaterial, MaterialFolder, MaterialFolderPartial, MaterialFolderTree,
+        SubjectMaterialTree,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    utils::mongo_utils::extract_valid_fields,
+};
+
+pub struct MaterialFolderService {
+    pub collection: Collection<MaterialFolder>,
+    pub material_collection: Collection<LearningMaterial>,
+}
+
+impl MaterialFolderService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<MaterialFolder>("material_folders"),
+            material_collection: db.collection::<LearningMaterial>("learning_materials"),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(
+                vec![("subject_id", 1), ("parent_id", 1), ("position", 1)],
+                false,
+            ),
+            IndexDef::single("school_id", false),
+        ];
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<MaterialFolder, AppError> {
+        self.collection
+            .find_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?
+            .ok_or(AppError {
+                message: "Folder not found".into(),
+            })
+    }
+
+    /// A parent must be in the same subject and must not be the folder
+    /// itself or one of its descendants
+    async fn validate_parent(
+        &self,
+        parent_id: ObjectId,
+        folder_id: Option<ObjectId>,
+        subject_id: Option<ObjectId>,
+    ) -> Result<(), AppError> {
+        let mut next = Some(parent_id);
+        while let Some(id) = next {
+            if Some(id) == folder_id {
+                return Err(AppError {
+                    message: "A folder cannot be moved inside itself".into(),
+                });
+            }
+            let folder = self.find_one(&IdType::ObjectId(id)).await?;
+            if folder.subject_id != subject_id {
+                return Err(AppError {
+                    message: "Parent folder belongs to another subject".into(),
+                });
+            }
+            next = folder.parent_id;
+        }
+        Ok(())
+    }
+
+    pub async fn create(&self, dto: MaterialFolder) -> Result<MaterialFolder, AppError> {
+        self.ensure_indexes().await?;
+
+        if dto.name.trim().is_empty() {
+            return Err(AppError {
+                message: "Folder name is required".into(),
+            });
+        }
+        let subject_id = dto.subject_id.ok_or(AppError {
+            message: "Subject ID is required".into(),
+        })?;
+        if let Some(parent_id) = dto.parent_id {
+            self.validate_parent(parent_id, None, Some(subject_id))
+                .await?;
+        }
+
+        // New folders go last among their siblings
+        let siblings = self
+            .collection
+            .count_documents(doc! { "subject_id": subject_id, "parent_id": dto.parent_id })
+            .await?;
+        let folder = MaterialFolder {
+            name: dto.name.trim().to_string(),
+            position: siblings as i32,
+            ..dto
+        };
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .create::<MaterialFolder>(extract_valid_fields(folder.to_document()?), None)
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        id: &IdType,
+        update: &MaterialFolderPartial,
+    ) -> Result<MaterialFolder, AppError> {
+        let existing = self.find_one(id).await?;
+        let mut update_data = update.clone();
+        // Folders do not move between subjects
+        update_data.subject_id = None;
+        update_data.school_id = None;
+
+        if let Some(name) = &update.name {
+            if name.trim().is_empty() {
+                return Err(AppError {
+                    message: "Folder name cannot be empty".into(),
+                });
+            }
+        }
+        if let Some(Some(parent_id)) = update.parent_id {
+            self.validate_parent(parent_id, existing.id, existing.subject_id)
+                .await?;
+        }
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .update_one_and_fetch::<MaterialFolder>(
+                id,
+                extract_valid_fields(MaterialFolder::from_partial(update_data)?),
+            )
+            .await
+    }
+
+    /// Remove a folder; its materials and subfolders move up to its parent
+    pub async fn delete(&self, id: &IdType) -> Result<MaterialFolder, AppError> {
+        let folder = self.find_one(id).await?;
+
+        self.material_collection
+            .update_many(
+                doc! { "folder_id": folder.id },
+                doc! { "$set": { "folder_id": folder.parent_id } },
+            )
+            .await?;
+        self.collection
+            .update_many(
+                doc! { "parent_id": folder.id },
+                doc! { "$set": { "parent_id": folder.parent_id } },
+            )
+            .await?;
+        self.collection
+            .delete_one(doc! { "_id": folder.id })
+            .await?;
+
+        Ok(folder)
+    }
+
+    fn parse_ids(ids: &[String]) -> Result<Vec<ObjectId>, AppError> {
+        ids.iter()
+            .map(|id| IdType::from_string(id.clone()).to_object_id())
+            .collect()
+    }
+
+    /// Put sibling folders in the given order
+    pub async fn reorder_folders(&self, ids: &[String]) -> Result<Vec<MaterialFolder>, AppError> {
+        let ids = Self::parse_ids(ids)?;
+        let folders: Vec<MaterialFolder> = self
+            .collection
+            .find(doc! { "_id": { "$in": &ids } })
+            .await?
+            .try_collect()
+            .await?;
+        if folders.len() != ids.len() {
+            return Err(AppError {
+                message: "Some folders were not found".into(),
+            });
+        }
+        if folders
+            .iter()
+            .any(|f| f.subject_id != folders[0].subject_id || f.parent_id != folders[0].parent_id)
+        {
+            return Err(AppError {
+                message: "Only folders side by side in the same subject can be reordered together"
+                    .into(),
+            });
+        }
+
+        for (position, id) in ids.iter().enumerate() {
+            self.collection
+                .update_one(
+                    doc! { "_id": id },
+                    doc! { "$set": { "position": position as i32 } },
+                )
+                .await?;
+        }
+
+        let mut reordered: Vec<MaterialFolder> = self
+            .collection
+            .find(doc! { "_id": { "$in": &ids } })
+            .await?
+            .try_collect()
+            .await?;
+        reordered.sort_by_key(|f| f.position);
+        Ok(reordered)
+    }
+
+    /// Put the materials of one folder, or of no folder, in the given order
+    pub async fn reorder_materials(
+        &self,
+        folder_id: Option<ObjectId>,
+        ids: &[String],
+    ) -> Result<Vec<LearningMaterial>, AppError> {
+        let ids = Self::parse_ids(ids)?;
+        let found = self
+            .material_collection
+            .count_documents(doc! { "_id": { "$in": &ids }, "folder_id": folder_id })
+            .await?;
+        if found as usize != ids.len() {
+            return Err(AppError {
+                message: "Materials must all be in this folder".into(),
+            });
+        }
+
+        for (position, id) in ids.iter().enumerate() {
+            self.material_collection
+                .update_one(
+                    doc! { "_id": id },
+                    doc! { "$set": { "position": position as i32 } },
+                )
+                .await?;
+        }
+
+        let mut reordered: Vec<LearningMaterial> = self
+            .material_collection
+            .find(doc! { "_id": { "$in": &ids } })
+            .await?
+            .try_collect()
+            .await?;
+        reordered.sort_by_key(|m| m.position);
+        Ok(reordered)
+    }
+
+    /// Folders of a subject with their materials, in order. `visible`
+    /// narrows the materials, e.g. to what a student may see.
+    pub async fn tree(
+        &self,
+        subject_id: ObjectId,
+        visible: Option<Document>,
+    ) -> Result<SubjectMaterialTree, AppError> {
+        let folders: Vec<MaterialFolder> = self
+            .collection
+            .find(doc! { "subject_id": subject_id })
+            .sort(doc! { "position": 1, "created_at": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut material_filter = doc! { "subject_id": subject_id, "deleted_at": null };
+        if let Some(visible) = visible {
+            material_filter.extend(visible);
+        }
+        let materials: Vec<LearningMaterial> = self
+            .material_collection
+            .find(material_filter)
+            .sort(doc! { "position": 1, "created_at": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let folder_ids: Vec<Option<ObjectId>> = folders.iter().map(|f| f.id).collect();
+        let mut materials_by_folder: HashMap<Option<ObjectId>, Vec<LearningMaterial>> =
+            HashMap::new();
+        for material in materials {
+            // Materials pointing at a missing folder show up unfiled
+            let key = material
+                .folder_id
+                .filter(|id| folder_ids.contains(&Some(*id)));
+            materials_by_folder.entry(key).or_default().push(material);
+        }
+
+        let mut children: HashMap<Option<ObjectId>, Vec<MaterialFolder>> = HashMap::new();
+        for folder in folders {
+            children.entry(folder.parent_id).or_default().push(folder);
+        }
+
+        fn build(
+            parent_id: Option<ObjectId>,
+            children: &mut HashMap<Option<ObjectId>, Vec<MaterialFolder>>,
+            materials: &mut HashMap<Option<ObjectId>, Vec<LearningMaterial>>,
+        ) -> Vec<MaterialFolderTree> {
+            children
+                .remove(&parent_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|folder| MaterialFolderTree {
+                    materials: materials.remove(&folder.id).unwrap_or_default(),
+                    folders: build(folder.id, children, materials),
+                    folder,
+                })
+                .collect()
+        }
+
+        let folders = build(None, &mut children, &mut materials_by_folder);
+        Ok(SubjectMaterialTree {
+            folders,
+            materials: materials_by_folder.remove(&None).unwrap_or_default(),
+        })
+    }
+}
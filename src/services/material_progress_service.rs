@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class_subject::ClassSubject,
+        learning_material::{
+            LearningEngagementSummary, LearningMaterial, MaterialCompletion, MaterialEngagement,
+            MaterialEngagementReport, MaterialEventType, MaterialType, RecordMaterialEvent,
+            StudentMaterialEngagement, SubjectMaterialProgress,
+        },
+        student::{Student, StudentStatus},
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::learning_material_service::LearningMaterialService,
+};
+
+/// Longest stretch of video one event can add; clients report watch time
+/// every few minutes, so anything above this is a stale or broken player
+const MAX_EVENT_SECONDS: i64 = 60 * 60;
+
+pub struct MaterialProgressService {
+    pub collection: Collection<MaterialEngagement>,
+    pub db: Database,
+}
+
+impl MaterialProgressService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<MaterialEngagement>("material_engagements"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("material_id", 1), ("student_id", 1)], true),
+            IndexDef::compound(vec![("student_id", 1), ("subject_id", 1)], false),
+        ];
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    /// Count a view, download or completion by a student. Only materials the
+    /// student can currently see are tracked.
+    pub async fn record_event(
+        &self,
+        id: &IdType,
+        student_id: ObjectId,
+        event: RecordMaterialEvent,
+    ) -> Result<MaterialEngagement, AppError> {
+        self.ensure_indexes().await?;
+        let materials = LearningMaterialService::new(&self.db);
+        let material = materials
+            .find_one(Some(id), Some(doc! { "deleted_at": null }))
+            .await?;
+        let material_id = material.id.ok_or(AppError {
+            message: "Learning material not found".into(),
+        })?;
+        material
+            .is_available(&materials.completed_ids(student_id).await?)
+            .map_err(|message| AppError { message })?;
+
+        let seconds = event.seconds.unwrap_or(0);
+        if seconds < 0 {
+            return Err(AppError {
+                message: "Watched seconds cannot be negative".into(),
+            });
+        }
+        let video_seconds = if material.material_type == MaterialType::Video {
+            seconds.min(MAX_EVENT_SECONDS)
+        } else {
+            0
+        };
+
+        let mut inc = doc! { "video_seconds": video_seconds };
+        match event.event_type {
+            MaterialEventType::View => {
+                inc.insert("view_count", 1_i64);
+            }
+            MaterialEventType::Download => {
+                inc.insert("download_count", 1_i64);
+            }
+            MaterialEventType::Complete => {
+                materials.mark_complete(id, student_id).await?;
+            }
+        }
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        self.collection
+            .find_one_and_update(
+                doc! { "material_id": material_id, "student_id": student_id },
+                doc! {
+                    "$inc": inc,
+                    "$set": { "last_opened_at": now.clone(), "subject_id": material.subject_id },
+                    "$setOnInsert": { "first_opened_at": now },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Failed to record activity".into(),
+            })
+    }
+
+    /// Class the material is for, directly or through its class subject
+    async fn material_class_id(
+        &self,
+        material: &LearningMaterial,
+    ) -> Result<Option<ObjectId>, AppError> {
+        if material.class_id.is_some() {
+            return Ok(material.class_id);
+        }
+        let Some(subject_id) = material.subject_id else {
+            return Ok(None);
+        };
+        Ok(self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(doc! { "_id": subject_id })
+            .await?
+            .and_then(|s| s.class_id))
+    }
+
+    /// Active students of the class in the material's audience, split into
+    /// those who opened or completed it and those who have not yet
+    pub async fn material_report(&self, id: &IdType) -> Result<MaterialEngagementReport, AppError> {
+        let materials = LearningMaterialService::new(&self.db);
+        let material = materials
+            .find_one(Some(id), Some(doc! { "deleted_at": null }))
+            .await?;
+        let material_id = material.id.ok_or(AppError {
+            message: "Learning material not found".into(),
+        })?;
+        let class_id = self.material_class_id(&material).await?.ok_or(AppError {
+            message: "This material is not linked to a class".into(),
+        })?;
+
+        let students: Vec<Student> = self
+            .db
+            .collection::<Student>("students")
+            .find(doc! { "class_id": class_id })
+            .sort(doc! { "name": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut engagements: HashMap<ObjectId, MaterialEngagement> = self
+            .collection
+            .find(doc! { "material_id": material_id })
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .map(|e| (e.student_id, e))
+            .collect();
+        let completions: HashMap<ObjectId, DateTime<Utc>> = materials
+            .completion_collection
+            .find(doc! { "material_id": material_id })
+            .await?
+            .try_collect::<Vec<MaterialCompletion>>()
+            .await?
+            .into_iter()
+            .map(|c| (c.student_id, c.completed_at))
+            .collect();
+
+        let mut engaged = Vec::new();
+        let mut not_engaged = Vec::new();
+        let mut total_students = 0;
+        for student in students {
+            if student.status != StudentStatus::Active {
+                continue;
+            }
+            total_students += 1;
+            let Some(student_id) = student.id else {
+                continue;
+            };
+            let engagement = engagements.remove(&student_id);
+            let completed_at = completions.get(&student_id).copied();
+            if engagement.is_none() && completed_at.is_none() {
+                not_engaged.push(student);
+            } else {
+                engaged.push(StudentMaterialEngagement {
+                    student,
+                    engagement,
+                    completed_at,
+                });
+            }
+        }
+
+        Ok(MaterialEngagementReport {
+            material_id,
+            title: material.title,
+            total_students,
+            completed_count: engaged.iter().filter(|e| e.completed_at.is_some()).count() as i64,
+            engaged,
+            not_engaged,
+        })
+    }
+
+    /// Share of released materials the student has completed, per subject.
+    /// Covers materials of the student's class and of its class subjects.
+    pub async fn student_progress(
+        &self,
+        student_id: ObjectId,
+    ) -> Result<Vec<SubjectMaterialProgress>, AppError> {
+        let student = self
+            .db
+            .collection::<Student>("students")
+            .find_one(doc! { "_id": student_id })
+            .await?
+            .ok_or(AppError {
+                message: "Student not found".into(),
+            })?;
+        let class_id = student.class_id.ok_or(AppError {
+            message: "Student is not in a class".into(),
+        })?;
+
+        let subjects: Vec<ClassSubject> = self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find(doc! { "class_id": class_id })
+            .sort(doc! { "name": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        let subject_ids: Vec<ObjectId> = subjects.iter().filter_map(|s| s.id).collect();
+
+        let mut filter = doc! {
+            "deleted_at": null,
+            "$or": [{ "class_id": class_id }, { "subject_id": { "$in": &subject_ids } }],
+        };
+        filter.extend(LearningMaterialService::visible_filter(None));
+        let materials: Vec<LearningMaterial> = LearningMaterialService::new(&self.db)
+            .collection
+            .find(filter)
+            .await?
+            .try_collect()
+            .await?;
+
+        let engagements: Vec<MaterialEngagement> = self
+            .collection
+            .find(doc! { "student_id": student_id })
+            .await?
+            .try_collect()
+            .await?;
+        let completed: HashSet<ObjectId> = LearningMaterialService::new(&self.db)
+            .completed_ids(student_id)
+            .await?
+            .into_iter()
+            .collect();
+
+        let opened: HashSet<ObjectId> = engagements.iter().map(|e| e.material_id).collect();
+
+        // (total, opened, completed) per subject
+        let mut counts: HashMap<Option<ObjectId>, (i64, i64, i64)> = HashMap::new();
+        for material in &materials {
+            let Some(material_id) = material.id else {
+                continue;
+            };
+            let entry = counts.entry(material.subject_id).or_default();
+            entry.0 += 1;
+            if opened.contains(&material_id) || completed.contains(&material_id) {
+                entry.1 += 1;
+            }
+            if completed.contains(&material_id) {
+                entry.2 += 1;
+            }
+        }
+
+        let progress = |subject_id: Option<ObjectId>,
+                        subject_name: Option<String>,
+                        (total, opened, completed): (i64, i64, i64)| {
+            SubjectMaterialProgress {
+                subject_id,
+                subject_name,
+                total_materials: total,
+                opened_materials: opened,
+                completed_materials: completed,
+                progress_percentage: if total > 0 {
+                    (completed as f64 / total as f64 * 10000.0).round() / 100.0
+                } else {
+                    0.0
+                },
+            }
+        };
+
+        let mut result: Vec<SubjectMaterialProgress> = subjects
+            .into_iter()
+            .map(|subject| {
+                let subject_counts = counts.remove(&subject.id).unwrap_or_default();
+                progress(subject.id, Some(subject.name), subject_counts)
+            })
+            .collect();
+        // Class-wide materials, or ones whose subject has since been removed
+        let other = counts
+            .into_values()
+            .fold((0, 0, 0), |acc, c| (acc.0 + c.0, acc.1 + c.1, acc.2 + c.2));
+        if other.0 > 0 {
+            result.push(progress(None, None, other));
+        }
+        Ok(result)
+    }
+
+    /// Totals across subjects for the parent dashboard
+    pub async fn summary(
+        &self,
+        student_id: ObjectId,
+    ) -> Result<LearningEngagementSummary, AppError> {
+        let subjects = self.student_progress(student_id).await?;
+        let engagements: Vec<MaterialEngagement> = self
+            .collection
+            .find(doc! { "student_id": student_id })
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(LearningEngagementSummary {
+            total_materials: subjects.iter().map(|s| s.total_materials).sum(),
+            opened_materials: subjects.iter().map(|s| s.opened_materials).sum(),
+            completed_materials: subjects.iter().map(|s| s.completed_materials).sum(),
+            video_minutes: engagements.iter().map(|e| e.video_seconds).sum::<i64>() / 60,
+            last_active_at: engagements.iter().map(|e| e.last_opened_at).max(),
+            subjects,
+        })
+    }
+}
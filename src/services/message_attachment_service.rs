@@ -0,0 +1,605 @@
+use chrono::{Duration, Utc};
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, spec::BinarySubtype, Binary, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use base64::Engine as _;
+
+use crate::{
+    domain::message_attachment::{
+        AttachmentLimits, AttachmentStatus, MessageAttachment, UpdateAttachmentLimits,
+        DEFAULT_CHUNK_BYTES, MAX_CHUNK_BYTES,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    schema::common_schema::ActorRef,
+    services::cloudinary_service::CloudinaryService,
+};
+
+/// Cloudinary raw uploads are capped at 50MB by `CloudinaryService::upload_file`
+const MAX_ALLOWED_FILE_BYTES: i64 = 50 * 1024 * 1024;
+/// Thumbnails go through the image endpoint which accepts up to 5MB
+const MAX_THUMBNAIL_SOURCE_BYTES: i64 = 5 * 1024 * 1024;
+const STALE_UPLOAD_HOURS: i64 = 24;
+const ATTACHMENT_FOLDER: &str = "messaging/attachments";
+
+pub struct MessageAttachmentService {
+    pub collection: Collection<MessageAttachment>,
+    pub chunk_collection: Collection<Document>,
+    pub limits_collection: Collection<AttachmentLimits>,
+    pub db: Database,
+}
+
+impl MessageAttachmentService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<MessageAttachment>("message_attachments"),
+            chunk_collection: db.collection::<Document>("message_attachment_chunks"),
+            limits_collection: db.collection::<AttachmentLimits>("attachment_limits"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let attachment_indexes = vec![
+            IndexDef::single("conversation_id", false),
+            IndexDef::compound(vec![("status", 1), ("created_at", 1)], false),
+        ];
+        let chunk_indexes = vec![IndexDef::compound(
+            vec![("attachment_id", 1), ("index", 1)],
+            true,
+        )];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&attachment_indexes)
+            .await?;
+        BaseRepository::new(self.chunk_collection.clone())
+            .ensure_indexes(&chunk_indexes)
+            .await?;
+
+        Ok(())
+    }
+
+    // =========================
+    // LIMITS
+    // =========================
+
+    /// Conversations outside a school use the default limits
+    pub async fn get_limits(&self, school_id: Option<ObjectId>) -> Result<AttachmentLimits, AppError> {
+        let Some(school_id) = school_id else {
+            return Ok(AttachmentLimits::new(ObjectId::from_bytes([0; 12])));
+        };
+
+        let limits = self
+            .limits_collection
+            .find_one(doc! { "_id": school_id })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch attachment limits: {}", e),
+            })?;
+
+        Ok(limits.unwrap_or_else(|| AttachmentLimits::new(school_id)))
+    }
+
+    pub async fn update_limits(
+        &self,
+        school_id: ObjectId,
+        update: &UpdateAttachmentLimits,
+    ) -> Result<AttachmentLimits, AppError> {
+        let mut limits = self.get_limits(Some(school_id)).await?;
+
+        if let Some(max) = update.max_file_size {
+            if !(1..=MAX_ALLOWED_FILE_BYTES).contains(&max) {
+                return Err(AppError {
+                    message: format!(
+                        "max_file_size must be between 1 and {} bytes",
+                        MAX_ALLOWED_FILE_BYTES
+                    ),
+                });
+            }
+            limits.max_file_size = max;
+        }
+
+        if let Some(types) = &update.allowed_mime_types {
+            let types: Vec<String> = types
+                .iter()
+                .map(|t| t.trim().to_ascii_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if types.iter().any(|t| !t.contains('/')) {
+                return Err(AppError {
+                    message: "MIME types must look like 'type/subtype' or 'type/*'".into(),
+                });
+            }
+            limits.allowed_mime_types = types;
+        }
+
+        limits.updated_at = Utc::now();
+
+        self.limits_collection
+            .find_one_and_replace(doc! { "_id": school_id }, &limits)
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to update attachment limits: {}", e),
+            })?
+            .ok_or(AppError {
+                message: "Failed to update attachment limits".into(),
+            })
+    }
+
+    // =========================
+    // UPLOAD PIPELINE
+    // =========================
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start_upload(
+        &self,
+        conversation_id: ObjectId,
+        school_id: Option<ObjectId>,
+        uploaded_by: ActorRef,
+        file_name: &str,
+        mime_type: &str,
+        total_size: i64,
+        chunk_size: Option<i64>,
+        encrypted: bool,
+    ) -> Result<MessageAttachment, AppError> {
+        self.ensure_indexes().await?;
+        self.cleanup_stale_uploads().await?;
+
+        let file_name = file_name.trim();
+        if file_name.is_empty() || file_name.len() > 255 {
+            return Err(AppError {
+                message: "File name must be 1-255 characters".into(),
+            });
+        }
+
+        let limits = self.get_limits(school_id).await?;
+        if total_size <= 0 || total_size > limits.max_file_size {
+            return Err(AppError {
+                message: format!("File size must be between 1 and {} bytes", limits.max_file_size),
+            });
+        }
+        if !limits.allows_mime_type(mime_type) {
+            return Err(AppError {
+                message: format!("File type '{}' is not allowed", mime_type),
+            });
+        }
+
+        let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_BYTES);
+        if !(1..=MAX_CHUNK_BYTES).contains(&chunk_size) {
+            return Err(AppError {
+                message: format!("chunk_size must be between 1 and {} bytes", MAX_CHUNK_BYTES),
+            });
+        }
+
+        let total_chunks = ((total_size + chunk_size - 1) / chunk_size) as i32;
+
+        let attachment = MessageAttachment {
+            id: None,
+            school_id,
+            conversation_id,
+            uploaded_by,
+            file_name: file_name.to_string(),
+            mime_type: mime_type.to_ascii_lowercase(),
+            total_size,
+            chunk_size,
+            total_chunks,
+            received_chunks: vec![],
+            encrypted,
+            status: AttachmentStatus::Uploading,
+            file_url: None,
+            file_public_id: None,
+            thumbnail_url: None,
+            thumbnail_public_id: None,
+            completed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let result = self
+            .collection
+            .insert_one(&attachment)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to start upload: {}", e),
+            })?;
+
+        Ok(MessageAttachment {
+            id: result.inserted_id.as_object_id(),
+            ..attachment
+        })
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<MessageAttachment, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<MessageAttachment>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Attachment not found".into(),
+            })
+    }
+
+    /// Store one chunk. Re-sending a chunk overwrites it so clients can retry.
+    pub async fn put_chunk(
+        &self,
+        attachment: &MessageAttachment,
+        index: i32,
+        data: Vec<u8>,
+    ) -> Result<MessageAttachment, AppError> {
+        let attachment_id = attachment.id.ok_or(AppError {
+            message: "Attachment has no id".into(),
+        })?;
+
+        if attachment.status != AttachmentStatus::Uploading {
+            return Err(AppError {
+                message: "Upload is no longer accepting chunks".into(),
+            });
+        }
+        if index < 0 || index >= attachment.total_chunks {
+            return Err(AppError {
+                message: format!("Chunk index must be between 0 and {}", attachment.total_chunks - 1),
+            });
+        }
+
+        let expected = if index == attachment.total_chunks - 1 {
+            attachment.total_size - attachment.chunk_size * (attachment.total_chunks as i64 - 1)
+        } else {
+            attachment.chunk_size
+        };
+        if data.len() as i64 != expected {
+            return Err(AppError {
+                message: format!("Chunk {} must be {} bytes, got {}", index, expected, data.len()),
+            });
+        }
+
+        self.chunk_collection
+            .update_one(
+                doc! { "attachment_id": attachment_id, "index": index },
+                doc! {
+                    "$set": {
+                        "data": Binary { subtype: BinarySubtype::Generic, bytes: data },
+                        "updated_at": bson::to_bson(&Utc::now()).unwrap(),
+                    }
+                },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to store chunk: {}", e),
+            })?;
+
+        self.collection
+            .find_one_and_update(
+                doc! { "_id": attachment_id },
+                doc! {
+                    "$addToSet": { "received_chunks": index },
+                    "$set": { "updated_at": bson::to_bson(&Utc::now()).unwrap() },
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to record chunk: {}", e),
+            })?
+            .ok_or(AppError {
+                message: "Attachment not found".into(),
+            })
+    }
+
+    /// Assemble the chunks, push the blob to storage and drop the chunks
+    pub async fn complete(&self, attachment: &MessageAttachment) -> Result<MessageAttachment, AppError> {
+        let attachment_id = attachment.id.ok_or(AppError {
+            message: "Attachment has no id".into(),
+        })?;
+
+        if attachment.status == AttachmentStatus::Complete {
+            return Ok(attachment.clone());
+        }
+        if attachment.status != AttachmentStatus::Uploading {
+            return Err(AppError {
+                message: "Upload was cancelled".into(),
+            });
+        }
+        if !attachment.is_fully_received() {
+            let missing: Vec<i32> = (0..attachment.total_chunks)
+                .filter(|i| !attachment.received_chunks.contains(i))
+                .collect();
+            return Err(AppError {
+                message: format!("Missing chunks: {:?}", missing),
+            });
+        }
+
+        let mut cursor = self
+            .chunk_collection
+            .find(doc! { "attachment_id": attachment_id })
+            .sort(doc! { "index": 1 })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to read chunks: {}", e),
+            })?;
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(attachment.total_size as usize);
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate chunks: {}", e),
+        })? {
+            let chunk = cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize chunk: {}", e),
+            })?;
+            let data = chunk.get_binary_generic("data").map_err(|e| AppError {
+                message: format!("Corrupt chunk: {}", e),
+            })?;
+            bytes.extend_from_slice(data);
+        }
+
+        if bytes.len() as i64 != attachment.total_size {
+            return Err(AppError {
+                message: "Assembled file size does not match the declared size".into(),
+            });
+        }
+
+        // Thumbnails need the plaintext, so they only exist for unencrypted images
+        let mut thumbnail_url = None;
+        let mut thumbnail_public_id = None;
+        if !attachment.encrypted
+            && attachment.mime_type.starts_with("image/")
+            && attachment.total_size <= MAX_THUMBNAIL_SOURCE_BYTES
+        {
+            let data_uri = format!(
+                "data:{};base64,{}",
+                attachment.mime_type,
+                base64::engine::general_purpose::STANDARD.encode(&bytes)
+            );
+            match CloudinaryService::upload_to_cloudinary(&data_uri).await {
+                Ok(res) => {
+                    thumbnail_url = Some(res.secure_url.replacen(
+                        "/upload/",
+                        "/upload/c_thumb,w_320,h_320/",
+                        1,
+                    ));
+                    thumbnail_public_id = Some(res.public_id);
+                }
+                Err(e) => eprintln!("⚠️ Thumbnail generation failed: {}", e),
+            }
+        }
+
+        let file_name = format!("{}.bin", attachment_id.to_hex());
+        let uploaded = CloudinaryService::upload_file(bytes, &file_name, ATTACHMENT_FOLDER)
+            .await
+            .map_err(|e| AppError { message: e })?;
+
+        let update = doc! {
+            "status": bson::to_bson(&AttachmentStatus::Complete).unwrap(),
+            "file_url": uploaded.url,
+            "file_public_id": uploaded.public_id,
+            "thumbnail_url": thumbnail_url,
+            "thumbnail_public_id": thumbnail_public_id,
+            "completed_at": bson::to_bson(&Utc::now()).unwrap(),
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let completed = repo
+            .update_one_and_fetch::<MessageAttachment>(&IdType::ObjectId(attachment_id), update)
+            .await?;
+
+        self.delete_chunks(&attachment_id).await?;
+
+        Ok(completed)
+    }
+
+    /// Cancel an upload or delete a completed blob that no message references
+    pub async fn release_if_unreferenced(&self, attachment_id: &ObjectId) -> Result<bool, AppError> {
+        // Soft-deleted messages still reference the blob until they are purged
+        let references = self
+            .db
+            .collection::<Document>("messages")
+            .count_documents(reference_filter(attachment_id))
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to count attachment references: {}", e),
+            })?;
+        if references > 0 {
+            return Ok(false);
+        }
+
+        let attachment = self.find_one(&IdType::ObjectId(*attachment_id)).await?;
+        if attachment.status == AttachmentStatus::Deleted {
+            return Ok(true);
+        }
+
+        if let Some(public_id) = &attachment.file_public_id {
+            CloudinaryService::delete_file(public_id)
+                .await
+                .map_err(|e| AppError { message: e })?;
+        }
+        if let Some(public_id) = &attachment.thumbnail_public_id {
+            if let Err(e) = CloudinaryService::delete_from_cloudinary(public_id).await {
+                eprintln!("⚠️ Failed to delete attachment thumbnail: {}", e);
+            }
+        }
+
+        self.delete_chunks(attachment_id).await?;
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<MessageAttachment>(
+            &IdType::ObjectId(*attachment_id),
+            doc! {
+                "status": bson::to_bson(&AttachmentStatus::Deleted).unwrap(),
+                "file_url": null,
+                "thumbnail_url": null,
+            },
+        )
+        .await?;
+
+        Ok(true)
+    }
+
+    async fn delete_chunks(&self, attachment_id: &ObjectId) -> Result<(), AppError> {
+        self.chunk_collection
+            .delete_many(doc! { "attachment_id": attachment_id })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to delete chunks: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Drop chunks of uploads abandoned for more than a day, and release
+    /// completed blobs that no message picked up within a day.
+    /// Dates are stored as strings, so the cutoff is compared in Rust.
+    async fn cleanup_stale_uploads(&self) -> Result<(), AppError> {
+        let cutoff = Utc::now() - Duration::hours(STALE_UPLOAD_HOURS);
+
+        let mut cursor = self
+            .collection
+            .find(doc! { "status": { "$in": [
+                bson::to_bson(&AttachmentStatus::Uploading).unwrap(),
+                bson::to_bson(&AttachmentStatus::Complete).unwrap()
+            ] } })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch pending uploads: {}", e),
+            })?;
+
+        let mut stale = vec![];
+        let mut unsent = vec![];
+        while cursor.advance().await.map_err(|e| AppError {
+            message: format!("Failed to iterate pending uploads: {}", e),
+        })? {
+            let attachment = cursor.deserialize_current().map_err(|e| AppError {
+                message: format!("Failed to deserialize upload: {}", e),
+            })?;
+            let last_touched = attachment.completed_at.unwrap_or(attachment.updated_at);
+            if last_touched >= cutoff {
+                continue;
+            }
+            if attachment.status == AttachmentStatus::Complete {
+                unsent.extend(attachment.id);
+            } else {
+                stale.extend(attachment.id);
+            }
+        }
+
+        for id in stale {
+            self.delete_chunks(&id).await?;
+            self.collection
+                .update_one(
+                    doc! { "_id": id },
+                    doc! { "$set": { "status": bson::to_bson(&AttachmentStatus::Deleted).unwrap() } },
+                )
+                .await
+                .map_err(|e| AppError {
+                    message: format!("Failed to expire upload: {}", e),
+                })?;
+        }
+
+        // A storage hiccup should not block the upload that triggered this;
+        // the blob is retried on the next sweep
+        for id in unsent {
+            if let Err(e) = self.release_if_unreferenced(&id).await {
+                eprintln!("⚠️ Failed to release unsent attachment: {}", e.message);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Messages pointing at the attachment. Messages stored before ids were kept
+/// as ObjectIds hold a hex string, and those references count too.
+pub(crate) fn reference_filter(attachment_id: &ObjectId) -> Document {
+    doc! { "attachment_id": { "$in": [attachment_id, attachment_id.to_hex()] } }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Bson;
+
+    use super::*;
+    use crate::domain::{
+        common_details::UserRole,
+        message::{Message, MessageType},
+    };
+
+    fn file_message(attachment_id: ObjectId) -> Message {
+        Message {
+            id: None,
+            school_id: None,
+            conversation_id: ObjectId::new(),
+            sender: ActorRef {
+                id: ObjectId::new(),
+                role: UserRole::TEACHER,
+            },
+            encrypted_payload: "ciphertext".into(),
+            nonce: "nonce".into(),
+            key_version: 1,
+            message_type: MessageType::FILE,
+            file_url: None,
+            file_public_id: None,
+            reply_to_message_id: None,
+            attachment_id: Some(attachment_id),
+            read_by: vec![],
+            reactions: vec![],
+            edit_history: vec![],
+            client_message_id: "client-1".into(),
+            edited_at: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn is_referenced(stored: &Document, attachment_id: &ObjectId) -> bool {
+        let filter = reference_filter(attachment_id);
+        let accepted = filter
+            .get_document("attachment_id")
+            .and_then(|f| f.get_array("$in"))
+            .unwrap();
+        stored.get("attachment_id").is_some_and(|v| accepted.contains(v))
+    }
+
+    #[test]
+    fn sent_message_keeps_its_attachment_from_being_released() {
+        let attachment_id = ObjectId::new();
+        let stored = file_message(attachment_id).to_document().unwrap();
+
+        assert_eq!(
+            stored.get("attachment_id"),
+            Some(&Bson::ObjectId(attachment_id))
+        );
+        assert!(is_referenced(&stored, &attachment_id));
+        assert!(!is_referenced(&stored, &ObjectId::new()));
+    }
+
+    #[test]
+    fn message_stored_with_hex_ids_still_references_its_attachment() {
+        let attachment_id = ObjectId::new();
+        let legacy = bson::to_document(&file_message(attachment_id)).unwrap();
+
+        assert_eq!(
+            legacy.get("attachment_id"),
+            Some(&Bson::String(attachment_id.to_hex()))
+        );
+        assert!(is_referenced(&legacy, &attachment_id));
+    }
+
+    #[test]
+    fn stored_message_keeps_ids_and_is_not_soft_deleted() {
+        let message = file_message(ObjectId::new());
+        let stored = message.to_document().unwrap();
+
+        assert_eq!(
+            stored.get("conversation_id"),
+            Some(&Bson::ObjectId(message.conversation_id))
+        );
+        assert_eq!(
+            stored.get_document("sender").unwrap().get("id"),
+            Some(&Bson::ObjectId(message.sender.id))
+        );
+        assert!(!stored.contains_key("deleted_at"));
+    }
+}
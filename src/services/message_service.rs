@@ -1,23 +1,30 @@
 use crate::{
-    domain::message::{Message, MessageWithRelations},
+    domain::message::{
+        Message, MessageEdit, MessageFile, MessageReaction, MessageType, MessageWithRelations,
+    },
     errors::AppError,
     models::{
         id_model::IdType,
         mongo_model::IndexDef,
     },
-    pipeline::message_pipeline::message_pipeline,
+    pipeline::message_pipeline::{message_files_pipeline, message_pipeline},
     repositories::base_repo::BaseRepository,
-    utils::mongo_utils::extract_valid_fields,
+    schema::common_schema::ActorRef,
+    utils::mongo_utils::to_stored_document,
 };
 use chrono::Utc;
 use mongodb::{
     bson::{doc, oid::ObjectId, Document},
+    options::ReturnDocument,
     Collection, Database,
 };
 use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+const MAX_PAYLOAD_SIZE: usize = 100 * 1024;
+const MAX_EMOJI_LEN: usize = 32;
+
 pub struct MessageService {
     pub collection: Collection<Message>,
     recent_message_ids: Arc<RwLock<HashSet<String>>>,
@@ -38,6 +45,7 @@ impl MessageService {
             IndexDef::single("sender.id", false),
             IndexDef::single("client_message_id", true),
             IndexDef::single("deleted_at", false),
+            IndexDef::single("reply_to_message_id", false),
         ];
 
         let repo = BaseRepository::new(
@@ -65,14 +73,23 @@ impl MessageService {
             }
         }
 
-        if dto.encrypted_payload.len() > 100 * 1024 {
+        if dto.encrypted_payload.len() > MAX_PAYLOAD_SIZE {
             return Err(AppError { message: "Encrypted payload too large".to_string() });
         }
 
+        // Replies must point at a live message of the same conversation
+        if let Some(reply_to_id) = dto.reply_to_message_id {
+            self.find_one_in_conversation(reply_to_id, dto.conversation_id)
+                .await
+                .map_err(|_| AppError {
+                    message: "Replied message not found in this conversation".to_string(),
+                })?;
+        }
+
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
 
         let message = repo
-            .create::<Message>(extract_valid_fields(dto.to_document()?), Some(&["client_message_id"]))
+            .create::<Message>(dto.to_document()?, Some(&["client_message_id"]))
             .await?;
 
         Ok(message)
@@ -120,22 +137,26 @@ impl MessageService {
         conversation_id: ObjectId,
         page: i64,
         limit: i64,
-    ) -> Result<(Vec<Message>, i64), AppError> {
+    ) -> Result<(Vec<MessageFile>, i64), AppError> {
         let skip = (page - 1) * limit;
 
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
 
-        let extra_match = doc! {
+        let match_stage = doc! {
             "conversation_id": conversation_id,
             "message_type": "FILE",
             "deleted_at": { "$exists": false }
         };
 
-        let (messages, total, _, _) = repo
-            .get_all::<Message>(None, &[], Some(limit), Some(skip), Some(extra_match))
+        let result = repo
+            .aggregate_with_paginate::<MessageFile>(
+                message_files_pipeline(match_stage),
+                Some(limit),
+                Some(skip),
+            )
             .await?;
 
-        Ok((messages, total))
+        Ok((result.data, result.total))
     }
 
     pub async fn soft_delete(&self, id: &IdType) -> Result<Message, AppError> {
@@ -154,6 +175,187 @@ impl MessageService {
         Ok(message)
     }
 
+    /// Remove a sender's message for good, including already soft-deleted ones.
+    /// Its attachment may be released afterwards.
+    pub async fn hard_delete(
+        &self,
+        id: ObjectId,
+        conversation_id: ObjectId,
+        sender_id: ObjectId,
+    ) -> Result<Message, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        let message = repo
+            .find_one::<Message>(
+                // Older messages hold these ids as hex strings
+                doc! {
+                    "_id": id,
+                    "conversation_id": { "$in": [conversation_id, conversation_id.to_hex()] },
+                    "sender.id": { "$in": [sender_id, sender_id.to_hex()] },
+                },
+                None,
+            )
+            .await?
+            .ok_or(AppError {
+                message: "Message not found or not sent by you".into(),
+            })?;
+
+        repo.delete_one(&IdType::ObjectId(id)).await?;
+
+        Ok(message)
+    }
+
+    pub async fn find_one_in_conversation(
+        &self,
+        id: ObjectId,
+        conversation_id: ObjectId,
+    ) -> Result<Message, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        let filter = doc! {
+            "_id": id,
+            "conversation_id": conversation_id,
+            "deleted_at": { "$exists": false }
+        };
+
+        repo.find_one::<Message>(filter, None)
+            .await?
+            .ok_or(AppError {
+                message: "Message not found".into(),
+            })
+    }
+
+    // =========================
+    // EDIT
+    // =========================
+
+    /// Replace the encrypted payload, keeping the previous ciphertext in `edit_history`
+    pub async fn edit(
+        &self,
+        id: &IdType,
+        encrypted_payload: String,
+        nonce: String,
+        key_version: i32,
+    ) -> Result<Message, AppError> {
+        if encrypted_payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(AppError { message: "Encrypted payload too large".to_string() });
+        }
+
+        let existing = self.find_one(id).await?;
+
+        if existing.message_type != MessageType::TEXT {
+            return Err(AppError {
+                message: "Only text messages can be edited".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        let previous = MessageEdit {
+            encrypted_payload: existing.encrypted_payload,
+            nonce: existing.nonce,
+            key_version: existing.key_version,
+            edited_at: now,
+        };
+
+        let previous_bson = mongodb::bson::to_bson(&previous).map_err(|e| AppError {
+            message: format!("Failed to serialize message edit: {}", e),
+        })?;
+
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "_id": IdType::to_object_id(id)?,
+                    "deleted_at": { "$exists": false }
+                },
+                doc! {
+                    "$push": { "edit_history": previous_bson },
+                    "$set": {
+                        "encrypted_payload": encrypted_payload,
+                        "nonce": nonce,
+                        "key_version": key_version,
+                        "edited_at": mongodb::bson::to_bson(&now).unwrap()
+                    }
+                },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Message not found".into(),
+            })
+    }
+
+    // =========================
+    // REACTIONS
+    // =========================
+
+    pub async fn add_reaction(
+        &self,
+        id: &IdType,
+        user: ActorRef,
+        emoji: &str,
+    ) -> Result<Message, AppError> {
+        let emoji = validate_emoji(emoji)?;
+        let message_id = IdType::to_object_id(id)?;
+
+        let reaction = MessageReaction {
+            emoji: emoji.clone(),
+            user: user.clone(),
+            created_at: Utc::now(),
+        };
+
+        let reaction_bson = to_stored_document(&reaction)?;
+
+        // Only push when this user has not reacted with the same emoji yet
+        let updated = self
+            .collection
+            .find_one_and_update(
+                doc! {
+                    "_id": message_id,
+                    "deleted_at": { "$exists": false },
+                    "reactions": {
+                        "$not": { "$elemMatch": { "user.id": user.id, "emoji": &emoji } }
+                    }
+                },
+                doc! { "$push": { "reactions": reaction_bson } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?;
+
+        match updated {
+            Some(message) => Ok(message),
+            None => {
+                // Distinguish a missing message from a duplicate reaction
+                self.find_one(id).await?;
+                Err(AppError {
+                    message: "You already reacted with this emoji".into(),
+                })
+            }
+        }
+    }
+
+    pub async fn remove_reaction(
+        &self,
+        id: &IdType,
+        user_id: ObjectId,
+        emoji: &str,
+    ) -> Result<Message, AppError> {
+        let emoji = validate_emoji(emoji)?;
+
+        self.collection
+            .find_one_and_update(
+                doc! {
+                    "_id": IdType::to_object_id(id)?,
+                    "deleted_at": { "$exists": false }
+                },
+                doc! { "$pull": { "reactions": { "user.id": user_id, "emoji": emoji } } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Message not found".into(),
+            })
+    }
+
     // =========================
     // GET CONVERSATION MESSAGES WITH RELATIONS
     // =========================
@@ -204,3 +406,22 @@ impl MessageService {
             })
     }
 }
+
+fn validate_emoji(emoji: &str) -> Result<String, AppError> {
+    let emoji = emoji.trim();
+
+    if emoji.is_empty() || emoji.len() > MAX_EMOJI_LEN {
+        return Err(AppError {
+            message: "Invalid reaction emoji".into(),
+        });
+    }
+
+    // Reactions are emoji only, not free text
+    if emoji.chars().any(|c| c.is_ascii_alphanumeric() || c.is_whitespace()) {
+        return Err(AppError {
+            message: "Invalid reaction emoji".into(),
+        });
+    }
+
+    Ok(emoji.to_string())
+}
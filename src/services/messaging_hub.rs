@@ -0,0 +1,85 @@
+use futures::channel::mpsc;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub type SocketChannel = mpsc::UnboundedSender<String>;
+
+type ConversationSockets = Arc<RwLock<HashMap<String, HashMap<Uuid, SocketChannel>>>>;
+
+/// Fan-out of realtime messaging events to the WebSocket connections
+/// opened on `/m/ws/{conversation_id}`
+#[derive(Clone)]
+pub struct MessagingHub {
+    sockets: ConversationSockets,
+}
+
+impl MessagingHub {
+    pub fn new() -> Self {
+        Self {
+            sockets: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Register a socket for a conversation
+    pub async fn join(&self, conversation_id: &str) -> (Uuid, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded();
+        let socket_id = Uuid::new_v4();
+
+        let mut sockets = self.sockets.write().await;
+        sockets
+            .entry(conversation_id.to_string())
+            .or_default()
+            .insert(socket_id, tx);
+
+        (socket_id, rx)
+    }
+
+    /// Remove a socket, dropping the conversation entry once it is empty
+    pub async fn leave(&self, conversation_id: &str, socket_id: &Uuid) {
+        let mut sockets = self.sockets.write().await;
+        if let Some(conversation_sockets) = sockets.get_mut(conversation_id) {
+            conversation_sockets.remove(socket_id);
+            if conversation_sockets.is_empty() {
+                sockets.remove(conversation_id);
+            }
+        }
+    }
+
+    /// Send an event to every socket connected to the conversation
+    pub async fn broadcast<T: Serialize>(&self, conversation_id: &str, event: &T) {
+        let message = match serde_json::to_string(event) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("Failed to serialize messaging event: {}", e);
+                return;
+            }
+        };
+
+        let mut disconnected = Vec::new();
+        {
+            let sockets = self.sockets.read().await;
+            let Some(conversation_sockets) = sockets.get(conversation_id) else {
+                return;
+            };
+
+            for (socket_id, sender) in conversation_sockets.iter() {
+                if sender.unbounded_send(message.clone()).is_err() {
+                    disconnected.push(*socket_id);
+                }
+            }
+        }
+
+        for socket_id in disconnected {
+            self.leave(conversation_id, &socket_id).await;
+        }
+    }
+}
+
+impl Default for MessagingHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
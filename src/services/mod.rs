@@ -1,15 +1,21 @@
+pub mod academic_record_service;
+pub mod academic_year_service;
+pub mod ai_feedback_service;
 pub mod analytics_service;
 pub mod announcement_service;
 pub mod assessment_category_service;
+pub mod assignment_group_service;
 pub mod assignment_service;
 pub mod audit_log_service;
 pub mod auth_service;
 pub mod backup_service;
+pub mod chat_service;
 pub mod class_service;
 pub mod class_subject_service;
 pub mod class_timetable_service;
 pub mod cloudinary_service;
 pub mod comment_service;
+pub mod competency_service;
 pub mod conversation_service;
 pub mod database_status_service;
 pub mod education_year_service;
@@ -20,22 +26,38 @@ pub mod feature_service;
 pub mod gpa_calculation_service;
 pub mod grading_scale_service;
 pub mod join_school_request_service;
+pub mod lesson_plan_service;
 pub mod like_service;
 pub mod main_class_service;
+pub mod material_folder_service;
+pub mod material_progress_service;
+pub mod message_attachment_service;
 pub mod message_service;
+pub mod messaging_hub;
 pub mod parent_service;
+pub mod peer_review_service;
+pub mod promotion_service;
+pub mod quiz_service;
 pub mod ranking_service;
 pub mod recycle_bin_service;
+pub mod report_card_service;
+pub mod result_moderation_service;
 pub mod role_service;
+pub mod rubric_service;
+pub mod safeguarding_service;
 pub mod school_service;
 pub mod school_staff_service;
 pub mod school_timetable_service;
+pub mod score_import_service;
 pub mod score_service;
 pub mod sector_service;
 pub mod student_service;
+pub mod submission_similarity_service;
+pub mod syllabus_coverage_service;
 pub mod teacher_service;
 pub mod template_subject_service;
 pub mod trade_service;
 pub mod user_service;
 pub mod user_public_key_service;
 pub mod learning_material_service;
+pub mod learning_outcome_service;
@@ -27,6 +27,7 @@ use crate::{
     repositories::base_repo::BaseRepository,
     services::{
         announcement_service::AnnouncementService, cloudinary_service::CloudinaryService,
+        material_progress_service::MaterialProgressService,
     },
     utils::{
         email::is_valid_email,
@@ -453,6 +454,12 @@ impl ParentService {
         // Get outstanding fees (placeholder)
         let outstanding_fees = 0.0;
 
+        // Engagement with learning materials; left out when it cannot be worked out
+        let learning_engagement = MaterialProgressService::new(&db)
+            .summary(student_oid)
+            .await
+            .ok();
+
         Ok(ChildSummary {
             student_id: Some(student_oid),
             student_name: student.name,
@@ -460,6 +467,7 @@ impl ParentService {
             attendance_percentage,
             current_term_gpa,
             outstanding_fees,
+            learning_engagement,
         })
     }
 
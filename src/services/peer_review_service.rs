@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::{
+    domain::{
+        assignment::{Assignment, Submission, SubmissionStatus},
+        common_details::Paginated,
+        peer_review::{
+            OverridePeerReview, PeerReview, PeerReviewOverride, PeerReviewRound,
+            PeerReviewRoundStatus, PeerReviewStatus, PeerReviewSummary, PeerReviewWithRelations,
+            ReceivedReview, ReviewTask, ReviewerReliability, StartPeerReview, StudentPeerReviews,
+            SubmissionPeerScore, SubmitPeerReview,
+        },
+        rubric::Rubric,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    pipeline::assignment_pipeline::peer_review_with_students_pipeline,
+    repositories::base_repo::BaseRepository,
+    services::{assignment_service::AssignmentService, rubric_service::RubricService},
+    utils::{math_utils::round2, mongo_utils::to_stored_document},
+};
+
+pub struct PeerReviewService {
+    pub round_collection: Collection<PeerReviewRound>,
+    pub collection: Collection<PeerReview>,
+    pub db: Database,
+}
+
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+impl PeerReviewService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            round_collection: db.collection::<PeerReviewRound>("peer_review_rounds"),
+            collection: db.collection::<PeerReview>("peer_reviews"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let round_indexes = vec![IndexDef::single("assignment_id", true)];
+        BaseRepository::new(self.round_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&round_indexes)
+            .await?;
+
+        let review_indexes = vec![
+            IndexDef::compound(vec![("assignment_id", 1), ("reviewer_id", 1)], false),
+            IndexDef::compound(vec![("assignment_id", 1), ("reviewee_id", 1)], false),
+            IndexDef::compound(vec![("submission_id", 1), ("reviewer_id", 1)], true),
+        ];
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&review_indexes)
+            .await?;
+        Ok(())
+    }
+
+    async fn assignment(&self, assignment_id: ObjectId) -> Result<Assignment, AppError> {
+        AssignmentService::new(&self.db)
+            .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+            .await
+    }
+
+    async fn rubric(&self, rubric_id: ObjectId) -> Result<Rubric, AppError> {
+        RubricService::new(&self.db)
+            .find_one(&IdType::ObjectId(rubric_id))
+            .await
+    }
+
+    pub async fn find_round(&self, assignment_id: ObjectId) -> Result<PeerReviewRound, AppError> {
+        self.round_collection
+            .find_one(doc! { "assignment_id": assignment_id })
+            .await?
+            .ok_or(AppError {
+                message: "Peer review has not been set up for this assignment".into(),
+            })
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<PeerReview, AppError> {
+        self.collection
+            .find_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?
+            .ok_or(AppError {
+                message: "Peer review not found".into(),
+            })
+    }
+
+    async fn reviews_for(&self, assignment_id: ObjectId) -> Result<Vec<PeerReview>, AppError> {
+        Ok(self
+            .collection
+            .find(doc! { "assignment_id": assignment_id })
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    // =========================
+    // SETUP
+    // =========================
+
+    /// Hand every submission to `reviews_per_submission` other students.
+    /// Submitters are shuffled and each reviews the next N in the circle, so
+    /// everyone writes and receives the same number of reviews.
+    pub async fn start(
+        &self,
+        assignment_id: ObjectId,
+        dto: StartPeerReview,
+        created_by: Option<ObjectId>,
+    ) -> Result<PeerReviewRound, AppError> {
+        self.ensure_indexes().await?;
+
+        if self
+            .round_collection
+            .find_one(doc! { "assignment_id": assignment_id })
+            .await?
+            .is_some()
+        {
+            return Err(AppError {
+                message: "Peer review is already set up for this assignment".into(),
+            });
+        }
+
+        let assignment = self.assignment(assignment_id).await?;
+        if dto.review_deadline <= Utc::now() {
+            return Err(AppError {
+                message: "Review deadline must be in the future".into(),
+            });
+        }
+
+        let rubric_id = match dto.rubric_id {
+            Some(id) => IdType::to_object_id(&IdType::from_string(id))?,
+            None => assignment.rubric_id.ok_or(AppError {
+                message: "Choose a rubric or attach one to the assignment".into(),
+            })?,
+        };
+        self.rubric(rubric_id).await?;
+
+        let mut submissions: Vec<Submission> = AssignmentService::new(&self.db)
+            .submission_collection
+            .find(doc! { "assignment_id": assignment_id, "is_deleted": false })
+            .await?
+            .try_collect()
+            .await?;
+        submissions.retain(|s| s.id.is_some() && s.student_id.is_some());
+
+        let per_submission = dto.reviews_per_submission;
+        if per_submission < 1 {
+            return Err(AppError {
+                message: "Each submission needs at least one reviewer".into(),
+            });
+        }
+        if per_submission as usize >= submissions.len() {
+            return Err(AppError {
+                message: format!(
+                    "{} submission(s) cannot each get {} reviewers from classmates",
+                    submissions.len(),
+                    per_submission
+                ),
+            });
+        }
+
+        submissions.shuffle(&mut thread_rng());
+
+        let now = Utc::now();
+        let round = PeerReviewRound {
+            id: None,
+            school_id: assignment.school_id,
+            assignment_id,
+            rubric_id,
+            reviews_per_submission: per_submission,
+            review_deadline: dto.review_deadline,
+            status: PeerReviewRoundStatus::Open,
+            created_by,
+            created_at: now,
+            finalized_at: None,
+        };
+        let round_id = self
+            .round_collection
+            .clone_with_type::<Document>()
+            .insert_one(to_stored_document(&round)?)
+            .await?
+            .inserted_id
+            .as_object_id()
+            .unwrap_or_default();
+
+        let count = submissions.len();
+        let mut reviews = Vec::with_capacity(count * per_submission as usize);
+        for (i, submission) in submissions.iter().enumerate() {
+            for offset in 1..=per_submission as usize {
+                let reviewer = &submissions[(i + offset) % count];
+                let review = PeerReview {
+                    id: None,
+                    round_id,
+                    assignment_id,
+                    submission_id: submission.id.unwrap_or_default(),
+                    reviewee_id: submission.student_id.unwrap_or_default(),
+                    reviewer_id: reviewer.student_id.unwrap_or_default(),
+                    status: PeerReviewStatus::Pending,
+                    criterion_scores: None,
+                    score: None,
+                    comment: None,
+                    submitted_at: None,
+                    teacher_override: None,
+                    created_at: now,
+                };
+                reviews.push(to_stored_document(&review)?);
+            }
+        }
+        self.collection
+            .clone_with_type::<Document>()
+            .insert_many(reviews)
+            .await?;
+
+        Ok(PeerReviewRound {
+            id: Some(round_id),
+            ..round
+        })
+    }
+
+    // =========================
+    // STUDENTS
+    // =========================
+
+    /// Reviews a student has to write and, after the deadline, the ones
+    /// they received. Neither side sees who the other student is.
+    pub async fn for_student(
+        &self,
+        assignment_id: ObjectId,
+        student_id: ObjectId,
+    ) -> Result<StudentPeerReviews, AppError> {
+        let round = self.find_round(assignment_id).await?;
+        let rubric = self.rubric(round.rubric_id).await?;
+        let reviews = self.reviews_for(assignment_id).await?;
+
+        let submission_ids: Vec<ObjectId> = reviews
+            .iter()
+            .filter(|r| r.reviewer_id == student_id)
+            .map(|r| r.submission_id)
+            .collect();
+        let submissions: HashMap<ObjectId, Submission> = AssignmentService::new(&self.db)
+            .submission_collection
+            .find(doc! { "_id": { "$in": &submission_ids } })
+            .await?
+            .try_collect::<Vec<Submission>>()
+            .await?
+            .into_iter()
+            .filter_map(|s| s.id.map(|id| (id, s)))
+            .collect();
+
+        let to_review = reviews
+            .iter()
+            .filter(|r| r.reviewer_id == student_id)
+            .map(|r| {
+                let submission = submissions.get(&r.submission_id);
+                ReviewTask {
+                    review_id: r.id.unwrap_or_default(),
+                    status: r.status,
+                    review_deadline: round.review_deadline,
+                    file_url: submission.and_then(|s| s.file_url.clone()),
+                    text_content: submission.and_then(|s| s.text_content.clone()),
+                    criterion_scores: r.criterion_scores.clone(),
+                    comment: r.comment.clone(),
+                }
+            })
+            .collect();
+
+        let received = if Utc::now() > round.review_deadline {
+            reviews
+                .iter()
+                .filter(|r| r.reviewee_id == student_id && r.effective_score().is_some())
+                .map(|r| match &r.teacher_override {
+                    Some(o) => ReceivedReview {
+                        score: Some(o.score),
+                        criterion_scores: o.criterion_scores.clone(),
+                        comment: o.comment.clone().or(r.comment.clone()),
+                        overridden: true,
+                    },
+                    None => ReceivedReview {
+                        score: r.score,
+                        criterion_scores: r.criterion_scores.clone(),
+                        comment: r.comment.clone(),
+                        overridden: false,
+                    },
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(StudentPeerReviews {
+            round,
+            rubric,
+            to_review,
+            received,
+        })
+    }
+
+    /// Save a reviewer's scores; they can revise until the deadline
+    pub async fn submit(
+        &self,
+        review_id: &IdType,
+        reviewer_id: ObjectId,
+        dto: SubmitPeerReview,
+    ) -> Result<PeerReview, AppError> {
+        let review = self.find_one(review_id).await?;
+        if review.reviewer_id != reviewer_id {
+            return Err(AppError {
+                message: "This review is assigned to another student".into(),
+            });
+        }
+
+        let round = self.find_round(review.assignment_id).await?;
+        if round.status != PeerReviewRoundStatus::Open || Utc::now() > round.review_deadline {
+            return Err(AppError {
+                message: "The review deadline has passed".into(),
+            });
+        }
+
+        let assignment = self.assignment(review.assignment_id).await?;
+        let rubric = self.rubric(round.rubric_id).await?;
+        let scores = rubric
+            .grade(&dto.criterion_scores)
+            .map_err(|message| AppError { message })?;
+        let score = rubric.scaled_total(&scores, assignment.max_score);
+
+        let criterion_scores = bson::to_bson(&scores).map_err(|e| AppError {
+            message: format!("Failed to serialize criterion scores: {}", e),
+        })?;
+        self.collection
+            .update_one(
+                doc! { "_id": review.id },
+                doc! { "$set": {
+                    "status": bson::to_bson(&PeerReviewStatus::Submitted).unwrap(),
+                    "criterion_scores": criterion_scores,
+                    "score": score,
+                    "comment": dto.comment,
+                    "submitted_at": bson::to_bson(&Utc::now()).unwrap(),
+                } },
+            )
+            .await?;
+
+        self.find_one(review_id).await
+    }
+
+    // =========================
+    // TEACHERS
+    // =========================
+
+    pub async fn get_all_with_relations(
+        &self,
+        assignment_id: ObjectId,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Paginated<PeerReviewWithRelations>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let pipeline = peer_review_with_students_pipeline(doc! { "assignment_id": assignment_id });
+        repo.aggregate_with_paginate::<PeerReviewWithRelations>(pipeline, limit, skip)
+            .await
+    }
+
+    /// Replace a review's score, directly or through the rubric
+    pub async fn override_review(
+        &self,
+        review_id: &IdType,
+        dto: OverridePeerReview,
+        overridden_by: ObjectId,
+    ) -> Result<PeerReview, AppError> {
+        let review = self.find_one(review_id).await?;
+        let round = self.find_round(review.assignment_id).await?;
+        let assignment = self.assignment(review.assignment_id).await?;
+
+        let (score, criterion_scores) = match (dto.criterion_scores, dto.score) {
+            (Some(input), _) => {
+                let rubric = self.rubric(round.rubric_id).await?;
+                let scores = rubric
+                    .grade(&input)
+                    .map_err(|message| AppError { message })?;
+                (
+                    rubric.scaled_total(&scores, assignment.max_score),
+                    Some(scores),
+                )
+            }
+            (None, Some(score)) => (score, None),
+            (None, None) => {
+                return Err(AppError {
+                    message: "Give a score or criterion scores".into(),
+                })
+            }
+        };
+        if !(0.0..=assignment.max_score).contains(&score) {
+            return Err(AppError {
+                message: format!("Score must be between 0 and {}", assignment.max_score),
+            });
+        }
+
+        let teacher_override = PeerReviewOverride {
+            score,
+            criterion_scores,
+            comment: dto.comment,
+            overridden_by,
+            overridden_at: Utc::now(),
+        };
+        self.collection
+            .update_one(
+                doc! { "_id": review.id },
+                doc! { "$set": { "teacher_override": to_stored_document(&teacher_override)? } },
+            )
+            .await?;
+
+        self.find_one(review_id).await
+    }
+
+    /// Peer score per submission and how reliable each reviewer has been
+    pub async fn summary(&self, assignment_id: ObjectId) -> Result<PeerReviewSummary, AppError> {
+        let round = self.find_round(assignment_id).await?;
+        let assignment = self.assignment(assignment_id).await?;
+        let reviews = self.reviews_for(assignment_id).await?;
+
+        let teacher_scores: HashMap<ObjectId, f64> = AssignmentService::new(&self.db)
+            .submission_collection
+            .find(doc! {
+                "assignment_id": assignment_id,
+                "is_deleted": false,
+                "status": bson::to_bson(&SubmissionStatus::Graded).unwrap(),
+                "graded_by": { "$ne": null }
+            })
+            .await?
+            .try_collect::<Vec<Submission>>()
+            .await?
+            .into_iter()
+            .filter_map(|s| Some((s.id?, s.raw_score.or(s.score)?)))
+            .collect();
+
+        let mut by_submission: HashMap<ObjectId, Vec<&PeerReview>> = HashMap::new();
+        let mut by_reviewer: HashMap<ObjectId, Vec<&PeerReview>> = HashMap::new();
+        for review in &reviews {
+            by_submission
+                .entry(review.submission_id)
+                .or_default()
+                .push(review);
+            by_reviewer
+                .entry(review.reviewer_id)
+                .or_default()
+                .push(review);
+        }
+
+        let mut submissions: Vec<SubmissionPeerScore> = by_submission
+            .iter()
+            .map(|(submission_id, reviews)| {
+                let scores: Vec<f64> = reviews.iter().filter_map(|r| r.effective_score()).collect();
+                SubmissionPeerScore {
+                    submission_id: *submission_id,
+                    student_id: reviews[0].reviewee_id,
+                    reviews_assigned: reviews.len(),
+                    reviews_received: scores.len(),
+                    peer_score: mean(&scores).map(round2),
+                    scores,
+                }
+            })
+            .collect();
+        submissions.sort_by_key(|s| s.student_id);
+
+        let mut reviewers: Vec<ReviewerReliability> = by_reviewer
+            .iter()
+            .map(|(reviewer_id, own)| {
+                let deviations: Vec<f64> = own
+                    .iter()
+                    .filter_map(|review| {
+                        let given = review.score?;
+                        let reference = teacher_scores
+                            .get(&review.submission_id)
+                            .copied()
+                            .or_else(|| {
+                                let others: Vec<f64> = by_submission
+                                    .get(&review.submission_id)?
+                                    .iter()
+                                    .filter(|r| r.reviewer_id != *reviewer_id)
+                                    .filter_map(|r| r.effective_score())
+                                    .collect();
+                                mean(&others)
+                            })?;
+                        Some((given - reference).abs())
+                    })
+                    .collect();
+                let mean_deviation = mean(&deviations);
+
+                ReviewerReliability {
+                    reviewer_id: *reviewer_id,
+                    assigned: own.len(),
+                    completed: own.iter().filter(|r| r.score.is_some()).count(),
+                    overridden: own.iter().filter(|r| r.teacher_override.is_some()).count(),
+                    mean_deviation: mean_deviation.map(round2),
+                    reliability: mean_deviation
+                        .map(|d| round2((1.0 - d / assignment.max_score).clamp(0.0, 1.0))),
+                }
+            })
+            .collect();
+        reviewers.sort_by(|a, b| {
+            a.reliability
+                .unwrap_or(0.0)
+                .total_cmp(&b.reliability.unwrap_or(0.0))
+        });
+
+        Ok(PeerReviewSummary {
+            round,
+            submissions,
+            reviewers,
+        })
+    }
+
+    /// Close the round and store each submission's peer score
+    pub async fn finalize(&self, assignment_id: ObjectId) -> Result<PeerReviewSummary, AppError> {
+        let summary = self.summary(assignment_id).await?;
+        if summary.round.status == PeerReviewRoundStatus::Finalized {
+            return Err(AppError {
+                message: "Peer review is already finalized".into(),
+            });
+        }
+
+        let submissions = AssignmentService::new(&self.db).submission_collection;
+        for entry in &summary.submissions {
+            submissions
+                .update_one(
+                    doc! { "_id": entry.submission_id },
+                    doc! { "$set": {
+                        "peer_review_score": entry.peer_score,
+                        "updated_at": bson::to_bson(&Utc::now()).unwrap(),
+                    } },
+                )
+                .await?;
+        }
+
+        let now = Utc::now();
+        self.round_collection
+            .update_one(
+                doc! { "assignment_id": assignment_id },
+                doc! { "$set": {
+                    "status": bson::to_bson(&PeerReviewRoundStatus::Finalized).unwrap(),
+                    "finalized_at": bson::to_bson(&now).unwrap(),
+                } },
+            )
+            .await?;
+
+        Ok(PeerReviewSummary {
+            round: PeerReviewRound {
+                status: PeerReviewRoundStatus::Finalized,
+                finalized_at: Some(now),
+                ..summary.round
+            },
+            ..summary
+        })
+    }
+}
@@ -0,0 +1,684 @@
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        common_details::Paginated,
+        promotion::{
+            OverridePromotionResult, PromotionBatch, PromotionBatchStatus, PromotionResult,
+            PromotionRule, PromotionRulePartial, PromotionStatus,
+        },
+        student::{Student, StudentStatus},
+        student_term_result::StudentTermResult,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::chat_service::ChatService,
+    utils::mongo_utils::{extract_valid_fields, id_match, to_stored_document},
+};
+
+/// Subject pass mark used when the rule has no `subject_pass_percentage` in `custom_rules`
+const DEFAULT_SUBJECT_PASS_PERCENTAGE: f64 = 50.0;
+
+pub struct PromotionService {
+    pub rule_collection: Collection<PromotionRule>,
+    pub batch_collection: Collection<PromotionBatch>,
+    pub db: Database,
+}
+
+impl PromotionService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            rule_collection: db.collection::<PromotionRule>("promotion_rules"),
+            batch_collection: db.collection::<PromotionBatch>("promotion_batches"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let rule_indexes = vec![
+            IndexDef::compound(vec![("school_id", 1), ("education_year_id", 1)], false),
+            IndexDef::single("is_deleted", false),
+        ];
+        let batch_indexes = vec![
+            IndexDef::compound(
+                vec![("school_id", 1), ("from_class_id", 1), ("education_year_id", 1)],
+                false,
+            ),
+            IndexDef::single("status", false),
+        ];
+
+        BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&rule_indexes)
+            .await?;
+        BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&batch_indexes)
+            .await?;
+        Ok(())
+    }
+
+    // =========================
+    // RULES
+    // =========================
+
+    pub async fn create_rule(&self, rule: PromotionRule) -> Result<PromotionRule, AppError> {
+        self.ensure_indexes().await?;
+
+        if rule.min_gpa_threshold < 0.0 {
+            return Err(AppError {
+                message: "min_gpa_threshold cannot be negative".into(),
+            });
+        }
+        if let Some(attendance) = rule.min_attendance_percentage {
+            if !(0.0..=100.0).contains(&attendance) {
+                return Err(AppError {
+                    message: "min_attendance_percentage must be between 0 and 100".into(),
+                });
+            }
+        }
+
+        let repo = BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>());
+        let mut doc = to_stored_document(&rule)?;
+        doc.insert("is_deleted", false);
+
+        repo.create::<PromotionRule>(doc, None).await
+    }
+
+    pub async fn find_rule(&self, id: &IdType) -> Result<PromotionRule, AppError> {
+        let filter = doc! {
+            "_id": IdType::to_object_id(id)?,
+            "is_deleted": false
+        };
+
+        let repo = BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>());
+        repo.find_one::<PromotionRule>(filter, None)
+            .await?
+            .ok_or(AppError {
+                message: "Promotion rule not found".into(),
+            })
+    }
+
+    pub async fn get_rules(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<PromotionRule>, AppError> {
+        let repo = BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>());
+
+        let searchable = ["name", "_id", "school_id", "education_year_id"];
+
+        let mut match_filter = extra_match.unwrap_or_default();
+        match_filter.insert("is_deleted", false);
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<PromotionRule>(filter, &searchable, limit, skip, Some(match_filter))
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn update_rule(
+        &self,
+        id: &IdType,
+        update: &PromotionRulePartial,
+    ) -> Result<PromotionRule, AppError> {
+        let repo = BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>());
+        let mut update_doc = extract_valid_fields(bson::to_document(update).map_err(|e| AppError {
+            message: format!("Failed to serialize update: {}", e),
+        })?);
+        update_doc.remove("is_deleted");
+
+        repo.update_one_and_fetch::<PromotionRule>(id, update_doc)
+            .await
+    }
+
+    pub async fn delete_rule(&self, id: &IdType) -> Result<PromotionRule, AppError> {
+        let repo = BaseRepository::new(self.rule_collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<PromotionRule>(id, doc! { "is_deleted": true })
+            .await
+    }
+
+    /// The explicit rule when given, otherwise the most recently updated
+    /// rule of the school's education year
+    async fn resolve_rule(
+        &self,
+        school_id: &ObjectId,
+        education_year_id: &ObjectId,
+        rule_id: Option<ObjectId>,
+    ) -> Result<PromotionRule, AppError> {
+        let rule = match rule_id {
+            Some(id) => self.find_rule(&IdType::ObjectId(id)).await?,
+            None => self
+                .rule_collection
+                .find_one(doc! {
+                    "school_id": school_id,
+                    "education_year_id": education_year_id,
+                    "is_deleted": false
+                })
+                .sort(doc! { "updated_at": -1 })
+                .await?
+                .ok_or(AppError {
+                    message: "No promotion rule defined for this education year".into(),
+                })?,
+        };
+
+        if rule.school_id != Some(*school_id) {
+            return Err(AppError {
+                message: "Promotion rule does not belong to this school".into(),
+            });
+        }
+
+        Ok(rule)
+    }
+
+    // =========================
+    // EVALUATION
+    // =========================
+
+    /// Evaluate every active student of a class and store the outcome as a
+    /// draft batch for review. Older drafts of the same class are cancelled.
+    pub async fn preview(
+        &self,
+        school_id: ObjectId,
+        education_year_id: ObjectId,
+        from_class_id: ObjectId,
+        to_class_id: Option<ObjectId>,
+        rule_id: Option<ObjectId>,
+        created_by: ObjectId,
+    ) -> Result<PromotionBatch, AppError> {
+        self.ensure_indexes().await?;
+
+        if to_class_id == Some(from_class_id) {
+            return Err(AppError {
+                message: "Target class must differ from the current class".into(),
+            });
+        }
+        self.ensure_class_exists(&from_class_id).await?;
+        if let Some(to_class_id) = to_class_id {
+            self.ensure_class_exists(&to_class_id).await?;
+        }
+
+        let rule = self
+            .resolve_rule(&school_id, &education_year_id, rule_id)
+            .await?;
+
+        let mut cursor = self
+            .db
+            .collection::<Student>("students")
+            .find(doc! {
+                "class_id": from_class_id,
+                "is_active": true,
+                "status": bson::to_bson(&StudentStatus::Active).unwrap()
+            })
+            .sort(doc! { "name": 1 })
+            .await?;
+
+        let mut promotion_results = Vec::new();
+        while cursor.advance().await? {
+            let student: Student = cursor.deserialize_current()?;
+            promotion_results.push(
+                self.evaluate_student(&rule, &student, &school_id, &education_year_id, to_class_id)
+                    .await?,
+            );
+        }
+
+        self.batch_collection
+            .update_many(
+                doc! {
+                    "school_id": school_id,
+                    "from_class_id": from_class_id,
+                    "education_year_id": education_year_id,
+                    "status": bson::to_bson(&PromotionBatchStatus::Draft).unwrap()
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&PromotionBatchStatus::Cancelled).unwrap(),
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .await?;
+
+        let batch = PromotionBatch {
+            id: None,
+            school_id: Some(school_id),
+            education_year_id: Some(education_year_id),
+            from_class_id: Some(from_class_id),
+            to_class_id,
+            rule_id: rule.id,
+            status: PromotionBatchStatus::Draft,
+            promotion_results,
+            executed_by: None,
+            executed_at: None,
+            created_by: Some(created_by),
+            created_at: None,
+            updated_at: None,
+        };
+
+        let repo = BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&batch)?;
+
+        repo.create::<PromotionBatch>(doc, None).await
+    }
+
+    async fn evaluate_student(
+        &self,
+        rule: &PromotionRule,
+        student: &Student,
+        school_id: &ObjectId,
+        education_year_id: &ObjectId,
+        to_class_id: Option<ObjectId>,
+    ) -> Result<PromotionResult, AppError> {
+        let student_id = student.id.ok_or(AppError {
+            message: "Student has no ID".into(),
+        })?;
+
+        let mut cursor = self
+            .db
+            .collection::<StudentTermResult>("student_term_results")
+            .find(doc! {
+                "student_id": id_match(&student_id),
+                "education_year_id": id_match(education_year_id),
+                "is_finalized": true
+            })
+            .await?;
+        let mut term_results: Vec<StudentTermResult> = Vec::new();
+        while cursor.advance().await? {
+            term_results.push(cursor.deserialize_current()?);
+        }
+
+        let attendance_percentage = self.attendance_percentage(&student_id, school_id).await?;
+
+        let mut result = PromotionResult {
+            student_id: Some(student_id),
+            student_name: student.name.clone(),
+            current_gpa: 0.0,
+            promotion_status: PromotionStatus::Pending,
+            reason: String::new(),
+            promoted_to_class_id: None,
+            attendance_percentage,
+            failed_subjects: vec![],
+            is_overridden: false,
+            override_reason: None,
+            overridden_by: None,
+        };
+
+        // Without results there is nothing to judge; staff decide manually
+        if term_results.is_empty() {
+            result.reason = "No term results recorded for this education year".into();
+            return Ok(result);
+        }
+
+        result.current_gpa =
+            term_results.iter().map(|r| r.gpa).sum::<f64>() / term_results.len() as f64;
+
+        let mut failures: Vec<String> = Vec::new();
+
+        if result.current_gpa < rule.min_gpa_threshold {
+            failures.push(format!(
+                "GPA {:.2} is below {:.2}",
+                result.current_gpa, rule.min_gpa_threshold
+            ));
+        }
+
+        // Missing attendance records do not block promotion
+        if let (Some(min), Some(actual)) = (rule.min_attendance_percentage, attendance_percentage) {
+            if actual < min {
+                failures.push(format!("Attendance {:.1}% is below {:.1}%", actual, min));
+            }
+        }
+
+        let pass_mark = subject_pass_percentage(rule);
+        for required in &rule.required_subjects_passed {
+            let percentages: Vec<f64> = term_results
+                .iter()
+                .flat_map(|r| r.subject_results.iter())
+                .filter(|s| {
+                    s.class_subject_id.map(|id| id.to_hex()).as_deref() == Some(required.as_str())
+                        || s.subject_name.eq_ignore_ascii_case(required)
+                })
+                .map(|s| s.percentage)
+                .collect();
+
+            if percentages.is_empty() {
+                result.failed_subjects.push(required.clone());
+                continue;
+            }
+
+            let average = percentages.iter().sum::<f64>() / percentages.len() as f64;
+            if average < pass_mark {
+                let name = term_results
+                    .iter()
+                    .flat_map(|r| r.subject_results.iter())
+                    .find(|s| {
+                        s.class_subject_id.map(|id| id.to_hex()).as_deref()
+                            == Some(required.as_str())
+                    })
+                    .map(|s| s.subject_name.clone())
+                    .unwrap_or_else(|| required.clone());
+                result.failed_subjects.push(name);
+            }
+        }
+        if !result.failed_subjects.is_empty() {
+            failures.push(format!(
+                "Required subjects not passed: {}",
+                result.failed_subjects.join(", ")
+            ));
+        }
+
+        if failures.is_empty() {
+            match to_class_id {
+                Some(to_class_id) => {
+                    result.promotion_status = PromotionStatus::Promoted;
+                    result.promoted_to_class_id = Some(to_class_id);
+                    result.reason = "Meets all promotion criteria".into();
+                }
+                None => {
+                    result.promotion_status = PromotionStatus::Graduated;
+                    result.reason = "Meets all graduation criteria".into();
+                }
+            }
+        } else {
+            result.promotion_status = PromotionStatus::Repeated;
+            result.reason = failures.join("; ");
+        }
+
+        Ok(result)
+    }
+
+    /// Present and late days count as attended; `None` when nothing was recorded
+    async fn attendance_percentage(
+        &self,
+        student_id: &ObjectId,
+        school_id: &ObjectId,
+    ) -> Result<Option<f64>, AppError> {
+        let attendance = self.db.collection::<Document>("attendance");
+
+        let total = attendance
+            .count_documents(doc! { "student_id": student_id, "school_id": school_id })
+            .await?;
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let attended = attendance
+            .count_documents(doc! {
+                "student_id": student_id,
+                "school_id": school_id,
+                "status": { "$in": ["Present", "Late"] }
+            })
+            .await?;
+
+        Ok(Some(attended as f64 / total as f64 * 100.0))
+    }
+
+    async fn ensure_class_exists(&self, class_id: &ObjectId) -> Result<(), AppError> {
+        let count = self
+            .db
+            .collection::<Document>("classes")
+            .count_documents(doc! { "_id": class_id })
+            .await?;
+
+        if count == 0 {
+            return Err(AppError {
+                message: format!("Class {} not found", class_id.to_hex()),
+            });
+        }
+        Ok(())
+    }
+
+    // =========================
+    // BATCHES
+    // =========================
+
+    pub async fn find_batch(&self, id: &IdType) -> Result<PromotionBatch, AppError> {
+        let repo = BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<PromotionBatch>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Promotion batch not found".into(),
+            })
+    }
+
+    pub async fn get_batches(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<PromotionBatch>, AppError> {
+        let repo = BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>());
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<PromotionBatch>(None, &[], limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Replace the rule's decision for one student of a draft batch
+    pub async fn override_result(
+        &self,
+        batch_id: &IdType,
+        student_id: &ObjectId,
+        input: &OverridePromotionResult,
+        overridden_by: ObjectId,
+    ) -> Result<PromotionBatch, AppError> {
+        let batch = self.find_batch(batch_id).await?;
+        ensure_draft(&batch)?;
+
+        if input.reason.trim().is_empty() {
+            return Err(AppError {
+                message: "A reason is required to override a promotion decision".into(),
+            });
+        }
+
+        let promoted_to_class_id = match input.promotion_status {
+            PromotionStatus::Promoted => {
+                let target = match &input.promoted_to_class_id {
+                    Some(id) => ObjectId::parse_str(id).map_err(|_| AppError {
+                        message: "Invalid promoted_to_class_id".into(),
+                    })?,
+                    None => batch.to_class_id.ok_or(AppError {
+                        message: "promoted_to_class_id is required for a graduating class".into(),
+                    })?,
+                };
+                if Some(target) == batch.from_class_id {
+                    return Err(AppError {
+                        message: "Target class must differ from the current class".into(),
+                    });
+                }
+                self.ensure_class_exists(&target).await?;
+                Some(target)
+            }
+            PromotionStatus::Pending => {
+                return Err(AppError {
+                    message: "A decision cannot be overridden back to Pending".into(),
+                });
+            }
+            _ => None,
+        };
+
+        let mut results = batch.promotion_results.clone();
+        let result = results
+            .iter_mut()
+            .find(|r| r.student_id == Some(*student_id))
+            .ok_or(AppError {
+                message: "Student is not part of this promotion batch".into(),
+            })?;
+
+        result.promotion_status = input.promotion_status.clone();
+        result.promoted_to_class_id = promoted_to_class_id;
+        result.is_overridden = true;
+        result.override_reason = Some(input.reason.trim().to_string());
+        result.overridden_by = Some(overridden_by);
+
+        let repo = BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>());
+        let update = doc! {
+            "promotion_results": results
+                .iter()
+                .map(to_stored_document)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        repo.update_one_and_fetch::<PromotionBatch>(batch_id, update)
+            .await
+    }
+
+    /// Apply a reviewed draft: promoted students move to their target class,
+    /// graduates leave the active roll, repeaters stay where they are. The
+    /// batch is only `Executed` once every student was updated.
+    pub async fn execute(
+        &self,
+        batch_id: &IdType,
+        executed_by: ObjectId,
+    ) -> Result<PromotionBatch, AppError> {
+        let batch = self.find_batch(batch_id).await?;
+        if batch.status != PromotionBatchStatus::Executing {
+            ensure_draft(&batch)?;
+        }
+
+        let pending = batch
+            .promotion_results
+            .iter()
+            .filter(|r| r.promotion_status == PromotionStatus::Pending)
+            .count();
+        if pending > 0 {
+            return Err(AppError {
+                message: format!("{} student(s) still need a decision before execution", pending),
+            });
+        }
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+
+        // Claim the draft first so it can no longer be edited or cancelled.
+        // A batch left `Executing` by a failed run is applied again; the
+        // student updates are idempotent.
+        let batch_oid = IdType::to_object_id(batch_id)?;
+        let executing = self
+            .batch_collection
+            .find_one_and_update(
+                doc! {
+                    "_id": batch_oid,
+                    "status": { "$in": [
+                        bson::to_bson(&PromotionBatchStatus::Draft).unwrap(),
+                        bson::to_bson(&PromotionBatchStatus::Executing).unwrap()
+                    ] }
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&PromotionBatchStatus::Executing).unwrap(),
+                    "updated_at": now.clone()
+                } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Promotion batch was already executed or cancelled".into(),
+            })?;
+
+        let students = self.db.collection::<Document>("students");
+        for result in &executing.promotion_results {
+            let Some(student_id) = result.student_id else {
+                continue;
+            };
+
+            let update = match (&result.promotion_status, result.promoted_to_class_id) {
+                (PromotionStatus::Promoted, Some(class_id)) => doc! {
+                    "$set": { "class_id": class_id, "updated_at": now.clone() },
+                    "$unset": { "subclass_id": "" }
+                },
+                (PromotionStatus::Graduated, _) => doc! {
+                    "$set": {
+                        "status": bson::to_bson(&StudentStatus::Graduated).unwrap(),
+                        "is_active": false,
+                        "updated_at": now.clone()
+                    }
+                },
+                _ => continue,
+            };
+
+            students
+                .update_one(doc! { "_id": student_id }, update)
+                .await
+                .map_err(|e| AppError {
+                    message: format!("Failed to update student {}: {}", student_id.to_hex(), e),
+                })?;
+        }
+
+        let executed = self
+            .batch_collection
+            .find_one_and_update(
+                doc! {
+                    "_id": batch_oid,
+                    "status": bson::to_bson(&PromotionBatchStatus::Executing).unwrap()
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&PromotionBatchStatus::Executed).unwrap(),
+                    "executed_by": executed_by,
+                    "executed_at": now.clone(),
+                    "updated_at": now
+                } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Promotion batch was already executed".into(),
+            })?;
+
+        ChatService::refresh_classes(
+            &self.db,
+            executed.from_class_id.into_iter().chain(
+                executed
+                    .promotion_results
+                    .iter()
+                    .filter_map(|r| r.promoted_to_class_id),
+            ),
+        );
+
+        Ok(executed)
+    }
+
+    pub async fn cancel(&self, batch_id: &IdType) -> Result<PromotionBatch, AppError> {
+        let batch = self.find_batch(batch_id).await?;
+        ensure_draft(&batch)?;
+
+        let repo = BaseRepository::new(self.batch_collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<PromotionBatch>(
+            batch_id,
+            doc! { "status": bson::to_bson(&PromotionBatchStatus::Cancelled).unwrap() },
+        )
+        .await
+    }
+}
+
+fn ensure_draft(batch: &PromotionBatch) -> Result<(), AppError> {
+    if batch.status != PromotionBatchStatus::Draft {
+        return Err(AppError {
+            message: "Only draft promotion batches can be changed".into(),
+        });
+    }
+    Ok(())
+}
+
+fn subject_pass_percentage(rule: &PromotionRule) -> f64 {
+    rule.custom_rules
+        .as_ref()
+        .and_then(|c| c.get("subject_pass_percentage"))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(DEFAULT_SUBJECT_PASS_PERCENTAGE)
+}
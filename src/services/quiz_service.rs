@@ -0,0 +1,1336 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+use rand::{seq::SliceRandom, thread_rng};
+use regex::RegexBuilder;
+
+use crate::{
+    domain::{
+        assessment_category::AssessmentCategory,
+        assignment::{Assignment, AssignmentStatus, Submission, SubmissionStatus},
+        common_details::Paginated,
+        quiz::{
+            AttemptQuestion, CreateQuizQuestion, GradedAnswer, QuestionType, Quiz, QuizAnswer,
+            QuizAttempt, QuizAttemptStatus, QuizAttemptView, QuizGradingPolicy, QuizQuestion,
+            ShortAnswerMatch, StudentQuizQuestion, SubmitQuizAttempt, UpdateQuizQuestion,
+            UpsertQuiz,
+        },
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::assignment_service::AssignmentService,
+    utils::{
+        math_utils::round2, mongo_utils::to_stored_document, object_id::parse_object_id_value,
+    },
+};
+
+/// Network slack allowed on top of a quiz's time limit
+const SUBMIT_GRACE_SECONDS: i64 = 60;
+
+pub struct QuizService {
+    pub question_collection: Collection<QuizQuestion>,
+    pub collection: Collection<Quiz>,
+    pub attempt_collection: Collection<QuizAttempt>,
+    pub db: Database,
+}
+
+impl QuizService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            question_collection: db.collection::<QuizQuestion>("quiz_questions"),
+            collection: db.collection::<Quiz>("quizzes"),
+            attempt_collection: db.collection::<QuizAttempt>("quiz_attempts"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let question_indexes = vec![
+            IndexDef::compound(vec![("class_subject_id", 1), ("topic_id", 1)], false),
+            IndexDef::single("is_deleted", false),
+        ];
+        let quiz_indexes = vec![
+            IndexDef::single("assignment_id", true),
+            IndexDef::compound(
+                vec![
+                    ("class_subject_id", 1),
+                    ("exam_id", 1),
+                    ("assessment_category_id", 1),
+                ],
+                false,
+            ),
+        ];
+        let attempt_indexes = vec![
+            IndexDef::compound(
+                vec![
+                    ("assignment_id", 1),
+                    ("student_id", 1),
+                    ("attempt_number", 1),
+                ],
+                true,
+            ),
+            IndexDef::single("quiz_id", false),
+        ];
+
+        BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        )
+        .ensure_indexes(&question_indexes)
+        .await?;
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&quiz_indexes)
+            .await?;
+        BaseRepository::new(
+            self.attempt_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        )
+        .ensure_indexes(&attempt_indexes)
+        .await
+    }
+
+    // =========================
+    // QUESTION BANK
+    // =========================
+
+    pub async fn create_question(
+        &self,
+        school_id: Option<ObjectId>,
+        dto: CreateQuizQuestion,
+        created_by: ObjectId,
+    ) -> Result<QuizQuestion, AppError> {
+        self.ensure_indexes().await?;
+
+        let class_subject_id = parse_object_id_value(&dto.class_subject_id)?;
+        let subject_exists = self
+            .db
+            .collection::<Document>("class_subjects")
+            .count_documents(doc! { "_id": class_subject_id })
+            .await?;
+        if subject_exists == 0 {
+            return Err(AppError {
+                message: "Class subject not found".into(),
+            });
+        }
+        let topic_id = self
+            .resolve_topic(class_subject_id, dto.topic_id.as_deref())
+            .await?;
+
+        let now = Utc::now();
+        let question = QuizQuestion {
+            id: None,
+            school_id,
+            class_subject_id,
+            topic_id,
+            question_type: dto.question_type,
+            prompt: dto.prompt,
+            points: dto.points.unwrap_or(1.0),
+            options: dto.options,
+            correct_option_ids: dto.correct_option_ids,
+            partial_credit: dto.partial_credit,
+            correct_boolean: dto.correct_boolean,
+            numeric_answer: dto.numeric_answer,
+            numeric_tolerance: dto.numeric_tolerance,
+            accepted_answers: dto.accepted_answers,
+            answer_match: dto.answer_match,
+            case_sensitive: dto.case_sensitive,
+            explanation: dto.explanation,
+            created_by: Some(created_by),
+            is_deleted: false,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+        Self::validate_question(&question)?;
+
+        let repo = BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        repo.create::<QuizQuestion>(to_stored_document(&question)?, None)
+            .await
+    }
+
+    pub async fn find_question(&self, id: &IdType) -> Result<QuizQuestion, AppError> {
+        let repo = BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        repo.find_one::<QuizQuestion>(
+            doc! { "_id": IdType::to_object_id(id)?, "is_deleted": false },
+            None,
+        )
+        .await?
+        .ok_or(AppError {
+            message: "Question not found".into(),
+        })
+    }
+
+    pub async fn get_questions(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<QuizQuestion>, AppError> {
+        let repo = BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        let searchable = [
+            "_id",
+            "class_subject_id",
+            "topic_id",
+            "question_type",
+            "prompt",
+        ];
+
+        let mut match_doc = extra_match.unwrap_or_default();
+        match_doc.insert("is_deleted", false);
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<QuizQuestion>(filter, &searchable, limit, skip, Some(match_doc))
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Changing the answer key does not touch graded attempts until the
+    /// quiz is regraded
+    pub async fn update_question(
+        &self,
+        id: &IdType,
+        dto: UpdateQuizQuestion,
+    ) -> Result<QuizQuestion, AppError> {
+        let mut question = self.find_question(id).await?;
+
+        if dto.topic_id.is_some() {
+            question.topic_id = self
+                .resolve_topic(question.class_subject_id, dto.topic_id.as_deref())
+                .await?;
+        }
+        if let Some(prompt) = dto.prompt {
+            question.prompt = prompt;
+        }
+        if let Some(points) = dto.points {
+            question.points = points;
+        }
+        if let Some(options) = dto.options {
+            question.options = options;
+        }
+        if let Some(ids) = dto.correct_option_ids {
+            question.correct_option_ids = ids;
+        }
+        if let Some(partial_credit) = dto.partial_credit {
+            question.partial_credit = partial_credit;
+        }
+        if dto.correct_boolean.is_some() {
+            question.correct_boolean = dto.correct_boolean;
+        }
+        if dto.numeric_answer.is_some() {
+            question.numeric_answer = dto.numeric_answer;
+        }
+        if dto.numeric_tolerance.is_some() {
+            question.numeric_tolerance = dto.numeric_tolerance;
+        }
+        if let Some(accepted) = dto.accepted_answers {
+            question.accepted_answers = accepted;
+        }
+        if let Some(answer_match) = dto.answer_match {
+            question.answer_match = answer_match;
+        }
+        if let Some(case_sensitive) = dto.case_sensitive {
+            question.case_sensitive = case_sensitive;
+        }
+        if dto.explanation.is_some() {
+            question.explanation = dto.explanation;
+        }
+        Self::validate_question(&question)?;
+
+        let mut set = to_stored_document(&question)?;
+        set.remove("_id");
+        set.remove("created_at");
+        let repo = BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        repo.update_one_and_fetch::<QuizQuestion>(id, set).await
+    }
+
+    /// Soft delete, attempts that used the question are still graded with it
+    pub async fn delete_question(&self, id: &IdType) -> Result<QuizQuestion, AppError> {
+        let question = self.find_question(id).await?;
+        let repo = BaseRepository::new(
+            self.question_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        repo.update_one_and_fetch::<QuizQuestion>(id, doc! { "is_deleted": true })
+            .await?;
+        Ok(question)
+    }
+
+    async fn resolve_topic(
+        &self,
+        class_subject_id: ObjectId,
+        topic_id: Option<&str>,
+    ) -> Result<Option<ObjectId>, AppError> {
+        let Some(topic_id) = topic_id else {
+            return Ok(None);
+        };
+        let topic_id = parse_object_id_value(topic_id)?;
+        let exists = self
+            .db
+            .collection::<Document>("subject_topics")
+            .count_documents(doc! { "_id": topic_id, "subject_id": class_subject_id })
+            .await?;
+        if exists == 0 {
+            return Err(AppError {
+                message: "Topic does not belong to this class subject".into(),
+            });
+        }
+        Ok(Some(topic_id))
+    }
+
+    fn validate_question(question: &QuizQuestion) -> Result<(), AppError> {
+        let fail = |message: &str| {
+            Err(AppError {
+                message: message.into(),
+            })
+        };
+
+        if question.prompt.trim().is_empty() {
+            return fail("Question prompt is required");
+        }
+        if question.points <= 0.0 {
+            return fail("Question points must be greater than 0");
+        }
+
+        match question.question_type {
+            QuestionType::MultipleChoice | QuestionType::MultiSelect => {
+                if question.options.len() < 2 {
+                    return fail("At least two options are required");
+                }
+                let mut ids: Vec<&str> = question.options.iter().map(|o| o.id.as_str()).collect();
+                ids.sort_unstable();
+                ids.dedup();
+                if ids.len() != question.options.len() || ids.iter().any(|id| id.is_empty()) {
+                    return fail("Option ids must be unique and not empty");
+                }
+                if question
+                    .correct_option_ids
+                    .iter()
+                    .any(|id| !ids.contains(&id.as_str()))
+                {
+                    return fail("Correct options must be among the question's options");
+                }
+                let correct = question.correct_option_ids.len();
+                if question.question_type == QuestionType::MultipleChoice && correct != 1 {
+                    return fail("Multiple choice questions need exactly one correct option");
+                }
+                if correct == 0 {
+                    return fail("At least one correct option is required");
+                }
+            }
+            QuestionType::TrueFalse => {
+                if question.correct_boolean.is_none() {
+                    return fail("correct_boolean is required for true/false questions");
+                }
+            }
+            QuestionType::Numeric => {
+                if question.numeric_answer.is_none() {
+                    return fail("numeric_answer is required for numeric questions");
+                }
+                if question.numeric_tolerance.is_some_and(|t| t < 0.0) {
+                    return fail("numeric_tolerance cannot be negative");
+                }
+            }
+            QuestionType::ShortAnswer => {
+                if question
+                    .accepted_answers
+                    .iter()
+                    .all(|a| a.trim().is_empty())
+                {
+                    return fail("At least one accepted answer is required");
+                }
+                if question.answer_match == ShortAnswerMatch::Pattern {
+                    for pattern in &question.accepted_answers {
+                        if let Err(e) = RegexBuilder::new(pattern).build() {
+                            return Err(AppError {
+                                message: format!("Invalid answer pattern '{}': {}", pattern, e),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // =========================
+    // QUIZ SETTINGS
+    // =========================
+
+    async fn find_assignment(&self, assignment_id: ObjectId) -> Result<Assignment, AppError> {
+        AssignmentService::new(&self.db)
+            .find_one_assignment(Some(&IdType::ObjectId(assignment_id)), None)
+            .await
+    }
+
+    pub async fn find_quiz(&self, assignment_id: ObjectId) -> Result<Quiz, AppError> {
+        self.collection
+            .find_one(doc! { "assignment_id": assignment_id })
+            .await?
+            .ok_or(AppError {
+                message: "This assignment has no quiz".into(),
+            })
+    }
+
+    /// Turn an assignment into an auto-graded quiz, or change its settings
+    pub async fn upsert_quiz(
+        &self,
+        assignment_id: ObjectId,
+        dto: UpsertQuiz,
+        created_by: ObjectId,
+    ) -> Result<Quiz, AppError> {
+        self.ensure_indexes().await?;
+
+        let assignment = self.find_assignment(assignment_id).await?;
+        let class_subject_id = assignment.subject_id.ok_or(AppError {
+            message: "Assignment has no subject".into(),
+        })?;
+
+        if dto.question_ids.is_empty() {
+            return Err(AppError {
+                message: "A quiz needs at least one question".into(),
+            });
+        }
+        if dto.time_limit_minutes.is_some_and(|m| m <= 0) {
+            return Err(AppError {
+                message: "time_limit_minutes must be greater than 0".into(),
+            });
+        }
+        if dto.max_attempts.is_some_and(|a| a <= 0) {
+            return Err(AppError {
+                message: "max_attempts must be greater than 0".into(),
+            });
+        }
+
+        let question_ids = dto
+            .question_ids
+            .iter()
+            .map(|id| parse_object_id_value(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let found = self
+            .question_collection
+            .count_documents(doc! {
+                "_id": { "$in": &question_ids },
+                "class_subject_id": class_subject_id,
+                "is_deleted": false
+            })
+            .await?;
+        if found as usize != question_ids.len() {
+            return Err(AppError {
+                message: "Questions must be distinct bank questions of the assignment's subject"
+                    .into(),
+            });
+        }
+
+        let assessment_category_id = dto
+            .assessment_category_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+        if let Some(category_id) = assessment_category_id {
+            let category = self
+                .db
+                .collection::<AssessmentCategory>("assessment_categories")
+                .find_one(doc! { "_id": category_id, "is_deleted": { "$ne": true } })
+                .await?
+                .ok_or(AppError {
+                    message: "Assessment category not found".into(),
+                })?;
+            if category
+                .class_subject_id
+                .is_some_and(|id| id != class_subject_id)
+            {
+                return Err(AppError {
+                    message: "Assessment category belongs to another class subject".into(),
+                });
+            }
+        }
+        let exam_id = dto
+            .exam_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+
+        let now = Utc::now();
+        let existing = self
+            .collection
+            .find_one(doc! { "assignment_id": assignment_id })
+            .await?;
+        let quiz = Quiz {
+            id: existing.as_ref().and_then(|q| q.id),
+            school_id: assignment.school_id,
+            assignment_id,
+            class_subject_id,
+            question_ids,
+            shuffle_questions: dto.shuffle_questions,
+            shuffle_options: dto.shuffle_options,
+            time_limit_minutes: dto.time_limit_minutes,
+            max_attempts: dto.max_attempts,
+            grading_policy: dto.grading_policy,
+            show_correct_answers: dto.show_correct_answers,
+            assessment_category_id,
+            exam_id,
+            created_by: existing
+                .as_ref()
+                .and_then(|q| q.created_by)
+                .or(Some(created_by)),
+            created_at: existing.as_ref().and_then(|q| q.created_at).or(Some(now)),
+            updated_at: Some(now),
+        };
+
+        let mut set = to_stored_document(&quiz)?;
+        set.remove("_id");
+        let quiz = self
+            .collection
+            .find_one_and_update(
+                doc! { "assignment_id": assignment_id },
+                doc! { "$set": set },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Failed to save quiz".into(),
+            })?;
+
+        AssignmentService::new(&self.db)
+            .collection
+            .update_one(
+                doc! { "_id": assignment_id },
+                doc! { "$set": {
+                    "auto_grade_enabled": true,
+                    "assessment_category_id": quiz.assessment_category_id,
+                    "exam_id": quiz.exam_id
+                } },
+            )
+            .await?;
+
+        Ok(quiz)
+    }
+
+    /// Only quizzes nobody has attempted can be removed
+    pub async fn delete_quiz(&self, assignment_id: ObjectId) -> Result<Quiz, AppError> {
+        let quiz = self.find_quiz(assignment_id).await?;
+        let attempts = self
+            .attempt_collection
+            .count_documents(doc! { "assignment_id": assignment_id })
+            .await?;
+        if attempts > 0 {
+            return Err(AppError {
+                message: "Students have already attempted this quiz".into(),
+            });
+        }
+
+        self.collection
+            .delete_one(doc! { "assignment_id": assignment_id })
+            .await?;
+        AssignmentService::new(&self.db)
+            .collection
+            .update_one(
+                doc! { "_id": assignment_id },
+                doc! { "$set": { "auto_grade_enabled": false } },
+            )
+            .await?;
+        Ok(quiz)
+    }
+
+    // =========================
+    // ATTEMPTS
+    // =========================
+
+    pub async fn find_attempt(&self, id: &IdType) -> Result<QuizAttempt, AppError> {
+        self.attempt_collection
+            .find_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?
+            .ok_or(AppError {
+                message: "Quiz attempt not found".into(),
+            })
+    }
+
+    pub async fn get_attempts(
+        &self,
+        assignment_id: ObjectId,
+        student_id: Option<ObjectId>,
+    ) -> Result<Vec<QuizAttempt>, AppError> {
+        let mut filter = doc! { "assignment_id": assignment_id };
+        if let Some(student_id) = student_id {
+            filter.insert("student_id", student_id);
+        }
+
+        let attempts = self
+            .attempt_collection
+            .find(filter)
+            .sort(doc! { "student_id": 1, "attempt_number": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        Ok(attempts)
+    }
+
+    /// Lay out a new attempt. An unfinished attempt is resumed instead, one
+    /// that ran out of time is closed first.
+    pub async fn start_attempt(
+        &self,
+        assignment_id: ObjectId,
+        student_id: ObjectId,
+        actor: ObjectId,
+    ) -> Result<QuizAttemptView, AppError> {
+        self.ensure_indexes().await?;
+
+        let assignment = self.find_assignment(assignment_id).await?;
+        if assignment.status != AssignmentStatus::Published {
+            return Err(AppError {
+                message: "Assignment is not published".into(),
+            });
+        }
+        let quiz = self.find_quiz(assignment_id).await?;
+
+        let attempts = self.get_attempts(assignment_id, Some(student_id)).await?;
+        if let Some(open) = attempts
+            .iter()
+            .find(|a| a.status == QuizAttemptStatus::InProgress)
+        {
+            if !Self::is_expired(open) {
+                return self.view(open.clone(), false).await;
+            }
+            self.grade_attempt(open.clone(), &quiz, &assignment, vec![], actor)
+                .await?;
+        }
+
+        let attempt_number = attempts.len() as i32 + 1;
+        if quiz.max_attempts.is_some_and(|max| attempt_number > max) {
+            return Err(AppError {
+                message: "No attempts left for this quiz".into(),
+            });
+        }
+        AssignmentService::new(&self.db)
+            .validate_submission_deadline(&assignment)
+            .await?;
+
+        let mut rng = thread_rng();
+        let questions = self.load_questions(&quiz.question_ids).await?;
+        let mut layout: Vec<AttemptQuestion> = quiz
+            .question_ids
+            .iter()
+            .filter_map(|id| questions.get(id))
+            .map(|q| {
+                let mut option_ids: Vec<String> = q.options.iter().map(|o| o.id.clone()).collect();
+                if quiz.shuffle_options {
+                    option_ids.shuffle(&mut rng);
+                }
+                AttemptQuestion {
+                    question_id: q.id.unwrap_or_default(),
+                    option_ids,
+                }
+            })
+            .collect();
+        if quiz.shuffle_questions {
+            layout.shuffle(&mut rng);
+        }
+
+        let now = Utc::now();
+        let attempt = QuizAttempt {
+            id: None,
+            quiz_id: quiz.id.unwrap_or_default(),
+            assignment_id,
+            student_id,
+            attempt_number,
+            questions: layout,
+            started_at: now,
+            expires_at: quiz
+                .time_limit_minutes
+                .map(|m| now + Duration::minutes(m as i64)),
+            submitted_at: None,
+            answers: vec![],
+            raw_score: None,
+            raw_max_score: None,
+            score: None,
+            status: QuizAttemptStatus::InProgress,
+            timed_out: false,
+        };
+
+        let repo = BaseRepository::new(
+            self.attempt_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        );
+        let attempt = repo
+            .create::<QuizAttempt>(to_stored_document(&attempt)?, None)
+            .await
+            .map_err(|e| {
+                if e.message.contains("duplicate key") {
+                    AppError {
+                        message: "An attempt was started at the same time, try again".into(),
+                    }
+                } else {
+                    e
+                }
+            })?;
+        self.view(attempt, false).await
+    }
+
+    pub async fn submit_attempt(
+        &self,
+        id: &IdType,
+        student_id: ObjectId,
+        dto: SubmitQuizAttempt,
+        actor: ObjectId,
+    ) -> Result<QuizAttemptView, AppError> {
+        let attempt = self.find_attempt(id).await?;
+        if attempt.student_id != student_id {
+            return Err(AppError {
+                message: "This attempt belongs to another student".into(),
+            });
+        }
+        if attempt.status == QuizAttemptStatus::Graded {
+            return Err(AppError {
+                message: "This attempt was already submitted".into(),
+            });
+        }
+
+        let quiz = self.find_quiz(attempt.assignment_id).await?;
+        let assignment = self.find_assignment(attempt.assignment_id).await?;
+        let answers = if Self::is_expired(&attempt) {
+            vec![]
+        } else {
+            dto.answers
+        };
+
+        let graded = self
+            .grade_attempt(attempt, &quiz, &assignment, answers, actor)
+            .await?;
+        self.view(graded, quiz.show_correct_answers).await
+    }
+
+    /// Attempt with its questions stripped of answer keys. `reveal` adds
+    /// per-question results and explanations once the attempt is graded.
+    pub async fn view(
+        &self,
+        mut attempt: QuizAttempt,
+        reveal: bool,
+    ) -> Result<QuizAttemptView, AppError> {
+        let ids: Vec<ObjectId> = attempt.questions.iter().map(|q| q.question_id).collect();
+        let questions = self.load_questions(&ids).await?;
+        let reveal = reveal && attempt.status == QuizAttemptStatus::Graded;
+
+        let questions_view = attempt
+            .questions
+            .iter()
+            .filter_map(|slot| {
+                let q = questions.get(&slot.question_id)?;
+                let options = slot
+                    .option_ids
+                    .iter()
+                    .filter_map(|id| q.options.iter().find(|o| &o.id == id).cloned())
+                    .collect();
+                let result = attempt
+                    .answers
+                    .iter()
+                    .find(|a| a.answer.question_id == slot.question_id)
+                    .cloned();
+                Some(StudentQuizQuestion {
+                    question_id: slot.question_id,
+                    question_type: q.question_type,
+                    prompt: q.prompt.clone(),
+                    points: q.points,
+                    options,
+                    result: result.filter(|_| reveal),
+                    explanation: q.explanation.clone().filter(|_| reveal),
+                })
+            })
+            .collect();
+
+        if !reveal {
+            attempt.answers.clear();
+        }
+        Ok(QuizAttemptView {
+            attempt,
+            questions_view,
+        })
+    }
+
+    fn is_expired(attempt: &QuizAttempt) -> bool {
+        attempt
+            .expires_at
+            .is_some_and(|at| Utc::now() > at + Duration::seconds(SUBMIT_GRACE_SECONDS))
+    }
+
+    async fn load_questions(
+        &self,
+        ids: &[ObjectId],
+    ) -> Result<HashMap<ObjectId, QuizQuestion>, AppError> {
+        let questions: Vec<QuizQuestion> = self
+            .question_collection
+            .find(doc! { "_id": { "$in": ids } })
+            .await?
+            .try_collect()
+            .await?;
+        Ok(questions
+            .into_iter()
+            .filter_map(|q| q.id.map(|id| (id, q)))
+            .collect())
+    }
+
+    // =========================
+    // GRADING
+    // =========================
+
+    async fn grade_attempt(
+        &self,
+        attempt: QuizAttempt,
+        quiz: &Quiz,
+        assignment: &Assignment,
+        answers: Vec<QuizAnswer>,
+        actor: ObjectId,
+    ) -> Result<QuizAttempt, AppError> {
+        let attempt_id = attempt.id.ok_or(AppError {
+            message: "Attempt has no id".into(),
+        })?;
+        let timed_out = Self::is_expired(&attempt);
+        let (graded, raw_score, raw_max) = self.grade_answers(&attempt, answers).await?;
+        let score = Self::scale(raw_score, raw_max, assignment.max_score);
+
+        let graded_docs = graded
+            .iter()
+            .map(to_stored_document)
+            .collect::<Result<Vec<_>, _>>()?;
+        let attempt = self
+            .attempt_collection
+            .find_one_and_update(
+                doc! {
+                    "_id": attempt_id,
+                    "status": bson::to_bson(&QuizAttemptStatus::InProgress).unwrap()
+                },
+                doc! { "$set": {
+                    "answers": graded_docs,
+                    "raw_score": raw_score,
+                    "raw_max_score": raw_max,
+                    "score": score,
+                    "status": bson::to_bson(&QuizAttemptStatus::Graded).unwrap(),
+                    "submitted_at": bson::to_bson(&Utc::now()).unwrap(),
+                    "timed_out": timed_out
+                } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "This attempt was already submitted".into(),
+            })?;
+
+        self.record_result(quiz, assignment, attempt.student_id, actor)
+            .await?;
+        Ok(attempt)
+    }
+
+    /// Grade the answers against the questions laid out for the attempt.
+    /// Unanswered questions score zero, answers to other questions are ignored.
+    async fn grade_answers(
+        &self,
+        attempt: &QuizAttempt,
+        answers: Vec<QuizAnswer>,
+    ) -> Result<(Vec<GradedAnswer>, f64, f64), AppError> {
+        let ids: Vec<ObjectId> = attempt.questions.iter().map(|q| q.question_id).collect();
+        let questions = self.load_questions(&ids).await?;
+        let mut answers: HashMap<ObjectId, QuizAnswer> =
+            answers.into_iter().map(|a| (a.question_id, a)).collect();
+
+        let mut graded = Vec::new();
+        let (mut raw_score, mut raw_max) = (0.0, 0.0);
+        for slot in &attempt.questions {
+            let Some(question) = questions.get(&slot.question_id) else {
+                continue;
+            };
+            let answer = answers.remove(&slot.question_id).unwrap_or(QuizAnswer {
+                question_id: slot.question_id,
+                selected_option_ids: vec![],
+                boolean_answer: None,
+                numeric_answer: None,
+                text_answer: None,
+            });
+
+            let fraction = Self::grade_question(question, &answer);
+            let points_awarded = round2(question.points * fraction);
+            raw_score += points_awarded;
+            raw_max += question.points;
+            graded.push(GradedAnswer {
+                answer,
+                is_correct: fraction >= 1.0,
+                points_awarded,
+                points_possible: question.points,
+            });
+        }
+        Ok((graded, round2(raw_score), round2(raw_max)))
+    }
+
+    /// Share of the question's points earned, between 0 and 1
+    fn grade_question(question: &QuizQuestion, answer: &QuizAnswer) -> f64 {
+        let correct = |ok: bool| if ok { 1.0 } else { 0.0 };
+
+        match question.question_type {
+            QuestionType::MultipleChoice => correct(
+                answer.selected_option_ids.len() == 1
+                    && question
+                        .correct_option_ids
+                        .contains(&answer.selected_option_ids[0]),
+            ),
+            QuestionType::MultiSelect => {
+                let mut selected = answer.selected_option_ids.clone();
+                selected.sort_unstable();
+                selected.dedup();
+                let right = selected
+                    .iter()
+                    .filter(|id| question.correct_option_ids.contains(id))
+                    .count();
+                let wrong = selected.len() - right;
+                let expected = question.correct_option_ids.len();
+
+                if question.partial_credit && expected > 0 {
+                    (right as f64 - wrong as f64).max(0.0) / expected as f64
+                } else {
+                    correct(wrong == 0 && right == expected)
+                }
+            }
+            QuestionType::TrueFalse => correct(
+                answer.boolean_answer.is_some()
+                    && answer.boolean_answer == question.correct_boolean,
+            ),
+            QuestionType::Numeric => match (answer.numeric_answer, question.numeric_answer) {
+                (Some(given), Some(expected)) => {
+                    let tolerance = question.numeric_tolerance.unwrap_or(0.0);
+                    // Slack for the rounding in decimal answers, so 9.76 is
+                    // within 0.05 of 9.81
+                    let slack = 1e-9 * given.abs().max(expected.abs()).max(1.0);
+                    correct((given - expected).abs() <= tolerance + slack)
+                }
+                _ => 0.0,
+            },
+            QuestionType::ShortAnswer => {
+                let Some(text) = answer.text_answer.as_deref() else {
+                    return 0.0;
+                };
+                let given = normalize(text);
+                correct(
+                    !given.is_empty()
+                        && question.accepted_answers.iter().any(|accepted| {
+                            match question.answer_match {
+                                ShortAnswerMatch::Exact if question.case_sensitive => {
+                                    normalize(accepted) == given
+                                }
+                                ShortAnswerMatch::Exact => {
+                                    normalize(accepted).to_lowercase() == given.to_lowercase()
+                                }
+                                ShortAnswerMatch::Pattern => {
+                                    RegexBuilder::new(&format!("^(?:{})$", accepted))
+                                        .case_insensitive(!question.case_sensitive)
+                                        .build()
+                                        .is_ok_and(|re| re.is_match(&given))
+                                }
+                            }
+                        }),
+                )
+            }
+        }
+    }
+
+    fn scale(raw_score: f64, raw_max: f64, max_score: f64) -> f64 {
+        if raw_max <= 0.0 {
+            return 0.0;
+        }
+        round2(raw_score / raw_max * max_score)
+    }
+
+    /// Grade attempts again with the current answer keys, for one student or
+    /// the whole class
+    pub async fn regrade(
+        &self,
+        assignment_id: ObjectId,
+        student_id: Option<ObjectId>,
+        actor: ObjectId,
+    ) -> Result<usize, AppError> {
+        let quiz = self.find_quiz(assignment_id).await?;
+        let assignment = self.find_assignment(assignment_id).await?;
+
+        let attempts: Vec<QuizAttempt> = self
+            .get_attempts(assignment_id, student_id)
+            .await?
+            .into_iter()
+            .filter(|a| a.status == QuizAttemptStatus::Graded)
+            .collect();
+
+        let mut students = Vec::new();
+        for attempt in &attempts {
+            let answers = attempt.answers.iter().map(|a| a.answer.clone()).collect();
+            let (graded, raw_score, raw_max) = self.grade_answers(attempt, answers).await?;
+            let graded_docs = graded
+                .iter()
+                .map(to_stored_document)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            self.attempt_collection
+                .update_one(
+                    doc! { "_id": attempt.id },
+                    doc! { "$set": {
+                        "answers": graded_docs,
+                        "raw_score": raw_score,
+                        "raw_max_score": raw_max,
+                        "score": Self::scale(raw_score, raw_max, assignment.max_score)
+                    } },
+                )
+                .await?;
+            if !students.contains(&attempt.student_id) {
+                students.push(attempt.student_id);
+            }
+        }
+
+        for student_id in &students {
+            self.record_result(&quiz, &assignment, *student_id, actor)
+                .await?;
+        }
+        Ok(attempts.len())
+    }
+
+    // =========================
+    // SUBMISSION & SCORE
+    // =========================
+
+    /// Write the kept attempt score to the student's submission, then carry
+    /// it into the gradebook; a refused gradebook update is recorded as the
+    /// submission's `score_sync_error`
+    async fn record_result(
+        &self,
+        quiz: &Quiz,
+        assignment: &Assignment,
+        student_id: ObjectId,
+        actor: ObjectId,
+    ) -> Result<(), AppError> {
+        let attempts: Vec<QuizAttempt> = self
+            .get_attempts(quiz.assignment_id, Some(student_id))
+            .await?
+            .into_iter()
+            .filter(|a| a.status == QuizAttemptStatus::Graded)
+            .collect();
+        let kept = match quiz.grading_policy {
+            QuizGradingPolicy::Highest => attempts
+                .iter()
+                .filter_map(|a| a.score)
+                .max_by(|a, b| a.total_cmp(b)),
+            QuizGradingPolicy::Latest => attempts
+                .iter()
+                .max_by_key(|a| a.attempt_number)
+                .and_then(|a| a.score),
+        };
+        let Some(kept) = kept else {
+            return Ok(());
+        };
+        let first_started = attempts.iter().map(|a| a.started_at).min();
+
+        let assignment_service = AssignmentService::new(&self.db);
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let existing = assignment_service
+            .submission_collection
+            .find_one(doc! {
+                "assignment_id": quiz.assignment_id,
+                "student_id": student_id,
+                "is_deleted": false
+            })
+            .await?;
+
+        let submission_id = match existing {
+            Some(submission) => {
+                let mut set = doc! { "auto_grade_score": kept, "updated_at": now.clone() };
+                // A teacher's manual grade wins over the quiz score
+                if submission.graded_by.is_none() {
+                    set.insert("score", kept);
+                    set.insert("status", bson::to_bson(&SubmissionStatus::Graded).unwrap());
+                    set.insert("graded_at", now);
+                }
+                assignment_service
+                    .submission_collection
+                    .update_one(doc! { "_id": submission.id }, doc! { "$set": set })
+                    .await?;
+                submission.id
+            }
+            None => {
+                let submission = Submission {
+                    id: None,
+                    assignment_id: Some(quiz.assignment_id),
+                    student_id: Some(student_id),
+                    group_id: None,
+                    file_url: None,
+                    file_id: None,
+                    comment: None,
+                    text_content: None,
+                    is_late: first_started.is_some_and(|at| at > assignment.due_date),
+                    late_days: None,
+                    late_penalty: None,
+                    score: Some(kept),
+                    raw_score: None,
+                    rubric_scores: None,
+                    feedback: None,
+                    feedback_file_url: None,
+                    feedback_file_id: None,
+                    contributions: Vec::new(),
+                    member_grades: Vec::new(),
+                    graded_at: Some(Utc::now()),
+                    graded_by: None,
+                    status: SubmissionStatus::Graded,
+                    auto_grade_score: Some(kept),
+                    score_sync_error: None,
+                    peer_review_score: None,
+                    ai_feedback: None,
+                    ai_suggested_score: None,
+                    ai_feedback_status: None,
+                    ai_feedback_generated_at: None,
+                    version: 1,
+                    versions: Vec::new(),
+                    is_deleted: false,
+                    deleted_at: None,
+                    submitted_at: first_started.unwrap_or_else(Utc::now),
+                    updated_at: Utc::now(),
+                };
+                BaseRepository::new(
+                    assignment_service
+                        .submission_collection
+                        .clone()
+                        .clone_with_type::<Document>(),
+                )
+                .create::<Submission>(submission.to_document()?, None)
+                .await?
+                .id
+            }
+        };
+
+        // The attempt stays graded when the gradebook refuses the score
+        if let Some(submission_id) = submission_id {
+            let assignment = self.align_assignment(quiz, assignment).await?;
+            assignment_service
+                .record_score_sync(&assignment, submission_id, &[student_id], actor)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The quiz's category and exam decide where its marks go; quizzes set
+    /// up before they were kept on the assignment get them copied over
+    async fn align_assignment(
+        &self,
+        quiz: &Quiz,
+        assignment: &Assignment,
+    ) -> Result<Assignment, AppError> {
+        if assignment.assessment_category_id == quiz.assessment_category_id
+            && assignment.exam_id == quiz.exam_id
+        {
+            return Ok(assignment.clone());
+        }
+
+        AssignmentService::new(&self.db)
+            .collection
+            .update_one(
+                doc! { "_id": quiz.assignment_id },
+                doc! { "$set": {
+                    "assessment_category_id": quiz.assessment_category_id,
+                    "exam_id": quiz.exam_id
+                } },
+            )
+            .await?;
+        Ok(Assignment {
+            assessment_category_id: quiz.assessment_category_id,
+            exam_id: quiz.exam_id,
+            ..assignment.clone()
+        })
+    }
+}
+
+/// Trim and collapse runs of whitespace
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(question_type: QuestionType) -> QuizQuestion {
+        QuizQuestion {
+            id: None,
+            school_id: None,
+            class_subject_id: ObjectId::new(),
+            topic_id: None,
+            question_type,
+            prompt: "Question".into(),
+            points: 4.0,
+            options: Vec::new(),
+            correct_option_ids: Vec::new(),
+            partial_credit: false,
+            correct_boolean: None,
+            numeric_answer: None,
+            numeric_tolerance: None,
+            accepted_answers: Vec::new(),
+            answer_match: ShortAnswerMatch::Exact,
+            case_sensitive: false,
+            explanation: None,
+            created_by: None,
+            is_deleted: false,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn answer() -> QuizAnswer {
+        QuizAnswer {
+            question_id: ObjectId::new(),
+            selected_option_ids: Vec::new(),
+            boolean_answer: None,
+            numeric_answer: None,
+            text_answer: None,
+        }
+    }
+
+    fn selecting(ids: &[&str]) -> QuizAnswer {
+        QuizAnswer {
+            selected_option_ids: ids.iter().map(|id| id.to_string()).collect(),
+            ..answer()
+        }
+    }
+
+    fn numeric(value: f64) -> QuizAnswer {
+        QuizAnswer {
+            numeric_answer: Some(value),
+            ..answer()
+        }
+    }
+
+    fn text(value: &str) -> QuizAnswer {
+        QuizAnswer {
+            text_answer: Some(value.into()),
+            ..answer()
+        }
+    }
+
+    fn multi_select(partial_credit: bool) -> QuizQuestion {
+        QuizQuestion {
+            correct_option_ids: vec!["a".into(), "b".into(), "c".into()],
+            partial_credit,
+            ..question(QuestionType::MultiSelect)
+        }
+    }
+
+    #[test]
+    fn multi_select_is_all_or_nothing_without_partial_credit() {
+        let q = multi_select(false);
+
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["c", "a", "b"])), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "b"])), 0.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "b", "c", "d"])), 0.0);
+    }
+
+    #[test]
+    fn multi_select_partial_credit_takes_off_wrong_picks() {
+        let q = multi_select(true);
+
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "b", "c"])), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "b", "b"])), 2.0 / 3.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "b", "d"])), 1.0 / 3.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&["a", "d", "e"])), 0.0);
+        assert_eq!(QuizService::grade_question(&q, &selecting(&[])), 0.0);
+    }
+
+    #[test]
+    fn numeric_answers_within_tolerance_are_correct() {
+        let q = QuizQuestion {
+            numeric_answer: Some(9.81),
+            numeric_tolerance: Some(0.05),
+            ..question(QuestionType::Numeric)
+        };
+
+        assert_eq!(QuizService::grade_question(&q, &numeric(9.81)), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &numeric(9.76)), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &numeric(9.86)), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &numeric(9.9)), 0.0);
+        assert_eq!(QuizService::grade_question(&q, &answer()), 0.0);
+
+        let exact = QuizQuestion {
+            numeric_tolerance: None,
+            ..q
+        };
+        assert_eq!(QuizService::grade_question(&exact, &numeric(9.81)), 1.0);
+        assert_eq!(QuizService::grade_question(&exact, &numeric(9.8)), 0.0);
+    }
+
+    #[test]
+    fn short_answers_match_exactly_after_normalizing() {
+        let q = QuizQuestion {
+            accepted_answers: vec!["Kigali City".into()],
+            ..question(QuestionType::ShortAnswer)
+        };
+        assert_eq!(QuizService::grade_question(&q, &text("  kigali   city ")), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &text("Kigali")), 0.0);
+        assert_eq!(QuizService::grade_question(&q, &text("   ")), 0.0);
+
+        let strict = QuizQuestion {
+            case_sensitive: true,
+            ..q
+        };
+        assert_eq!(QuizService::grade_question(&strict, &text("Kigali City")), 1.0);
+        assert_eq!(QuizService::grade_question(&strict, &text("kigali city")), 0.0);
+    }
+
+    #[test]
+    fn short_answer_patterns_match_the_whole_answer() {
+        let q = QuizQuestion {
+            accepted_answers: vec!["photo ?synthesis".into(), "[".into()],
+            answer_match: ShortAnswerMatch::Pattern,
+            ..question(QuestionType::ShortAnswer)
+        };
+
+        assert_eq!(QuizService::grade_question(&q, &text("Photosynthesis")), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &text("photo synthesis")), 1.0);
+        assert_eq!(QuizService::grade_question(&q, &text("photosynthesis is")), 0.0);
+        assert_eq!(QuizService::grade_question(&q, &text("[")), 0.0);
+
+        let strict = QuizQuestion {
+            case_sensitive: true,
+            ..q
+        };
+        assert_eq!(QuizService::grade_question(&strict, &text("Photosynthesis")), 0.0);
+    }
+
+    #[test]
+    fn scale_maps_raw_points_onto_the_assignment() {
+        assert_eq!(QuizService::scale(7.0, 8.0, 20.0), 17.5);
+        assert_eq!(QuizService::scale(1.0, 3.0, 10.0), 3.33);
+        assert_eq!(QuizService::scale(5.0, 0.0, 20.0), 0.0);
+    }
+}
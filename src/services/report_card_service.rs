@@ -0,0 +1,963 @@
+use std::{collections::HashMap, io::Write};
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class::Class,
+        common_details::Paginated,
+        education_year::EducationYear,
+        exam::Exam,
+        parent::AttendanceSummary,
+        report_card::{
+            ReportCardFailure, ReportCardJob, ReportCardJobStatus, ReportCardTemplate,
+            ReportCardVerification, UpdateReportCardTemplate,
+        },
+        school::School,
+        score::Score,
+        student::Student,
+        student_term_result::StudentTermResult,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::academic_record_service::ResultVisibility,
+    utils::{
+        code::generate_verification_code,
+        mongo_utils::to_stored_document,
+        pdf_utils::{fit_text, hex_color, JpegImage, PdfDocument, PAGE_HEIGHT, PAGE_WIDTH},
+    },
+};
+
+const REPORT_CARD_DIR: &str = "report_cards";
+const MARGIN: f32 = 40.0;
+/// Space kept free at the bottom of a page for the summary and footer
+const BOTTOM_RESERVE: f32 = 90.0;
+
+/// Everything shared by the cards of one exam, loaded once per job
+struct CardContext {
+    school_id: ObjectId,
+    school_name: String,
+    logo: Option<Vec<u8>>,
+    template: ReportCardTemplate,
+    exam: Exam,
+    year_label: Option<String>,
+    class_names: HashMap<ObjectId, String>,
+}
+
+pub struct ReportCardService {
+    pub template_collection: Collection<ReportCardTemplate>,
+    pub job_collection: Collection<ReportCardJob>,
+    pub verification_collection: Collection<ReportCardVerification>,
+    pub db: Database,
+    pub main_db: Database,
+}
+
+impl ReportCardService {
+    pub fn new(db: &Database, main_db: &Database) -> Self {
+        Self {
+            template_collection: db.collection::<ReportCardTemplate>("report_card_templates"),
+            job_collection: db.collection::<ReportCardJob>("report_card_jobs"),
+            verification_collection: main_db
+                .collection::<ReportCardVerification>("report_card_verifications"),
+            db: db.clone(),
+            main_db: main_db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let template_indexes = vec![IndexDef::single("school_id", true)];
+        let job_indexes = vec![
+            IndexDef::compound(vec![("class_id", 1), ("exam_id", 1)], false),
+            IndexDef::single("status", false),
+        ];
+        // One current code per card; superseded codes stay verifiable
+        let verification_indexes = vec![
+            IndexDef::single("verification_code", true),
+            IndexDef {
+                fields: vec![
+                    ("school_id".into(), 1),
+                    ("student_id".into(), 1),
+                    ("exam_id".into(), 1),
+                ],
+                unique: true,
+                partial: Some(doc! { "superseded": false }),
+                name: Some("current_card_per_exam".into()),
+            },
+        ];
+
+        BaseRepository::new(self.template_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&template_indexes)
+            .await?;
+        BaseRepository::new(self.job_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&job_indexes)
+            .await?;
+        BaseRepository::new(self.verification_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&verification_indexes)
+            .await?;
+        Ok(())
+    }
+
+    // =========================
+    // TEMPLATE
+    // =========================
+
+    /// The school's layout, or the default one when none was saved
+    pub async fn get_template(&self, school_id: ObjectId) -> Result<ReportCardTemplate, AppError> {
+        Ok(self
+            .template_collection
+            .find_one(doc! { "school_id": school_id })
+            .await?
+            .unwrap_or_else(|| ReportCardTemplate::new(Some(school_id))))
+    }
+
+    pub async fn update_template(
+        &self,
+        school_id: ObjectId,
+        update: &UpdateReportCardTemplate,
+    ) -> Result<ReportCardTemplate, AppError> {
+        self.ensure_indexes().await?;
+
+        if let Some(color) = &update.primary_color {
+            let hex = color.trim().trim_start_matches('#');
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(AppError {
+                    message: "primary_color must be a hex colour like #1F3A93".into(),
+                });
+            }
+        }
+        if update.title.as_deref().map(str::trim) == Some("") {
+            return Err(AppError {
+                message: "Title cannot be empty".into(),
+            });
+        }
+
+        let mut template = self.get_template(school_id).await?;
+        if let Some(v) = &update.title {
+            template.title = v.trim().to_string();
+        }
+        if let Some(v) = &update.primary_color {
+            template.primary_color = v.trim().to_string();
+        }
+        if let Some(v) = update.show_logo {
+            template.show_logo = v;
+        }
+        if let Some(v) = update.show_category_scores {
+            template.show_category_scores = v;
+        }
+        if let Some(v) = update.show_rank {
+            template.show_rank = v;
+        }
+        if let Some(v) = update.show_attendance {
+            template.show_attendance = v;
+        }
+        if let Some(v) = update.show_remarks {
+            template.show_remarks = v;
+        }
+        if update.principal_name.is_some() {
+            template.principal_name = update.principal_name.clone();
+        }
+        if update.footer_text.is_some() {
+            template.footer_text = update.footer_text.clone();
+        }
+        template.updated_at = Some(Utc::now());
+
+        let mut set = to_stored_document(&template)?;
+        set.remove("_id");
+
+        self.template_collection
+            .find_one_and_update(doc! { "school_id": school_id }, doc! { "$set": set })
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Failed to save report card template".into(),
+            })
+    }
+
+    // =========================
+    // SINGLE CARD
+    // =========================
+
+    async fn load_context(
+        &self,
+        school_id: ObjectId,
+        exam_id: ObjectId,
+    ) -> Result<CardContext, AppError> {
+        let school = self
+            .main_db
+            .collection::<School>("schools")
+            .find_one(doc! { "_id": school_id })
+            .await?
+            .ok_or(AppError {
+                message: "School not found".into(),
+            })?;
+        let exam = self
+            .db
+            .collection::<Exam>("exams")
+            .find_one(doc! { "_id": exam_id })
+            .await?
+            .ok_or(AppError {
+                message: "Exam not found".into(),
+            })?;
+
+        let year_label = match exam.education_year_id {
+            Some(year_id) => self
+                .db
+                .collection::<EducationYear>("education_years")
+                .find_one(doc! { "_id": year_id })
+                .await?
+                .map(|y| y.label),
+            None => None,
+        };
+
+        let template = self.get_template(school_id).await?;
+        let logo = match (&school.logo, template.show_logo) {
+            (Some(url), true) => fetch_logo(url).await,
+            _ => None,
+        };
+
+        Ok(CardContext {
+            school_id,
+            school_name: school.name,
+            logo,
+            template,
+            exam,
+            year_label,
+            class_names: HashMap::new(),
+        })
+    }
+
+    async fn class_name(
+        &self,
+        ctx: &mut CardContext,
+        class_id: Option<ObjectId>,
+    ) -> Result<Option<String>, AppError> {
+        let Some(class_id) = class_id else {
+            return Ok(None);
+        };
+        if let Some(name) = ctx.class_names.get(&class_id) {
+            return Ok(Some(name.clone()));
+        }
+
+        let name = self
+            .db
+            .collection::<Class>("classes")
+            .find_one(doc! { "_id": class_id })
+            .await?
+            .map(|c| c.name);
+        if let Some(name) = &name {
+            ctx.class_names.insert(class_id, name.clone());
+        }
+        Ok(name)
+    }
+
+    /// Render the card of one student and register its verification code
+    pub async fn generate(
+        &self,
+        school_id: ObjectId,
+        student: &Student,
+        exam_id: ObjectId,
+        visibility: ResultVisibility,
+    ) -> Result<(ReportCardVerification, Vec<u8>), AppError> {
+        self.ensure_indexes().await?;
+        let mut ctx = self.load_context(school_id, exam_id).await?;
+        self.render_card(&mut ctx, student, visibility).await
+    }
+
+    async fn render_card(
+        &self,
+        ctx: &mut CardContext,
+        student: &Student,
+        visibility: ResultVisibility,
+    ) -> Result<(ReportCardVerification, Vec<u8>), AppError> {
+        let student_id = student.id.ok_or(AppError {
+            message: "Student has no ID".into(),
+        })?;
+        let exam_id = ctx.exam.id.ok_or(AppError {
+            message: "Exam has no ID".into(),
+        })?;
+
+        // A card carries a public verification code, so drafts never get one
+        let mut filter = doc! { "student_id": student_id, "exam_id": exam_id };
+        visibility.finalized_or_stricter().apply(&mut filter);
+        let result = self
+            .db
+            .collection::<StudentTermResult>("student_term_results")
+            .find_one(filter)
+            .await?
+            .ok_or(AppError {
+                message: "No finalized result available for this student and exam".into(),
+            })?;
+
+        let class_name = self
+            .class_name(ctx, result.class_id.or(student.class_id))
+            .await?;
+        let remarks = self.subject_remarks(&student_id, &exam_id).await?;
+        let attendance = self.attendance_summary(&student_id, &ctx.school_id).await?;
+        let verification = self
+            .register_verification(ctx, student_id, exam_id, student, &result, class_name.clone())
+            .await?;
+
+        let pdf = render_pdf(ctx, student, &result, class_name, &remarks, &attendance, &verification);
+        Ok((verification, pdf))
+    }
+
+    /// Teacher remarks entered with the scores, joined per class subject
+    async fn subject_remarks(
+        &self,
+        student_id: &ObjectId,
+        exam_id: &ObjectId,
+    ) -> Result<HashMap<ObjectId, String>, AppError> {
+        let mut cursor = self
+            .db
+            .collection::<Score>("scores")
+            .find(doc! {
+                "student_id": student_id,
+                "exam_id": exam_id,
+                "is_deleted": false,
+                "remarks": { "$nin": [null, ""] }
+            })
+            .await?;
+
+        let mut remarks: HashMap<ObjectId, Vec<String>> = HashMap::new();
+        while cursor.advance().await? {
+            let score: Score = cursor.deserialize_current()?;
+            if let (Some(subject_id), Some(remark)) = (score.class_subject_id, score.remarks) {
+                remarks.entry(subject_id).or_default().push(remark);
+            }
+        }
+
+        Ok(remarks
+            .into_iter()
+            .map(|(id, list)| (id, list.join("; ")))
+            .collect())
+    }
+
+    async fn attendance_summary(
+        &self,
+        student_id: &ObjectId,
+        school_id: &ObjectId,
+    ) -> Result<AttendanceSummary, AppError> {
+        let rows: Vec<Document> = self
+            .db
+            .collection::<Document>("attendance")
+            .aggregate(vec![
+                doc! { "$match": { "student_id": student_id, "school_id": school_id } },
+                doc! { "$group": { "_id": "$status", "count": { "$sum": 1 } } },
+            ])
+            .await?
+            .try_collect()
+            .await?;
+
+        let count = |status: &str| {
+            rows.iter()
+                .find(|d| d.get_str("_id").ok() == Some(status))
+                .and_then(|d| d.get_i32("count").ok())
+                .unwrap_or(0) as i64
+        };
+        let present_count = count("Present");
+        let absent_count = count("Absent");
+        let late_count = count("Late");
+        let excused_count = count("Excused");
+        let total_days = present_count + absent_count + late_count + excused_count;
+
+        Ok(AttendanceSummary {
+            present_count,
+            absent_count,
+            late_count,
+            excused_count,
+            total_days,
+            attendance_percentage: if total_days > 0 {
+                (present_count + late_count) as f64 / total_days as f64 * 100.0
+            } else {
+                0.0
+            },
+            recent_records: vec![],
+        })
+    }
+
+    /// A code vouches for the marks printed with it. Reissuing a card with
+    /// the same marks keeps its code; changed marks get a new code and the
+    /// old one is marked superseded, so earlier printouts stop verifying as
+    /// current.
+    async fn register_verification(
+        &self,
+        ctx: &CardContext,
+        student_id: ObjectId,
+        exam_id: ObjectId,
+        student: &Student,
+        result: &StudentTermResult,
+        class_name: Option<String>,
+    ) -> Result<ReportCardVerification, AppError> {
+        let now = Utc::now();
+        let mut card = ReportCardVerification {
+            id: None,
+            verification_code: generate_verification_code(),
+            school_id: ctx.school_id,
+            school_name: ctx.school_name.clone(),
+            student_id,
+            student_name: student.name.clone(),
+            registration_number: student.registration_number.clone(),
+            class_name,
+            exam_id,
+            exam_name: ctx.exam.name.clone(),
+            education_year_label: ctx.year_label.clone(),
+            term_id: result.term_id.clone(),
+            average_percentage: result.average_percentage,
+            gpa: result.gpa,
+            grade: result.grade.clone(),
+            rank_in_class: result.rank_in_class,
+            total_students: result.total_students,
+            issued_at: now,
+            superseded: false,
+            superseded_by_code: None,
+            superseded_at: None,
+            created_at: Some(now),
+        };
+
+        let current = self
+            .verification_collection
+            .find_one(doc! {
+                "school_id": ctx.school_id,
+                "student_id": student_id,
+                "exam_id": exam_id,
+                "superseded": { "$ne": true }
+            })
+            .await?;
+
+        if let Some(current) = current {
+            if current.same_result(&card) {
+                return self
+                    .verification_collection
+                    .find_one_and_update(
+                        doc! { "_id": current.id },
+                        doc! { "$set": { "issued_at": bson::to_bson(&now).unwrap() } },
+                    )
+                    .return_document(ReturnDocument::After)
+                    .await?
+                    .ok_or(AppError {
+                        message: "Failed to register report card".into(),
+                    });
+            }
+
+            self.verification_collection
+                .update_one(
+                    doc! { "_id": current.id },
+                    doc! { "$set": {
+                        "superseded": true,
+                        "superseded_by_code": &card.verification_code,
+                        "superseded_at": bson::to_bson(&now).unwrap()
+                    } },
+                )
+                .await?;
+        }
+
+        let inserted = self
+            .verification_collection
+            .insert_one(&card)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to register report card: {}", e),
+            })?;
+        card.id = inserted.inserted_id.as_object_id();
+        Ok(card)
+    }
+
+    /// Public check of a printed code
+    pub async fn verify(&self, code: &str) -> Result<ReportCardVerification, AppError> {
+        self.verification_collection
+            .find_one(doc! { "verification_code": code.trim().to_uppercase() })
+            .await?
+            .ok_or(AppError {
+                message: "No report card matches this verification code".into(),
+            })
+    }
+
+    // =========================
+    // CLASS JOBS
+    // =========================
+
+    pub async fn find_job(&self, id: &IdType) -> Result<ReportCardJob, AppError> {
+        let repo = BaseRepository::new(self.job_collection.clone().clone_with_type::<Document>());
+        repo.find_one::<ReportCardJob>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Report card job not found".into(),
+            })
+    }
+
+    pub async fn get_jobs(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<ReportCardJob>, AppError> {
+        let repo = BaseRepository::new(self.job_collection.clone().clone_with_type::<Document>());
+        let searchable = ["_id", "class_id", "exam_id", "status"];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<ReportCardJob>(None, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Queue generation of every active student's card in the class
+    pub async fn start_class_job(
+        &self,
+        school_id: ObjectId,
+        class_id: ObjectId,
+        exam_id: ObjectId,
+        created_by: ObjectId,
+    ) -> Result<ReportCardJob, AppError> {
+        self.ensure_indexes().await?;
+
+        let running = self
+            .job_collection
+            .count_documents(doc! {
+                "class_id": class_id,
+                "exam_id": exam_id,
+                "status": { "$in": [
+                    bson::to_bson(&ReportCardJobStatus::Pending).unwrap(),
+                    bson::to_bson(&ReportCardJobStatus::Running).unwrap()
+                ] }
+            })
+            .await?;
+        if running > 0 {
+            return Err(AppError {
+                message: "Report cards for this class and exam are already being generated".into(),
+            });
+        }
+
+        let job = ReportCardJob {
+            id: None,
+            school_id,
+            class_id,
+            exam_id,
+            status: ReportCardJobStatus::Pending,
+            total: 0,
+            generated: 0,
+            failures: vec![],
+            file_path: None,
+            size_bytes: None,
+            error_message: None,
+            created_by: Some(created_by),
+            created_at: None,
+            updated_at: None,
+            completed_at: None,
+        };
+
+        let repo = BaseRepository::new(self.job_collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&job)?;
+        let job = repo.create::<ReportCardJob>(doc, None).await?;
+
+        let job_id = job.id.ok_or(AppError {
+            message: "Report card job ID not found".into(),
+        })?;
+        let db = self.db.clone();
+        let main_db = self.main_db.clone();
+
+        actix_rt::spawn(async move {
+            let service = ReportCardService::new(&db, &main_db);
+            if let Err(e) = service.run_job(job_id).await {
+                service
+                    .job_collection
+                    .update_one(
+                        doc! { "_id": job_id },
+                        doc! { "$set": {
+                            "status": bson::to_bson(&ReportCardJobStatus::Failed).unwrap(),
+                            "error_message": e.message,
+                            "completed_at": bson::to_bson(&Utc::now()).unwrap(),
+                            "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                        } },
+                    )
+                    .await
+                    .ok();
+            }
+        });
+
+        Ok(job)
+    }
+
+    async fn run_job(&self, job_id: ObjectId) -> Result<(), AppError> {
+        let job = self.find_job(&IdType::ObjectId(job_id)).await?;
+
+        let mut students = Vec::new();
+        let mut cursor = self
+            .db
+            .collection::<Student>("students")
+            .find(doc! { "class_id": job.class_id, "is_active": true })
+            .sort(doc! { "name": 1 })
+            .await?;
+        while cursor.advance().await? {
+            students.push(cursor.deserialize_current()?);
+        }
+
+        self.job_collection
+            .update_one(
+                doc! { "_id": job_id },
+                doc! { "$set": {
+                    "status": bson::to_bson(&ReportCardJobStatus::Running).unwrap(),
+                    "total": students.len() as i64,
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .await?;
+
+        let mut ctx = self.load_context(job.school_id, job.exam_id).await?;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        let zip_error = |e: std::io::Error| AppError {
+            message: format!("Failed to write zip: {}", e),
+        };
+
+        let mut generated = 0i64;
+        for student in &students {
+            let Some(student_id) = student.id else {
+                continue;
+            };
+
+            let update = match self
+                .render_card(&mut ctx, student, ResultVisibility::Finalized)
+                .await
+            {
+                Ok((_, pdf)) => {
+                    zip.start_file(card_file_name(student), options)
+                        .map_err(|e| AppError {
+                            message: format!("Failed to write zip: {}", e),
+                        })?;
+                    zip.write_all(&pdf).map_err(zip_error)?;
+                    generated += 1;
+                    doc! { "$set": { "generated": generated } }
+                }
+                Err(e) => {
+                    let failure = ReportCardFailure {
+                        student_id,
+                        student_name: student.name.clone(),
+                        reason: e.message,
+                    };
+                    doc! { "$push": { "failures": to_stored_document(&failure)? } }
+                }
+            };
+
+            self.job_collection
+                .update_one(doc! { "_id": job_id }, update)
+                .await?;
+        }
+
+        let bytes = zip
+            .finish()
+            .map_err(|e| AppError {
+                message: format!("Failed to write zip: {}", e),
+            })?
+            .into_inner();
+
+        std::fs::create_dir_all(REPORT_CARD_DIR).map_err(zip_error)?;
+        let file_path = format!("{}/report_cards_{}.zip", REPORT_CARD_DIR, job_id.to_hex());
+        std::fs::write(&file_path, &bytes).map_err(zip_error)?;
+
+        self.job_collection
+            .update_one(
+                doc! { "_id": job_id },
+                doc! { "$set": {
+                    "status": bson::to_bson(&ReportCardJobStatus::Completed).unwrap(),
+                    "file_path": file_path,
+                    "size_bytes": bytes.len() as i64,
+                    "completed_at": bson::to_bson(&Utc::now()).unwrap(),
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Cloudinary serves any upload as JPEG with `f_jpg`, which embeds directly
+async fn fetch_logo(url: &str) -> Option<Vec<u8>> {
+    let url = match url.split_once("/upload/") {
+        Some((base, rest)) => format!("{}/upload/f_jpg/{}", base, rest),
+        None => url.to_string(),
+    };
+
+    let response = reqwest::get(&url).await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.bytes().await.ok().map(|b| b.to_vec())
+}
+
+fn card_file_name(student: &Student) -> String {
+    let base = student
+        .registration_number
+        .clone()
+        .unwrap_or_else(|| student.name.clone());
+    let safe: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let id = student.id.map(|id| id.to_hex()).unwrap_or_default();
+    format!("{}_{}.pdf", safe, id)
+}
+
+fn render_pdf(
+    ctx: &CardContext,
+    student: &Student,
+    result: &StudentTermResult,
+    class_name: Option<String>,
+    remarks: &HashMap<ObjectId, String>,
+    attendance: &AttendanceSummary,
+    verification: &ReportCardVerification,
+) -> Vec<u8> {
+    let template = &ctx.template;
+    let primary = hex_color(&template.primary_color);
+    let white = (1.0, 1.0, 1.0);
+    let black = (0.0, 0.0, 0.0);
+    let gray = (0.35, 0.35, 0.35);
+    let right = PAGE_WIDTH - MARGIN;
+
+    let mut pdf = PdfDocument::new(&format!("{} - {}", template.title, student.name));
+    if let Some(logo) = ctx.logo.clone().and_then(JpegImage::parse) {
+        pdf.set_image(logo);
+    }
+
+    // Header band
+    pdf.fill_rect(0.0, PAGE_HEIGHT - 90.0, PAGE_WIDTH, 90.0, primary);
+    let text_x = if pdf.has_image() {
+        pdf.draw_image(MARGIN, PAGE_HEIGHT - 80.0, 70.0, 70.0);
+        MARGIN + 85.0
+    } else {
+        MARGIN
+    };
+    pdf.text_color(white);
+    pdf.text(text_x, PAGE_HEIGHT - 45.0, 18.0, true, &ctx.school_name);
+    pdf.text(text_x, PAGE_HEIGHT - 68.0, 12.0, false, &template.title);
+    pdf.text_color(black);
+
+    // Student and exam details
+    let mut y = PAGE_HEIGHT - 120.0;
+    let details_left = [
+        ("Student", Some(student.name.clone())),
+        ("Reg. No", student.registration_number.clone()),
+        ("Class", class_name),
+    ];
+    let details_right = [
+        ("Exam", Some(ctx.exam.name.clone())),
+        ("Academic year", ctx.year_label.clone()),
+        ("Term", result.term_id.clone()),
+    ];
+    for ((left_label, left), (right_label, right_value)) in details_left.iter().zip(&details_right)
+    {
+        pdf.text(MARGIN, y, 10.0, true, &format!("{}:", left_label));
+        pdf.text(MARGIN + 60.0, y, 10.0, false, left.as_deref().unwrap_or("-"));
+        pdf.text(PAGE_WIDTH / 2.0 + 20.0, y, 10.0, true, &format!("{}:", right_label));
+        pdf.text(
+            PAGE_WIDTH / 2.0 + 105.0,
+            y,
+            10.0,
+            false,
+            &fit_text(right_value.as_deref().unwrap_or("-"), 10.0, false, 150.0),
+        );
+        y -= 16.0;
+    }
+
+    // Subject table
+    let columns = [MARGIN + 280.0, MARGIN + 345.0, MARGIN + 410.0, right - 5.0];
+    let table_header = |pdf: &mut PdfDocument, y: f32| {
+        pdf.fill_rect(MARGIN, y - 6.0, right - MARGIN, 20.0, primary);
+        pdf.text_color(white);
+        pdf.text(MARGIN + 6.0, y, 10.0, true, "Subject");
+        for (x, label) in columns.iter().zip(["Score %", "Grade", "Points", "Credits"]) {
+            pdf.text_right(*x, y, 10.0, true, label);
+        }
+        pdf.text_color(black);
+    };
+
+    y -= 16.0;
+    table_header(&mut pdf, y);
+    y -= 22.0;
+
+    for subject in &result.subject_results {
+        let categories = if template.show_category_scores && !subject.category_scores.is_empty() {
+            Some(
+                subject
+                    .category_scores
+                    .iter()
+                    .map(|c| format!("{} {:.1}/{:.1}", c.category_name, c.score, c.max_score))
+                    .collect::<Vec<_>>()
+                    .join("  |  "),
+            )
+        } else {
+            None
+        };
+        let remark = subject
+            .class_subject_id
+            .and_then(|id| remarks.get(&id))
+            .filter(|_| template.show_remarks);
+        let row_height =
+            18.0 + categories.as_ref().map_or(0.0, |_| 11.0) + remark.map_or(0.0, |_| 11.0);
+
+        if y - row_height < BOTTOM_RESERVE {
+            pdf.new_page();
+            y = PAGE_HEIGHT - MARGIN;
+            table_header(&mut pdf, y);
+            y -= 22.0;
+        }
+
+        pdf.text(MARGIN + 6.0, y, 10.0, false, &fit_text(&subject.subject_name, 10.0, false, 250.0));
+        pdf.text_right(columns[0], y, 10.0, false, &format!("{:.1}", subject.percentage));
+        pdf.text_right(columns[1], y, 10.0, true, &subject.grade);
+        pdf.text_right(
+            columns[2],
+            y,
+            10.0,
+            false,
+            &subject
+                .grade_points
+                .map(|p| format!("{:.2}", p))
+                .unwrap_or_else(|| "-".into()),
+        );
+        pdf.text_right(
+            columns[3],
+            y,
+            10.0,
+            false,
+            &subject
+                .credits
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "-".into()),
+        );
+
+        pdf.text_color(gray);
+        if let Some(categories) = &categories {
+            y -= 11.0;
+            pdf.text(MARGIN + 14.0, y, 8.0, false, &fit_text(categories, 8.0, false, 480.0));
+        }
+        if let Some(remark) = remark {
+            y -= 11.0;
+            pdf.text(
+                MARGIN + 14.0,
+                y,
+                8.0,
+                false,
+                &fit_text(&format!("Remark: {}", remark), 8.0, false, 480.0),
+            );
+        }
+        pdf.text_color(black);
+
+        y -= 5.0;
+        pdf.line(MARGIN, y, right, y, 0.5, 0.8);
+        y -= 13.0;
+    }
+
+    // Summary, attendance and signatures share the last page
+    let summary_height = 150.0;
+    if y - summary_height < MARGIN {
+        pdf.new_page();
+        y = PAGE_HEIGHT - MARGIN;
+    }
+
+    y -= 6.0;
+    let max_gpa = result.max_gpa.unwrap_or(4.0);
+    let mut summary = vec![
+        format!("Average: {:.1}%", result.average_percentage),
+        format!("GPA: {:.2} / {:.1}", result.gpa, max_gpa),
+        format!("Grade: {}", result.grade),
+    ];
+    if template.show_rank {
+        if let Some(rank) = result.rank_in_class {
+            summary.push(match result.total_students {
+                Some(total) => format!("Position: {} of {}", rank, total),
+                None => format!("Position: {}", rank),
+            });
+        }
+    }
+    let step = (right - MARGIN) / summary.len() as f32;
+    for (i, item) in summary.iter().enumerate() {
+        pdf.text(MARGIN + step * i as f32, y, 11.0, true, item);
+    }
+    y -= 22.0;
+
+    if template.show_attendance && attendance.total_days > 0 {
+        pdf.text(MARGIN, y, 10.0, true, "Attendance:");
+        pdf.text(
+            MARGIN + 70.0,
+            y,
+            10.0,
+            false,
+            &format!(
+                "{:.1}%  (present {}, late {}, absent {}, excused {} of {} days)",
+                attendance.attendance_percentage,
+                attendance.present_count,
+                attendance.late_count,
+                attendance.absent_count,
+                attendance.excused_count,
+                attendance.total_days
+            ),
+        );
+        y -= 22.0;
+    }
+
+    y -= 30.0;
+    pdf.line(MARGIN, y, MARGIN + 180.0, y, 0.7, 0.2);
+    pdf.line(right - 180.0, y, right, y, 0.7, 0.2);
+    y -= 12.0;
+    pdf.text(MARGIN, y, 9.0, false, "Class teacher");
+    pdf.text(
+        right - 180.0,
+        y,
+        9.0,
+        false,
+        &match &template.principal_name {
+            Some(name) => format!("Principal: {}", name),
+            None => "Principal".to_string(),
+        },
+    );
+
+    // Footer with the verification code
+    pdf.text_color(gray);
+    if let Some(footer) = &template.footer_text {
+        pdf.text_center(
+            PAGE_WIDTH / 2.0,
+            MARGIN + 24.0,
+            8.0,
+            false,
+            &fit_text(footer, 8.0, false, right - MARGIN),
+        );
+    }
+    let verify_hint = match std::env::var("REPORT_CARD_VERIFY_URL") {
+        Ok(url) => format!(
+            "Verify at {}/{}",
+            url.trim_end_matches('/'),
+            verification.verification_code
+        ),
+        Err(_) => "Verify at /report-cards/verify/<code>".to_string(),
+    };
+    pdf.text_center(
+        PAGE_WIDTH / 2.0,
+        MARGIN + 10.0,
+        8.0,
+        false,
+        &format!(
+            "Verification code: {}   |   {}   |   Issued {}",
+            verification.verification_code,
+            verify_hint,
+            verification.issued_at.format("%Y-%m-%d")
+        ),
+    );
+
+    pdf.finish()
+}
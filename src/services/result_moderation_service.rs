@@ -0,0 +1,751 @@
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class_subject::ClassSubject,
+        common_details::Paginated,
+        exam::Exam,
+        result_moderation::{
+            ReopenRequest, ReopenRequestStatus, ResultSheet, ResultSheetStatus,
+            ResultSheetTransition,
+        },
+        student_term_result::StudentTermResult,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    schema::common_schema::ActorRef,
+    services::gpa_calculation_service::GpaCalculationService,
+    utils::{
+        mongo_utils::{id_match, to_stored_document},
+        status_utils::transition_status,
+    },
+};
+
+pub struct ResultModerationService {
+    pub sheet_collection: Collection<ResultSheet>,
+    pub reopen_collection: Collection<ReopenRequest>,
+    pub db: Database,
+}
+
+impl ResultModerationService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            sheet_collection: db.collection::<ResultSheet>("result_sheets"),
+            reopen_collection: db.collection::<ReopenRequest>("result_reopen_requests"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let sheet_indexes = vec![
+            IndexDef::compound(vec![("exam_id", 1), ("class_subject_id", 1)], true),
+            IndexDef::compound(vec![("exam_id", 1), ("class_id", 1)], false),
+            IndexDef::single("status", false),
+        ];
+        let reopen_indexes = vec![
+            IndexDef::compound(vec![("result_sheet_id", 1), ("status", 1)], false),
+            IndexDef::single("school_id", false),
+        ];
+
+        BaseRepository::new(self.sheet_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&sheet_indexes)
+            .await?;
+        BaseRepository::new(self.reopen_collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&reopen_indexes)
+            .await?;
+        Ok(())
+    }
+
+    // =========================
+    // SCORE LOCKING
+    // =========================
+
+    /// Fails unless the sheet for this exam and class subject is still a draft.
+    /// Returns the approved re-open request when the sheet was unlocked by one.
+    pub async fn ensure_scores_editable(
+        &self,
+        exam_id: Option<ObjectId>,
+        class_subject_id: Option<ObjectId>,
+    ) -> Result<Option<ObjectId>, AppError> {
+        let (Some(exam_id), Some(class_subject_id)) = (exam_id, class_subject_id) else {
+            return Ok(None);
+        };
+
+        let Some(sheet) = self
+            .sheet_collection
+            .find_one(doc! { "exam_id": exam_id, "class_subject_id": class_subject_id })
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if sheet.status != ResultSheetStatus::Draft {
+            return Err(AppError {
+                message: format!(
+                    "Scores are locked while the result sheet is {:?}",
+                    sheet.status
+                ),
+            });
+        }
+
+        let approved = self
+            .reopen_collection
+            .find_one(doc! {
+                "result_sheet_id": sheet.id,
+                "status": bson::to_bson(&ReopenRequestStatus::Approved).unwrap()
+            })
+            .await?;
+
+        Ok(approved.and_then(|r| r.id))
+    }
+
+    // =========================
+    // SHEETS
+    // =========================
+
+    pub async fn find_sheet(&self, id: &IdType) -> Result<ResultSheet, AppError> {
+        let repo = BaseRepository::new(self.sheet_collection.clone().clone_with_type::<Document>());
+        repo.find_one::<ResultSheet>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Result sheet not found".into(),
+            })
+    }
+
+    pub async fn get_sheets(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<ResultSheet>, AppError> {
+        let repo = BaseRepository::new(self.sheet_collection.clone().clone_with_type::<Document>());
+        let searchable = ["_id", "exam_id", "class_subject_id", "class_id", "status"];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<ResultSheet>(None, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Sheets are created lazily the first time a teacher submits
+    async fn get_or_create_sheet(
+        &self,
+        school_id: Option<ObjectId>,
+        exam_id: ObjectId,
+        class_subject_id: ObjectId,
+    ) -> Result<ResultSheet, AppError> {
+        self.ensure_indexes().await?;
+
+        let subject = self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(doc! { "_id": class_subject_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class subject not found".into(),
+            })?;
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let sheet = self
+            .sheet_collection
+            .find_one_and_update(
+                doc! { "exam_id": exam_id, "class_subject_id": class_subject_id },
+                doc! { "$setOnInsert": {
+                    "school_id": school_id.or(subject.school_id),
+                    "class_id": subject.class_id,
+                    "status": bson::to_bson(&ResultSheetStatus::Draft).unwrap(),
+                    "history": [],
+                    "created_at": now.clone(),
+                    "updated_at": now
+                } },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?
+            .ok_or(AppError {
+                message: "Failed to open result sheet".into(),
+            })?;
+
+        Ok(sheet)
+    }
+
+    /// Move a sheet between states atomically, recording who did it
+    async fn transition(
+        &self,
+        sheet_id: ObjectId,
+        from: &[ResultSheetStatus],
+        to: ResultSheetStatus,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<ResultSheet, AppError> {
+        let current = self.find_sheet(&IdType::ObjectId(sheet_id)).await?;
+        let entry = ResultSheetTransition {
+            from: current.status,
+            to,
+            by: actor.clone(),
+            note,
+            at: Utc::now(),
+        };
+        transition_status(&self.sheet_collection, sheet_id, from, entry, "Result sheet").await
+    }
+
+    /// Teacher hands in the scores of a class subject for an exam
+    pub async fn submit(
+        &self,
+        school_id: Option<ObjectId>,
+        exam_id: ObjectId,
+        class_subject_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<ResultSheet, AppError> {
+        let scores = self
+            .db
+            .collection::<Document>("scores")
+            .count_documents(doc! {
+                "exam_id": exam_id,
+                "class_subject_id": class_subject_id,
+                "is_deleted": false
+            })
+            .await?;
+        if scores == 0 {
+            return Err(AppError {
+                message: "No scores have been entered for this subject and exam".into(),
+            });
+        }
+
+        let sheet = self
+            .get_or_create_sheet(school_id, exam_id, class_subject_id)
+            .await?;
+        let sheet_id = sheet.id.ok_or(AppError {
+            message: "Result sheet has no ID".into(),
+        })?;
+
+        self.transition(
+            sheet_id,
+            &[ResultSheetStatus::Draft],
+            ResultSheetStatus::Submitted,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    pub async fn moderate(
+        &self,
+        sheet_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<ResultSheet, AppError> {
+        self.transition(
+            sheet_id,
+            &[ResultSheetStatus::Submitted],
+            ResultSheetStatus::Moderated,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    /// Send a submitted or moderated sheet back to the teacher for corrections
+    pub async fn return_to_draft(
+        &self,
+        sheet_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<ResultSheet, AppError> {
+        if note.as_deref().map(str::trim).unwrap_or_default().is_empty() {
+            return Err(AppError {
+                message: "A note is required when returning a result sheet".into(),
+            });
+        }
+
+        self.transition(
+            sheet_id,
+            &[ResultSheetStatus::Submitted, ResultSheetStatus::Moderated],
+            ResultSheetStatus::Draft,
+            actor,
+            note,
+        )
+        .await
+    }
+
+    /// Lock the sheet. Once every subject of the class is finalized for the
+    /// exam, results are recalculated and marked finalized.
+    pub async fn finalize(
+        &self,
+        sheet_id: ObjectId,
+        actor: &ActorRef,
+        note: Option<String>,
+    ) -> Result<ResultSheet, AppError> {
+        let sheet = self
+            .transition(
+                sheet_id,
+                &[ResultSheetStatus::Moderated],
+                ResultSheetStatus::Finalized,
+                actor,
+                note,
+            )
+            .await?;
+
+        // Changes made under an approved re-open are now locked again
+        self.reopen_collection
+            .update_many(
+                doc! {
+                    "result_sheet_id": sheet_id,
+                    "status": bson::to_bson(&ReopenRequestStatus::Approved).unwrap()
+                },
+                doc! { "$set": {
+                    "status": bson::to_bson(&ReopenRequestStatus::Closed).unwrap(),
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .await?;
+
+        self.finalize_class_results(&sheet).await?;
+        Ok(sheet)
+    }
+
+    async fn finalize_class_results(&self, sheet: &ResultSheet) -> Result<(), AppError> {
+        let Some(class_id) = sheet.class_id else {
+            return Ok(());
+        };
+
+        let mut cursor = self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find(doc! { "class_id": class_id, "disable": { "$ne": true } })
+            .await?;
+        let mut subject_ids = Vec::new();
+        while cursor.advance().await? {
+            let subject: ClassSubject = cursor.deserialize_current()?;
+            if let Some(id) = subject.id {
+                subject_ids.push(id);
+            }
+        }
+
+        let finalized = self
+            .sheet_collection
+            .count_documents(doc! {
+                "exam_id": sheet.exam_id,
+                "class_subject_id": { "$in": &subject_ids },
+                "status": bson::to_bson(&ResultSheetStatus::Finalized).unwrap()
+            })
+            .await?;
+        if (finalized as usize) < subject_ids.len() {
+            return Ok(());
+        }
+
+        let exam = self
+            .db
+            .collection::<Exam>("exams")
+            .find_one(doc! { "_id": sheet.exam_id })
+            .await?
+            .ok_or(AppError {
+                message: "Exam not found".into(),
+            })?;
+        let (Some(education_year_id), Some(school_id)) =
+            (exam.education_year_id, exam.school_id.or(sheet.school_id))
+        else {
+            return Err(AppError {
+                message: "Exam has no education year or school".into(),
+            });
+        };
+
+        let results = self.db.collection::<StudentTermResult>("student_term_results");
+        // Results stored before ids were kept as ObjectIds hold hex strings
+        let filter = doc! {
+            "exam_id": id_match(&sheet.exam_id),
+            "class_id": id_match(&class_id)
+        };
+
+        // A re-opened subject leaves the class results unlocked, recalculate
+        // them with the corrected scores before locking again
+        GpaCalculationService::new(&self.db)
+            .calculate_class_results(
+                &class_id,
+                &sheet.exam_id,
+                &education_year_id,
+                &school_id,
+                exam.term_id.clone(),
+            )
+            .await?;
+
+        results
+            .update_many(
+                filter,
+                doc! { "$set": {
+                    "is_finalized": true,
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                } },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Make the finalized results of a class visible to students and parents
+    pub async fn publish(&self, exam_id: ObjectId, class_id: ObjectId) -> Result<u64, AppError> {
+        let results = self.db.collection::<StudentTermResult>("student_term_results");
+        let filter = doc! { "exam_id": id_match(&exam_id), "class_id": id_match(&class_id) };
+
+        let total = results.count_documents(filter.clone()).await?;
+        if total == 0 {
+            return Err(AppError {
+                message: "No results have been calculated for this class and exam".into(),
+            });
+        }
+
+        let mut unfinalized = filter.clone();
+        unfinalized.insert("is_finalized", doc! { "$ne": true });
+        if results.count_documents(unfinalized).await? > 0 {
+            return Err(AppError {
+                message: "All subjects must be finalized before results are published".into(),
+            });
+        }
+
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let updated = results
+            .update_many(
+                filter,
+                doc! { "$set": {
+                    "is_published": true,
+                    "published_at": now.clone(),
+                    "updated_at": now
+                } },
+            )
+            .await?;
+
+        Ok(updated.modified_count)
+    }
+
+    // =========================
+    // RE-OPEN REQUESTS
+    // =========================
+
+    pub async fn find_reopen_request(&self, id: &IdType) -> Result<ReopenRequest, AppError> {
+        let repo = BaseRepository::new(self.reopen_collection.clone().clone_with_type::<Document>());
+        repo.find_one::<ReopenRequest>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Re-open request not found".into(),
+            })
+    }
+
+    pub async fn get_reopen_requests(
+        &self,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<ReopenRequest>, AppError> {
+        let repo = BaseRepository::new(self.reopen_collection.clone().clone_with_type::<Document>());
+        let searchable = ["_id", "result_sheet_id", "exam_id", "class_subject_id", "status"];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<ReopenRequest>(None, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn request_reopen(
+        &self,
+        sheet_id: ObjectId,
+        actor: ActorRef,
+        reason: String,
+    ) -> Result<ReopenRequest, AppError> {
+        if reason.trim().is_empty() {
+            return Err(AppError {
+                message: "A reason is required to re-open finalized results".into(),
+            });
+        }
+
+        let sheet = self.find_sheet(&IdType::ObjectId(sheet_id)).await?;
+        if sheet.status != ResultSheetStatus::Finalized {
+            return Err(AppError {
+                message: "Only finalized result sheets can be re-opened".into(),
+            });
+        }
+
+        let open = self
+            .reopen_collection
+            .count_documents(doc! {
+                "result_sheet_id": sheet_id,
+                "status": { "$in": [
+                    bson::to_bson(&ReopenRequestStatus::Pending).unwrap(),
+                    bson::to_bson(&ReopenRequestStatus::Approved).unwrap()
+                ] }
+            })
+            .await?;
+        if open > 0 {
+            return Err(AppError {
+                message: "This result sheet already has an open re-open request".into(),
+            });
+        }
+
+        let request = ReopenRequest {
+            id: None,
+            school_id: sheet.school_id,
+            result_sheet_id: sheet_id,
+            exam_id: sheet.exam_id,
+            class_subject_id: sheet.class_subject_id,
+            requested_by: actor,
+            reason,
+            status: ReopenRequestStatus::Pending,
+            reviewed_by: None,
+            review_note: None,
+            reviewed_at: None,
+            created_at: None,
+            updated_at: None,
+        };
+
+        let repo = BaseRepository::new(self.reopen_collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&request)?;
+        repo.create::<ReopenRequest>(doc, None).await
+    }
+
+    /// Approving puts the sheet back to draft and withdraws the class results
+    /// from publication until the sheet is finalized again
+    pub async fn review_reopen(
+        &self,
+        request_id: &IdType,
+        actor: ActorRef,
+        approve: bool,
+        note: Option<String>,
+    ) -> Result<ReopenRequest, AppError> {
+        let request = self.find_reopen_request(request_id).await?;
+        if request.status != ReopenRequestStatus::Pending {
+            return Err(AppError {
+                message: "Re-open request has already been reviewed".into(),
+            });
+        }
+
+        if approve {
+            let sheet = self
+                .transition(
+                    request.result_sheet_id,
+                    &[ResultSheetStatus::Finalized],
+                    ResultSheetStatus::Draft,
+                    &actor,
+                    Some(format!("Re-opened: {}", request.reason)),
+                )
+                .await?;
+
+            if let Some(class_id) = sheet.class_id {
+                self.db
+                    .collection::<StudentTermResult>("student_term_results")
+                    .update_many(
+                        doc! {
+                            "exam_id": id_match(&sheet.exam_id),
+                            "class_id": id_match(&class_id)
+                        },
+                        doc! {
+                            "$set": {
+                                "is_finalized": false,
+                                "is_published": false,
+                                "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                            },
+                            "$unset": { "published_at": "" }
+                        },
+                    )
+                    .await?;
+            }
+        }
+
+        let status = if approve {
+            ReopenRequestStatus::Approved
+        } else {
+            ReopenRequestStatus::Rejected
+        };
+
+        let repo = BaseRepository::new(self.reopen_collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<ReopenRequest>(
+            request_id,
+            doc! {
+                "status": bson::to_bson(&status).unwrap(),
+                "reviewed_by": to_stored_document(&actor)?,
+                "review_note": note,
+                "reviewed_at": bson::to_bson(&Utc::now()).unwrap()
+            },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::domain::{
+        common_details::{SubjectCategory, UserRole},
+        exam::{ExamStatus, ExamType},
+    };
+    use crate::utils::test_utils::test_database;
+
+    #[actix_rt::test]
+    #[ignore = "needs MongoDB, see TEST_MONGODB_URI"]
+    async fn calculated_results_are_finalized_and_published() {
+        let db = test_database("test_result_moderation").await;
+        let (school_id, year_id, class_id) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let (student_id, legacy_student_id) = (ObjectId::new(), ObjectId::new());
+        let (subject_id, category_id, exam_id) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let now = bson::to_bson(&Utc::now()).unwrap();
+
+        db.collection::<Document>("students")
+            .insert_one(doc! {
+                "_id": student_id,
+                "school_id": school_id,
+                "class_id": class_id,
+                "name": "Student",
+                "email": "student@example.com",
+                "is_active": true
+            })
+            .await
+            .unwrap();
+        db.collection::<Document>("class_subjects")
+            .insert_one(doc! {
+                "_id": subject_id,
+                "school_id": school_id,
+                "class_id": class_id,
+                "name": "Mathematics",
+                "code": "MATH",
+                "category": bson::to_bson(&SubjectCategory::Mathematics).unwrap(),
+                "estimated_hours": 40
+            })
+            .await
+            .unwrap();
+        // Stored the way the category and exam services write them, with hex ids
+        db.collection::<Document>("assessment_categories")
+            .insert_one(doc! {
+                "_id": category_id,
+                "school_id": school_id.to_hex(),
+                "class_subject_id": subject_id.to_hex(),
+                "education_year_id": year_id.to_hex(),
+                "name": "Exam",
+                "code": "EX",
+                "weight_percentage": 100.0,
+                "is_deleted": false
+            })
+            .await
+            .unwrap();
+        db.collection::<Document>("exams")
+            .insert_one(doc! {
+                "_id": exam_id,
+                "school_id": school_id.to_hex(),
+                "education_year_id": year_id.to_hex(),
+                "name": "Term 1",
+                "exam_type": bson::to_bson(&ExamType::Final).unwrap(),
+                "status": bson::to_bson(&ExamStatus::Completed).unwrap(),
+                "start_date": now.clone(),
+                "end_date": now.clone()
+            })
+            .await
+            .unwrap();
+        db.collection::<Document>("scores")
+            .insert_one(doc! {
+                "school_id": school_id,
+                "student_id": student_id,
+                "class_subject_id": subject_id,
+                "exam_id": exam_id,
+                "assessment_category_id": category_id,
+                "score": 36.0,
+                "max_score": 50.0,
+                "percentage": 72.0,
+                "is_deleted": false
+            })
+            .await
+            .unwrap();
+        // A draft result written before results kept ObjectIds
+        db.collection::<Document>("student_term_results")
+            .insert_one(doc! {
+                "school_id": school_id.to_hex(),
+                "student_id": legacy_student_id.to_hex(),
+                "class_id": class_id.to_hex(),
+                "education_year_id": year_id.to_hex(),
+                "exam_id": exam_id.to_hex(),
+                "subject_results": [],
+                "total_score": 0.0,
+                "total_max_score": 0.0,
+                "average_percentage": 0.0,
+                "gpa": 0.0,
+                "grade": "F",
+                "is_finalized": false,
+                "is_published": false
+            })
+            .await
+            .unwrap();
+
+        let calculated = GpaCalculationService::new(&db)
+            .calculate_class_results(&class_id, &exam_id, &year_id, &school_id, None)
+            .await
+            .unwrap();
+        assert_eq!(calculated.len(), 1);
+        assert_eq!(calculated[0].average_percentage, 72.0);
+
+        let service = ResultModerationService::new(&db);
+        let sheet = ResultSheet {
+            id: None,
+            school_id: Some(school_id),
+            exam_id,
+            class_subject_id: subject_id,
+            class_id: Some(class_id),
+            status: ResultSheetStatus::Moderated,
+            history: Vec::new(),
+            created_at: Some(Utc::now()),
+            updated_at: Some(Utc::now()),
+        };
+        let sheet_id = service
+            .sheet_collection
+            .insert_one(&sheet)
+            .await
+            .unwrap()
+            .inserted_id
+            .as_object_id()
+            .unwrap();
+
+        let actor = ActorRef {
+            id: ObjectId::new(),
+            role: UserRole::SCHOOLSTAFF,
+        };
+        service.finalize(sheet_id, &actor, None).await.unwrap();
+        assert_eq!(service.publish(exam_id, class_id).await.unwrap(), 2);
+
+        let results: Vec<Document> = db
+            .collection::<Document>("student_term_results")
+            .find(doc! {})
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        for result in &results {
+            assert_eq!(result.get_bool("is_finalized"), Ok(true));
+            assert_eq!(result.get_bool("is_published"), Ok(true));
+        }
+        let calculated = results
+            .iter()
+            .find(|r| r.get_object_id("student_id") == Ok(student_id))
+            .expect("calculated result is stored with ObjectIds");
+        assert_eq!(calculated.get_object_id("exam_id"), Ok(exam_id));
+
+        db.drop().await.unwrap();
+    }
+}
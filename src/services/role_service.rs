@@ -458,6 +458,36 @@ impl RoleService {
                 description: Some("Toggle features".to_string()),
                 scope: PermissionScope::School,
             },
+            Permission {
+                name: "safeguarding.moderate".to_string(),
+                description: Some("Manage messaging policy and review reported messages".to_string()),
+                scope: PermissionScope::School,
+            },
+            Permission {
+                name: "results.moderate".to_string(),
+                description: Some("Moderate submitted results and review re-open requests".to_string()),
+                scope: PermissionScope::School,
+            },
+            Permission {
+                name: "results.finalize".to_string(),
+                description: Some("Finalize and publish moderated results".to_string()),
+                scope: PermissionScope::School,
+            },
+            Permission {
+                name: "competency.sign_off".to_string(),
+                description: Some("Sign off competency assessments made by other assessors".to_string()),
+                scope: PermissionScope::School,
+            },
+            Permission {
+                name: "syllabus.view_coverage".to_string(),
+                description: Some("View the school-wide syllabus coverage dashboard".to_string()),
+                scope: PermissionScope::School,
+            },
+            Permission {
+                name: "lesson_plans.approve".to_string(),
+                description: Some("Approve or return lesson plans submitted by teachers".to_string()),
+                scope: PermissionScope::School,
+            },
         ]
     }
 }
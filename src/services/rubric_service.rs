@@ -0,0 +1,155 @@
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        common_details::Paginated,
+        rubric::{Rubric, RubricPartial},
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    utils::mongo_utils::{extract_valid_fields, to_stored_document},
+};
+
+pub struct RubricService {
+    pub collection: Collection<Rubric>,
+    pub db: Database,
+}
+
+impl RubricService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<Rubric>("rubrics"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("school_id", 1), ("teacher_id", 1)], false),
+            IndexDef::single("is_deleted", false),
+        ];
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.ensure_indexes(&indexes).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, mut rubric: Rubric) -> Result<Rubric, AppError> {
+        self.ensure_indexes().await?;
+        rubric.normalize().map_err(|message| AppError { message })?;
+
+        let now = Utc::now();
+        rubric.id = None;
+        rubric.is_deleted = false;
+        rubric.created_at = Some(now);
+        rubric.updated_at = Some(now);
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.create::<Rubric>(extract_valid_fields(to_stored_document(&rubric)?), None)
+            .await
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<Rubric, AppError> {
+        let filter = doc! {
+            "_id": IdType::to_object_id(id)?,
+            "is_deleted": false
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.find_one::<Rubric>(filter, None)
+            .await?
+            .ok_or(AppError {
+                message: "Rubric not found".into(),
+            })
+    }
+
+    /// School-wide rubrics plus, for a teacher, their own
+    pub async fn get_all(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+        teacher_id: Option<ObjectId>,
+    ) -> Result<Paginated<Rubric>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+
+        let searchable = ["title", "description", "_id", "school_id", "teacher_id"];
+
+        let mut match_filter = extra_match.unwrap_or_default();
+        match_filter.insert("is_deleted", false);
+        if let Some(teacher_id) = teacher_id {
+            match_filter.insert("teacher_id", doc! { "$in": [bson::Bson::Null, teacher_id] });
+        }
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<Rubric>(filter, &searchable, limit, skip, Some(match_filter))
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn update(&self, id: &IdType, update: &RubricPartial) -> Result<Rubric, AppError> {
+        let mut merged = self.find_one(id).await?;
+        if let Some(title) = &update.title {
+            merged.title = title.clone();
+        }
+        if let Some(description) = &update.description {
+            merged.description = description.clone();
+        }
+        if let Some(criteria) = &update.criteria {
+            merged.criteria = criteria.clone();
+        }
+        merged.normalize().map_err(|message| AppError { message })?;
+
+        let criteria = bson::to_bson(&merged.criteria).map_err(|e| AppError {
+            message: format!("Failed to serialize criteria: {}", e),
+        })?;
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<Rubric>(
+            id,
+            doc! {
+                "title": &merged.title,
+                "description": &merged.description,
+                "criteria": criteria,
+            },
+        )
+        .await
+    }
+
+    /// Soft delete, refused while an assignment still grades with it
+    pub async fn delete(&self, id: &IdType) -> Result<Rubric, AppError> {
+        let rubric = self.find_one(id).await?;
+
+        let in_use = self
+            .db
+            .collection::<Document>("assignments")
+            .count_documents(doc! {
+                "rubric_id": IdType::to_object_id(id)?,
+                "is_deleted": { "$ne": true }
+            })
+            .await?;
+        if in_use > 0 {
+            return Err(AppError {
+                message: format!("Rubric is attached to {} assignment(s)", in_use),
+            });
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<Rubric>(id, doc! { "is_deleted": true })
+            .await?;
+
+        Ok(rubric)
+    }
+}
@@ -0,0 +1,409 @@
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::ReturnDocument,
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        common_details::{Paginated, UserRole},
+        parent::Parent,
+        safeguarding::{
+            MessageReport, MessagingPolicy, ReportStatus, UpdateMessagingPolicy,
+        },
+        student::Student,
+        teacher::Teacher,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    schema::common_schema::ActorRef,
+    utils::mongo_utils::to_stored_document,
+};
+
+const MAX_REPORT_CONTENT_LEN: usize = 10_000;
+
+pub struct SafeguardingService {
+    pub policy_collection: Collection<MessagingPolicy>,
+    pub report_collection: Collection<MessageReport>,
+    pub db: Database,
+}
+
+impl SafeguardingService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            policy_collection: db.collection::<MessagingPolicy>("messaging_policies"),
+            report_collection: db.collection::<MessageReport>("message_reports"),
+            db: db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("school_id", 1), ("status", 1), ("created_at", -1)], false),
+            IndexDef::single("message_id", false),
+            IndexDef::single("reporter.id", false),
+            IndexDef::single("reported_user.id", false),
+        ];
+
+        let repo = BaseRepository::new(self.report_collection.clone().clone_with_type::<Document>());
+        repo.ensure_indexes(&indexes).await?;
+        Ok(())
+    }
+
+    // =========================
+    // POLICY
+    // =========================
+
+    pub async fn get_policy(&self, school_id: &ObjectId) -> Result<MessagingPolicy, AppError> {
+        let policy = self
+            .policy_collection
+            .find_one(doc! { "_id": school_id })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch messaging policy: {}", e),
+            })?;
+
+        Ok(policy.unwrap_or_else(|| MessagingPolicy::new(*school_id)))
+    }
+
+    pub async fn update_policy(
+        &self,
+        school_id: &ObjectId,
+        update: &UpdateMessagingPolicy,
+        updated_by: ObjectId,
+    ) -> Result<MessagingPolicy, AppError> {
+        let mut set = doc! {
+            "updated_by": updated_by,
+            "updated_at": bson::to_bson(&Utc::now()).unwrap(),
+        };
+        if let Some(v) = update.students_contact_class_teachers_only {
+            set.insert("students_contact_class_teachers_only", v);
+        }
+        if let Some(v) = update.allow_student_to_student {
+            set.insert("allow_student_to_student", v);
+        }
+        if let Some(v) = update.parent_oversight {
+            set.insert("parent_oversight", v);
+        }
+
+        self.policy_collection
+            .find_one_and_update(doc! { "_id": school_id }, doc! { "$set": set })
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to update messaging policy: {}", e),
+            })?
+            .ok_or(AppError {
+                message: "Failed to update messaging policy".into(),
+            })
+    }
+
+    /// Whether a conversation outside any school would include a student,
+    /// by the roles given or by the user accounts
+    pub async fn involves_student(&self, participants: &[ActorRef]) -> Result<bool, AppError> {
+        if participants.iter().any(|p| p.role == UserRole::STUDENT) {
+            return Ok(true);
+        }
+
+        let ids: Vec<ObjectId> = participants.iter().map(|p| p.id).collect();
+        let students = self
+            .db
+            .collection::<Document>("users")
+            .count_documents(doc! {
+                "_id": { "$in": ids },
+                "role": bson::to_bson(&UserRole::STUDENT).unwrap()
+            })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch users: {}", e),
+            })?;
+        Ok(students > 0)
+    }
+
+    /// Roles come from the client, so check them against the school's
+    /// student and teacher records before any rule relies on them
+    async fn verify_roles(&self, participants: &[ActorRef]) -> Result<(), AppError> {
+        for participant in participants {
+            let is_student = self.find_student(&participant.id).await?.is_some();
+            let is_teacher = participant.role == UserRole::TEACHER
+                && self.find_teacher(&participant.id).await?.is_some();
+
+            let matches = match participant.role {
+                UserRole::STUDENT => is_student,
+                UserRole::TEACHER => is_teacher,
+                _ => !is_student,
+            };
+            if !matches {
+                return Err(AppError {
+                    message: "Participant roles do not match the school's records".into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the school's rules to a conversation about to be created
+    pub async fn check_new_conversation(
+        &self,
+        policy: &MessagingPolicy,
+        creator: &ActorRef,
+        participants: &[ActorRef],
+    ) -> Result<(), AppError> {
+        self.verify_roles(participants).await?;
+
+        let only_students = participants.iter().all(|p| p.role == UserRole::STUDENT);
+        if !policy.allow_student_to_student && only_students {
+            return Err(AppError {
+                message: "Student-to-student messaging is disabled for this school".into(),
+            });
+        }
+
+        if creator.role != UserRole::STUDENT || !policy.students_contact_class_teachers_only {
+            return Ok(());
+        }
+
+        let student = self.find_student(&creator.id).await?.ok_or(AppError {
+            message: "Student record not found".into(),
+        })?;
+        let class_ids: Vec<ObjectId> = student
+            .class_id
+            .into_iter()
+            .chain(student.subclass_id)
+            .collect();
+
+        for participant in participants.iter().filter(|p| p.id != creator.id) {
+            let allowed = match participant.role {
+                UserRole::STUDENT => policy.allow_student_to_student,
+                UserRole::TEACHER => self.teaches_any(&participant.id, &class_ids).await?,
+                _ => false,
+            };
+
+            if !allowed {
+                return Err(AppError {
+                    message: "Students may only start conversations with teachers of their class"
+                        .into(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parents who must hold a read-only key for a conversation between
+    /// their child and a teacher when oversight is on
+    pub async fn required_observers(
+        &self,
+        policy: &MessagingPolicy,
+        participants: &[ActorRef],
+    ) -> Result<Vec<ActorRef>, AppError> {
+        let has_teacher = participants.iter().any(|p| p.role == UserRole::TEACHER);
+        if !policy.parent_oversight || !has_teacher {
+            return Ok(vec![]);
+        }
+
+        let mut observers: Vec<ActorRef> = vec![];
+        for participant in participants.iter().filter(|p| p.role == UserRole::STUDENT) {
+            let Some(student_id) = self.find_student(&participant.id).await?.and_then(|s| s.id)
+            else {
+                continue;
+            };
+
+            let mut cursor = self
+                .db
+                .collection::<Parent>("parents")
+                .find(doc! { "student_ids": student_id, "is_active": true })
+                .await
+                .map_err(|e| AppError {
+                    message: format!("Failed to fetch parents: {}", e),
+                })?;
+
+            while cursor.advance().await.map_err(|e| AppError {
+                message: format!("Failed to iterate parents: {}", e),
+            })? {
+                let parent = cursor.deserialize_current().map_err(|e| AppError {
+                    message: format!("Failed to deserialize parent: {}", e),
+                })?;
+
+                // Keys and public keys are looked up by user id
+                if let Some(id) = parent.user_id.or(parent.id) {
+                    if !observers.iter().any(|o| o.id == id)
+                        && !participants.iter().any(|p| p.id == id)
+                    {
+                        observers.push(ActorRef {
+                            id,
+                            role: UserRole::PARENT,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(observers)
+    }
+
+    /// Participant ids may be either the school member id or the user id
+    async fn find_student(&self, id: &ObjectId) -> Result<Option<Student>, AppError> {
+        self.db
+            .collection::<Student>("students")
+            .find_one(doc! { "$or": [{ "_id": id }, { "user_id": id }] })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch student: {}", e),
+            })
+    }
+
+    async fn find_teacher(&self, id: &ObjectId) -> Result<Option<Teacher>, AppError> {
+        self.db
+            .collection::<Teacher>("teachers")
+            .find_one(doc! { "$or": [{ "_id": id }, { "user_id": id }] })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to fetch teacher: {}", e),
+            })
+    }
+
+    async fn teaches_any(&self, id: &ObjectId, class_ids: &[ObjectId]) -> Result<bool, AppError> {
+        if class_ids.is_empty() {
+            return Ok(false);
+        }
+
+        let Some(teacher) = self.find_teacher(id).await? else {
+            return Ok(false);
+        };
+        let Some(teacher_id) = teacher.id else {
+            return Ok(false);
+        };
+
+        if teacher
+            .class_ids
+            .unwrap_or_default()
+            .iter()
+            .any(|c| class_ids.contains(c))
+        {
+            return Ok(true);
+        }
+
+        let class_teacher = self
+            .db
+            .collection::<Document>("classes")
+            .count_documents(doc! { "_id": { "$in": class_ids }, "class_teacher_id": teacher_id })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to check class teacher: {}", e),
+            })?;
+        if class_teacher > 0 {
+            return Ok(true);
+        }
+
+        let subject_teacher = self
+            .db
+            .collection::<Document>("class_subjects")
+            .count_documents(doc! { "class_id": { "$in": class_ids }, "teacher_id": teacher_id })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to check subject teacher: {}", e),
+            })?;
+
+        Ok(subject_teacher > 0)
+    }
+
+    // =========================
+    // REPORTS
+    // =========================
+
+    pub async fn create_report(&self, report: MessageReport) -> Result<MessageReport, AppError> {
+        self.ensure_indexes().await?;
+
+        if report.decrypted_content.trim().is_empty() {
+            return Err(AppError {
+                message: "Reported content is required".into(),
+            });
+        }
+        if report.decrypted_content.len() > MAX_REPORT_CONTENT_LEN {
+            return Err(AppError {
+                message: "Reported content is too large".into(),
+            });
+        }
+
+        let existing = self
+            .report_collection
+            .find_one(doc! {
+                "message_id": report.message_id,
+                "reporter.id": report.reporter.id,
+            })
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to check existing report: {}", e),
+            })?;
+        if existing.is_some() {
+            return Err(AppError {
+                message: "You have already reported this message".into(),
+            });
+        }
+
+        let result = self
+            .report_collection
+            .insert_one(&report)
+            .await
+            .map_err(|e| AppError {
+                message: format!("Failed to create report: {}", e),
+            })?;
+
+        Ok(MessageReport {
+            id: result.inserted_id.as_object_id(),
+            ..report
+        })
+    }
+
+    pub async fn find_report(&self, id: &IdType) -> Result<MessageReport, AppError> {
+        let repo = BaseRepository::new(self.report_collection.clone().clone_with_type::<Document>());
+
+        repo.find_one::<MessageReport>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Report not found".into(),
+            })
+    }
+
+    pub async fn get_reports(
+        &self,
+        extra_match: Option<Document>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+    ) -> Result<Paginated<MessageReport>, AppError> {
+        let repo = BaseRepository::new(self.report_collection.clone().clone_with_type::<Document>());
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<MessageReport>(None, &[], limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    pub async fn review_report(
+        &self,
+        id: &IdType,
+        status: ReportStatus,
+        reviewer: ActorRef,
+        resolution_note: Option<String>,
+    ) -> Result<MessageReport, AppError> {
+        let now = bson::to_bson(&Utc::now()).unwrap();
+        let update = doc! {
+            "status": bson::to_bson(&status).unwrap(),
+            "reviewed_by": to_stored_document(&reviewer)?,
+            "resolution_note": resolution_note,
+            "reviewed_at": now,
+        };
+
+        let repo = BaseRepository::new(self.report_collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<MessageReport>(id, update).await
+    }
+}
@@ -0,0 +1,634 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+};
+
+use calamine::Reader;
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    Database,
+};
+use rust_xlsxwriter::{Format, Workbook};
+
+use crate::{
+    domain::{
+        assessment_category::AssessmentCategory,
+        class_subject::ClassSubject,
+        exam::Exam,
+        score::{Score, ScorePartial},
+        score_import::{
+            MarkSheetFormat, MarkSheetQuery, ScoreImportAction, ScoreImportReport, ScoreImportRow,
+            ScoreImportRowError, MARK_SHEET_COLUMNS,
+        },
+        student::Student,
+    },
+    errors::AppError,
+    models::id_model::IdType,
+    services::score_service::ScoreService,
+    utils::object_id::parse_object_id_value,
+};
+
+const IMPORT_CHANGE_REASON: &str = "Mark sheet import";
+const IMPORT_UNDO_REASON: &str = "Mark sheet import undone";
+
+/// Scores an import has written so far, by kind
+#[derive(Default)]
+struct WrittenRows {
+    created: Vec<ObjectId>,
+    /// Students whose existing score was overwritten
+    updated: Vec<ObjectId>,
+}
+
+/// Exam, subject, category and roster a mark sheet is checked against
+struct MarkSheetContext {
+    exam: Exam,
+    subject: ClassSubject,
+    category: AssessmentCategory,
+    students: Vec<Student>,
+    existing: HashMap<ObjectId, Score>,
+}
+
+pub struct ScoreImportService {
+    pub db: Database,
+    pub score_service: ScoreService,
+}
+
+impl ScoreImportService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            db: db.clone(),
+            score_service: ScoreService::new(db),
+        }
+    }
+
+    async fn load_context(&self, query: &MarkSheetQuery) -> Result<MarkSheetContext, AppError> {
+        let exam_id = parse_object_id_value(&query.exam_id)?;
+        let class_subject_id = parse_object_id_value(&query.class_subject_id)?;
+        let category_id = parse_object_id_value(&query.assessment_category_id)?;
+
+        let exam = self
+            .db
+            .collection::<Exam>("exams")
+            .find_one(doc! { "_id": exam_id, "is_deleted": { "$ne": true } })
+            .await?
+            .ok_or(AppError {
+                message: "Exam not found".into(),
+            })?;
+        let subject = self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(doc! { "_id": class_subject_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class subject not found".into(),
+            })?;
+        let category = self
+            .db
+            .collection::<AssessmentCategory>("assessment_categories")
+            .find_one(doc! { "_id": category_id, "is_deleted": { "$ne": true } })
+            .await?
+            .ok_or(AppError {
+                message: "Assessment category not found".into(),
+            })?;
+
+        if category
+            .class_subject_id
+            .is_some_and(|id| id != class_subject_id)
+        {
+            return Err(AppError {
+                message: "Assessment category belongs to another class subject".into(),
+            });
+        }
+        let class_id = subject.class_id.ok_or(AppError {
+            message: "Class subject is not assigned to a class".into(),
+        })?;
+
+        let mut students = Vec::new();
+        let mut cursor = self
+            .db
+            .collection::<Student>("students")
+            .find(doc! { "class_id": class_id, "is_active": true })
+            .sort(doc! { "name": 1 })
+            .await?;
+        while cursor.advance().await? {
+            students.push(cursor.deserialize_current()?);
+        }
+
+        let mut existing = HashMap::new();
+        let mut cursor = self
+            .score_service
+            .collection
+            .find(doc! {
+                "exam_id": exam_id,
+                "class_subject_id": class_subject_id,
+                "assessment_category_id": category_id,
+                "is_deleted": false
+            })
+            .await?;
+        while cursor.advance().await? {
+            let score: Score = cursor.deserialize_current()?;
+            if let Some(student_id) = score.student_id {
+                existing.insert(student_id, score);
+            }
+        }
+
+        Ok(MarkSheetContext {
+            exam,
+            subject,
+            category,
+            students,
+            existing,
+        })
+    }
+
+    // =========================
+    // TEMPLATE
+    // =========================
+
+    /// Mark sheet pre-filled with the class roster and any scores already entered
+    pub async fn template(
+        &self,
+        query: &MarkSheetQuery,
+    ) -> Result<(Vec<u8>, MarkSheetFormat, String), AppError> {
+        let ctx = self.load_context(query).await?;
+        let format = query.format.unwrap_or_default();
+        let default_max = query.max_score.unwrap_or(100.0);
+
+        let rows: Vec<[String; 6]> = ctx
+            .students
+            .iter()
+            .map(|student| {
+                let existing = student.id.and_then(|id| ctx.existing.get(&id));
+                [
+                    student.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    student.registration_number.clone().unwrap_or_default(),
+                    student.name.clone(),
+                    existing.map(|s| s.score.to_string()).unwrap_or_default(),
+                    existing.map_or(default_max, |s| s.max_score).to_string(),
+                    existing
+                        .and_then(|s| s.remarks.clone())
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        let name = sanitize(&format!(
+            "{}_{}_{}",
+            ctx.exam.name, ctx.subject.code, ctx.category.code
+        ));
+        let bytes = match format {
+            MarkSheetFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(MARK_SHEET_COLUMNS).map_err(csv_error)?;
+                for row in &rows {
+                    writer.write_record(row).map_err(csv_error)?;
+                }
+                writer.into_inner().map_err(|e| AppError {
+                    message: format!("Failed to write CSV: {}", e),
+                })?
+            }
+            MarkSheetFormat::Xlsx => xlsx_template(&rows)?,
+        };
+
+        let extension = match format {
+            MarkSheetFormat::Csv => "csv",
+            MarkSheetFormat::Xlsx => "xlsx",
+        };
+        Ok((bytes, format, format!("{}.{}", name, extension)))
+    }
+
+    // =========================
+    // IMPORT
+    // =========================
+
+    /// Validate every row, then save all of them or none: when a write fails
+    /// part-way, the rows already written are undone and the error names the
+    /// row. A dry run only returns the report.
+    pub async fn import(
+        &self,
+        query: &MarkSheetQuery,
+        bytes: &[u8],
+        file_name: Option<&str>,
+        entered_by: ObjectId,
+    ) -> Result<ScoreImportReport, AppError> {
+        let ctx = self.load_context(query).await?;
+        let exam_id = ctx.exam.id;
+        let class_subject_id = ctx.subject.id;
+
+        // Locked sheets are rejected before looking at the file
+        let reopen_request_id = self
+            .score_service
+            .moderation_service
+            .ensure_scores_editable(exam_id, class_subject_id)
+            .await?;
+
+        let format = MarkSheetFormat::detect(file_name, bytes);
+        let table = parse_table(bytes, format)?;
+        let dry_run = query.dry_run.unwrap_or(false);
+
+        let mut report = ScoreImportReport {
+            dry_run,
+            ..Default::default()
+        };
+        let (rows, errors) = self.validate(&ctx, &table, query.max_score)?;
+        report.total_rows = rows.len() + errors.len();
+        report.errors = errors;
+
+        for row in &rows {
+            match row.action {
+                ScoreImportAction::Create => report.created += 1,
+                ScoreImportAction::Update => report.updated += 1,
+                ScoreImportAction::Unchanged => report.unchanged += 1,
+                ScoreImportAction::Skip => report.skipped += 1,
+            }
+        }
+        report.rows = rows;
+
+        if dry_run || !report.errors.is_empty() {
+            return Ok(report);
+        }
+
+        let mut written = WrittenRows::default();
+        if let Err((row, e)) = self
+            .write_rows(&ctx, &report.rows, entered_by, reopen_request_id, &mut written)
+            .await
+        {
+            let failed = format!("Row {} ({}): {}", row.row, row.student_name, e.message);
+            let message = match self.undo(&ctx, &written, entered_by).await {
+                Ok(()) => format!("{}. No scores were imported", failed),
+                Err(undo_error) => format!(
+                    "{}. Undoing the rows already saved failed: {}",
+                    failed, undo_error.message
+                ),
+            };
+            return Err(AppError { message });
+        }
+
+        report.applied = true;
+        Ok(report)
+    }
+
+    async fn write_rows<'a>(
+        &self,
+        ctx: &MarkSheetContext,
+        rows: &'a [ScoreImportRow],
+        entered_by: ObjectId,
+        reopen_request_id: Option<ObjectId>,
+        written: &mut WrittenRows,
+    ) -> Result<(), (&'a ScoreImportRow, AppError)> {
+        for row in rows {
+            match row.action {
+                ScoreImportAction::Create => {
+                    let score = Score {
+                        id: None,
+                        school_id: ctx.subject.school_id.or(ctx.exam.school_id),
+                        student_id: Some(row.student_id),
+                        class_subject_id: ctx.subject.id,
+                        exam_id: ctx.exam.id,
+                        assessment_category_id: ctx.category.id,
+                        education_year_id: ctx.exam.education_year_id,
+                        score: row.score.unwrap_or_default(),
+                        max_score: row.max_score.unwrap_or_default(),
+                        percentage: 0.0,
+                        remarks: row.remarks.clone(),
+                        entered_by: Some(entered_by),
+                        created_at: None,
+                        updated_at: None,
+                        is_deleted: false,
+                    };
+                    let created = self.score_service.create(score).await.map_err(|e| (row, e))?;
+                    written.created.extend(created.id);
+                    self.score_service
+                        .log_created(
+                            &created,
+                            &entered_by,
+                            Some(IMPORT_CHANGE_REASON.to_string()),
+                            reopen_request_id,
+                        )
+                        .await
+                        .map_err(|e| (row, e))?;
+                }
+                // Updates go through `update` so every change lands in the score audit log
+                ScoreImportAction::Update => {
+                    let Some(score_id) = ctx.existing.get(&row.student_id).and_then(|s| s.id)
+                    else {
+                        continue;
+                    };
+                    let update = ScorePartial {
+                        score: row.score,
+                        max_score: row.max_score,
+                        remarks: Some(row.remarks.clone()),
+                        ..Default::default()
+                    };
+                    self.score_service
+                        .update(
+                            &IdType::ObjectId(score_id),
+                            &update,
+                            &entered_by,
+                            Some(IMPORT_CHANGE_REASON.to_string()),
+                        )
+                        .await
+                        .map_err(|e| (row, e))?;
+                    written.updated.push(row.student_id);
+                }
+                ScoreImportAction::Unchanged | ScoreImportAction::Skip => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Put back the scores an interrupted import already wrote
+    async fn undo(
+        &self,
+        ctx: &MarkSheetContext,
+        written: &WrittenRows,
+        entered_by: ObjectId,
+    ) -> Result<(), AppError> {
+        if !written.created.is_empty() {
+            self.score_service
+                .collection
+                .delete_many(doc! { "_id": { "$in": &written.created } })
+                .await?;
+            self.score_service
+                .audit_collection
+                .delete_many(doc! { "score_id": { "$in": &written.created } })
+                .await?;
+        }
+
+        for student_id in &written.updated {
+            let Some(previous) = ctx.existing.get(student_id) else {
+                continue;
+            };
+            let Some(score_id) = previous.id else {
+                continue;
+            };
+            let restore = ScorePartial {
+                score: Some(previous.score),
+                max_score: Some(previous.max_score),
+                remarks: Some(previous.remarks.clone()),
+                ..Default::default()
+            };
+            self.score_service
+                .update(
+                    &IdType::ObjectId(score_id),
+                    &restore,
+                    &entered_by,
+                    Some(IMPORT_UNDO_REASON.to_string()),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        ctx: &MarkSheetContext,
+        table: &[Vec<String>],
+        default_max: Option<f64>,
+    ) -> Result<(Vec<ScoreImportRow>, Vec<ScoreImportRowError>), AppError> {
+        let header = table.first().ok_or(AppError {
+            message: "The mark sheet is empty".into(),
+        })?;
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+
+        let id_col = column("student_id");
+        let reg_col = column("registration_number");
+        let score_col = column("score").ok_or(AppError {
+            message: "The mark sheet has no 'score' column".into(),
+        })?;
+        let max_col = column("max_score");
+        let remarks_col = column("remarks");
+        if id_col.is_none() && reg_col.is_none() {
+            return Err(AppError {
+                message: "The mark sheet needs a 'student_id' or 'registration_number' column"
+                    .into(),
+            });
+        }
+
+        let by_id: HashMap<ObjectId, &Student> = ctx
+            .students
+            .iter()
+            .filter_map(|s| s.id.map(|id| (id, s)))
+            .collect();
+        let by_reg: HashMap<String, &Student> = ctx
+            .students
+            .iter()
+            .filter_map(|s| {
+                s.registration_number
+                    .as_ref()
+                    .map(|r| (r.trim().to_lowercase(), s))
+            })
+            .collect();
+
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (index, cells) in table.iter().enumerate().skip(1) {
+            let row_number = index + 1;
+            let cell = |col: Option<usize>| {
+                col.and_then(|c| cells.get(c))
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty())
+            };
+            if cells.iter().all(|c| c.trim().is_empty()) {
+                continue;
+            }
+
+            let mut error = |student: Option<String>, message: String| {
+                errors.push(ScoreImportRowError {
+                    row: row_number,
+                    student,
+                    message,
+                });
+            };
+
+            let student = match (cell(id_col), cell(reg_col)) {
+                (Some(id), _) => ObjectId::parse_str(id).ok().and_then(|id| by_id.get(&id)),
+                (None, Some(reg)) => by_reg.get(&reg.to_lowercase()),
+                (None, None) => None,
+            };
+            let Some(student) = student else {
+                error(
+                    cell(reg_col).or(cell(id_col)).map(str::to_string),
+                    "Student is not in this class".into(),
+                );
+                continue;
+            };
+            let Some(student_id) = student.id else {
+                continue;
+            };
+            if !seen.insert(student_id) {
+                error(Some(student.name.clone()), "Student appears more than once".into());
+                continue;
+            }
+
+            let existing = ctx.existing.get(&student_id);
+            let remarks = cell(remarks_col).map(str::to_string);
+
+            let Some(score_text) = cell(Some(score_col)) else {
+                rows.push(ScoreImportRow {
+                    row: row_number,
+                    student_id,
+                    student_name: student.name.clone(),
+                    score: None,
+                    max_score: None,
+                    previous_score: existing.map(|s| s.score),
+                    remarks,
+                    action: ScoreImportAction::Skip,
+                });
+                continue;
+            };
+
+            let Ok(score) = score_text.parse::<f64>() else {
+                error(
+                    Some(student.name.clone()),
+                    format!("Score '{}' is not a number", score_text),
+                );
+                continue;
+            };
+            let max_score = match cell(max_col) {
+                Some(text) => match text.parse::<f64>() {
+                    Ok(v) => Some(v),
+                    Err(_) => {
+                        error(
+                            Some(student.name.clone()),
+                            format!("Max score '{}' is not a number", text),
+                        );
+                        continue;
+                    }
+                },
+                None => existing.map(|s| s.max_score).or(default_max),
+            };
+            let Some(max_score) = max_score else {
+                error(Some(student.name.clone()), "Max score is missing".into());
+                continue;
+            };
+
+            if max_score <= 0.0 {
+                error(Some(student.name.clone()), "Max score must be greater than 0".into());
+                continue;
+            }
+            if !(0.0..=max_score).contains(&score) {
+                error(
+                    Some(student.name.clone()),
+                    format!("Score {} must be between 0 and {}", score, max_score),
+                );
+                continue;
+            }
+
+            let action = match existing {
+                None => ScoreImportAction::Create,
+                Some(s) if s.score == score && s.max_score == max_score && s.remarks == remarks => {
+                    ScoreImportAction::Unchanged
+                }
+                Some(_) => ScoreImportAction::Update,
+            };
+
+            rows.push(ScoreImportRow {
+                row: row_number,
+                student_id,
+                student_name: student.name.clone(),
+                score: Some(score),
+                max_score: Some(max_score),
+                previous_score: existing.map(|s| s.score),
+                remarks,
+                action,
+            });
+        }
+
+        Ok((rows, errors))
+    }
+}
+
+/// Read the first sheet (XLSX) or the whole file (CSV) as text cells
+fn parse_table(bytes: &[u8], format: MarkSheetFormat) -> Result<Vec<Vec<String>>, AppError> {
+    match format {
+        MarkSheetFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .trim(csv::Trim::All)
+                .from_reader(bytes);
+            reader
+                .records()
+                .map(|record| {
+                    record
+                        .map(|r| r.iter().map(str::to_string).collect())
+                        .map_err(csv_error)
+                })
+                .collect()
+        }
+        MarkSheetFormat::Xlsx => {
+            let mut workbook = calamine::open_workbook_auto_from_rs(Cursor::new(bytes.to_vec()))
+                .map_err(|e| AppError {
+                    message: format!("Failed to read XLSX file: {}", e),
+                })?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or(AppError {
+                    message: "The workbook has no sheets".into(),
+                })?
+                .map_err(|e| AppError {
+                    message: format!("Failed to read XLSX sheet: {}", e),
+                })?;
+            Ok(range
+                .rows()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect())
+        }
+    }
+}
+
+fn xlsx_template(rows: &[[String; 6]]) -> Result<Vec<u8>, AppError> {
+    let xlsx_error = |e: rust_xlsxwriter::XlsxError| AppError {
+        message: format!("Failed to write XLSX file: {}", e),
+    };
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Marks").map_err(xlsx_error)?;
+    let bold = Format::new().set_bold();
+
+    for (col, title) in MARK_SHEET_COLUMNS.iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col as u16, *title, &bold)
+            .map_err(xlsx_error)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let r = i as u32 + 1;
+        for (col, value) in row.iter().enumerate() {
+            let col = col as u16;
+            // Numeric columns stay numbers so teachers can use formulas
+            match value.parse::<f64>() {
+                Ok(number) if col == 3 || col == 4 => sheet.write_number(r, col, number),
+                _ => sheet.write_string(r, col, value),
+            }
+            .map_err(xlsx_error)?;
+        }
+    }
+
+    sheet.set_column_width(0, 26).map_err(xlsx_error)?;
+    sheet.set_column_width(1, 20).map_err(xlsx_error)?;
+    sheet.set_column_width(2, 30).map_err(xlsx_error)?;
+    sheet.set_column_width(5, 40).map_err(xlsx_error)?;
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+    workbook.save_to_buffer().map_err(xlsx_error)
+}
+
+fn csv_error(e: csv::Error) -> AppError {
+    AppError {
+        message: format!("Failed to process CSV: {}", e),
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
@@ -14,12 +14,14 @@ use crate::{
         mongo_model::IndexDef,
     },
     repositories::base_repo::BaseRepository,
+    services::result_moderation_service::ResultModerationService,
     utils::mongo_utils::extract_valid_fields,
 };
 
 pub struct ScoreService {
     pub collection: Collection<Score>,
     pub audit_collection: Collection<ScoreAuditLog>,
+    pub moderation_service: ResultModerationService,
 }
 
 impl ScoreService {
@@ -27,6 +29,7 @@ impl ScoreService {
         Self {
             collection: db.collection::<Score>("scores"),
             audit_collection: db.collection::<ScoreAuditLog>("score_audit_logs"),
+            moderation_service: ResultModerationService::new(db),
         }
     }
 
@@ -59,6 +62,9 @@ impl ScoreService {
 
     pub async fn create(&self, mut score: Score) -> Result<Score, AppError> {
         self.ensure_indexes().await?;
+        self.moderation_service
+            .ensure_scores_editable(score.exam_id, score.class_subject_id)
+            .await?;
 
         // Calculate percentage
         score.percentage = if score.max_score > 0.0 {
@@ -75,9 +81,7 @@ impl ScoreService {
         }
 
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
-        let mut doc = extract_valid_fields(mongodb::bson::to_document(&score).map_err(|e| AppError {
-            message: format!("Failed to serialize score: {}", e),
-        })?);
+        let mut doc = extract_valid_fields(score.to_document()?);
         doc.insert("is_deleted", false);
 
         repo.create::<Score>(doc, None).await.map_err(|e| {
@@ -161,6 +165,10 @@ impl ScoreService {
         change_reason: Option<String>,
     ) -> Result<Score, AppError> {
         let existing = self.find_one(id).await?;
+        let reopen_request_id = self
+            .moderation_service
+            .ensure_scores_editable(existing.exam_id, existing.class_subject_id)
+            .await?;
 
         let mut update_doc = Document::new();
         let mut new_score_value = existing.score;
@@ -207,6 +215,7 @@ impl ScoreService {
                 new_score: new_score_value,
                 changed_by: Some(*changed_by),
                 change_reason,
+                reopen_request_id,
                 changed_at: Some(chrono::Utc::now()),
             };
             self.audit_collection.insert_one(audit_log).await?;
@@ -216,7 +225,34 @@ impl ScoreService {
         repo.update_one_and_fetch::<Score>(id, update_doc).await
     }
 
+    /// Audit entry for a score entered for the first time, from 0
+    pub async fn log_created(
+        &self,
+        score: &Score,
+        changed_by: &ObjectId,
+        change_reason: Option<String>,
+        reopen_request_id: Option<ObjectId>,
+    ) -> Result<(), AppError> {
+        let audit_log = ScoreAuditLog {
+            id: None,
+            score_id: score.id,
+            old_score: 0.0,
+            new_score: score.score,
+            changed_by: Some(*changed_by),
+            change_reason,
+            reopen_request_id,
+            changed_at: Some(chrono::Utc::now()),
+        };
+        self.audit_collection.insert_one(audit_log).await?;
+        Ok(())
+    }
+
     pub async fn delete(&self, id: &IdType) -> Result<Score, AppError> {
+        let existing = self.find_one(id).await?;
+        self.moderation_service
+            .ensure_scores_editable(existing.exam_id, existing.class_subject_id)
+            .await?;
+
         let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
         let update_doc = doc! { "is_deleted": true };
 
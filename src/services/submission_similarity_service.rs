@@ -0,0 +1,511 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        assignment::{Assignment, Submission},
+        submission_similarity::{
+            OverlapPassage, RunSimilarityCheck, SimilarityPair, SimilarityReport,
+            SkippedSubmission, SubmissionFingerprint,
+        },
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::{
+        assignment_service::AssignmentService, education_year_service::EducationYearService,
+    },
+    utils::{mongo_utils::to_stored_document, text_extract::extract_text_from_url},
+};
+
+const SHINGLE_SIZE: usize = 5;
+const SIGNATURE_SIZE: usize = 128;
+const DEFAULT_THRESHOLD: f64 = 0.3;
+const MAX_PRIOR_SUBMISSIONS: i64 = 500;
+
+// =========================
+// SHINGLING AND MINHASH
+// =========================
+
+struct Token {
+    word: String,
+    byte_start: usize,
+    byte_end: usize,
+    char_start: usize,
+    char_end: usize,
+}
+
+/// Lowercased alphanumeric words with their positions in the text
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+
+    for (char_index, (byte_index, c)) in text.char_indices().enumerate() {
+        if c.is_alphanumeric() {
+            let token = current.get_or_insert_with(|| Token {
+                word: String::new(),
+                byte_start: byte_index,
+                byte_end: byte_index,
+                char_start: char_index,
+                char_end: char_index,
+            });
+            token.word.extend(c.to_lowercase());
+            token.byte_end = byte_index + c.len_utf8();
+            token.char_end = char_index + 1;
+        } else if let Some(token) = current.take() {
+            tokens.push(token);
+        }
+    }
+    tokens.extend(current);
+    tokens
+}
+
+/// FNV-1a, stable across builds so cached signatures stay comparable
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Hash of every run of `SHINGLE_SIZE` consecutive words
+fn shingles(tokens: &[Token]) -> Vec<u64> {
+    tokens
+        .windows(SHINGLE_SIZE)
+        .map(|window| {
+            let joined: Vec<&str> = window.iter().map(|t| t.word.as_str()).collect();
+            fnv1a(joined.join(" ").as_bytes())
+        })
+        .collect()
+}
+
+fn minhash(shingles: &[u64]) -> Vec<u64> {
+    (0..SIGNATURE_SIZE as u64)
+        .map(|i| {
+            let seed = splitmix64(i + 1);
+            shingles
+                .iter()
+                .map(|h| splitmix64(h ^ seed))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+fn estimate_similarity(a: &[u64], b: &[u64]) -> f64 {
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    same as f64 / SIGNATURE_SIZE as f64
+}
+
+fn round4(value: f64) -> f64 {
+    (value * 10000.0).round() / 10000.0
+}
+
+struct Fingerprinted {
+    submission_id: ObjectId,
+    student_id: Option<ObjectId>,
+    prior_assignment_id: Option<ObjectId>,
+    text: String,
+    tokens: Vec<Token>,
+    shingles: Vec<u64>,
+    signature: Vec<u64>,
+}
+
+impl Fingerprinted {
+    fn new(submission: &Submission, text: String, prior_assignment_id: Option<ObjectId>) -> Self {
+        let tokens = tokenize(&text);
+        let shingles = shingles(&tokens);
+        let signature = minhash(&shingles);
+        Self {
+            submission_id: submission.id.unwrap_or_default(),
+            student_id: submission.student_id,
+            prior_assignment_id,
+            text,
+            tokens,
+            shingles,
+            signature,
+        }
+    }
+}
+
+/// Exact similarity of two texts and the passages they share
+fn compare(a: &Fingerprinted, b: &Fingerprinted, estimated: f64) -> SimilarityPair {
+    let set_a: HashSet<u64> = a.shingles.iter().copied().collect();
+    let set_b: HashSet<u64> = b.shingles.iter().copied().collect();
+    let shared = set_a.intersection(&set_b).count();
+    let union = set_a.len() + set_b.len() - shared;
+    let similarity = if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    };
+
+    let mut first_in_a: HashMap<u64, usize> = HashMap::new();
+    for (i, h) in a.shingles.iter().enumerate() {
+        first_in_a.entry(*h).or_insert(i);
+    }
+
+    // Walk b, growing each match as long as both texts keep agreeing
+    let mut passages = Vec::new();
+    let mut covered_a = vec![false; a.tokens.len()];
+    let mut covered_b = vec![false; b.tokens.len()];
+    let mut p = 0;
+    while p < b.shingles.len() {
+        let Some(&q) = first_in_a.get(&b.shingles[p]) else {
+            p += 1;
+            continue;
+        };
+        let mut len = 1;
+        while p + len < b.shingles.len()
+            && q + len < a.shingles.len()
+            && b.shingles[p + len] == a.shingles[q + len]
+        {
+            len += 1;
+        }
+
+        let words = len + SHINGLE_SIZE - 1;
+        let (a_first, a_last) = (&a.tokens[q], &a.tokens[q + words - 1]);
+        let (b_first, b_last) = (&b.tokens[p], &b.tokens[p + words - 1]);
+        covered_a[q..q + words].iter_mut().for_each(|c| *c = true);
+        covered_b[p..p + words].iter_mut().for_each(|c| *c = true);
+        passages.push(OverlapPassage {
+            a_start: a_first.char_start,
+            a_end: a_last.char_end,
+            b_start: b_first.char_start,
+            b_end: b_last.char_end,
+            text: b.text[b_first.byte_start..b_last.byte_end].to_string(),
+        });
+
+        p += len;
+    }
+
+    let coverage = |covered: &[bool]| {
+        if covered.is_empty() {
+            0.0
+        } else {
+            covered.iter().filter(|c| **c).count() as f64 / covered.len() as f64
+        }
+    };
+
+    SimilarityPair {
+        submission_a_id: a.submission_id,
+        student_a_id: a.student_id,
+        submission_b_id: b.submission_id,
+        student_b_id: b.student_id,
+        prior_assignment_id: b.prior_assignment_id,
+        estimated_similarity: round4(estimated),
+        similarity: round4(similarity),
+        coverage_a: round4(coverage(&covered_a)),
+        coverage_b: round4(coverage(&covered_b)),
+        passages,
+    }
+}
+
+// =========================
+// SERVICE
+// =========================
+
+pub struct SubmissionSimilarityService {
+    pub collection: Collection<SimilarityReport>,
+    pub fingerprint_collection: Collection<SubmissionFingerprint>,
+    pub db: Database,
+    pub main_db: Database,
+}
+
+impl SubmissionSimilarityService {
+    pub fn new(db: &Database, main_db: &Database) -> Self {
+        Self {
+            collection: db.collection::<SimilarityReport>("similarity_reports"),
+            fingerprint_collection: db
+                .collection::<SubmissionFingerprint>("submission_fingerprints"),
+            db: db.clone(),
+            main_db: main_db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let report_indexes = vec![IndexDef::compound(
+            vec![("assignment_id", 1), ("generated_at", -1)],
+            false,
+        )];
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&report_indexes)
+            .await?;
+
+        let fingerprint_indexes = vec![IndexDef::compound(
+            vec![("submission_id", 1), ("version", 1), ("shingle_size", 1)],
+            true,
+        )];
+        BaseRepository::new(
+            self.fingerprint_collection
+                .clone()
+                .clone_with_type::<Document>(),
+        )
+        .ensure_indexes(&fingerprint_indexes)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn find_one(&self, id: &IdType) -> Result<SimilarityReport, AppError> {
+        self.collection
+            .find_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?
+            .ok_or(AppError {
+                message: "Similarity report not found".into(),
+            })
+    }
+
+    pub async fn latest_for_assignment(
+        &self,
+        assignment_id: &IdType,
+    ) -> Result<SimilarityReport, AppError> {
+        self.collection
+            .find_one(doc! { "assignment_id": IdType::to_object_id(assignment_id)? })
+            .sort(doc! { "generated_at": -1 })
+            .await?
+            .ok_or(AppError {
+                message: "No similarity report for this assignment yet".into(),
+            })
+    }
+
+    /// Text of a submission: the typed answer plus any readable file. The
+    /// result is cached per submission version.
+    async fn submission_text(&self, submission: &Submission) -> Result<String, String> {
+        let submission_id = submission.id.ok_or("Submission has no id")?;
+        let cached = self
+            .fingerprint_collection
+            .find_one(doc! {
+                "submission_id": submission_id,
+                "version": submission.version,
+                "shingle_size": SHINGLE_SIZE as i64
+            })
+            .await
+            .ok()
+            .flatten();
+        if let Some(cached) = cached {
+            return Ok(cached.text);
+        }
+
+        let mut parts: Vec<String> = [&submission.text_content, &submission.comment]
+            .into_iter()
+            .flatten()
+            .filter(|t| !t.trim().is_empty())
+            .cloned()
+            .collect();
+        let mut file_error = None;
+        if let Some(url) = &submission.file_url {
+            match extract_text_from_url(url).await {
+                Ok(text) => parts.push(text),
+                Err(e) => file_error = Some(e),
+            }
+        }
+
+        let text = parts.join("\n\n");
+        if tokenize(&text).len() < SHINGLE_SIZE * 2 {
+            return Err(file_error.unwrap_or_else(|| "Too little text to compare".into()));
+        }
+
+        let fingerprint = Fingerprinted::new(submission, text.clone(), None);
+        let cached = SubmissionFingerprint {
+            id: None,
+            submission_id,
+            version: submission.version,
+            shingle_size: SHINGLE_SIZE,
+            text: text.clone(),
+            signature: fingerprint.signature.iter().map(|h| *h as i64).collect(),
+            created_at: Utc::now(),
+        };
+        if let Ok(doc) = to_stored_document(&cached) {
+            // A failed cache write only costs a re-download next time
+            let _ = self
+                .fingerprint_collection
+                .clone_with_type::<Document>()
+                .insert_one(doc)
+                .await;
+        }
+
+        Ok(text)
+    }
+
+    /// Submissions to earlier years' assignments on the same class subject,
+    /// or on class subjects created from the same template subject
+    async fn prior_submissions(
+        &self,
+        assignment: &Assignment,
+    ) -> Result<Vec<(Submission, ObjectId)>, AppError> {
+        let Some(subject_id) = assignment.subject_id else {
+            return Ok(vec![]);
+        };
+
+        let subjects = self.db.collection::<Document>("class_subjects");
+        let mut subject_ids = vec![subject_id];
+        let template_id = subjects
+            .find_one(doc! { "_id": subject_id })
+            .await?
+            .and_then(|s| s.get_object_id("main_subject_id").ok());
+        if let Some(template_id) = template_id {
+            let related: Vec<Document> = subjects
+                .find(doc! { "main_subject_id": template_id, "_id": { "$ne": subject_id } })
+                .await?
+                .try_collect()
+                .await?;
+            subject_ids.extend(related.iter().filter_map(|s| s.get_object_id("_id").ok()));
+        }
+
+        // Anything due before the education year of this assignment
+        let year_start = EducationYearService::new(&self.main_db)
+            .get_current_year_and_term(Some(assignment.due_date))
+            .await
+            .map(|(year, _)| year.start_date)
+            .unwrap_or(assignment.due_date - Duration::days(365));
+
+        let assignments: Vec<Document> = self
+            .db
+            .collection::<Document>("assignments")
+            .find(doc! {
+                "subject_id": { "$in": subject_ids },
+                "due_date": { "$lt": bson::to_bson(&year_start).unwrap() },
+                "is_deleted": { "$ne": true }
+            })
+            .await?
+            .try_collect()
+            .await?;
+        let assignment_ids: Vec<ObjectId> = assignments
+            .iter()
+            .filter_map(|a| a.get_object_id("_id").ok())
+            .collect();
+        if assignment_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let submissions: Vec<Submission> = AssignmentService::new(&self.db)
+            .submission_collection
+            .find(doc! { "assignment_id": { "$in": assignment_ids }, "is_deleted": false })
+            .sort(doc! { "submitted_at": -1 })
+            .limit(MAX_PRIOR_SUBMISSIONS)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(submissions
+            .into_iter()
+            .filter_map(|s| s.assignment_id.map(|a| (s, a)))
+            .collect())
+    }
+
+    /// Compare every pair of submissions to the assignment, and each of them
+    /// with prior years' work. MinHash estimates pick the pairs worth an
+    /// exact comparison; pairs at or above `threshold` are reported.
+    pub async fn run(
+        &self,
+        assignment_id: &IdType,
+        options: RunSimilarityCheck,
+        generated_by: Option<ObjectId>,
+    ) -> Result<SimilarityReport, AppError> {
+        self.ensure_indexes().await?;
+
+        let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD);
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AppError {
+                message: "Threshold must be between 0 and 1".into(),
+            });
+        }
+
+        let assignment_service = AssignmentService::new(&self.db);
+        let assignment = assignment_service
+            .find_one_assignment(Some(assignment_id), None)
+            .await?;
+        let assignment_oid = IdType::to_object_id(assignment_id)?;
+
+        let submissions: Vec<Submission> = assignment_service
+            .submission_collection
+            .find(doc! { "assignment_id": assignment_oid, "is_deleted": false })
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut skipped = Vec::new();
+        let mut current = Vec::new();
+        for submission in &submissions {
+            match self.submission_text(submission).await {
+                Ok(text) => current.push(Fingerprinted::new(submission, text, None)),
+                Err(reason) => skipped.push(SkippedSubmission {
+                    submission_id: submission.id.unwrap_or_default(),
+                    student_id: submission.student_id,
+                    reason,
+                }),
+            }
+        }
+
+        let mut prior = Vec::new();
+        if options.include_prior_years.unwrap_or(true) {
+            for (submission, prior_assignment_id) in self.prior_submissions(&assignment).await? {
+                // Unreadable old work is not worth reporting
+                if let Ok(text) = self.submission_text(&submission).await {
+                    prior.push(Fingerprinted::new(
+                        &submission,
+                        text,
+                        Some(prior_assignment_id),
+                    ));
+                }
+            }
+        }
+
+        // The estimate has an error around 0.05, so candidates get some slack
+        let candidate_cutoff = (threshold - 0.1).max(0.0);
+        let mut pairs = Vec::new();
+        for (i, a) in current.iter().enumerate() {
+            for b in current[i + 1..].iter().chain(prior.iter()) {
+                if a.student_id.is_some() && a.student_id == b.student_id {
+                    continue;
+                }
+                let estimated = estimate_similarity(&a.signature, &b.signature);
+                if estimated < candidate_cutoff {
+                    continue;
+                }
+                let pair = compare(a, b, estimated);
+                if pair.similarity >= threshold {
+                    pairs.push(pair);
+                }
+            }
+        }
+        pairs.sort_by(|x, y| y.similarity.total_cmp(&x.similarity));
+
+        let report = SimilarityReport {
+            id: None,
+            school_id: assignment.school_id,
+            assignment_id: assignment_oid,
+            class_subject_id: assignment.subject_id,
+            threshold,
+            shingle_size: SHINGLE_SIZE,
+            submissions_checked: current.len(),
+            prior_submissions_checked: prior.len(),
+            skipped,
+            pairs,
+            generated_by,
+            generated_at: Utc::now(),
+        };
+
+        let result = self
+            .collection
+            .clone_with_type::<Document>()
+            .insert_one(to_stored_document(&report)?)
+            .await?;
+        Ok(SimilarityReport {
+            id: result.inserted_id.as_object_id(),
+            ..report
+        })
+    }
+}
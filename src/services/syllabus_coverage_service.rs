@@ -0,0 +1,566 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Collection, Database,
+};
+
+use crate::{
+    domain::{
+        class_subject::ClassSubject,
+        class_timetable::ClassTimetable,
+        common_details::Paginated,
+        education_year::{EducationYear, Term},
+        syllabus_coverage::{
+            CoverageDashboard, CreateLessonLog, LessonLog, SubjectCoverage, TopicCoverage,
+            UpdateLessonLog,
+        },
+        template_subject::TemplateTopic,
+    },
+    errors::AppError,
+    models::{id_model::IdType, mongo_model::IndexDef},
+    repositories::base_repo::BaseRepository,
+    services::education_year_service::EducationYearService,
+    utils::{
+        math_utils::round2,
+        mongo_utils::{id_match, to_stored_document},
+        object_id::parse_object_id_value,
+    },
+};
+
+/// Percentage points a subject may trail the calendar before it is flagged
+const BEHIND_TOLERANCE: f64 = 10.0;
+
+pub struct SyllabusCoverageService {
+    pub collection: Collection<LessonLog>,
+    pub db: Database,
+    pub main_db: Database,
+}
+
+impl SyllabusCoverageService {
+    pub fn new(db: &Database, main_db: &Database) -> Self {
+        Self {
+            collection: db.collection::<LessonLog>("lesson_logs"),
+            db: db.clone(),
+            main_db: main_db.clone(),
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), AppError> {
+        let indexes = vec![
+            IndexDef::compound(vec![("class_subject_id", 1), ("lesson_date", -1)], false),
+            IndexDef::compound(vec![("education_year_id", 1), ("term_order", 1)], false),
+            IndexDef::single("teacher_id", false),
+            IndexDef::single("logged_by", false),
+        ];
+
+        BaseRepository::new(self.collection.clone().clone_with_type::<Document>())
+            .ensure_indexes(&indexes)
+            .await
+    }
+
+    // =========================
+    // LESSON LOGS
+    // =========================
+
+    pub async fn find_one(&self, id: &IdType) -> Result<LessonLog, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.find_one::<LessonLog>(doc! { "_id": IdType::to_object_id(id)? }, None)
+            .await?
+            .ok_or(AppError {
+                message: "Lesson log not found".into(),
+            })
+    }
+
+    pub async fn get_all(
+        &self,
+        filter: Option<String>,
+        limit: Option<i64>,
+        skip: Option<i64>,
+        extra_match: Option<Document>,
+    ) -> Result<Paginated<LessonLog>, AppError> {
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let searchable = [
+            "_id",
+            "class_id",
+            "class_subject_id",
+            "teacher_id",
+            "topic_order",
+            "topic_title",
+            "note",
+        ];
+
+        let (data, total, total_pages, current_page) = repo
+            .get_all::<LessonLog>(filter, &searchable, limit, skip, extra_match)
+            .await?;
+
+        Ok(Paginated {
+            data,
+            total,
+            total_pages,
+            current_page,
+        })
+    }
+
+    /// Log a lesson against a topic. With a timetable period the duration is
+    /// the period's length, otherwise the teacher must give one.
+    pub async fn create(
+        &self,
+        school_id: Option<ObjectId>,
+        dto: CreateLessonLog,
+        logged_by: ObjectId,
+    ) -> Result<LessonLog, AppError> {
+        self.ensure_indexes().await?;
+
+        let class_subject_id = parse_object_id_value(&dto.class_subject_id)?;
+        let subject = self.find_subject(class_subject_id).await?;
+        let topic_title = Self::find_topic(&subject, &dto.topic_order)?.title.clone();
+
+        let (year, term) = EducationYearService::new(&self.main_db)
+            .get_current_year_and_term(Some(dto.lesson_date))
+            .await?;
+        let term_order = term.as_ref().map(|t| t.order);
+
+        let period_id = dto
+            .period_id
+            .as_deref()
+            .map(parse_object_id_value)
+            .transpose()?;
+        let duration_minutes = match period_id {
+            Some(period_id) => {
+                self.period_duration(&subject, year.id, term_order, period_id, dto.lesson_date)
+                    .await?
+            }
+            None => dto.duration_minutes.ok_or(AppError {
+                message: "duration_minutes is required when no period is given".into(),
+            })?,
+        };
+        Self::validate_duration(duration_minutes)?;
+
+        let now = Utc::now();
+        let log = LessonLog {
+            id: None,
+            school_id: school_id.or(subject.school_id),
+            class_id: subject.class_id,
+            class_subject_id,
+            education_year_id: year.id,
+            term_order,
+            topic_order: dto.topic_order,
+            topic_title,
+            lesson_date: dto.lesson_date,
+            period_id,
+            duration_minutes,
+            note: dto.note,
+            teacher_id: subject.teacher_id,
+            logged_by,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        let doc = to_stored_document(&log)?;
+        repo.create::<LessonLog>(doc, None).await
+    }
+
+    pub async fn update(&self, id: &IdType, dto: UpdateLessonLog) -> Result<LessonLog, AppError> {
+        let log = self.find_one(id).await?;
+
+        let mut set = doc! {};
+        if let Some(order) = dto.topic_order {
+            let subject = self.find_subject(log.class_subject_id).await?;
+            let topic = Self::find_topic(&subject, &order)?;
+            set.insert("topic_title", topic.title.clone());
+            set.insert("topic_order", order);
+        }
+        if let Some(minutes) = dto.duration_minutes {
+            if log.period_id.is_some() {
+                return Err(AppError {
+                    message: "Duration of a timetabled lesson comes from its period".into(),
+                });
+            }
+            Self::validate_duration(minutes)?;
+            set.insert("duration_minutes", minutes);
+        }
+        if let Some(note) = dto.note {
+            set.insert("note", note);
+        }
+
+        let repo = BaseRepository::new(self.collection.clone().clone_with_type::<Document>());
+        repo.update_one_and_fetch::<LessonLog>(id, set).await
+    }
+
+    pub async fn delete(&self, id: &IdType) -> Result<LessonLog, AppError> {
+        let log = self.find_one(id).await?;
+        self.collection
+            .delete_one(doc! { "_id": IdType::to_object_id(id)? })
+            .await?;
+        Ok(log)
+    }
+
+    pub async fn find_subject(&self, class_subject_id: ObjectId) -> Result<ClassSubject, AppError> {
+        self.db
+            .collection::<ClassSubject>("class_subjects")
+            .find_one(doc! { "_id": class_subject_id })
+            .await?
+            .ok_or(AppError {
+                message: "Class subject not found".into(),
+            })
+    }
+
+    /// Top-level topic or sub-topic with the given order
+    pub(crate) fn find_topic<'a>(
+        subject: &'a ClassSubject,
+        order: &str,
+    ) -> Result<&'a TemplateTopic, AppError> {
+        subject
+            .topics
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|t| std::iter::once(t).chain(t.subtopics.as_deref().unwrap_or_default()))
+            .find(|t| t.order == order)
+            .ok_or(AppError {
+                message: format!("Subject has no topic {}", order),
+            })
+    }
+
+    fn validate_duration(minutes: i32) -> Result<(), AppError> {
+        if !(1..=600).contains(&minutes) {
+            return Err(AppError {
+                message: "duration_minutes must be between 1 and 600".into(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The period must sit on the lesson's weekday in the class timetable and
+    /// be scheduled for this subject
+    pub(crate) async fn period_duration(
+        &self,
+        subject: &ClassSubject,
+        education_year_id: Option<ObjectId>,
+        term_order: Option<i32>,
+        period_id: ObjectId,
+        lesson_date: DateTime<Utc>,
+    ) -> Result<i32, AppError> {
+        let class_id = subject.class_id.ok_or(AppError {
+            message: "Subject is not assigned to a class".into(),
+        })?;
+
+        // Timetables are stored with hex ids
+        let mut filter = doc! {
+            "class_id": id_match(&class_id),
+            "education_year_id": education_year_id.as_ref().map(id_match)
+        };
+        if let Some(order) = term_order {
+            filter.insert("term_order", order);
+        }
+        let timetable = self
+            .db
+            .collection::<ClassTimetable>("class_timetables")
+            .find_one(filter)
+            .await?
+            .ok_or(AppError {
+                message: "Class timetable not found for the lesson date".into(),
+            })?;
+
+        let period = timetable
+            .weekly_schedule
+            .iter()
+            .filter(|d| d.day == lesson_date.weekday() && !d.is_holiday)
+            .flat_map(|d| d.periods.iter())
+            .find(|p| p.period_id == period_id)
+            .ok_or(AppError {
+                message: "Period is not on the timetable for that day".into(),
+            })?;
+
+        if period.subject_id.is_some() && period.subject_id != subject.id {
+            return Err(AppError {
+                message: "Period is scheduled for another subject".into(),
+            });
+        }
+
+        Ok(period.duration_minutes)
+    }
+
+    // =========================
+    // COVERAGE
+    // =========================
+
+    /// Given year, or the current one, with the term running today
+    pub(crate) async fn resolve_year(
+        &self,
+        education_year_id: Option<&IdType>,
+    ) -> Result<(EducationYear, Option<Term>), AppError> {
+        let year_service = EducationYearService::new(&self.main_db);
+        match education_year_id {
+            Some(id) => {
+                let year = year_service.find_one(Some(id), None).await?;
+                let now = Utc::now();
+                let term = year
+                    .terms
+                    .iter()
+                    .find(|t| t.start_date <= now && t.end_date >= now)
+                    .cloned();
+                Ok((year, term))
+            }
+            None => year_service.get_current_year_and_term(None).await,
+        }
+    }
+
+    async fn logs_for_year(
+        &self,
+        year: &EducationYear,
+        mut filter: Document,
+    ) -> Result<HashMap<ObjectId, Vec<LessonLog>>, AppError> {
+        filter.insert("education_year_id", year.id);
+        let logs: Vec<LessonLog> = self
+            .collection
+            .find(filter)
+            .sort(doc! { "lesson_date": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut by_subject: HashMap<ObjectId, Vec<LessonLog>> = HashMap::new();
+        for log in logs {
+            by_subject
+                .entry(log.class_subject_id)
+                .or_default()
+                .push(log);
+        }
+        Ok(by_subject)
+    }
+
+    pub async fn subject_coverage(
+        &self,
+        class_subject_id: ObjectId,
+        education_year_id: Option<&IdType>,
+    ) -> Result<SubjectCoverage, AppError> {
+        let subject = self.find_subject(class_subject_id).await?;
+        let (year, _) = self.resolve_year(education_year_id).await?;
+        let mut logs = self
+            .logs_for_year(&year, doc! { "class_subject_id": class_subject_id })
+            .await?;
+
+        Ok(Self::coverage_of(
+            &subject,
+            logs.remove(&class_subject_id).unwrap_or_default(),
+            &year,
+            Utc::now(),
+        ))
+    }
+
+    /// Every active subject of the school, furthest behind first
+    pub async fn dashboard(
+        &self,
+        education_year_id: Option<&IdType>,
+        class_id: Option<ObjectId>,
+    ) -> Result<CoverageDashboard, AppError> {
+        let (year, term) = self.resolve_year(education_year_id).await?;
+        let now = Utc::now();
+
+        let mut subject_filter = doc! { "disable": { "$ne": true } };
+        let mut log_filter = doc! {};
+        if let Some(class_id) = class_id {
+            subject_filter.insert("class_id", class_id);
+            log_filter.insert("class_id", class_id);
+        }
+        let subjects: Vec<ClassSubject> = self
+            .db
+            .collection::<ClassSubject>("class_subjects")
+            .find(subject_filter)
+            .await?
+            .try_collect()
+            .await?;
+        let mut logs = self.logs_for_year(&year, log_filter).await?;
+
+        let mut coverages: Vec<SubjectCoverage> = subjects
+            .iter()
+            .map(|s| {
+                let subject_logs = s.id.and_then(|id| logs.remove(&id)).unwrap_or_default();
+                Self::coverage_of(s, subject_logs, &year, now)
+            })
+            .collect();
+        coverages.sort_by(|a, b| {
+            let gap_a = a.expected_percentage - a.coverage_percentage;
+            let gap_b = b.expected_percentage - b.coverage_percentage;
+            gap_b.total_cmp(&gap_a)
+        });
+
+        let total_subjects = coverages.len();
+        let behind_subjects = coverages.iter().filter(|c| c.is_behind).count();
+        let average_coverage = if total_subjects == 0 {
+            0.0
+        } else {
+            round2(
+                coverages.iter().map(|c| c.coverage_percentage).sum::<f64>()
+                    / total_subjects as f64,
+            )
+        };
+
+        Ok(CoverageDashboard {
+            education_year_id: year.id,
+            education_year_label: year.label.clone(),
+            term_name: term.as_ref().map(|t| t.name.clone()),
+            term_order: term.as_ref().map(|t| t.order),
+            days_remaining_in_term: term
+                .as_ref()
+                .map(|t| (t.end_date - now).num_days().max(0))
+                .unwrap_or(0),
+            total_subjects,
+            behind_subjects,
+            average_coverage,
+            subjects: coverages,
+            generated_at: now,
+        })
+    }
+
+    /// Hours taught per top-level topic against the planned hours. Lessons on
+    /// a sub-topic count towards its parent, and hours taught beyond a topic's
+    /// plan do not make up for another topic.
+    fn coverage_of(
+        subject: &ClassSubject,
+        logs: Vec<LessonLog>,
+        year: &EducationYear,
+        now: DateTime<Utc>,
+    ) -> SubjectCoverage {
+        let topics = subject.topics.as_deref().unwrap_or_default();
+
+        let mut parent_of: HashMap<&str, &str> = HashMap::new();
+        for topic in topics {
+            parent_of.insert(&topic.order, &topic.order);
+            for sub in topic.subtopics.as_deref().unwrap_or_default() {
+                parent_of.insert(&sub.order, &topic.order);
+            }
+        }
+
+        let mut per_topic: HashMap<&str, (f64, usize, Option<DateTime<Utc>>)> = HashMap::new();
+        let mut taught_hours = 0.0;
+        let mut last_lesson_at: Option<DateTime<Utc>> = None;
+        for log in &logs {
+            let hours = log.duration_minutes as f64 / 60.0;
+            taught_hours += hours;
+            last_lesson_at = last_lesson_at.max(Some(log.lesson_date));
+            if let Some(parent) = parent_of.get(log.topic_order.as_str()) {
+                let entry = per_topic.entry(parent).or_default();
+                entry.0 += hours;
+                entry.1 += 1;
+                entry.2 = entry.2.max(Some(log.lesson_date));
+            }
+        }
+
+        let topic_coverage: Vec<TopicCoverage> = topics
+            .iter()
+            .zip(planned_topic_hours(subject))
+            .map(|(t, planned)| {
+                let (taught, lessons, last) =
+                    per_topic.get(t.order.as_str()).copied().unwrap_or_default();
+                TopicCoverage {
+                    order: t.order.clone(),
+                    title: t.title.clone(),
+                    planned_hours: round2(planned),
+                    taught_hours: round2(taught),
+                    lessons,
+                    coverage_percentage: percentage(taught, planned),
+                    last_taught_at: last,
+                }
+            })
+            .collect();
+
+        let (planned_hours, covered_hours) = if topic_coverage.is_empty() {
+            let planned = subject.estimated_hours as f64;
+            (planned, taught_hours.min(planned))
+        } else {
+            topic_coverage.iter().fold((0.0, 0.0), |(p, c), t| {
+                (p + t.planned_hours, c + t.taught_hours.min(t.planned_hours))
+            })
+        };
+
+        let coverage_percentage = percentage(covered_hours, planned_hours);
+        let (elapsed_days, remaining_days) = teaching_days(year, now);
+        let total_days = elapsed_days + remaining_days;
+        let expected_percentage = if total_days > 0 {
+            round2(elapsed_days as f64 / total_days as f64 * 100.0)
+        } else {
+            0.0
+        };
+        let weekly_hours_needed = (remaining_days > 0).then(|| {
+            round2((planned_hours - covered_hours).max(0.0) / (remaining_days as f64 / 7.0))
+        });
+
+        SubjectCoverage {
+            class_subject_id: subject.id.unwrap_or_default(),
+            class_id: subject.class_id,
+            teacher_id: subject.teacher_id,
+            subject_name: subject.name.clone(),
+            subject_code: subject.code.clone(),
+            planned_hours: round2(planned_hours),
+            taught_hours: round2(taught_hours),
+            coverage_percentage,
+            expected_percentage,
+            lessons: logs.len(),
+            last_lesson_at,
+            weekly_hours_needed,
+            is_behind: coverage_percentage + BEHIND_TOLERANCE < expected_percentage,
+            topics: topic_coverage,
+        }
+    }
+}
+
+/// Planned hours of each top-level topic, in order. Topics without an
+/// estimate share what is left of the subject's hours.
+pub(crate) fn planned_topic_hours(subject: &ClassSubject) -> Vec<f64> {
+    let topics = subject.topics.as_deref().unwrap_or_default();
+    let planned_of = |t: &TemplateTopic| {
+        t.estimated_hours.or_else(|| {
+            let subs = t.subtopics.as_deref().unwrap_or_default();
+            let hours: i32 = subs.iter().filter_map(|s| s.estimated_hours).sum();
+            (hours > 0).then_some(hours)
+        })
+    };
+    let estimated: Vec<Option<i32>> = topics.iter().map(planned_of).collect();
+    let unestimated = estimated.iter().filter(|h| h.is_none()).count();
+    let leftover = (subject.estimated_hours - estimated.iter().flatten().sum::<i32>()).max(0);
+    let share = if unestimated > 0 {
+        leftover as f64 / unestimated as f64
+    } else {
+        0.0
+    };
+
+    estimated
+        .into_iter()
+        .map(|hours| hours.map(|h| h as f64).unwrap_or(share))
+        .collect()
+}
+
+/// Term days of the year already gone and still to come. Holidays between
+/// terms are not counted, a year without terms counts as one long term.
+fn teaching_days(year: &EducationYear, now: DateTime<Utc>) -> (i64, i64) {
+    let spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = if year.terms.is_empty() {
+        vec![(year.start_date, year.end_date)]
+    } else {
+        year.terms
+            .iter()
+            .map(|t| (t.start_date, t.end_date))
+            .collect()
+    };
+
+    spans
+        .iter()
+        .fold((0, 0), |(elapsed, remaining), (start, end)| {
+            let total = (*end - *start).num_days().max(0);
+            let gone = (now.min(*end) - *start).num_days().clamp(0, total);
+            (elapsed + gone, remaining + total - gone)
+        })
+}
+
+fn percentage(part: f64, whole: f64) -> f64 {
+    if whole <= 0.0 {
+        return 0.0;
+    }
+    round2((part / whole * 100.0).min(100.0))
+}
+
@@ -12,6 +12,22 @@ pub fn generate_code() -> String {
         .collect()
 }
 
+/// Twelve characters in groups of four, e.g. "7KQ2-M9XD-4TPA". Similar looking
+/// characters are left out so codes can be typed from a printout.
+pub fn generate_verification_code() -> String {
+    let mut rng = thread_rng();
+    let chars: Vec<char> = "ABCDEFGHJKLMNPQRSTUVWXYZ23456789".chars().collect();
+
+    (0..3)
+        .map(|_| {
+            (0..4)
+                .map(|_| *chars.choose(&mut rng).unwrap())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
 pub fn generate_school_registration_number(school: &School) -> Option<String> {
     let year = Utc::now().year();
     let random = rand::random::<u16>() % 10000;
@@ -0,0 +1,4 @@
+/// Round to two decimals, the precision scores and percentages are shown with
+pub fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -7,12 +7,18 @@ pub mod db_utils;
 pub mod email;
 pub mod hash;
 pub mod jwt;
+pub mod math_utils;
 pub mod mongo_utils;
 pub mod names;
 pub mod object_id;
 pub mod partial_macro;
+pub mod pdf_utils;
 pub mod route_utils;
 pub mod school_token;
 pub mod school_utils;
+pub mod status_utils;
+pub mod text_extract;
+#[cfg(test)]
+pub mod test_utils;
 pub mod time_utils;
 pub mod user_utils;
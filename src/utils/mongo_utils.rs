@@ -1,4 +1,7 @@
-use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::bson::{self, doc, oid::ObjectId, Bson, Document};
+use serde::Serialize;
+
+use crate::errors::AppError;
 
 /// Recursively removes:
 /// - null values
@@ -14,6 +17,28 @@ pub fn extract_valid_fields(doc: Document) -> Document {
     out
 }
 
+/// Serialize a value for storing or embedding in an update.
+///
+/// `bson::to_document` presents itself as human readable, so fields using
+/// `object_id_helpers` come out as hex strings and no longer match ObjectId
+/// filters. The raw serializer keeps them as ObjectIds.
+pub fn to_stored_document<T: Serialize>(value: &T) -> Result<Document, AppError> {
+    bson::to_raw_document_buf(value)
+        .map_err(|e| AppError {
+            message: format!("Failed to serialize document: {}", e),
+        })?
+        .to_document()
+        .map_err(|e| AppError {
+            message: format!("Failed to read serialized document: {}", e),
+        })
+}
+
+/// Filter value matching an id stored either as an ObjectId or, by
+/// `bson::to_document`, as its hex string
+pub fn id_match(id: &ObjectId) -> Document {
+    doc! { "$in": [id, id.to_hex()] }
+}
+
 fn clean_document(prefix: Option<String>, doc: Document, out: &mut Document) {
     for (key, value) in doc {
         let full_key = match &prefix {
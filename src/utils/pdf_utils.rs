@@ -0,0 +1,275 @@
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+/// A4 portrait, in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+const FONT_REGULAR: Name = Name(b"F1");
+const FONT_BOLD: Name = Name(b"F2");
+const IMAGE_NAME: Name = Name(b"Im1");
+
+/// Helvetica advance widths for ASCII 32..=126, per 1000 units of font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+    722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+    667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+    556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+    500, 334, 260, 334, 584,
+];
+
+/// Baseline JPEG that can be embedded as-is with the DCT filter
+pub struct JpegImage {
+    pub data: Vec<u8>,
+    pub width: u16,
+    pub height: u16,
+    pub components: u8,
+}
+
+impl JpegImage {
+    /// Reads the frame header for the size; `None` when the bytes are not a JPEG
+    pub fn parse(data: Vec<u8>) -> Option<Self> {
+        if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+            return None;
+        }
+
+        let mut i = 2;
+        while i + 9 < data.len() {
+            if data[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let marker = data[i + 1];
+            let length = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+
+            // SOF0..SOF15 except DHT (C4), JPG (C8) and DAC (CC)
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let height = u16::from_be_bytes([data[i + 5], data[i + 6]]);
+                let width = u16::from_be_bytes([data[i + 7], data[i + 8]]);
+                let components = data[i + 9];
+                return Some(Self {
+                    data,
+                    width,
+                    height,
+                    components,
+                });
+            }
+            i += 2 + length;
+        }
+        None
+    }
+}
+
+/// Width of `text` in points when set in Helvetica at `size`
+pub fn text_width(text: &str, size: f32, bold: bool) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => HELVETICA_WIDTHS[(code - 32) as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    let scale = if bold { 1.06 } else { 1.0 };
+    units as f32 * size / 1000.0 * scale
+}
+
+/// Shorten `text` with an ellipsis so it fits in `max_width`
+pub fn fit_text(text: &str, size: f32, bold: bool, max_width: f32) -> String {
+    if text_width(text, size, bold) <= max_width {
+        return text.to_string();
+    }
+    let mut fitted: String = text.to_string();
+    while !fitted.is_empty() && text_width(&format!("{}...", fitted), size, bold) > max_width {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+/// Parse "#RRGGBB" into PDF colour components, black on bad input
+pub fn hex_color(hex: &str) -> (f32, f32, f32) {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return (0.0, 0.0, 0.0);
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map(|v| v as f32 / 255.0)
+            .unwrap_or(0.0)
+    };
+    (channel(0), channel(2), channel(4))
+}
+
+/// The standard fonts are WinAnsi encoded, anything outside Latin-1 becomes '?'
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
+
+/// Minimal multi-page document on A4 with the two Helvetica faces and an
+/// optional image shared by all pages
+pub struct PdfDocument {
+    title: String,
+    pages: Vec<Content>,
+    image: Option<JpegImage>,
+}
+
+impl PdfDocument {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            pages: vec![Content::new()],
+            image: None,
+        }
+    }
+
+    pub fn set_image(&mut self, image: JpegImage) {
+        self.image = Some(image);
+    }
+
+    pub fn has_image(&self) -> bool {
+        self.image.is_some()
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(Content::new());
+    }
+
+    fn page(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("document always has a page")
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let font = if bold { FONT_BOLD } else { FONT_REGULAR };
+        let bytes = encode(text);
+        let page = self.page();
+        page.begin_text();
+        page.set_font(font, size);
+        page.next_line(x, y);
+        page.show(Str(&bytes));
+        page.end_text();
+    }
+
+    pub fn text_right(&mut self, right: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let width = text_width(text, size, bold);
+        self.text(right - width, y, size, bold, text);
+    }
+
+    pub fn text_center(&mut self, center: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let width = text_width(text, size, bold);
+        self.text(center - width / 2.0, y, size, bold, text);
+    }
+
+    pub fn text_color(&mut self, (r, g, b): (f32, f32, f32)) {
+        self.page().set_fill_rgb(r, g, b);
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: (f32, f32, f32)) {
+        let page = self.page();
+        page.save_state();
+        page.set_fill_rgb(color.0, color.1, color.2);
+        page.rect(x, y, width, height);
+        page.fill_nonzero();
+        page.restore_state();
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, gray: f32) {
+        let page = self.page();
+        page.save_state();
+        page.set_stroke_gray(gray);
+        page.set_line_width(width);
+        page.move_to(x1, y1);
+        page.line_to(x2, y2);
+        page.stroke();
+        page.restore_state();
+    }
+
+    /// Draw the document image fitted inside the box, keeping its aspect ratio
+    pub fn draw_image(&mut self, x: f32, y: f32, max_width: f32, max_height: f32) {
+        let Some((width, height)) = self
+            .image
+            .as_ref()
+            .map(|img| (img.width as f32, img.height as f32))
+        else {
+            return;
+        };
+        if width == 0.0 || height == 0.0 {
+            return;
+        }
+
+        let scale = (max_width / width).min(max_height / height);
+        let (w, h) = (width * scale, height * scale);
+        let page = self.page();
+        page.save_state();
+        page.transform([w, 0.0, 0.0, h, x, y + (max_height - h) / 2.0]);
+        page.x_object(IMAGE_NAME);
+        page.restore_state();
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut pdf = Pdf::new();
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let regular_id = Ref::new(3);
+        let bold_id = Ref::new(4);
+        let info_id = Ref::new(5);
+        let image_id = Ref::new(6);
+        let first_page = 7;
+
+        let page_ids: Vec<Ref> = (0..self.pages.len())
+            .map(|i| Ref::new(first_page + 2 * i as i32))
+            .collect();
+
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        pdf.document_info(info_id)
+            .title(TextStr(&self.title))
+            .creator(TextStr("Space Together"));
+
+        pdf.type1_font(regular_id)
+            .base_font(Name(b"Helvetica"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.type1_font(bold_id)
+            .base_font(Name(b"Helvetica-Bold"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+        if let Some(img) = &self.image {
+            let mut image = pdf.image_xobject(image_id, &img.data);
+            image.filter(Filter::DctDecode);
+            image.width(img.width as i32);
+            image.height(img.height as i32);
+            match img.components {
+                1 => image.color_space().device_gray(),
+                4 => image.color_space().device_cmyk(),
+                _ => image.color_space().device_rgb(),
+            };
+            image.bits_per_component(8);
+            image.finish();
+        }
+
+        for (content, page_id) in self.pages.into_iter().zip(&page_ids) {
+            let content_id = Ref::new(page_id.get() + 1);
+            let mut page = pdf.page(*page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+
+            let mut resources = page.resources();
+            resources
+                .fonts()
+                .pair(FONT_REGULAR, regular_id)
+                .pair(FONT_BOLD, bold_id);
+            if self.image.is_some() {
+                resources.x_objects().pair(IMAGE_NAME, image_id);
+            }
+            resources.finish();
+            page.finish();
+
+            pdf.stream(content_id, &content.finish());
+        }
+
+        pdf.finish()
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt::Debug;
+
+use chrono::Utc;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId},
+    options::ReturnDocument,
+    Collection,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    domain::common_details::StatusTransition, errors::AppError,
+    utils::mongo_utils::to_stored_document,
+};
+
+/// Move a document between workflow states atomically, pushing the step
+/// onto its `history`. `entry.from` is the status it was read with; the
+/// write only goes through while the status is still one of `allowed`.
+/// `label` names the document in errors, e.g. "Lesson plan".
+pub async fn transition_status<T, S>(
+    collection: &Collection<T>,
+    id: ObjectId,
+    allowed: &[S],
+    entry: StatusTransition<S>,
+    label: &str,
+) -> Result<T, AppError>
+where
+    T: DeserializeOwned + Send + Sync,
+    S: Serialize + PartialEq + Debug,
+{
+    if !allowed.contains(&entry.from) {
+        return Err(AppError {
+            message: format!("{} cannot move from {:?} to {:?}", label, entry.from, entry.to),
+        });
+    }
+
+    let allowed_bson: Vec<_> = allowed.iter().map(|s| bson::to_bson(s).unwrap()).collect();
+
+    collection
+        .find_one_and_update(
+            doc! { "_id": id, "status": { "$in": allowed_bson } },
+            doc! {
+                "$set": {
+                    "status": bson::to_bson(&entry.to).unwrap(),
+                    "updated_at": bson::to_bson(&Utc::now()).unwrap()
+                },
+                "$push": { "history": to_stored_document(&entry)? }
+            },
+        )
+        .return_document(ReturnDocument::After)
+        .await?
+        .ok_or(AppError {
+            message: format!("{} was changed by someone else, reload and try again", label),
+        })
+}
@@ -0,0 +1,12 @@
+use mongodb::{bson::oid::ObjectId, Database};
+
+/// Throwaway database on the server at `TEST_MONGODB_URI`, for tests marked
+/// `#[ignore]` that need MongoDB. Drop it at the end of the test.
+pub async fn test_database(prefix: &str) -> Database {
+    let uri =
+        std::env::var("TEST_MONGODB_URI").unwrap_or_else(|_| "mongodb://localhost:27017".into());
+    mongodb::Client::with_uri_str(uri)
+        .await
+        .expect("TEST_MONGODB_URI is not reachable")
+        .database(&format!("{}_{}", prefix, ObjectId::new()))
+}
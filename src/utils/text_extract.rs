@@ -0,0 +1,232 @@
+use std::{
+    io::{Cursor, Read},
+    panic::{self, AssertUnwindSafe},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use regex::Regex;
+
+/// Largest file downloaded for extraction
+const MAX_FILE_BYTES: usize = 20 * 1024 * 1024;
+/// Largest document body read out of a .docx/.odt archive
+const MAX_DOCUMENT_XML_BYTES: u64 = 20 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("text extraction regex failed"))
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+fn too_large() -> String {
+    format!(
+        "File is larger than the {} MB extraction limit",
+        MAX_FILE_BYTES / (1024 * 1024)
+    )
+}
+
+/// Download a stored submission file and pull plain text out of it
+pub async fn extract_text_from_url(url: &str) -> Result<String, String> {
+    let mut response = client()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Could not download file: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Could not download file: {}", response.status()));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_FILE_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Could not read file: {}", e))?
+    {
+        if bytes.len() + chunk.len() > MAX_FILE_BYTES {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let url = url.to_string();
+    // PDF parsing is CPU bound; keep it off the async workers
+    actix_rt::task::spawn_blocking(move || {
+        extract_text(&bytes, &url, content_type.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Text extraction failed: {}", e))?
+}
+
+/// Plain text, HTML, PDF, Word (.docx) and OpenDocument (.odt) files are
+/// supported; the kind is taken from the content itself, then the name
+pub fn extract_text(
+    bytes: &[u8],
+    name: &str,
+    content_type: Option<&str>,
+) -> Result<String, String> {
+    let name = name
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+
+    if bytes.starts_with(b"%PDF") {
+        return extract_pdf(bytes);
+    }
+    if bytes.starts_with(b"PK") {
+        return extract_office(bytes);
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+    if name.ends_with(".html") || name.ends_with(".htm") || content_type.contains("html") {
+        return Ok(strip_markup(&text));
+    }
+    if content_type.starts_with("text/")
+        || [".txt", ".md", ".csv", ".rtf"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+    {
+        return Ok(normalize(&text));
+    }
+
+    Err("Unsupported file type".into())
+}
+
+/// Text layer of a PDF; scanned pages without one come out empty
+fn extract_pdf(bytes: &[u8]) -> Result<String, String> {
+    // The parser panics on some malformed files instead of returning an error
+    let text = panic::catch_unwind(AssertUnwindSafe(|| {
+        pdf_extract::extract_text_from_mem(bytes)
+    }))
+    .map_err(|_| "Unreadable PDF".to_string())?
+    .map_err(|e| format!("Unreadable PDF: {}", e))?;
+
+    Ok(normalize(&text))
+}
+
+fn extract_office(bytes: &[u8]) -> Result<String, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("Unreadable document: {}", e))?;
+
+    for entry in ["word/document.xml", "content.xml"] {
+        if let Ok(mut file) = archive.by_name(entry) {
+            if file.size() > MAX_DOCUMENT_XML_BYTES {
+                return Err(too_large());
+            }
+            // The declared size can lie, so cap what is actually inflated
+            let mut xml = String::new();
+            file.by_ref()
+                .take(MAX_DOCUMENT_XML_BYTES + 1)
+                .read_to_string(&mut xml)
+                .map_err(|e| format!("Unreadable document: {}", e))?;
+            if xml.len() as u64 > MAX_DOCUMENT_XML_BYTES {
+                return Err(too_large());
+            }
+            return Ok(strip_markup(&xml));
+        }
+    }
+
+    Err("Unsupported file type".into())
+}
+
+fn strip_markup(markup: &str) -> String {
+    static SCRIPT_STYLE: OnceLock<Regex> = OnceLock::new();
+    static BLOCK_END: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+
+    let text = regex(
+        &SCRIPT_STYLE,
+        r"(?is)<(script|style)[^>]*>.*?</(script|style)>",
+    )
+    .replace_all(markup, " ");
+    let text = regex(
+        &BLOCK_END,
+        r"(?i)</(w:p|text:p|text:h|p|div|li|h[1-6]|tr)>|<br\s*/?>",
+    )
+    .replace_all(&text, "\n");
+    let text = regex(&TAG, r"<[^>]+>").replace_all(&text, "");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    normalize(&text)
+}
+
+fn normalize(text: &str) -> String {
+    static BLANK_LINES: OnceLock<Regex> = OnceLock::new();
+
+    let text = text.replace("\r\n", "\n");
+    regex(&BLANK_LINES, r"\n\s*\n+")
+        .replace_all(text.trim(), "\n\n")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    fn docx(body: &[u8]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "word/document.xml",
+            SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+        )
+        .unwrap();
+        zip.write_all(body).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn reads_word_documents() {
+        let file = docx(b"<w:p><w:t>First</w:t></w:p><w:p><w:t>Second &amp; last</w:t></w:p>");
+
+        assert_eq!(
+            extract_text(&file, "essay.docx", None).unwrap(),
+            "First\nSecond & last"
+        );
+    }
+
+    #[test]
+    fn refuses_documents_that_inflate_past_the_limit() {
+        let file = docx(&vec![b' '; MAX_DOCUMENT_XML_BYTES as usize + 1]);
+
+        assert!(file.len() < MAX_FILE_BYTES);
+        assert!(extract_text(&file, "essay.docx", None)
+            .unwrap_err()
+            .contains("extraction limit"));
+    }
+
+    #[test]
+    fn reports_unreadable_pdfs() {
+        assert!(extract_text(b"%PDF-1.4 garbage", "essay.pdf", None)
+            .unwrap_err()
+            .starts_with("Unreadable PDF"));
+    }
+}